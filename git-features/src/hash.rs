@@ -37,6 +37,22 @@ mod _impl {
 
 pub use _impl::Sha1;
 
+pub type Sha256Digest = [u8; 32];
+
+#[derive(Default, Clone)]
+pub struct Sha256(sha2::Sha256);
+
+impl Sha256 {
+    pub fn update(&mut self, d: &[u8]) {
+        use sha2::Digest;
+        self.0.update(d)
+    }
+    pub fn digest(self) -> Sha256Digest {
+        use sha2::Digest;
+        self.0.finalize().into()
+    }
+}
+
 pub fn crc32_update(previous_value: u32, bytes: &[u8]) -> u32 {
     crc::crc32::update(previous_value, &crc::crc32::IEEE_TABLE, bytes)
 }
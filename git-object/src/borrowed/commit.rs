@@ -41,10 +41,10 @@ fn parse_message(i: &[u8]) -> IResult<&[u8], &BStr, Error> {
 }
 
 fn parse(i: &[u8]) -> IResult<&[u8], Commit, Error> {
-    let (i, tree) =
-        parse::header_field(i, b"tree", parse::hex_sha1).map_err(Error::context("tree <40 lowercase hex char>"))?;
-    let (i, parents) = many0(|i| parse::header_field(i, b"parent", parse::hex_sha1))(i)
-        .map_err(Error::context("zero or more 'parent <40 lowercase hex char>'"))?;
+    let (i, tree) = parse::header_field(i, b"tree", parse::hex_hash)
+        .map_err(Error::context("tree <40 or 64 lowercase hex char>"))?;
+    let (i, parents) = many0(|i| parse::header_field(i, b"parent", parse::hex_hash))(i)
+        .map_err(Error::context("zero or more 'parent <40 or 64 lowercase hex char>'"))?;
     let (i, author) =
         parse::header_field(i, b"author", parse::signature).map_err(Error::context("author <signature>"))?;
     let (i, committer) =
@@ -74,7 +74,7 @@ fn parse(i: &[u8]) -> IResult<&[u8], Commit, Error> {
 
 impl<'a> Commit<'a> {
     pub fn tree(&self) -> owned::Id {
-        owned::Id::from_40_bytes_in_hex(self.tree).expect("prior validation")
+        owned::Id::from_hex(self.tree).expect("prior validation")
     }
     pub fn from_bytes(d: &'a [u8]) -> Result<Commit<'a>, Error> {
         parse(d).map(|(_, t)| t).map_err(Error::from)
@@ -1,32 +1,38 @@
-use crate::SHA1_SIZE;
-use bstr::ByteSlice;
-use std::convert::{TryFrom, TryInto};
-use std::fmt;
+use crate::{HashKind, SHA1_SIZE, SHA256_SIZE};
+use quick_error::quick_error;
+use std::{convert::TryFrom, fmt};
 
-/// A reference to a SHA1 identifying objects
+quick_error! {
+    #[derive(Debug, PartialEq, Eq)]
+    pub enum Error {
+        InvalidByteSliceLength(len: usize) {
+            display("An id must be {} or {} bytes long, got {}", SHA1_SIZE, SHA256_SIZE, len)
+        }
+    }
+}
+
+/// A reference to a hash identifying objects, which is 20 bytes long for SHA1 and 32 bytes long for SHA256
 #[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
 #[cfg_attr(feature = "serde1", derive(serde::Serialize))]
-pub struct Id<'a>(&'a [u8; SHA1_SIZE]);
+pub struct Id<'a>(&'a [u8]);
 
 impl<'a> Id<'a> {
-    pub fn kind(&self) -> crate::HashKind {
-        crate::HashKind::Sha1
+    pub fn kind(&self) -> HashKind {
+        HashKind::from_len_in_bytes(self.0.len()).expect("valid length checked on instantiation")
     }
     pub fn first_byte(&self) -> u8 {
         self.0[0]
     }
-}
-
-/// Sha1 specific methods
-impl<'a> Id<'a> {
-    pub fn to_sha1_hex(&self) -> [u8; SHA1_SIZE * 2] {
-        let mut buf = [0u8; SHA1_SIZE * 2];
-        hex::encode_to_slice(self.0, &mut buf).expect("to count correctly");
-        buf
-    }
-    pub fn sha1(&self) -> &[u8; SHA1_SIZE] {
+    pub fn as_slice(&self) -> &'a [u8] {
         self.0
     }
+    pub fn to_hex_string(&self) -> String {
+        hex::encode(self.0)
+    }
+    pub(crate) fn from_valid_slice(v: &'a [u8]) -> Self {
+        debug_assert!(HashKind::from_len_in_bytes(v.len()).is_some());
+        Id(v)
+    }
 }
 
 impl<'a> From<&'a [u8; SHA1_SIZE]> for Id<'a> {
@@ -35,17 +41,26 @@ impl<'a> From<&'a [u8; SHA1_SIZE]> for Id<'a> {
     }
 }
 
+impl<'a> From<&'a [u8; SHA256_SIZE]> for Id<'a> {
+    fn from(v: &'a [u8; SHA256_SIZE]) -> Self {
+        Id(v)
+    }
+}
+
 impl<'a> TryFrom<&'a [u8]> for Id<'a> {
-    type Error = std::array::TryFromSliceError;
+    type Error = Error;
 
     fn try_from(value: &'a [u8]) -> Result<Self, Self::Error> {
-        Ok(Id(value.try_into()?))
+        match HashKind::from_len_in_bytes(value.len()) {
+            Some(_) => Ok(Id(value)),
+            None => Err(Error::InvalidByteSliceLength(value.len())),
+        }
     }
 }
 
 impl fmt::Display for Id<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", &self.to_sha1_hex().as_bstr())
+        f.write_str(&self.to_hex_string())
     }
 }
 
@@ -78,7 +93,7 @@ impl<'de: 'a, 'a> serde::Deserialize<'de> for Id<'a> {
                         return serde::export::Err(__err);
                     }
                 };
-                serde::export::Ok(Id(__field0.try_into().expect("exactly 20 bytes")))
+                serde::export::Ok(Id::try_from(__field0).expect("exactly 20 or 32 bytes"))
             }
             #[inline]
             fn visit_seq<__A>(self, mut __seq: __A) -> serde::export::Result<Self::Value, __A::Error>
@@ -99,7 +114,7 @@ impl<'de: 'a, 'a> serde::Deserialize<'de> for Id<'a> {
                         ));
                     }
                 };
-                serde::export::Ok(Id(__field0.try_into().expect("exactly 20 bytes")))
+                serde::export::Ok(Id::try_from(__field0).expect("exactly 20 or 32 bytes"))
             }
         }
         serde::Deserializer::deserialize_newtype_struct(
@@ -4,8 +4,8 @@
 mod commit;
 pub use commit::Commit;
 
-mod id;
-pub use id::*;
+pub mod id;
+pub use id::Id;
 
 mod tag;
 pub use tag::Tag;
@@ -3,7 +3,7 @@ use bstr::BStr;
 use crate::{
     borrowed,
     borrowed::{parse, Blob, Commit, Tag, Tree},
-    Kind, Time,
+    HashKind, Kind, Time,
 };
 
 mod error;
@@ -35,9 +35,15 @@ pub enum Object<'a> {
 }
 
 impl<'a> Object<'a> {
+    /// Parse an object of `kind` from `bytes`, which refers to other objects by SHA1.
     pub fn from_bytes(kind: Kind, bytes: &'a [u8]) -> Result<Object<'a>, Error> {
+        Self::from_bytes_with_hash(kind, bytes, HashKind::Sha1)
+    }
+
+    /// Parse an object of `kind` from `bytes`, which refers to other objects by ids produced by the `hash` kind.
+    pub fn from_bytes_with_hash(kind: Kind, bytes: &'a [u8], hash: HashKind) -> Result<Object<'a>, Error> {
         Ok(match kind {
-            Kind::Tree => Object::Tree(Tree::from_bytes(bytes)?),
+            Kind::Tree => Object::Tree(Tree::from_bytes_with_hash(bytes, hash)?),
             Kind::Blob => Object::Blob(Blob { data: bytes }),
             Kind::Commit => Object::Commit(Commit::from_bytes(bytes)?),
             Kind::Tag => Object::Tag(Tag::from_bytes(bytes)?),
@@ -1,6 +1,6 @@
 use crate::{
     borrowed::{Error, Signature},
-    ByteSlice, Sign, Time,
+    ByteSlice, Sign, Time, SHA1_SIZE, SHA256_SIZE,
 };
 use bstr::{BStr, BString, ByteVec};
 use btoi::btoi;
@@ -68,8 +68,12 @@ fn is_hex_digit_lc(b: u8) -> bool {
     }
 }
 
-pub(crate) fn hex_sha1(i: &[u8]) -> IResult<&[u8], &BStr, Error> {
-    take_while_m_n(40usize, 40, is_hex_digit_lc)(i).map(|(i, o)| (i, o.as_bstr()))
+pub(crate) fn hex_hash(i: &[u8]) -> IResult<&[u8], &BStr, Error> {
+    alt((
+        take_while_m_n(SHA256_SIZE * 2, SHA256_SIZE * 2, is_hex_digit_lc),
+        take_while_m_n(SHA1_SIZE * 2, SHA1_SIZE * 2, is_hex_digit_lc),
+    ))(i)
+    .map(|(i, o)| (i, o.as_bstr()))
 }
 
 pub(crate) fn signature(i: &[u8]) -> IResult<&[u8], Signature, Error> {
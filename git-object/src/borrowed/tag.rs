@@ -15,7 +15,7 @@ use nom::{
 #[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 pub struct Tag<'a> {
-    // Target SHA1 in hex, always 40 lower case characters from 0-9 and a-f
+    // Target id in hex, 40 lower case characters from 0-9 and a-f for SHA1, or 64 for SHA256
     #[cfg_attr(feature = "serde1", serde(borrow))]
     pub target: &'a BStr,
     // The name of the tag, e.g. "v1.0"
@@ -27,8 +27,8 @@ pub struct Tag<'a> {
 }
 
 fn parse(i: &[u8]) -> IResult<&[u8], Tag, Error> {
-    let (i, target) =
-        parse::header_field(i, b"object", parse::hex_sha1).map_err(Error::context("object <40 lowercase hex char>"))?;
+    let (i, target) = parse::header_field(i, b"object", parse::hex_hash)
+        .map_err(Error::context("object <40 or 64 lowercase hex char>"))?;
 
     let (i, kind) =
         parse::header_field(i, b"type", take_while1(is_alphabetic)).map_err(Error::context("type <object kind>"))?;
@@ -100,7 +100,7 @@ fn parse_message(i: &[u8]) -> IResult<&[u8], (&BStr, Option<&BStr>), Error> {
 
 impl<'a> Tag<'a> {
     pub fn target(&self) -> owned::Id {
-        owned::Id::from_40_bytes_in_hex(self.target).expect("prior validation")
+        owned::Id::from_hex(self.target).expect("prior validation")
     }
     pub fn from_bytes(d: &'a [u8]) -> Result<Tag<'a>, Error> {
         parse(d).map(|(_, t)| t).map_err(Error::from)
@@ -1,4 +1,4 @@
-use crate::{borrowed, borrowed::parse::SPACE, borrowed::Error, TreeMode};
+use crate::{borrowed, borrowed::parse::SPACE, borrowed::Error, HashKind, TreeMode};
 use bstr::{BStr, ByteSlice};
 use nom::{
     bytes::complete::{tag, take, take_while1, take_while_m_n},
@@ -44,29 +44,35 @@ impl TryFrom<&[u8]> for TreeMode {
 }
 
 const NULL: &[u8] = b"\0";
-fn parse_entry(i: &[u8]) -> IResult<&[u8], Entry, Error> {
+fn parse_entry(i: &[u8], hash: HashKind) -> IResult<&[u8], Entry, Error> {
     let (i, mode) = terminated(take_while_m_n(5, 6, is_digit), tag(SPACE))(i)?;
     let mode = TreeMode::try_from(mode).map_err(nom::Err::Error)?;
     let (i, filename) = terminated(take_while1(|b| b != NULL[0]), tag(NULL))(i)?;
-    let (i, oid) = take(20u8)(i)?;
+    let (i, oid) = take(hash.len_in_bytes())(i)?;
 
     Ok((
         i,
         Entry {
             mode,
             filename: filename.as_bstr(),
-            oid: borrowed::Id::try_from(oid).expect("we counted exactly 20 bytes"),
+            oid: borrowed::Id::try_from(oid).expect("we counted exactly the bytes of an id"),
         },
     ))
 }
 
-fn parse(i: &[u8]) -> IResult<&[u8], Tree, Error> {
-    let (i, entries) = all_consuming(many1(parse_entry))(i)?;
+fn parse(i: &[u8], hash: HashKind) -> IResult<&[u8], Tree, Error> {
+    let (i, entries) = all_consuming(many1(|i| parse_entry(i, hash)))(i)?;
     Ok((i, Tree { entries }))
 }
 
 impl<'a> Tree<'a> {
+    /// Parse a tree whose entries refer to objects by SHA1.
     pub fn from_bytes(d: &'a [u8]) -> Result<Tree<'a>, Error> {
-        parse(d).map(|(_, t)| t).map_err(Error::from)
+        Self::from_bytes_with_hash(d, HashKind::Sha1)
+    }
+
+    /// Parse a tree whose entries refer to objects by ids produced by the `hash` kind.
+    pub fn from_bytes_with_hash(d: &'a [u8], hash: HashKind) -> Result<Tree<'a>, Error> {
+        parse(d, hash).map(|(_, t)| t).map_err(Error::from)
     }
 }
@@ -17,6 +17,41 @@ pub mod commit;
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 pub enum HashKind {
     Sha1,
+    Sha256,
+}
+
+impl HashKind {
+    /// The amount of bytes of ids produced by this kind of hash.
+    pub fn len_in_bytes(&self) -> usize {
+        match self {
+            HashKind::Sha1 => SHA1_SIZE,
+            HashKind::Sha256 => SHA256_SIZE,
+        }
+    }
+
+    /// The amount of hex characters needed to display ids produced by this kind of hash.
+    pub fn len_in_hex(&self) -> usize {
+        self.len_in_bytes() * 2
+    }
+
+    /// The kind of hash producing ids of `len` bytes, if there is one.
+    pub fn from_len_in_bytes(len: usize) -> Option<HashKind> {
+        Some(match len {
+            SHA1_SIZE => HashKind::Sha1,
+            SHA256_SIZE => HashKind::Sha256,
+            _ => return None,
+        })
+    }
+
+    /// The kind of hash named by the `extensions.objectFormat` configuration value of a repository, i.e. `sha1` or
+    /// `sha256`.
+    pub fn from_object_format(name: &str) -> Option<HashKind> {
+        Some(match name {
+            "sha1" => HashKind::Sha1,
+            "sha256" => HashKind::Sha256,
+            _ => return None,
+        })
+    }
 }
 
 impl Default for HashKind {
@@ -24,7 +24,7 @@ impl Into<owned::Tag> for borrowed::Tag<'_> {
             pgp_signature,
         } = self;
         owned::Tag {
-            target: owned::Id::from_40_bytes_in_hex(&target).expect("40 bytes hex sha1"),
+            target: owned::Id::from_hex(&target).expect("40 or 64 bytes hex"),
             name: name.to_owned(),
             target_kind,
             message: message.to_owned(),
@@ -46,11 +46,11 @@ impl Into<owned::Commit> for borrowed::Commit<'_> {
             extra_headers,
         } = self;
         owned::Commit {
-            tree: owned::Id::from_40_bytes_in_hex(&tree).expect("40 bytes hex sha1"),
+            tree: owned::Id::from_hex(&tree).expect("40 or 64 bytes hex"),
             parents: SmallVec::from_iter(
                 parents
                     .iter()
-                    .map(|parent| owned::Id::from_40_bytes_in_hex(parent).expect("40 bytes hex sha1")),
+                    .map(|parent| owned::Id::from_hex(parent).expect("40 or 64 bytes hex")),
             ),
             author: author.into(),
             committer: committer.into(),
@@ -83,7 +83,7 @@ impl Into<owned::Tree> for borrowed::Tree<'_> {
 
 impl<'a> From<borrowed::Id<'a>> for owned::Id {
     fn from(v: borrowed::Id<'a>) -> Self {
-        owned::Id::from_borrowed_sha1(v.sha1())
+        owned::Id::from_bytes(v.as_slice()).expect("borrowed ids to have a valid length")
     }
 }
 
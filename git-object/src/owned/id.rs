@@ -1,30 +1,65 @@
-use crate::{borrowed, SHA1_SIZE};
-use bstr::ByteSlice;
-use std::{fmt, io, ops::Deref};
+use crate::{borrowed, HashKind, SHA1_SIZE, SHA256_SIZE};
+use std::{fmt, io};
 
-/// An owned SHA1 identifying objects
-#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
-#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
-pub struct Id([u8; SHA1_SIZE]);
+/// An owned hash identifying objects, whose length depends on the kind of hash that produced it
+///
+/// SHA256 ids are kept on the heap so that ids, and the many structures holding them, are hardly larger than SHA1 ids.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub enum Id {
+    Sha1([u8; SHA1_SIZE]),
+    Sha256(Box<[u8; SHA256_SIZE]>),
+}
 
 impl Id {
-    pub fn kind(&self) -> crate::HashKind {
-        crate::HashKind::Sha1
+    pub fn kind(&self) -> HashKind {
+        match self {
+            Id::Sha1(_) => HashKind::Sha1,
+            Id::Sha256(_) => HashKind::Sha256,
+        }
     }
     pub fn to_borrowed(&self) -> borrowed::Id {
-        borrowed::Id::from(&self.0)
+        borrowed::Id::from_valid_slice(self.as_slice())
     }
     pub fn as_slice(&self) -> &[u8] {
-        self.0.as_ref()
+        match self {
+            Id::Sha1(id) => id.as_ref(),
+            Id::Sha256(id) => &id[..],
+        }
     }
     pub fn as_mut_slice(&mut self) -> &mut [u8] {
-        self.0.as_mut()
+        match self {
+            Id::Sha1(id) => id.as_mut(),
+            Id::Sha256(id) => &mut id[..],
+        }
     }
     pub fn write_to(&self, mut out: impl io::Write) -> io::Result<()> {
-        out.write_all(&self.to_sha1_hex())
+        out.write_all(self.to_hex_string().as_bytes())
+    }
+    /// The id with all bytes set to zero for the given `kind` of hash.
+    pub fn null_of(kind: HashKind) -> Self {
+        match kind {
+            HashKind::Sha1 => Id::Sha1([0u8; SHA1_SIZE]),
+            HashKind::Sha256 => Id::Sha256(Box::new([0u8; SHA256_SIZE])),
+        }
+    }
+    /// Create an id from `b`, whose length determines the kind of hash, or `None` if `b` is neither 20 nor 32 bytes long.
+    pub fn from_bytes(b: &[u8]) -> Option<Id> {
+        HashKind::from_len_in_bytes(b.len()).map(|kind| {
+            let mut id = Id::null_of(kind);
+            id.as_mut_slice().copy_from_slice(b);
+            id
+        })
+    }
+    /// Parse an id from 40 or 64 hex characters, depending on the kind of hash.
+    pub fn from_hex(buf: &[u8]) -> Result<Id, hex::FromHexError> {
+        use hex::FromHex;
+        Ok(match buf.len() {
+            len if len == SHA256_SIZE * 2 => Id::Sha256(Box::new(<[u8; SHA256_SIZE]>::from_hex(buf)?)),
+            _ => Id::Sha1(<[u8; SHA1_SIZE]>::from_hex(buf)?),
+        })
     }
-    pub fn null() -> Self {
-        Id([0u8; SHA1_SIZE])
+    pub fn to_hex_string(&self) -> String {
+        hex::encode(self.as_slice())
     }
 }
 
@@ -32,54 +67,125 @@ impl Id {
 impl Id {
     pub fn from_40_bytes_in_hex(buf: &[u8]) -> Result<Id, hex::FromHexError> {
         use hex::FromHex;
-        Ok(Id(<[u8; 20]>::from_hex(buf)?))
-    }
-    pub fn sha1(&self) -> &[u8; SHA1_SIZE] {
-        &self.0
-    }
-    pub fn to_sha1_hex(&self) -> [u8; SHA1_SIZE * 2] {
-        let mut hex_buf = [0u8; 40];
-        hex::encode_to_slice(self.0, &mut hex_buf).expect("we can count");
-        hex_buf
-    }
-    pub fn to_sha1_hex_string(&self) -> String {
-        let buf = self.to_sha1_hex();
-        std::str::from_utf8(&buf).expect("hex is valid UTF-8").to_string()
+        Ok(Id::Sha1(<[u8; SHA1_SIZE]>::from_hex(buf)?))
     }
     pub fn new_sha1(id: [u8; SHA1_SIZE]) -> Self {
-        Id(id)
+        Id::Sha1(id)
     }
     pub fn from_20_bytes(b: &[u8]) -> Id {
         let mut id = [0; SHA1_SIZE];
         id.copy_from_slice(b);
-        Id(id)
+        Id::Sha1(id)
+    }
+    pub fn null_sha1() -> Id {
+        Id::Sha1([0u8; SHA1_SIZE])
+    }
+    #[deprecated(note = "use null_sha1() or null_of(kind) instead")]
+    pub fn null() -> Self {
+        Id::null_sha1()
     }
+    /// Panics if this is not a SHA1 id.
+    #[deprecated(note = "use as_slice() instead, which works for all kinds of hashes")]
+    pub fn sha1(&self) -> &[u8; SHA1_SIZE] {
+        match self {
+            Id::Sha1(id) => id,
+            Id::Sha256(_) => panic!("BUG: sha1() called on a SHA256 id"),
+        }
+    }
+    /// Panics if this is not a SHA1 id.
+    #[deprecated(note = "use to_hex_string() instead, which works for all kinds of hashes")]
+    #[allow(deprecated)]
+    pub fn to_sha1_hex(&self) -> [u8; SHA1_SIZE * 2] {
+        let mut hex_buf = [0u8; SHA1_SIZE * 2];
+        hex::encode_to_slice(self.sha1(), &mut hex_buf).expect("we can count");
+        hex_buf
+    }
+    #[deprecated(note = "use to_hex_string() instead, which works for all kinds of hashes")]
+    pub fn to_sha1_hex_string(&self) -> String {
+        self.to_hex_string()
+    }
+    #[deprecated(note = "use new_sha1() or From<[u8; 20]> instead")]
     pub fn from_borrowed_sha1(b: &[u8; SHA1_SIZE]) -> Id {
-        let mut id = [0; SHA1_SIZE];
-        id.copy_from_slice(&b[..]);
-        Id(id)
+        Id::Sha1(*b)
     }
-    pub fn null_sha1() -> Id {
-        Id([0u8; 20])
+}
+
+/// Sha256 hash specific methods
+impl Id {
+    pub fn new_sha256(id: [u8; SHA256_SIZE]) -> Self {
+        Id::Sha256(Box::new(id))
     }
 }
 
 impl From<[u8; SHA1_SIZE]> for Id {
-    fn from(v: [u8; 20]) -> Self {
+    fn from(v: [u8; SHA1_SIZE]) -> Self {
         Self::new_sha1(v)
     }
 }
 
-impl Deref for Id {
-    type Target = [u8; SHA1_SIZE];
+impl From<[u8; SHA256_SIZE]> for Id {
+    fn from(v: [u8; SHA256_SIZE]) -> Self {
+        Self::new_sha256(v)
+    }
+}
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
+impl AsRef<[u8]> for Id {
+    fn as_ref(&self) -> &[u8] {
+        self.as_slice()
     }
 }
 
 impl fmt::Display for Id {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", &self.to_sha1_hex().as_bstr())
+        f.write_str(&self.to_hex_string())
+    }
+}
+
+/// Ids are serialized as plain bytes, without noting the kind of hash which follows from their length.
+#[cfg(feature = "serde1")]
+impl serde::Serialize for Id {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_bytes(self.as_slice())
+    }
+}
+
+#[cfg(feature = "serde1")]
+impl<'de> serde::Deserialize<'de> for Id {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct Visitor;
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = Id;
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{} or {} bytes", SHA1_SIZE, SHA256_SIZE)
+            }
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Id::from_bytes(v).ok_or_else(|| E::invalid_length(v.len(), &self))
+            }
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::SeqAccess<'de>,
+            {
+                let mut buf = [0u8; SHA256_SIZE];
+                let mut len = 0;
+                while let Some(byte) = seq.next_element()? {
+                    if len == buf.len() {
+                        return Err(serde::de::Error::invalid_length(len + 1, &self));
+                    }
+                    buf[len] = byte;
+                    len += 1;
+                }
+                self.visit_bytes(&buf[..len])
+            }
+        }
+        deserializer.deserialize_bytes(Visitor)
     }
 }
@@ -9,6 +9,10 @@ mod ser;
 mod id;
 pub use id::*;
 
+pub mod prefix;
+#[doc(inline)]
+pub use prefix::Prefix;
+
 mod tag;
 pub use tag::Tag;
 
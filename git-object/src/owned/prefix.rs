@@ -0,0 +1,130 @@
+use crate::{borrowed, owned, HashKind, SHA1_SIZE, SHA256_SIZE};
+use quick_error::quick_error;
+use std::{cmp::Ordering, convert::TryFrom, fmt, str::FromStr};
+
+quick_error! {
+    #[derive(Debug, PartialEq, Eq)]
+    pub enum Error {
+        TooShort(hex_len: usize) {
+            display("A prefix needs at least {} hex characters, got {}", Prefix::MIN_HEX_LEN, hex_len)
+        }
+        TooLong(hex_len: usize) {
+            display("A prefix can have at most {} hex characters, got {}", SHA256_SIZE * 2, hex_len)
+        }
+        InvalidHexCharacter(c: char, index: usize) {
+            display("Invalid hex character {:?} at position {}", c, index)
+        }
+    }
+}
+
+/// An abbreviated id identifying all objects whose id starts with the same hex characters.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Prefix {
+    /// The prefix as full id, with all bits past `hex_len` set to zero
+    id: owned::Id,
+    hex_len: usize,
+}
+
+impl Prefix {
+    /// The least amount of hex characters a prefix may have, like in git.
+    pub const MIN_HEX_LEN: usize = 4;
+
+    /// Create a prefix from the first `hex_len` hex characters of `id`.
+    pub fn new(id: borrowed::Id, hex_len: usize) -> Result<Self, Error> {
+        check_hex_len(hex_len)?;
+        let mut prefix = owned::Id::null_of(id.kind());
+        let num_bytes = hex_len.div_ceil(2);
+        if num_bytes > id.as_slice().len() {
+            return Err(Error::TooLong(hex_len));
+        }
+        prefix.as_mut_slice()[..num_bytes].copy_from_slice(&id.as_slice()[..num_bytes]);
+        if hex_len % 2 == 1 {
+            prefix.as_mut_slice()[num_bytes - 1] &= 0xf0;
+        }
+        Ok(Prefix { id: prefix, hex_len })
+    }
+
+    /// Parse a prefix from `hex` characters, which may be upper or lower case.
+    ///
+    /// Prefixes longer than a SHA1 in hex are padded to a SHA256 id.
+    pub fn from_hex(hex: &str) -> Result<Self, Error> {
+        check_hex_len(hex.len())?;
+        let mut id = owned::Id::null_of(if hex.len() > SHA1_SIZE * 2 {
+            HashKind::Sha256
+        } else {
+            HashKind::Sha1
+        });
+        for (index, c) in hex.chars().enumerate() {
+            let nibble = c.to_digit(16).ok_or(Error::InvalidHexCharacter(c, index))? as u8;
+            id.as_mut_slice()[index / 2] |= if index % 2 == 0 { nibble << 4 } else { nibble };
+        }
+        Ok(Prefix { id, hex_len: hex.len() })
+    }
+
+    /// The amount of hex characters this prefix consists of.
+    pub fn hex_len(&self) -> usize {
+        self.hex_len
+    }
+
+    /// The prefix as full id, padded with zeros, which is the smallest id that can match it.
+    pub fn as_id(&self) -> borrowed::Id<'_> {
+        self.id.to_borrowed()
+    }
+
+    /// Compare this prefix with the same amount of leading hex characters of `candidate`, to find the matching ones
+    /// in a sorted list of ids.
+    ///
+    /// Candidates shorter than this prefix, i.e. SHA1 ids compared to a prefix of more than 40 hex characters, never
+    /// match it.
+    pub fn cmp_id(&self, candidate: borrowed::Id) -> Ordering {
+        let (prefix, candidate) = (self.id.as_slice(), candidate.as_slice());
+        if candidate.len() * 2 < self.hex_len {
+            return prefix[..candidate.len()].cmp(candidate).then(Ordering::Greater);
+        }
+        let full_bytes = self.hex_len / 2;
+        prefix[..full_bytes]
+            .cmp(&candidate[..full_bytes])
+            .then_with(|| match self.hex_len % 2 {
+                0 => Ordering::Equal,
+                _ => prefix[full_bytes].cmp(&(candidate[full_bytes] & 0xf0)),
+            })
+    }
+
+    /// Returns true if `candidate` starts with this prefix.
+    pub fn matches(&self, candidate: borrowed::Id) -> bool {
+        self.cmp_id(candidate) == Ordering::Equal
+    }
+}
+
+fn check_hex_len(hex_len: usize) -> Result<(), Error> {
+    if hex_len < Prefix::MIN_HEX_LEN {
+        Err(Error::TooShort(hex_len))
+    } else if hex_len > SHA256_SIZE * 2 {
+        Err(Error::TooLong(hex_len))
+    } else {
+        Ok(())
+    }
+}
+
+impl FromStr for Prefix {
+    type Err = Error;
+
+    fn from_str(hex: &str) -> Result<Self, Self::Err> {
+        Prefix::from_hex(hex)
+    }
+}
+
+impl TryFrom<&str> for Prefix {
+    type Error = Error;
+
+    fn try_from(hex: &str) -> Result<Self, Self::Error> {
+        Prefix::from_hex(hex)
+    }
+}
+
+impl fmt::Display for Prefix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.id.to_hex_string()[..self.hex_len])
+    }
+}
@@ -26,7 +26,7 @@ impl From<Error> for io::Error {
 #[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 pub struct Tag {
-    // Target SHA1 in hex, always 40 lower case characters from 0-9 and a-f
+    // Target id in hex, 40 lower case characters from 0-9 and a-f for SHA1, or 64 for SHA256
     pub target: owned::Id,
     // The name of the tag, e.g. "v1.0"
     pub name: BString,
@@ -11,6 +11,19 @@ mod method {
         assert_eq!(commit.tree, "1b2dfb4ac5e42080b682fc676e9738c94ce6d54d");
         Ok(())
     }
+
+    #[test]
+    fn tree_sha256() -> Result<(), Box<dyn std::error::Error>> {
+        let fixture = fixture_bytes("commit", "sha256.txt");
+        let commit = Commit::from_bytes(&fixture)?;
+        assert_eq!(commit.tree().kind(), git_object::HashKind::Sha256);
+        assert_eq!(
+            commit.tree().to_hex_string(),
+            "9b2d027f03485291921ad400b126ebd9062773186afee60cd26559b21d0be9f5"
+        );
+        assert_eq!(commit.parents.len(), 1);
+        Ok(())
+    }
 }
 
 mod from_bytes {
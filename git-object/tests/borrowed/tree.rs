@@ -74,4 +74,34 @@ mod from_bytes {
         );
         Ok(())
     }
+
+    #[test]
+    fn sha256() -> Result<(), Box<dyn std::error::Error>> {
+        use git_object::{owned, HashKind};
+        let fixture = fixture_bytes("tree", "sha256.tree");
+        let tree = Tree::from_bytes_with_hash(&fixture, HashKind::Sha256)?;
+        assert_eq!(
+            tree.entries
+                .iter()
+                .map(|entry| (entry.mode, entry.filename, owned::Id::from(entry.oid)))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    TreeMode::Blob,
+                    b"a".as_bstr(),
+                    owned::Id::from_hex(b"1fcf8cb9e507ff20ecd3344802ede2043914087fdfc14fe56b885784e0eab63d")?
+                ),
+                (
+                    TreeMode::Tree,
+                    b"dir".as_bstr(),
+                    owned::Id::from_hex(b"cbdf5774614ae67edf24d0acb6c331f186b579952fc8074d04c843336b26b26b")?
+                )
+            ]
+        );
+        assert!(
+            Tree::from_bytes(&fixture).is_err(),
+            "SHA1 ids are too short to consume all entries"
+        );
+        Ok(())
+    }
 }
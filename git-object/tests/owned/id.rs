@@ -0,0 +1,20 @@
+use git_object::{owned, HashKind};
+
+#[test]
+fn from_bytes_derives_the_kind_from_the_length() {
+    assert_eq!(
+        owned::Id::from_bytes(&[1; 20]).map(|id| id.kind()),
+        Some(HashKind::Sha1)
+    );
+    assert_eq!(
+        owned::Id::from_bytes(&[1; 32]).map(|id| id.kind()),
+        Some(HashKind::Sha256)
+    );
+}
+
+#[test]
+fn from_bytes_rejects_invalid_lengths() {
+    for len in &[0, 19, 21, 31, 33] {
+        assert_eq!(owned::Id::from_bytes(&vec![0; *len]), None);
+    }
+}
@@ -26,6 +26,7 @@ macro_rules! round_trip {
     };
 }
 
+mod id;
 mod object;
 mod tag {
     round_trip!(
@@ -52,15 +53,30 @@ mod commit {
         "commit/signed-with-encoding.txt",
         "commit/unsigned.txt",
         "commit/whitespace.txt",
-        "commit/with-encoding.txt"
+        "commit/with-encoding.txt",
+        "commit/sha256.txt"
     );
 }
 
 mod tree {
     round_trip!(owned::Tree, borrowed::Tree, "tree/everything.tree");
+
+    #[test]
+    fn round_trip_sha256() -> Result<(), Box<dyn std::error::Error>> {
+        use bstr::ByteSlice;
+        use git_object::{borrowed, owned, HashKind};
+        let input = crate::fixture_bytes("tree/sha256.tree");
+        let item: owned::Tree = borrowed::Tree::from_bytes_with_hash(&input, HashKind::Sha256)?.into();
+        let mut output = Vec::new();
+        item.write_to(&mut output)?;
+        assert_eq!(output.as_bstr(), input.as_bstr());
+        Ok(())
+    }
 }
 
 mod blob {
     // It doesn't matter which data we use - it's not interpreted.
     round_trip!(owned::Blob, borrowed::Blob, "tree/everything.tree");
 }
+
+mod prefix;
@@ -0,0 +1,75 @@
+use git_object::owned::{self, prefix, Prefix};
+use std::cmp::Ordering;
+
+fn id(hex: &str) -> owned::Id {
+    owned::Id::from_40_bytes_in_hex(hex.as_bytes()).expect("40 bytes hex")
+}
+
+#[test]
+fn from_hex_round_trips_through_display() -> Result<(), prefix::Error> {
+    for hex in &["abcd", "abcde", "0123456789abcdef0123456789abcdef01234567"] {
+        let prefix = Prefix::from_hex(hex)?;
+        assert_eq!(prefix.hex_len(), hex.len());
+        assert_eq!(prefix.to_string(), *hex);
+    }
+    assert_eq!("ABCDE".parse::<Prefix>()?, Prefix::from_hex("abcde")?);
+    assert_eq!(
+        Prefix::from_hex("abcde")?.as_id().to_hex_string(),
+        "abcde00000000000000000000000000000000000",
+        "unused nibbles are zero"
+    );
+    Ok(())
+}
+
+#[test]
+fn from_hex_rejects_invalid_input() {
+    assert_eq!(Prefix::from_hex("abc"), Err(prefix::Error::TooShort(3)));
+    assert_eq!(Prefix::from_hex(&"a".repeat(65)), Err(prefix::Error::TooLong(65)));
+    assert_eq!(
+        Prefix::from_hex("abcg"),
+        Err(prefix::Error::InvalidHexCharacter('g', 3))
+    );
+}
+
+#[test]
+fn new_truncates_the_id() -> Result<(), prefix::Error> {
+    let full = id("0123456789abcdef0123456789abcdef01234567");
+    assert_eq!(Prefix::new(full.to_borrowed(), 7)?, Prefix::from_hex("0123456")?);
+    assert_eq!(Prefix::new(full.to_borrowed(), 40)?.as_id(), full.to_borrowed());
+    assert_eq!(Prefix::new(full.to_borrowed(), 2), Err(prefix::Error::TooShort(2)));
+    Ok(())
+}
+
+#[test]
+fn cmp_id_only_considers_the_prefix() -> Result<(), prefix::Error> {
+    let prefix = Prefix::from_hex("abcde")?;
+    let cmp = |start: &str, fill: char| {
+        let hex: String = start.chars().chain(std::iter::repeat(fill)).take(40).collect();
+        prefix.cmp_id(id(&hex).to_borrowed())
+    };
+    assert_eq!(cmp("abcde", '0'), Ordering::Equal);
+    assert_eq!(cmp("abcde", 'f'), Ordering::Equal);
+    assert_eq!(cmp("abcdf", '0'), Ordering::Less);
+    assert_eq!(cmp("abcdd", 'f'), Ordering::Greater);
+    assert!(prefix.matches(id("abcde12345678901234567890123456789012345").to_borrowed()));
+    assert!(!prefix.matches(id("abcd012345678901234567890123456789012345").to_borrowed()));
+    Ok(())
+}
+
+#[test]
+fn prefixes_longer_than_a_sha1_match_sha256_ids() -> Result<(), Box<dyn std::error::Error>> {
+    let full = owned::Id::from_hex(b"001cc91be706c78c4f70ff2e0d4034fb366e9742e04bcd0754666eeb9687b95a")?;
+    let prefix = Prefix::from_hex("001cc91be706c78c4f70ff2e0d4034fb366e9742e04")?;
+    assert_eq!(prefix.as_id().kind(), git_object::HashKind::Sha256);
+    assert!(prefix.matches(full.to_borrowed()));
+    assert!(
+        !prefix.matches(id("001cc91be706c78c4f70ff2e0d4034fb366e9742").to_borrowed()),
+        "SHA1 ids are too short to match"
+    );
+    assert_eq!(Prefix::new(full.to_borrowed(), 64)?.as_id(), full.to_borrowed());
+    assert_eq!(
+        Prefix::new(id("001cc91be706c78c4f70ff2e0d4034fb366e9742").to_borrowed(), 41),
+        Err(prefix::Error::TooLong(41))
+    );
+    Ok(())
+}
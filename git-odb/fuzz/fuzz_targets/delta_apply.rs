@@ -0,0 +1,14 @@
+#![no_main]
+use git_odb::pack;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // Let the first byte decide how much of the input is the base, and use the rest as delta.
+    let (base_len, data) = match data.split_first() {
+        Some((len, data)) => (*len as usize, data),
+        None => return,
+    };
+    let (base, delta) = data.split_at(base_len.min(data.len()));
+    let mut out = Vec::new();
+    pack::data::delta::apply(base, delta, &mut out).ok();
+});
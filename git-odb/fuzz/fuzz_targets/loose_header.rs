@@ -0,0 +1,7 @@
+#![no_main]
+use git_odb::loose;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    loose::object::header::decode(data).ok();
+});
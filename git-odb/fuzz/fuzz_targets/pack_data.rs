@@ -0,0 +1,24 @@
+#![no_main]
+use git_odb::pack;
+use libfuzzer_sys::fuzz_target;
+use std::io::Write;
+
+fuzz_target!(|data: &[u8]| {
+    let mut file = tempfile::NamedTempFile::new().expect("temporary file can be created");
+    file.write_all(data).expect("temporary file can be written");
+    let pack = match pack::data::File::at(file.path()) {
+        Ok(pack) => pack,
+        Err(_) => return,
+    };
+    let mut buf = Vec::new();
+    let mut offset = pack::data::File::HEADER_LEN as u64;
+    while let Ok(entry) = pack.entry(offset) {
+        offset = entry.data_offset + 1;
+        pack.decode_header(entry.clone(), |_id| None).ok();
+        pack.decode_entry(entry, &mut buf, |_id, _out| None, &mut pack::cache::DecodeEntryNoop)
+            .ok();
+    }
+    if let Ok(iter) = pack.streaming_iter() {
+        iter.take_while(Result::is_ok).for_each(drop);
+    }
+});
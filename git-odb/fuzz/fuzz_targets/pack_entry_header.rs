@@ -0,0 +1,15 @@
+#![no_main]
+use git_object::HashKind;
+use git_odb::pack;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    for hash_kind in &[HashKind::Sha1, HashKind::Sha256] {
+        if let Ok(entry) = pack::data::Entry::from_bytes(data, 12, *hash_kind) {
+            if let pack::data::Header::OfsDelta { base_distance } = entry.header {
+                entry.base_pack_offset(base_distance);
+            }
+        }
+        pack::data::Entry::from_read(data, 12, *hash_kind).ok();
+    }
+});
@@ -0,0 +1,23 @@
+#![no_main]
+use git_odb::pack;
+use libfuzzer_sys::fuzz_target;
+use std::io::Write;
+
+fuzz_target!(|data: &[u8]| {
+    let mut file = tempfile::NamedTempFile::new().expect("temporary file can be created");
+    file.write_all(data).expect("temporary file can be written");
+    let index = match pack::index::File::at(file.path()) {
+        Ok(index) => index,
+        Err(_) => return,
+    };
+    if let Ok(entries) = index.iter() {
+        for entry in entries {
+            index.lookup(entry.oid.to_borrowed());
+        }
+    }
+    for idx in 0..index.num_objects() {
+        index.oid_at_index(idx);
+        index.pack_offset_at_index(idx);
+        index.crc32_at_index(idx);
+    }
+});
@@ -0,0 +1,96 @@
+//! Alternates are object directories of other repositories whose objects are made available to ours, as configured
+//! in `objects/info/alternates` or the `GIT_ALTERNATE_OBJECT_DIRECTORIES` environment variable.
+use quick_error::quick_error;
+use std::{
+    ffi::OsStr,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+pub mod parse;
+
+/// The environment variable holding additional alternate object directories, separated like `PATH`.
+pub const ENV_VARIABLE: &str = "GIT_ALTERNATE_OBJECT_DIRECTORIES";
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error, path: PathBuf) {
+            display("Could not read alternates information at '{}'", path.display())
+            source(err)
+        }
+        Parse(err: parse::Error, path: PathBuf) {
+            display("Could not parse the alternates file at '{}'", path.display())
+            source(err)
+        }
+    }
+}
+
+/// Return the object directories listed in the `GIT_ALTERNATE_OBJECT_DIRECTORIES` environment variable, if set.
+///
+/// Relative paths are relative to the current working directory.
+pub fn from_env() -> Vec<PathBuf> {
+    std::env::var_os(ENV_VARIABLE)
+        .map(|value| split_paths(&value))
+        .unwrap_or_default()
+}
+
+/// Split `value` at the platform's path separator, skipping empty entries.
+pub fn split_paths(value: &OsStr) -> Vec<PathBuf> {
+    std::env::split_paths(value)
+        .filter(|path| !path.as_os_str().is_empty())
+        .collect()
+}
+
+/// Return all alternate object directories of the database at `objects_directory` in lookup order, following
+/// alternates of alternates recursively, with the directories of `from_env()` coming first.
+///
+/// Each object directory is returned only once, so cycles and directories reachable through multiple paths are
+/// harmless, and `objects_directory` itself is never part of the result.
+/// Like git, alternates which don't exist or aren't a directory are skipped.
+pub fn resolve(objects_directory: impl AsRef<Path>) -> Result<Vec<PathBuf>, Error> {
+    resolve_with(objects_directory, from_env())
+}
+
+/// As [`resolve()`], but with `additional` object directories taking the place of the ones in the environment.
+pub fn resolve_with(
+    objects_directory: impl AsRef<Path>,
+    additional: impl IntoIterator<Item = PathBuf>,
+) -> Result<Vec<PathBuf>, Error> {
+    let objects_directory = objects_directory.as_ref();
+    let mut seen =
+        vec![fs::canonicalize(objects_directory).map_err(|err| Error::Io(err, objects_directory.to_owned()))?];
+    let mut out = Vec::new();
+    let mut pending: Vec<PathBuf> = additional.into_iter().collect();
+    pending.extend(alternates_of(objects_directory)?);
+    pending.reverse();
+
+    while let Some(directory) = pending.pop() {
+        let canonical = match fs::canonicalize(&directory) {
+            Ok(canonical) if canonical.is_dir() => canonical,
+            Ok(_) => continue,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(Error::Io(err, directory)),
+        };
+        if seen.contains(&canonical) {
+            continue;
+        }
+        // Depth-first, so alternates of an alternate are searched before the alternates following it, like git does.
+        let nested = alternates_of(&canonical)?;
+        pending.extend(nested.into_iter().rev());
+        seen.push(canonical.clone());
+        out.push(canonical);
+    }
+    Ok(out)
+}
+
+/// Read the alternates file of `objects_directory`, which may not exist.
+fn alternates_of(objects_directory: &Path) -> Result<Vec<PathBuf>, Error> {
+    let path = objects_directory.join("info").join("alternates");
+    let content = match fs::read(&path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(Error::Io(err, path)),
+    };
+    parse::content(&content, objects_directory).map_err(|err| Error::Parse(err, path))
+}
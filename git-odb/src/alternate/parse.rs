@@ -0,0 +1,73 @@
+use git_object::bstr::ByteSlice;
+use quick_error::quick_error;
+use std::path::{Path, PathBuf};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Unquote(line: String) {
+            display("Could not unquote the alternate path in line '{}'", line)
+        }
+        IllformedUtf8(line: String) {
+            display("The alternate path in line '{}' is not valid UTF-8", line)
+        }
+    }
+}
+
+/// Parse the `input` of an `objects/info/alternates` file, returning one object directory per line.
+///
+/// Empty lines and lines starting with `#` are ignored, and lines starting with `"` are unquoted like C strings.
+/// Relative paths are relative to `objects_directory`, the directory the alternates file belongs to.
+pub fn content(input: &[u8], objects_directory: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut out = Vec::new();
+    for line in input.lines() {
+        if line.is_empty() || line.starts_with(b"#") {
+            continue;
+        }
+        let line = if line.starts_with(b"\"") {
+            unquote(line).ok_or_else(|| Error::Unquote(line.to_str_lossy().into_owned()))?
+        } else {
+            line.to_owned()
+        };
+        let path = line
+            .to_str()
+            .map_err(|_| Error::IllformedUtf8(line.to_str_lossy().into_owned()))?;
+        out.push(objects_directory.join(path));
+    }
+    Ok(out)
+}
+
+/// Remove the quotes around `quoted` and resolve its escape sequences, or return `None` if it isn't properly quoted.
+fn unquote(quoted: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(quoted.len());
+    let mut bytes = quoted.get(1..)?.iter().copied();
+    loop {
+        match bytes.next()? {
+            b'"' => return bytes.next().is_none().then_some(out),
+            b'\\' => {
+                let escaped = match bytes.next()? {
+                    b'a' => 0x07,
+                    b'b' => 0x08,
+                    b'f' => 0x0c,
+                    b'n' => b'\n',
+                    b'r' => b'\r',
+                    b't' => b'\t',
+                    b'v' => 0x0b,
+                    b @ b'0'..=b'3' => {
+                        let mut value = b - b'0';
+                        for _ in 0..2 {
+                            match bytes.next()? {
+                                digit @ b'0'..=b'7' => value = value << 3 | (digit - b'0'),
+                                _ => return None,
+                            }
+                        }
+                        value
+                    }
+                    b => b,
+                };
+                out.push(escaped);
+            }
+            b => out.push(b),
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! The table of contents shared by all files made of chunks, like commit-graphs and multi-pack-indices.
+//!
+//! It follows the header of the file and lists the id and offset of each chunk, terminated by an entry with a null id
+//! whose offset marks the end of the last chunk. The trailing checksum of the file comes after all chunks.
+use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
+use git_object::{bstr::ByteSlice, HashKind};
+use quick_error::quick_error;
+use std::{io, mem::size_of, ops::Range};
+
+/// The identifier of a chunk, like `OIDF` for the fan-out table.
+pub type Id = [u8; 4];
+
+/// The size of a single entry in the table of contents, its id followed by its offset.
+pub(crate) const TABLE_ENTRY_LEN: usize = size_of::<Id>() + size_of::<u64>();
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        TableOutOfBounds { num_chunks: usize } {
+            display("The table of {} chunks does not fit into the file", num_chunks)
+        }
+        InvalidRange { id: Id, start: u64, end: u64 } {
+            display("The chunk '{}' has an invalid range from {} to {}", id.as_bstr(), start, end)
+        }
+        MissingChunk(id: Id) {
+            display("The mandatory chunk '{}' was not found", id.as_bstr())
+        }
+    }
+}
+
+/// The id and byte range of all chunks in a file.
+pub(crate) struct Table {
+    chunks: Vec<(Id, Range<usize>)>,
+}
+
+impl Table {
+    /// Read the table of `num_chunks` chunks right behind the `header_len` bytes of the file in `data`, validating that
+    /// all chunks are in bounds and leave room for the trailing checksum of `hash_kind`.
+    pub fn from_bytes(data: &[u8], header_len: usize, num_chunks: usize, hash_kind: HashKind) -> Result<Table, Error> {
+        let table_end = header_len + (num_chunks + 1) * TABLE_ENTRY_LEN;
+        let data_end = data.len().saturating_sub(hash_kind.len_in_bytes());
+        if table_end > data_end {
+            return Err(Error::TableOutOfBounds { num_chunks });
+        }
+        let entries: Vec<_> = data[header_len..table_end]
+            .chunks(TABLE_ENTRY_LEN)
+            .map(|entry| {
+                let mut id = [0; 4];
+                id.copy_from_slice(&entry[..4]);
+                (id, BigEndian::read_u64(&entry[4..]))
+            })
+            .collect();
+        let mut chunks = Vec::with_capacity(num_chunks);
+        for pair in entries.windows(2) {
+            let ((id, start), (_, end)) = (pair[0], pair[1]);
+            if start < table_end as u64 || start > end || end > data_end as u64 {
+                return Err(Error::InvalidRange { id, start, end });
+            }
+            chunks.push((id, start as usize..end as usize));
+        }
+        Ok(Table { chunks })
+    }
+
+    /// Return the byte range of the chunk with `id`, if present.
+    pub fn range(&self, id: Id) -> Option<Range<usize>> {
+        self.chunks
+            .iter()
+            .find(|(chunk_id, _)| *chunk_id == id)
+            .map(|(_, range)| range.clone())
+    }
+
+    /// Return the byte range of the chunk with `id`, or an error if it is not present.
+    pub fn required_range(&self, id: Id) -> Result<Range<usize>, Error> {
+        self.range(id).ok_or(Error::MissingChunk(id))
+    }
+}
+
+/// Write the table of contents for `chunks`, pairs of id and size in bytes, to `out`, assuming the chunks are written
+/// in order right after it and the `header_len` bytes of the file.
+pub(crate) fn write_table(mut out: impl io::Write, header_len: usize, chunks: &[(Id, usize)]) -> io::Result<()> {
+    let mut chunk_offset = (header_len + (chunks.len() + 1) * TABLE_ENTRY_LEN) as u64;
+    for (id, len) in chunks {
+        out.write_all(id)?;
+        out.write_u64::<BigEndian>(chunk_offset)?;
+        chunk_offset += *len as u64;
+    }
+    out.write_all(&[0; 4])?;
+    out.write_u64::<BigEndian>(chunk_offset)
+}
@@ -0,0 +1,270 @@
+use crate::commit_graph::{self, init::COMMIT_DATA_ENTRY_LEN, Position};
+use byteorder::{BigEndian, ByteOrder};
+use git_object::{borrowed, owned, SHA1_SIZE};
+use quick_error::quick_error;
+use std::{convert::TryFrom, mem::size_of};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        MissingExtraEdges { id: owned::Id } {
+            display("Commit {} has more than two parents, but there is no extra edges list", id)
+        }
+        ExtraEdgesOutOfBounds { id: owned::Id, index: u32 } {
+            display("The parents of commit {} continue at index {} past the end of the extra edges list", id, index)
+        }
+        ParentOutOfBounds { id: owned::Id, parent: Position } {
+            display("Commit {} refers to parent at position {}, which isn't in this or any base graph", id, parent)
+        }
+    }
+}
+
+const N32_SIZE: usize = size_of::<u32>();
+/// The parent position marking the absence of a parent.
+pub(crate) const NO_PARENT: u32 = 0x7000_0000;
+/// Set in the second parent position if the parents continue in the extra edges list, at the index in the lower bits.
+/// Also marks the last parent of a commit in the extra edges list.
+pub(crate) const N32_HIGH_BIT: u32 = 1 << 31;
+pub(crate) const GENERATION_SHIFT: u32 = 2;
+pub(crate) const TIME_HIGH_BITS_MASK: u32 = 0b11;
+/// The largest generation number that can be stored. Commits with larger generation numbers are capped to it.
+pub(crate) const GENERATION_NUMBER_MAX: u32 = 0x3fff_ffff;
+
+/// Access to a commit in a [`Graph`][commit_graph::Graph].
+#[derive(Clone, Copy)]
+pub struct Commit<'a> {
+    file: &'a commit_graph::File,
+    /// The position of the first commit of `file` in the graph
+    base_position: Position,
+    /// The position of the commit in `file`
+    file_position: u32,
+}
+
+/// Iteration and access
+impl commit_graph::Graph {
+    /// Returns the position of the commit with the given SHA1, or `None` if it's not in this graph.
+    pub fn lookup(&self, id: borrowed::Id) -> Option<Position> {
+        let mut base_position = 0;
+        for file in &self.files {
+            if let Some(file_position) = file.lookup(id) {
+                return Some(base_position + file_position);
+            }
+            base_position += file.num_commits;
+        }
+        None
+    }
+
+    /// Returns the commit at `position`, which ranges from 0 to `self.num_commits()`.
+    ///
+    /// Panics if `position` is out of bounds.
+    pub fn commit_at(&self, position: Position) -> Commit<'_> {
+        let mut base_position = 0;
+        for file in &self.files {
+            if position < base_position + file.num_commits {
+                return Commit {
+                    file,
+                    base_position,
+                    file_position: position - base_position,
+                };
+            }
+            base_position += file.num_commits;
+        }
+        panic!(
+            "commit position {} out of bounds in graph with {} commits",
+            position, base_position
+        )
+    }
+
+    /// Returns the commit with the given SHA1, or `None` if it's not in this graph.
+    pub fn commit_by_id(&self, id: borrowed::Id) -> Option<Commit<'_>> {
+        self.lookup(id).map(|position| self.commit_at(position))
+    }
+
+    /// Returns the id of the commit at `position`.
+    pub fn id_at(&self, position: Position) -> borrowed::Id<'_> {
+        self.commit_at(position).id()
+    }
+
+    /// Iterate all commits in the order of their [position][Position].
+    pub fn iter_commits(&self) -> impl Iterator<Item = Commit<'_>> {
+        let mut base_position = 0;
+        self.files.iter().flat_map(move |file| {
+            let file_base_position = base_position;
+            base_position += file.num_commits;
+            (0..file.num_commits).map(move |file_position| Commit {
+                file,
+                base_position: file_base_position,
+                file_position,
+            })
+        })
+    }
+}
+
+/// Iteration and access
+impl commit_graph::File {
+    /// Returns 20 bytes sha1 at the given index in our list of (sorted) sha1 hashes.
+    /// The index ranges from 0 to self.num_commits()
+    pub fn id_at(&self, index: u32) -> borrowed::Id<'_> {
+        let start = self.lookup_ofs + index as usize * SHA1_SIZE;
+        borrowed::Id::try_from(&self.data[start..start + SHA1_SIZE]).expect("20 bytes SHA1 to be alright")
+    }
+
+    /// Returns the index of the given SHA1 in this file, which doesn't account for commits in base graphs.
+    pub fn lookup(&self, id: borrowed::Id) -> Option<u32> {
+        let first_byte = id.first_byte() as usize;
+        let mut upper_bound = self.fan[first_byte];
+        let mut lower_bound = if first_byte != 0 { self.fan[first_byte - 1] } else { 0 };
+
+        while lower_bound < upper_bound {
+            let mid = (lower_bound + upper_bound) / 2;
+            let mid_sha = self.id_at(mid);
+
+            use std::cmp::Ordering::*;
+            match id.cmp(&mid_sha) {
+                Less => upper_bound = mid,
+                Equal => return Some(mid),
+                Greater => lower_bound = mid + 1,
+            }
+        }
+        None
+    }
+
+    fn commit_data(&self, index: u32) -> &[u8] {
+        let start = self.commit_data_ofs + index as usize * COMMIT_DATA_ENTRY_LEN;
+        &self.data[start..start + COMMIT_DATA_ENTRY_LEN]
+    }
+
+    fn extra_edge(&self, index: u32) -> Option<u32> {
+        let range = self.extra_edges.as_ref()?;
+        let start = range.start + index as usize * N32_SIZE;
+        if start + N32_SIZE > range.end {
+            return None;
+        }
+        Some(BigEndian::read_u32(&self.data[start..]))
+    }
+}
+
+impl<'a> Commit<'a> {
+    /// The position of this commit in its graph.
+    pub fn position(&self) -> Position {
+        self.base_position + self.file_position
+    }
+
+    pub fn id(&self) -> borrowed::Id<'a> {
+        self.file.id_at(self.file_position)
+    }
+
+    /// The id of the tree this commit points to.
+    pub fn root_tree_id(&self) -> borrowed::Id<'a> {
+        borrowed::Id::try_from(&self.data()[..SHA1_SIZE]).expect("20 bytes SHA1 to be alright")
+    }
+
+    /// Iterate the positions of all parents of this commit in the graph, in the order they appear in the commit.
+    pub fn iter_parents(&self) -> Parents<'a> {
+        Parents {
+            commit: *self,
+            state: ParentsState::First,
+        }
+    }
+
+    /// The generation number of this commit, which is one more than the largest generation number of its parents,
+    /// and 1 for commits without parents.
+    pub fn generation(&self) -> u32 {
+        BigEndian::read_u32(&self.data()[SHA1_SIZE + N32_SIZE * 2..]) >> GENERATION_SHIFT
+    }
+
+    /// The time of the commit in seconds since epoch, as recorded in its committer signature.
+    pub fn committer_timestamp(&self) -> u64 {
+        let data = &self.data()[SHA1_SIZE + N32_SIZE * 2..];
+        let high_bits = BigEndian::read_u32(data) & TIME_HIGH_BITS_MASK;
+        (high_bits as u64) << 32 | BigEndian::read_u32(&data[N32_SIZE..]) as u64
+    }
+
+    fn data(&self) -> &'a [u8] {
+        self.file.commit_data(self.file_position)
+    }
+
+    fn parent_at(&self, index: usize) -> u32 {
+        BigEndian::read_u32(&self.data()[SHA1_SIZE + N32_SIZE * index..])
+    }
+
+    fn parent_position(&self, parent: u32) -> Result<Position, Error> {
+        if parent >= self.base_position + self.file.num_commits {
+            return Err(Error::ParentOutOfBounds {
+                id: self.id().into(),
+                parent,
+            });
+        }
+        Ok(parent)
+    }
+}
+
+enum ParentsState {
+    First,
+    Second,
+    ExtraEdge(u32),
+    Done,
+}
+
+/// An iterator over the [positions][Position] of the parents of a [`Commit`].
+pub struct Parents<'a> {
+    commit: Commit<'a>,
+    state: ParentsState,
+}
+
+impl<'a> Iterator for Parents<'a> {
+    type Item = Result<Position, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let parent = match self.state {
+            ParentsState::First => {
+                let parent = self.commit.parent_at(0);
+                if parent == NO_PARENT {
+                    self.state = ParentsState::Done;
+                    return None;
+                }
+                self.state = ParentsState::Second;
+                parent
+            }
+            ParentsState::Second => {
+                let parent = self.commit.parent_at(1);
+                if parent == NO_PARENT {
+                    self.state = ParentsState::Done;
+                    return None;
+                }
+                if parent & N32_HIGH_BIT == N32_HIGH_BIT {
+                    self.state = ParentsState::ExtraEdge(parent ^ N32_HIGH_BIT);
+                    return self.next();
+                }
+                self.state = ParentsState::Done;
+                parent
+            }
+            ParentsState::ExtraEdge(index) => {
+                let commit = self.commit;
+                if commit.file.extra_edges.is_none() {
+                    self.state = ParentsState::Done;
+                    return Some(Err(Error::MissingExtraEdges { id: commit.id().into() }));
+                }
+                let parent = match commit.file.extra_edge(index) {
+                    Some(parent) => parent,
+                    None => {
+                        self.state = ParentsState::Done;
+                        return Some(Err(Error::ExtraEdgesOutOfBounds {
+                            id: commit.id().into(),
+                            index,
+                        }));
+                    }
+                };
+                if parent & N32_HIGH_BIT == N32_HIGH_BIT {
+                    self.state = ParentsState::Done;
+                    parent ^ N32_HIGH_BIT
+                } else {
+                    self.state = ParentsState::ExtraEdge(index + 1);
+                    parent
+                }
+            }
+            ParentsState::Done => return None,
+        };
+        Some(self.commit.parent_position(parent))
+    }
+}
@@ -0,0 +1,234 @@
+use crate::{
+    chunk_file,
+    commit_graph::{self, chunk, FAN_LEN, OBJECT_HASH_SHA1, SIGNATURE, VERSION},
+};
+use byteorder::{BigEndian, ByteOrder};
+use filebuffer::FileBuffer;
+use git_object::{bstr::ByteSlice, owned, HashKind, SHA1_SIZE};
+use quick_error::quick_error;
+use std::{
+    convert::TryFrom,
+    mem::size_of,
+    path::{Path, PathBuf},
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: std::io::Error, path: std::path::PathBuf) {
+            display("Could not open commit-graph file at '{}'", path.display())
+            source(err)
+        }
+        Corrupt(msg: String) {
+            display("{}", msg)
+        }
+        UnsupportedVersion(version: u8) {
+            display("Unsupported commit-graph version: {}", version)
+        }
+        UnsupportedObjectHash(id: u8) {
+            display("Unsupported object hash with id {}", id)
+        }
+        Chunk(err: chunk_file::Error) {
+            display("Could not read the chunks of the commit-graph file")
+            from()
+            source(err)
+        }
+        NotFound(info_dir: PathBuf) {
+            display("Neither a commit-graph file nor a chain of commit-graphs was found in '{}'", info_dir.display())
+        }
+        ChainMismatch(path: PathBuf) {
+            display("The commit-graph at '{}' doesn't fit into the chain of graph files it is part of", path.display())
+        }
+    }
+}
+
+const N32_SIZE: usize = size_of::<u32>();
+const N64_SIZE: usize = size_of::<u64>();
+pub(crate) const HEADER_LEN: usize = SIGNATURE.len() + 4;
+/// The root tree id, two parent positions, and the generation number along with the commit time.
+pub(crate) const COMMIT_DATA_ENTRY_LEN: usize = SHA1_SIZE + N32_SIZE * 2 + N64_SIZE;
+
+pub(crate) const GRAPH_FILE_NAME: &str = "commit-graph";
+pub(crate) const CHAIN_DIR_NAME: &str = "commit-graphs";
+pub(crate) const CHAIN_FILE_NAME: &str = "commit-graph-chain";
+
+/// Instantiation
+impl commit_graph::File {
+    pub fn at(path: impl AsRef<Path>) -> Result<commit_graph::File, Error> {
+        Self::try_from(path.as_ref())
+    }
+}
+
+impl TryFrom<&Path> for commit_graph::File {
+    type Error = Error;
+
+    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+        let data = FileBuffer::open(path).map_err(|e| Error::Io(e, path.to_owned()))?;
+        if data.len() < HEADER_LEN + chunk_file::TABLE_ENTRY_LEN + SHA1_SIZE {
+            return Err(Error::Corrupt(format!(
+                "commit-graph of size {} is too small for even an empty graph",
+                data.len()
+            )));
+        }
+        let (sig, header) = data[..HEADER_LEN].split_at(SIGNATURE.len());
+        if sig != SIGNATURE {
+            return Err(Error::Corrupt("Invalid signature, expected a commit-graph file".into()));
+        }
+        let version = header[0];
+        if version != VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+        let hash_kind = match header[1] {
+            OBJECT_HASH_SHA1 => HashKind::Sha1,
+            id => return Err(Error::UnsupportedObjectHash(id)),
+        };
+        let num_chunks = header[2] as usize;
+        let num_base_graphs = header[3] as usize;
+
+        let chunks = chunk_file::Table::from_bytes(&data, HEADER_LEN, num_chunks, hash_kind)?;
+
+        let fan_range = chunks.required_range(chunk::OID_FANOUT)?;
+        if fan_range.len() != FAN_LEN * N32_SIZE {
+            return Err(Error::Corrupt(format!(
+                "The fan-out table has {} bytes, but {} were expected",
+                fan_range.len(),
+                FAN_LEN * N32_SIZE
+            )));
+        }
+        let mut fan = [0; FAN_LEN];
+        for (c, f) in data[fan_range].chunks(N32_SIZE).zip(fan.iter_mut()) {
+            *f = BigEndian::read_u32(c);
+        }
+        let num_commits = fan[FAN_LEN - 1];
+
+        let lookup_range = chunks.required_range(chunk::OID_LOOKUP)?;
+        if lookup_range.len() != num_commits as usize * SHA1_SIZE {
+            return Err(Error::Corrupt(format!(
+                "The commit id lookup table has {} bytes, but {} commits need {} bytes",
+                lookup_range.len(),
+                num_commits,
+                num_commits as usize * SHA1_SIZE
+            )));
+        }
+        let commit_data_range = chunks.required_range(chunk::COMMIT_DATA)?;
+        if commit_data_range.len() != num_commits as usize * COMMIT_DATA_ENTRY_LEN {
+            return Err(Error::Corrupt(format!(
+                "The commit data table has {} bytes, but {} commits need {} bytes",
+                commit_data_range.len(),
+                num_commits,
+                num_commits as usize * COMMIT_DATA_ENTRY_LEN
+            )));
+        }
+        let extra_edges = match chunks.range(chunk::EXTRA_EDGES) {
+            Some(range) if range.len() % N32_SIZE != 0 => {
+                return Err(Error::Corrupt(format!(
+                    "The extra edges list size of {} bytes is not a multiple of {}",
+                    range.len(),
+                    N32_SIZE
+                )))
+            }
+            range => range,
+        };
+        let base_graph_ids = match chunks.range(chunk::BASE_GRAPHS) {
+            None if num_base_graphs == 0 => Vec::new(),
+            None => return Err(chunk_file::Error::MissingChunk(chunk::BASE_GRAPHS).into()),
+            Some(range) if range.len() != num_base_graphs * SHA1_SIZE => {
+                return Err(Error::Corrupt(format!(
+                    "The base graphs chunk has {} bytes, but {} base graphs need {} bytes",
+                    range.len(),
+                    num_base_graphs,
+                    num_base_graphs * SHA1_SIZE
+                )))
+            }
+            Some(range) => data[range].chunks(SHA1_SIZE).map(owned::Id::from_20_bytes).collect(),
+        };
+
+        Ok(commit_graph::File {
+            path: path.to_owned(),
+            version,
+            hash_kind,
+            num_commits,
+            fan,
+            lookup_ofs: lookup_range.start,
+            commit_data_ofs: commit_data_range.start,
+            extra_edges,
+            base_graph_ids,
+            data,
+        })
+    }
+}
+
+/// Instantiation
+impl commit_graph::Graph {
+    /// Open the commit-graph in `info_dir`, usually `.git/objects/info`. Like git, a single `commit-graph` file is
+    /// preferred over a chain of split graph files in the `commit-graphs` directory.
+    pub fn at(info_dir: impl AsRef<Path>) -> Result<commit_graph::Graph, Error> {
+        let info_dir = info_dir.as_ref();
+        let graph_path = info_dir.join(GRAPH_FILE_NAME);
+        if graph_path.is_file() {
+            return Self::from_file(graph_path);
+        }
+        let chain_path = info_dir.join(CHAIN_DIR_NAME).join(CHAIN_FILE_NAME);
+        if chain_path.is_file() {
+            return Self::from_chain_file(chain_path);
+        }
+        Err(Error::NotFound(info_dir.to_owned()))
+    }
+
+    /// Open a graph consisting of the single, self-contained commit-graph file at `path`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<commit_graph::Graph, Error> {
+        Self::new(vec![commit_graph::File::at(path)?])
+    }
+
+    /// Open all graph files listed in the `commit-graph-chain` file at `path`, which are expected in the same directory.
+    pub fn from_chain_file(path: impl AsRef<Path>) -> Result<commit_graph::Graph, Error> {
+        let path = path.as_ref();
+        let chain = std::fs::read(path).map_err(|err| Error::Io(err, path.to_owned()))?;
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        let mut files = Vec::new();
+        for line in chain.lines().filter(|line| !line.is_empty()) {
+            let id = owned::Id::from_hex(line).map_err(|_| {
+                Error::Corrupt(format!(
+                    "The commit-graph chain at '{}' contains the invalid line {:?}",
+                    path.display(),
+                    line.as_bstr()
+                ))
+            })?;
+            let file = commit_graph::File::at(dir.join(format!("graph-{}.graph", id)))?;
+            if file.checksum() != id {
+                return Err(Error::ChainMismatch(file.path));
+            }
+            files.push(file);
+        }
+        Self::new(files)
+    }
+
+    /// Create a graph from all of its `files`, base first, assuring each one builds on all files before it.
+    pub fn new(files: Vec<commit_graph::File>) -> Result<commit_graph::Graph, Error> {
+        if files.is_empty() {
+            return Err(Error::Corrupt("A commit-graph needs at least one graph file".into()));
+        }
+        for (index, file) in files.iter().enumerate() {
+            let bases_match = file.base_graph_ids.len() == index
+                && file
+                    .base_graph_ids
+                    .iter()
+                    .zip(&files[..index])
+                    .all(|(id, base)| *id == base.checksum());
+            if !bases_match {
+                return Err(Error::ChainMismatch(file.path.clone()));
+            }
+        }
+        if files
+            .iter()
+            .try_fold(0u32, |sum, file| sum.checked_add(file.num_commits))
+            .is_none()
+        {
+            return Err(Error::Corrupt(format!(
+                "The commit-graph chain holds more than {} commits",
+                u32::MAX
+            )));
+        }
+        Ok(commit_graph::Graph { files })
+    }
+}
@@ -0,0 +1,83 @@
+//! Commit-graph files, which store the parents, root tree, commit time and generation number of commits to speed up
+//! history traversal without having to decode commits from packs.
+//!
+//! A graph is either a single file at `objects/info/commit-graph`, or a chain of split files in
+//! `objects/info/commit-graphs/`, each of which builds on all files before it.
+use filebuffer::FileBuffer;
+use git_object::{owned, HashKind};
+use std::{ops::Range, path::PathBuf};
+
+const SIGNATURE: &[u8] = b"CGPH";
+const VERSION: u8 = 1;
+const OBJECT_HASH_SHA1: u8 = 1;
+const FAN_LEN: usize = 256;
+
+/// The identifiers of all chunks we know, with the ones we ignore being skipped.
+pub(crate) mod chunk {
+    pub use crate::chunk_file::Id;
+
+    pub const OID_FANOUT: Id = *b"OIDF";
+    pub const OID_LOOKUP: Id = *b"OIDL";
+    pub const COMMIT_DATA: Id = *b"CDAT";
+    pub const EXTRA_EDGES: Id = *b"EDGE";
+    pub const BASE_GRAPHS: Id = *b"BASE";
+}
+
+/// The position of a commit in a [`Graph`], counting through all of its files in order, starting at the base.
+pub type Position = u32;
+
+/// A single memory mapped commit-graph file, which may be part of a chain of split files.
+pub struct File {
+    data: FileBuffer,
+    path: PathBuf,
+    version: u8,
+    hash_kind: HashKind,
+    num_commits: u32,
+    fan: [u32; FAN_LEN],
+    lookup_ofs: usize,
+    commit_data_ofs: usize,
+    extra_edges: Option<Range<usize>>,
+    base_graph_ids: Vec<owned::Id>,
+}
+
+impl File {
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+    pub fn hash_kind(&self) -> HashKind {
+        self.hash_kind
+    }
+    pub fn num_commits(&self) -> u32 {
+        self.num_commits
+    }
+    /// The checksums of all graph files this one builds on, base first. It's empty if this file stands on its own.
+    pub fn base_graph_ids(&self) -> &[owned::Id] {
+        &self.base_graph_ids
+    }
+}
+
+/// A commit-graph made of one or more [files][File], providing access to commits by [`Position`] or id.
+pub struct Graph {
+    files: Vec<File>,
+}
+
+impl Graph {
+    /// All files of the graph, base first.
+    pub fn files(&self) -> &[File] {
+        &self.files
+    }
+    pub fn num_commits(&self) -> u32 {
+        self.files.iter().map(|file| file.num_commits).sum()
+    }
+}
+
+pub mod init;
+
+mod access;
+pub use access::{Commit, Error, Parents};
+
+pub mod verify;
+pub mod write;
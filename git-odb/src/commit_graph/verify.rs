@@ -0,0 +1,204 @@
+use crate::{
+    commit_graph::{self, access},
+    pack,
+};
+use git_features::progress::{self, Progress};
+use git_object::{borrowed, owned, SHA1_SIZE};
+use quick_error::quick_error;
+use std::path::PathBuf;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Mismatch { path: PathBuf, expected: owned::Id, actual: owned::Id } {
+            display("commit-graph checksum mismatch in '{}': expected {}, got {}", path.display(), expected, actual)
+        }
+        OutOfOrder { path: PathBuf, index: u32 } {
+            display("The commit id at index {} in '{}' is not sorted correctly", index, path.display())
+        }
+        Parents(err: access::Error) {
+            display("The parents of a commit could not be read")
+            from()
+            source(err)
+        }
+        ObjectNotFound(id: owned::Id) {
+            display("Commit {} is not contained in the object database", id)
+        }
+        Locate(err: Box<dyn std::error::Error + Send + Sync>, id: owned::Id) {
+            display("Commit {} could not be retrieved from the object database", id)
+            source(&**err)
+        }
+        NotACommit { id: owned::Id, kind: git_object::Kind } {
+            display("Object {} is expected to be a commit, but is a {}", id, kind)
+        }
+        Parse(err: borrowed::Error, id: owned::Id) {
+            display("Commit {} could not be parsed", id)
+            source(err)
+        }
+        InvalidParent(id: owned::Id) {
+            display("A parent of commit {} is not a valid object id", id)
+        }
+        TreeMismatch { id: owned::Id, expected: owned::Id, actual: owned::Id } {
+            display("Commit {} points to tree {}, but the commit-graph has {}", id, expected, actual)
+        }
+        ParentsMismatch { id: owned::Id, expected: Vec<owned::Id>, actual: Vec<owned::Id> } {
+            display("Commit {} has parents {:?}, but the commit-graph has {:?}", id, expected, actual)
+        }
+        TimeMismatch { id: owned::Id, expected: u64, actual: u64 } {
+            display("Commit {} was committed at {}, but the commit-graph has {}", id, expected, actual)
+        }
+        GenerationMismatch { id: owned::Id, expected: u32, actual: u32 } {
+            display("Commit {} should have generation number {}, but the commit-graph has {}", id, expected, actual)
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Outcome {
+    pub num_commits: u32,
+    /// The amount of graph files, which is more than one for a chain of split graphs
+    pub num_files: u32,
+    /// The largest generation number of all commits, which is the length of the longest path through the history
+    pub max_generation: u32,
+}
+
+/// Verify and validate the content of a single commit-graph file
+impl commit_graph::File {
+    pub fn checksum(&self) -> owned::Id {
+        owned::Id::from_20_bytes(&self.data[self.data.len() - SHA1_SIZE..])
+    }
+
+    pub fn verify_checksum(&self, mut progress: impl Progress) -> Result<owned::Id, Error> {
+        let data_len_without_trailer = self.data.len() - SHA1_SIZE;
+        let actual = match crate::hash::bytes_of_file(
+            &self.path,
+            data_len_without_trailer,
+            git_object::HashKind::Sha1,
+            &mut progress,
+        ) {
+            Ok(id) => id,
+            Err(_io_err) => {
+                let start = std::time::Instant::now();
+                let mut hasher = git_features::hash::Sha1::default();
+                hasher.update(&self.data[..data_len_without_trailer]);
+                progress.inc_by(data_len_without_trailer);
+                progress.show_throughput(start);
+                owned::Id::new_sha1(hasher.digest())
+            }
+        };
+
+        let expected = self.checksum();
+        if actual == expected {
+            Ok(actual)
+        } else {
+            Err(Error::Mismatch {
+                path: self.path.clone(),
+                actual,
+                expected,
+            })
+        }
+    }
+}
+
+/// Verify and validate the content of all commit-graph files
+impl commit_graph::Graph {
+    /// Verify the checksums of all graph files and compare each commit in the graph to the commit parsed from `db`,
+    /// assuring root tree, parents and commit time match and that generation numbers are computed correctly.
+    pub fn verify_integrity(&self, db: impl crate::Locate, progress: impl Progress) -> Result<Outcome, Error> {
+        let mut root = progress;
+        for file in &self.files {
+            file.verify_checksum(root.add_child(format!(
+                "Sha1 of {}",
+                file.path.file_name().unwrap_or_else(|| file.path.as_os_str()).to_string_lossy()
+            )))?;
+        }
+
+        let mut progress = root.add_child("Checking commits");
+        progress.init(Some(self.num_commits() as usize), progress::count("commits"));
+        let mut buf = Vec::new();
+        let mut max_generation = 0;
+        for file in &self.files {
+            for index in 1..file.num_commits {
+                if file.id_at(index - 1) >= file.id_at(index) {
+                    return Err(Error::OutOfOrder {
+                        path: file.path.clone(),
+                        index,
+                    });
+                }
+            }
+        }
+        for commit in self.iter_commits() {
+            let id = commit.id();
+            let object = db
+                .locate(id, &mut buf, &mut pack::cache::DecodeEntryNoop)
+                .ok_or_else(|| Error::ObjectNotFound(id.into()))?
+                .map_err(|err| Error::Locate(Box::new(err), id.into()))?;
+            if object.kind != git_object::Kind::Commit {
+                return Err(Error::NotACommit {
+                    id: id.into(),
+                    kind: object.kind,
+                });
+            }
+            let parsed = borrowed::Commit::from_bytes(object.data).map_err(|err| Error::Parse(err, id.into()))?;
+
+            let expected = parsed.tree();
+            let actual = commit.root_tree_id().into();
+            if expected != actual {
+                return Err(Error::TreeMismatch {
+                    id: id.into(),
+                    expected,
+                    actual,
+                });
+            }
+
+            let mut parent_generation = 0;
+            let mut actual = Vec::new();
+            for parent in commit.iter_parents() {
+                let parent = self.commit_at(parent?);
+                parent_generation = parent_generation.max(parent.generation());
+                actual.push(parent.id().into());
+            }
+            let expected: Vec<_> = parsed
+                .parents
+                .iter()
+                .map(|hex| owned::Id::from_hex(hex).map_err(|_| Error::InvalidParent(id.into())))
+                .collect::<Result<_, _>>()?;
+            if expected != actual {
+                return Err(Error::ParentsMismatch {
+                    id: id.into(),
+                    expected,
+                    actual,
+                });
+            }
+
+            let expected = parsed.committer.time.time as u64;
+            let actual = commit.committer_timestamp();
+            if expected != actual {
+                return Err(Error::TimeMismatch {
+                    id: id.into(),
+                    expected,
+                    actual,
+                });
+            }
+
+            let expected = (parent_generation + 1).min(access::GENERATION_NUMBER_MAX);
+            let actual = commit.generation();
+            if expected != actual {
+                return Err(Error::GenerationMismatch {
+                    id: id.into(),
+                    expected,
+                    actual,
+                });
+            }
+            max_generation = max_generation.max(actual);
+            progress.inc();
+        }
+
+        Ok(Outcome {
+            num_commits: self.num_commits(),
+            num_files: self.files.len() as u32,
+            max_generation,
+        })
+    }
+}
@@ -0,0 +1,438 @@
+use crate::{
+    chunk_file,
+    commit_graph::{
+        self, access, chunk,
+        init::{self, CHAIN_DIR_NAME, CHAIN_FILE_NAME, COMMIT_DATA_ENTRY_LEN, GRAPH_FILE_NAME},
+        Position, FAN_LEN, OBJECT_HASH_SHA1, SIGNATURE, VERSION,
+    },
+    hash, pack,
+};
+use byteorder::{BigEndian, WriteBytesExt};
+use git_features::progress::{self, Progress};
+use git_object::{borrowed, owned, SHA1_SIZE};
+use quick_error::quick_error;
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+use tempfile::NamedTempFile;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error) {
+            display("An IO error occurred when writing the commit-graph")
+            from()
+            source(err)
+        }
+        Persist(err: tempfile::PersistError) {
+            display("Could not move the commit-graph file into its desired place")
+            from()
+            source(err)
+        }
+        Init(err: init::Error) {
+            display("The existing chain of commit-graphs could not be loaded")
+            from()
+            source(err)
+        }
+        NotFound(id: owned::Id) {
+            display("Object {} is not contained in the object database", id)
+        }
+        Locate(err: Box<dyn std::error::Error + Send + Sync>, id: owned::Id) {
+            display("Object {} could not be retrieved from the object database", id)
+            source(&**err)
+        }
+        NotACommit { id: owned::Id, kind: git_object::Kind } {
+            display("Object {} is expected to be a commit or a tag pointing to one, but is a {}", id, kind)
+        }
+        Parse(err: borrowed::Error, id: owned::Id) {
+            display("Object {} could not be parsed", id)
+            source(err)
+        }
+        InvalidParent(id: owned::Id) {
+            display("A parent of commit {} is not a valid object id", id)
+        }
+        UnknownKind(id: owned::Id) {
+            display("The kind of object {} in the pack could not be determined", id)
+        }
+        TooManyCommits(num_commits: usize) {
+            display("Only {} commits can be stored in a commit-graph, found {}", access::NO_PARENT, num_commits)
+        }
+        TooManyBaseGraphs(num_base_graphs: usize) {
+            display("Only {} base graphs can be referenced by a commit-graph, found {}", u8::MAX, num_base_graphs)
+        }
+    }
+}
+
+/// Determines how a commit-graph is written into the `objects/info` directory.
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum Mode {
+    /// Write all commits into a single `commit-graph` file, replacing an existing one.
+    Single,
+    /// Write all commits that are not yet in the chain of split graph files into a new file, and add it to the chain.
+    Split,
+}
+
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Outcome {
+    /// The hash over all bytes of the commit-graph file, also found in its trailing bytes
+    pub graph_hash: owned::Id,
+    /// The amount of commits written into the file
+    pub num_commits: u32,
+    /// The amount of commits in all graph files the written one builds on
+    pub num_base_commits: u32,
+}
+
+struct Entry {
+    id: owned::Id,
+    tree: owned::Id,
+    parents: Vec<owned::Id>,
+    time: u32,
+}
+
+/// Return the ids of all commits in `bundle`, which can be used as tips to write a commit-graph for all commits in a pack.
+pub fn commits_in_bundle(bundle: &pack::Bundle) -> Result<Vec<owned::Id>, Error> {
+    let mut ids = Vec::new();
+    for index_position in 0..bundle.index.num_objects() {
+        match bundle.kind_at_index(index_position) {
+            Some(git_object::Kind::Commit) => ids.push(bundle.index.oid_at_index(index_position).into()),
+            Some(_) => {}
+            None => return Err(Error::UnknownKind(bundle.index.oid_at_index(index_position).into())),
+        }
+    }
+    Ok(ids)
+}
+
+/// Writing commit-graph files
+impl commit_graph::File {
+    /// Write a commit-graph file with all commits reachable from `tips` to `out`, using `db` to find and parse them.
+    /// Tags among the `tips` are peeled to the commit they point to.
+    ///
+    /// If `base` is set, only commits not contained in it are written, and the file is meant to be the next one in the
+    /// chain of split graph files of `base`. Otherwise the written file is self-contained.
+    pub fn write_from_tips<'a>(
+        db: impl crate::Locate,
+        tips: impl IntoIterator<Item = borrowed::Id<'a>>,
+        base: Option<&commit_graph::Graph>,
+        out: impl io::Write,
+        mut progress: impl Progress,
+    ) -> Result<Outcome, Error> {
+        let num_base_commits = base.map(|base| base.num_commits()).unwrap_or(0);
+        let in_base = |id: &owned::Id| base.and_then(|base| base.lookup(id.to_borrowed()));
+        let mut buf = Vec::new();
+        let mut cache = pack::cache::DecodeEntryLRU::default();
+
+        progress.init(Some(3), progress::steps());
+        let mut entries = {
+            let mut progress = progress.add_child("walking commits");
+            progress.init(None, progress::count("commits"));
+            let mut entries = HashMap::new();
+            let mut stack = Vec::new();
+            for tip in tips {
+                stack.push(peel_to_commit(&db, tip.into(), &mut buf, &mut cache)?);
+            }
+            while let Some(id) = stack.pop() {
+                if entries.contains_key(&id) || in_base(&id).is_some() {
+                    continue;
+                }
+                let object = locate(&db, id.to_borrowed(), &mut buf, &mut cache)?;
+                if object.kind != git_object::Kind::Commit {
+                    return Err(Error::NotACommit { id, kind: object.kind });
+                }
+                let commit = borrowed::Commit::from_bytes(object.data).map_err(|err| Error::Parse(err, id.clone()))?;
+                let parents = commit
+                    .parents
+                    .iter()
+                    .map(|hex| owned::Id::from_hex(hex).map_err(|_| Error::InvalidParent(id.clone())))
+                    .collect::<Result<_, _>>()?;
+                let entry = Entry {
+                    id: id.clone(),
+                    tree: commit.tree(),
+                    parents,
+                    time: commit.committer.time.time,
+                };
+                stack.extend(entry.parents.iter().cloned());
+                entries.insert(id, entry);
+                progress.inc();
+            }
+            entries.into_values().collect::<Vec<_>>()
+        };
+        if num_base_commits as usize + entries.len() >= access::NO_PARENT as usize {
+            return Err(Error::TooManyCommits(num_base_commits as usize + entries.len()));
+        }
+        let base_graph_ids: Vec<_> = base
+            .map(|base| base.files().iter().map(|file| file.checksum()).collect())
+            .unwrap_or_default();
+        if base_graph_ids.len() > u8::MAX as usize {
+            return Err(Error::TooManyBaseGraphs(base_graph_ids.len()));
+        }
+
+        progress.inc();
+        let (parents, generations) = {
+            let _info = progress.add_child("computing generation numbers");
+            entries.sort_by(|a, b| a.id.cmp(&b.id));
+            let position_by_id: HashMap<_, _> = entries
+                .iter()
+                .enumerate()
+                .map(|(index, entry)| (entry.id.clone(), num_base_commits + index as Position))
+                .collect();
+            let parents: Vec<Vec<Position>> = entries
+                .iter()
+                .map(|entry| {
+                    entry
+                        .parents
+                        .iter()
+                        .map(|id| {
+                            position_by_id
+                                .get(id)
+                                .copied()
+                                .or_else(|| in_base(id))
+                                .expect("all parents to be walked or in the base graph")
+                        })
+                        .collect()
+                })
+                .collect();
+            let generations = compute_generations(&parents, num_base_commits, base);
+            (parents, generations)
+        };
+
+        progress.inc();
+        let _info = progress.add_child("writing commit-graph");
+        let graph_hash = write_chunks(&entries, &parents, &generations, &base_graph_ids, out)?;
+
+        progress.inc();
+        Ok(Outcome {
+            graph_hash,
+            num_commits: entries.len() as u32,
+            num_base_commits,
+        })
+    }
+}
+
+/// Writing commit-graphs into the `objects/info` directory
+impl commit_graph::Graph {
+    /// Write a commit-graph with all commits reachable from `tips` into `info_dir`, usually `.git/objects/info`,
+    /// using `db` to find and parse them.
+    ///
+    /// With [`Mode::Split`], a new graph file with all commits that aren't in the existing chain yet is added to it,
+    /// returning `None` if there are no such commits. Existing graph files are never merged. As a single
+    /// `commit-graph` file would take precedence over the chain, it is removed.
+    pub fn write_to_info_dir<'a>(
+        info_dir: impl AsRef<Path>,
+        db: impl crate::Locate,
+        tips: impl IntoIterator<Item = borrowed::Id<'a>>,
+        mode: Mode,
+        progress: impl Progress,
+    ) -> Result<Option<Outcome>, Error> {
+        let info_dir = info_dir.as_ref();
+        match mode {
+            Mode::Single => {
+                fs::create_dir_all(info_dir)?;
+                let mut file = NamedTempFile::new_in(info_dir)?;
+                let outcome =
+                    commit_graph::File::write_from_tips(db, tips, None, io::BufWriter::new(&mut file), progress)?;
+                file.persist(info_dir.join(GRAPH_FILE_NAME))?;
+                Ok(Some(outcome))
+            }
+            Mode::Split => {
+                let chain_dir = info_dir.join(CHAIN_DIR_NAME);
+                fs::create_dir_all(&chain_dir)?;
+                let chain_path = chain_dir.join(CHAIN_FILE_NAME);
+                let base = if chain_path.is_file() {
+                    Some(commit_graph::Graph::from_chain_file(&chain_path)?)
+                } else {
+                    None
+                };
+
+                let mut file = NamedTempFile::new_in(&chain_dir)?;
+                let outcome = commit_graph::File::write_from_tips(
+                    db,
+                    tips,
+                    base.as_ref(),
+                    io::BufWriter::new(&mut file),
+                    progress,
+                )?;
+                if outcome.num_commits == 0 {
+                    return Ok(None);
+                }
+                file.persist(chain_dir.join(format!("graph-{}.graph", outcome.graph_hash)))?;
+
+                let mut chain = NamedTempFile::new_in(&chain_dir)?;
+                for file in base.iter().flat_map(|base| base.files()) {
+                    writeln!(chain, "{}", file.checksum())?;
+                }
+                writeln!(chain, "{}", outcome.graph_hash)?;
+                chain.persist(&chain_path)?;
+
+                match fs::remove_file(info_dir.join(GRAPH_FILE_NAME)) {
+                    Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err.into()),
+                    _ => {}
+                }
+                Ok(Some(outcome))
+            }
+        }
+    }
+}
+
+fn locate<'a>(
+    db: &impl crate::Locate,
+    id: borrowed::Id,
+    buf: &'a mut Vec<u8>,
+    cache: &mut impl pack::cache::DecodeEntry,
+) -> Result<pack::Object<'a>, Error> {
+    db.locate(id, buf, cache)
+        .ok_or_else(|| Error::NotFound(id.into()))?
+        .map_err(|err| Error::Locate(Box::new(err), id.into()))
+}
+
+fn peel_to_commit(
+    db: &impl crate::Locate,
+    mut id: owned::Id,
+    buf: &mut Vec<u8>,
+    cache: &mut impl pack::cache::DecodeEntry,
+) -> Result<owned::Id, Error> {
+    loop {
+        let object = locate(db, id.to_borrowed(), buf, cache)?;
+        match object.kind {
+            git_object::Kind::Commit => return Ok(id),
+            git_object::Kind::Tag => {
+                id = borrowed::Tag::from_bytes(object.data)
+                    .map_err(|err| Error::Parse(err, id))?
+                    .target()
+            }
+            kind => return Err(Error::NotACommit { id, kind }),
+        }
+    }
+}
+
+/// Compute the generation number of each commit with the given `parents`, which is one more than the largest
+/// generation number of its parents, with parents before `num_base_commits` being looked up in `base`.
+fn compute_generations(
+    parents: &[Vec<Position>],
+    num_base_commits: u32,
+    base: Option<&commit_graph::Graph>,
+) -> Vec<u32> {
+    let parent_generation = |generations: &[u32], position: Position| match position.checked_sub(num_base_commits) {
+        Some(index) => generations[index as usize],
+        None => base
+            .expect("positions below the amount of base commits to be in the base graph")
+            .commit_at(position)
+            .generation(),
+    };
+    // A generation number of 0 means it wasn't computed yet
+    let mut generations = vec![0; parents.len()];
+    let mut stack = Vec::new();
+    for index in 0..parents.len() {
+        stack.push(index);
+        while let Some(&index) = stack.last() {
+            if generations[index] != 0 {
+                stack.pop();
+                continue;
+            }
+            let num_pending = stack.len();
+            let mut max_parent_generation = 0;
+            for &parent in &parents[index] {
+                let generation = parent_generation(&generations, parent);
+                if generation == 0 && parent >= num_base_commits {
+                    stack.push((parent - num_base_commits) as usize);
+                } else {
+                    max_parent_generation = max_parent_generation.max(generation);
+                }
+            }
+            if stack.len() == num_pending {
+                generations[index] = (max_parent_generation + 1).min(access::GENERATION_NUMBER_MAX);
+                stack.pop();
+            }
+        }
+    }
+    generations
+}
+
+fn write_chunks(
+    entries_sorted_by_id: &[Entry],
+    parents: &[Vec<Position>],
+    generations: &[u32],
+    base_graph_ids: &[owned::Id],
+    out: impl io::Write,
+) -> io::Result<owned::Id> {
+    let num_extra_edges: usize = parents
+        .iter()
+        .filter(|parents| parents.len() > 2)
+        .map(|parents| parents.len() - 1)
+        .sum();
+    let mut chunks = vec![
+        (chunk::OID_FANOUT, FAN_LEN * 4),
+        (chunk::OID_LOOKUP, entries_sorted_by_id.len() * SHA1_SIZE),
+        (chunk::COMMIT_DATA, entries_sorted_by_id.len() * COMMIT_DATA_ENTRY_LEN),
+    ];
+    if num_extra_edges != 0 {
+        chunks.push((chunk::EXTRA_EDGES, num_extra_edges * 4));
+    }
+    if !base_graph_ids.is_empty() {
+        chunks.push((chunk::BASE_GRAPHS, base_graph_ids.len() * SHA1_SIZE));
+    }
+
+    let mut out = io::BufWriter::with_capacity(8 * 4096, hash::Write::new(out, git_object::HashKind::Sha1));
+    out.write_all(SIGNATURE)?;
+    out.write_all(&[
+        VERSION,
+        OBJECT_HASH_SHA1,
+        chunks.len() as u8,
+        base_graph_ids.len() as u8,
+    ])?;
+
+    chunk_file::write_table(&mut out, init::HEADER_LEN, &chunks)?;
+
+    let mut entries_so_far = 0;
+    for byte in 0u8..=255 {
+        entries_so_far += entries_sorted_by_id[entries_so_far..]
+            .iter()
+            .take_while(|entry| entry.id.as_slice()[0] == byte)
+            .count();
+        out.write_u32::<BigEndian>(entries_so_far as u32)?;
+    }
+
+    for entry in entries_sorted_by_id {
+        out.write_all(entry.id.as_slice())?;
+    }
+
+    let mut extra_edges_so_far = 0;
+    for ((entry, parents), generation) in entries_sorted_by_id.iter().zip(parents).zip(generations) {
+        out.write_all(entry.tree.as_slice())?;
+        out.write_u32::<BigEndian>(parents.first().copied().unwrap_or(access::NO_PARENT))?;
+        out.write_u32::<BigEndian>(match parents.len() {
+            0 | 1 => access::NO_PARENT,
+            2 => parents[1],
+            num_parents => {
+                let first_edge = extra_edges_so_far as u32 | access::N32_HIGH_BIT;
+                extra_edges_so_far += num_parents - 1;
+                first_edge
+            }
+        })?;
+        // Commit times are stored with 34 bits, and ours only have 32
+        out.write_u32::<BigEndian>(generation << access::GENERATION_SHIFT)?;
+        out.write_u32::<BigEndian>(entry.time)?;
+    }
+
+    for parents in parents.iter().filter(|parents| parents.len() > 2) {
+        let (last, others) = parents[1..].split_last().expect("more than two parents");
+        for parent in others {
+            out.write_u32::<BigEndian>(*parent)?;
+        }
+        out.write_u32::<BigEndian>(*last | access::N32_HIGH_BIT)?;
+    }
+
+    for id in base_graph_ids {
+        out.write_all(id.as_slice())?;
+    }
+
+    let mut out = out.into_inner()?;
+    let hash: owned::Id = out.hash.digest();
+    out.inner.write_all(hash.as_slice())?;
+    out.inner.flush()?;
+    Ok(hash)
+}
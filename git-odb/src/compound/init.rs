@@ -0,0 +1,87 @@
+use crate::{alternate, compound, loose, pack};
+use quick_error::quick_error;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error, path: PathBuf) {
+            display("Could not list the pack directory at '{}'", path.display())
+            source(err)
+        }
+        Pack(err: pack::bundle::Error, path: PathBuf) {
+            display("Could not open the pack with index at '{}'", path.display())
+            source(err)
+        }
+        Alternate(err: alternate::Error) {
+            display("Could not resolve the alternate object directories")
+            from()
+            source(err)
+        }
+    }
+}
+
+/// Instantiation
+impl compound::Db {
+    /// Open the object database at `objects_directory`, usually `.git/objects`, loading all packs in its `pack`
+    /// directory if there is one.
+    ///
+    /// Like git, packs are ordered by modification time with the most recent pack first, as newly written packs are
+    /// more likely to contain the objects that are looked up.
+    ///
+    /// All alternate object directories are opened as well, see [`alternate::resolve()`] for how they are found.
+    pub fn at(objects_directory: impl Into<PathBuf>) -> Result<compound::Db, Error> {
+        let mut db = Self::without_alternates(objects_directory)?;
+        db.alternates = alternate::resolve(&db.loose.path)?
+            .into_iter()
+            .map(Self::without_alternates)
+            .collect::<Result<_, _>>()?;
+        Ok(db)
+    }
+
+    /// Open the object database at `objects_directory` like [`at()`][compound::Db::at()], but ignore its alternates.
+    pub fn without_alternates(objects_directory: impl Into<PathBuf>) -> Result<compound::Db, Error> {
+        let loose = loose::Db::at(objects_directory);
+        let bundles = bundles_in_directory(&loose.path.join("pack"))?;
+        Ok(compound::Db {
+            loose,
+            bundles,
+            alternates: Vec::new(),
+            object_cache: None,
+        })
+    }
+
+    /// Cache up to `capacity_in_bytes` of decoded objects to speed up repeated lookups, see [`compound::ObjectCache`].
+    pub fn with_object_cache(mut self, capacity_in_bytes: usize) -> Self {
+        self.object_cache = Some(compound::ObjectCache::new(capacity_in_bytes));
+        self
+    }
+}
+
+fn bundles_in_directory(directory: &Path) -> Result<Vec<pack::Bundle>, Error> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(Error::Io(err, directory.to_owned())),
+    };
+    let mut bundles = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|err| Error::Io(err, directory.to_owned()))?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("idx") {
+            continue;
+        }
+        let bundle = pack::Bundle::at(&path).map_err(|err| Error::Pack(err, path.clone()))?;
+        let modified = fs::metadata(path.with_extension("pack"))
+            .and_then(|meta| meta.modified())
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        bundles.push((modified, path, bundle));
+    }
+    bundles.sort_by(|(a_modified, a_path, _), (b_modified, b_path, _)| {
+        b_modified.cmp(a_modified).then_with(|| a_path.cmp(b_path))
+    });
+    Ok(bundles.into_iter().map(|(_, _, bundle)| bundle).collect())
+}
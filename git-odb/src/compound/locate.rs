@@ -0,0 +1,279 @@
+use crate::{compound, loose, pack};
+use git_object::{borrowed, owned};
+use quick_error::quick_error;
+use std::cell::RefCell;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Loose(err: loose::db::locate::Error) {
+            display("An error occurred while obtaining a loose object")
+            from()
+            source(err)
+        }
+        Pack(err: pack::data::decode::Error) {
+            display("An error occurred while decoding an object from a pack")
+            from()
+            source(err)
+        }
+        PackStream(err: pack::data::stream::Error) {
+            display("An error occurred while streaming an object from a pack")
+            from()
+            source(err)
+        }
+        DeltaBaseDepthExceeded(id: owned::Id) {
+            display("Gave up resolving the delta base {} after finding {} bases outside of the pack of their delta", id, MAX_OUT_OF_PACK_BASES)
+        }
+    }
+}
+
+/// The most delta bases to find in another pack or among loose objects while locating a single object, which prevents
+/// endless recursion if objects in different packs are each other's delta base.
+const MAX_OUT_OF_PACK_BASES: usize = 128;
+
+/// Pack offsets are shifted by this amount of bits to make room for the pack id, as all packs share the same cache.
+const PACK_ID_SHIFT: u32 = 48;
+
+/// A cache which keeps the entries of one pack apart from the ones of all other packs that share the `inner` cache.
+struct PackCache<'a, C> {
+    pack_id: u64,
+    inner: &'a mut C,
+}
+
+impl<'a, C> pack::cache::DecodeEntry for PackCache<'a, C>
+where
+    C: pack::cache::DecodeEntry,
+{
+    fn put(
+        &mut self,
+        offset: u64,
+        data: &[u8],
+        kind: git_object::Kind,
+        compressed_size: usize,
+        delta_chain_length: u32,
+    ) {
+        self.inner.put(
+            self.pack_id << PACK_ID_SHIFT | offset,
+            data,
+            kind,
+            compressed_size,
+            delta_chain_length,
+        )
+    }
+
+    fn get(&mut self, offset: u64, out: &mut Vec<u8>) -> Option<(git_object::Kind, usize)> {
+        self.inner.get(self.pack_id << PACK_ID_SHIFT | offset, out)
+    }
+}
+
+/// Object lookup
+impl compound::Db {
+    /// Find an object matching `id` in any of our packs or among our loose objects, in that order, while placing its
+    /// raw, decoded data into `buffer`. If it can't be found, our alternates are searched in order.
+    ///
+    /// If there is an [object cache][compound::Db::object_cache], it is consulted first and receives every object
+    /// that is found elsewhere.
+    ///
+    /// Unlike [`pack::Bundle::locate()`], bases of `RefDelta` entries which are not in the same pack are looked up in
+    /// the entire database, including alternates.
+    pub fn locate<'a>(
+        &self,
+        id: borrowed::Id,
+        buffer: &'a mut Vec<u8>,
+        pack_cache: &mut impl pack::cache::DecodeEntry,
+    ) -> Option<Result<pack::Object<'a>, Error>> {
+        self.locate_at_depth(id, buffer, pack_cache, 0)
+    }
+
+    /// Like [`locate()`][compound::Db::locate()], with `depth` being the amount of delta bases found outside of the
+    /// pack of their delta so far.
+    fn locate_at_depth<'a>(
+        &self,
+        id: borrowed::Id,
+        buffer: &'a mut Vec<u8>,
+        pack_cache: &mut impl pack::cache::DecodeEntry,
+        depth: usize,
+    ) -> Option<Result<pack::Object<'a>, Error>> {
+        if let Some(object_cache) = &self.object_cache {
+            if let Some(kind) = object_cache.get(id, buffer) {
+                return Some(Ok(pack::Object {
+                    kind,
+                    data: buffer.as_slice(),
+                }));
+            }
+        }
+        let res = self.locate_uncached(id, buffer, pack_cache, depth);
+        if let (Some(object_cache), Some(Ok(object))) = (&self.object_cache, &res) {
+            object_cache.put(id, object.kind, object.data);
+        }
+        res
+    }
+
+    fn locate_uncached<'a>(
+        &self,
+        id: borrowed::Id,
+        buffer: &'a mut Vec<u8>,
+        pack_cache: &mut impl pack::cache::DecodeEntry,
+        depth: usize,
+    ) -> Option<Result<pack::Object<'a>, Error>> {
+        let mut pack_id = 0;
+        for db in std::iter::once(self).chain(self.alternates.iter()) {
+            for bundle in &db.bundles {
+                if let Some(index_position) = bundle.index.lookup(id) {
+                    let mut cache = PackCache {
+                        pack_id,
+                        inner: pack_cache,
+                    };
+                    return Some(self.decode_from_pack(bundle, index_position, buffer, &mut cache, depth));
+                }
+                pack_id += 1;
+            }
+            if db.loose.contains(id) {
+                return crate::Locate::locate(&db.loose, id, buffer, pack_cache).map(|res| res.map_err(Error::Loose));
+            }
+        }
+        None
+    }
+
+    /// Obtain the kind and size of the object with `id` without decoding it, searching the same places in the same
+    /// order as [`locate()`][compound::Db::locate()].
+    pub fn header(&self, id: borrowed::Id) -> Option<Result<(git_object::Kind, u64), Error>> {
+        self.header_at_depth(id, 0)
+    }
+
+    /// Like [`header()`][compound::Db::header()], with `depth` being the amount of delta bases found outside of the
+    /// pack of their delta so far.
+    fn header_at_depth(&self, id: borrowed::Id, depth: usize) -> Option<Result<(git_object::Kind, u64), Error>> {
+        for db in std::iter::once(self).chain(self.alternates.iter()) {
+            for bundle in &db.bundles {
+                if let Some(index_position) = bundle.index.lookup(id) {
+                    let entry = match bundle.entry_at_index(index_position) {
+                        Ok(entry) => entry,
+                        Err(err) => return Some(Err(err.into())),
+                    };
+                    let base_error = RefCell::new(None);
+                    return Some(
+                        bundle
+                            .pack
+                            .decode_header(entry, |base_id| {
+                                self.resolve_header_base(bundle, base_id, depth).unwrap_or_else(|err| {
+                                    base_error.replace(Some(err));
+                                    None
+                                })
+                            })
+                            .map_err(|err| base_error.take().unwrap_or_else(|| err.into())),
+                    );
+                }
+            }
+            if let Some(res) = db.loose.header(id) {
+                return Some(res.map_err(Error::Loose));
+            }
+        }
+        None
+    }
+
+    /// Find the base of a `RefDelta` in `bundle`, or learn its kind from anywhere else in the database.
+    fn resolve_header_base(
+        &self,
+        bundle: &pack::Bundle,
+        base_id: borrowed::Id,
+        depth: usize,
+    ) -> Result<Option<pack::data::decode::ResolvedHeaderBase>, Error> {
+        if let Some(index_position) = bundle.index.lookup(base_id) {
+            return Ok(Some(pack::data::decode::ResolvedHeaderBase::InPack(
+                bundle.entry_at_index(index_position)?,
+            )));
+        }
+        if depth >= MAX_OUT_OF_PACK_BASES {
+            return Err(Error::DeltaBaseDepthExceeded(base_id.into()));
+        }
+        Ok(self
+            .header_at_depth(base_id, depth + 1)
+            .transpose()?
+            .map(|(kind, _size)| pack::data::decode::ResolvedHeaderBase::OutOfPack { kind }))
+    }
+
+    fn decode_from_pack<'a>(
+        &self,
+        bundle: &pack::Bundle,
+        index_position: u32,
+        buffer: &'a mut Vec<u8>,
+        cache: &mut impl pack::cache::DecodeEntry,
+        depth: usize,
+    ) -> Result<pack::Object<'a>, Error> {
+        let entry = bundle.entry_at_index(index_position)?;
+        let base_error = RefCell::new(None);
+        let outcome = bundle
+            .pack
+            .decode_entry(
+                entry,
+                buffer,
+                |base_id, out| {
+                    self.resolve_base(bundle, base_id, out, depth).unwrap_or_else(|err| {
+                        base_error.replace(Some(err));
+                        None
+                    })
+                },
+                cache,
+            )
+            .map_err(|err| base_error.take().unwrap_or_else(|| err.into()))?;
+        Ok(pack::Object {
+            kind: outcome.kind,
+            data: buffer.as_slice(),
+        })
+    }
+
+    /// Find the base of a `RefDelta` in `bundle`, or decode it into `out` from anywhere else in the database.
+    ///
+    /// `depth` is the amount of delta bases found outside of the pack of their delta so far, and an error is returned
+    /// once it would exceed [`MAX_OUT_OF_PACK_BASES`].
+    pub(crate) fn resolve_base(
+        &self,
+        bundle: &pack::Bundle,
+        base_id: borrowed::Id,
+        out: &mut Vec<u8>,
+        depth: usize,
+    ) -> Result<Option<pack::data::decode::ResolvedBase>, Error> {
+        if let Some(index_position) = bundle.index.lookup(base_id) {
+            return Ok(Some(pack::data::decode::ResolvedBase::InPack(
+                bundle.entry_at_index(index_position)?,
+            )));
+        }
+        if depth >= MAX_OUT_OF_PACK_BASES {
+            return Err(Error::DeltaBaseDepthExceeded(base_id.into()));
+        }
+        let mut buf = Vec::new();
+        let base = match self.locate_at_depth(base_id, &mut buf, &mut pack::cache::DecodeEntryNoop, depth + 1) {
+            Some(base) => base?,
+            None => return Ok(None),
+        };
+        out.resize(base.data.len(), 0);
+        out.copy_from_slice(base.data);
+        Ok(Some(pack::data::decode::ResolvedBase::OutOfPack {
+            kind: base.kind,
+            end: out.len(),
+        }))
+    }
+}
+
+impl crate::Locate for compound::Db {
+    type Error = Error;
+
+    fn locate<'a>(
+        &self,
+        id: borrowed::Id,
+        buffer: &'a mut Vec<u8>,
+        pack_cache: &mut impl pack::cache::DecodeEntry,
+    ) -> Option<Result<pack::Object<'a>, Self::Error>> {
+        compound::Db::locate(self, id, buffer, pack_cache)
+    }
+
+    fn header(
+        &self,
+        id: borrowed::Id,
+        _buffer: &mut Vec<u8>,
+        _pack_cache: &mut impl pack::cache::DecodeEntry,
+    ) -> Option<Result<(git_object::Kind, u64), Self::Error>> {
+        compound::Db::header(self, id)
+    }
+}
@@ -0,0 +1,26 @@
+//! An object database combining loose objects and all packs of an `objects` directory, as usually found in `.git/objects`.
+use crate::{loose, pack};
+
+/// A database for reading objects from all packs and the loose object store of an `objects` directory, and for
+/// writing loose objects into it.
+pub struct Db {
+    /// The loose object store at the root of the `objects` directory
+    pub loose: loose::Db,
+    /// All packs in the `pack` directory, which are searched in order
+    pub bundles: Vec<pack::Bundle>,
+    /// The databases of all alternate object directories, which are searched in order if an object isn't found in
+    /// this one. They never have alternates of their own, as these are already part of this list.
+    pub alternates: Vec<Db>,
+    /// If set, fully decoded objects are cached here and found before searching packs, loose objects or alternates.
+    pub object_cache: Option<ObjectCache>,
+}
+
+pub mod init;
+pub mod locate;
+mod object_cache;
+pub use object_cache::ObjectCache;
+mod prefix;
+pub mod repack;
+mod stream;
+pub use stream::Stream;
+mod write;
@@ -0,0 +1,120 @@
+use crate::pack;
+use git_object::{borrowed, owned};
+use std::collections::{BTreeMap, HashMap};
+
+/// A cache of fully decoded objects keyed by their id, bounded by the total amount of bytes it holds, which evicts
+/// the least recently used objects first.
+///
+/// Unlike the [pack cache][pack::cache::DecodeEntry] it avoids decoding objects altogether, and it serves loose and
+/// packed objects alike. It can be shared among threads.
+pub struct ObjectCache {
+    capacity: usize,
+    state: parking_lot::Mutex<State>,
+}
+
+struct Entry {
+    kind: git_object::Kind,
+    data: Vec<u8>,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct State {
+    entries: HashMap<owned::Id, Entry>,
+    by_last_use: BTreeMap<u64, owned::Id>,
+    clock: u64,
+    bytes: usize,
+    statistics: pack::cache::Statistics,
+}
+
+impl ObjectCache {
+    /// Create a new cache holding no more than `capacity_in_bytes` of object data.
+    pub fn new(capacity_in_bytes: usize) -> Self {
+        ObjectCache {
+            capacity: capacity_in_bytes,
+            state: Default::default(),
+        }
+    }
+
+    /// The maximum amount of bytes of object data this cache holds.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The amount of bytes of object data currently held.
+    pub fn bytes_used(&self) -> usize {
+        self.state.lock().bytes
+    }
+
+    /// The amount of objects currently held.
+    pub fn num_entries(&self) -> usize {
+        self.state.lock().entries.len()
+    }
+
+    /// The amount of lookups so far which could or could not be answered.
+    pub fn statistics(&self) -> pack::cache::Statistics {
+        self.state.lock().statistics
+    }
+
+    /// Store the `data` of the object with `id` and `kind`, unless it's larger than the whole cache.
+    pub fn put(&self, id: borrowed::Id, kind: git_object::Kind, data: &[u8]) {
+        if data.len() > self.capacity {
+            return;
+        }
+        let mut state = self.state.lock();
+        let id = owned::Id::from(id);
+        state.remove(&id);
+        while state.bytes + data.len() > self.capacity {
+            let least_recently_used = state
+                .by_last_use
+                .values()
+                .next()
+                .cloned()
+                .expect("an entry to evict if bytes are used");
+            state.remove(&least_recently_used);
+        }
+        state.clock += 1;
+        let last_used = state.clock;
+        state.by_last_use.insert(last_used, id.clone());
+        state.bytes += data.len();
+        state.entries.insert(
+            id,
+            Entry {
+                kind,
+                data: data.to_owned(),
+                last_used,
+            },
+        );
+    }
+
+    /// Copy the data of the object with `id` into `out` and return its kind, or `None` if it isn't cached.
+    pub fn get(&self, id: borrowed::Id, out: &mut Vec<u8>) -> Option<git_object::Kind> {
+        let mut state = self.state.lock();
+        let state = &mut *state;
+        let entry = match state.entries.get_mut(&owned::Id::from(id)) {
+            Some(entry) => entry,
+            None => {
+                state.statistics.misses += 1;
+                return None;
+            }
+        };
+        state.statistics.hits += 1;
+        state.by_last_use.remove(&entry.last_used);
+        state.clock += 1;
+        entry.last_used = state.clock;
+        state.by_last_use.insert(entry.last_used, id.into());
+
+        out.resize(entry.data.len(), 0);
+        out.copy_from_slice(&entry.data);
+        Some(entry.kind)
+    }
+}
+
+impl State {
+    fn remove(&mut self, id: &owned::Id) {
+        if let Some(entry) = self.entries.remove(id) {
+            self.by_last_use.remove(&entry.last_used);
+            self.bytes -= entry.data.len();
+        }
+    }
+}
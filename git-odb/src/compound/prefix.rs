@@ -0,0 +1,47 @@
+use crate::{compound, prefix};
+use git_object::{borrowed, owned};
+
+/// Lookup by abbreviated ids
+impl compound::Db {
+    /// Find the only object whose id starts with `prefix` in any of our packs, among our loose objects or in our
+    /// alternates, or return an error listing all candidates if there is more than one.
+    ///
+    /// Objects stored more than once, like in multiple packs, count as a single candidate.
+    pub fn lookup_prefix(&self, prefix: &owned::Prefix) -> Option<Result<owned::Id, prefix::Error>> {
+        let mut candidates = Vec::new();
+        for db in std::iter::once(self).chain(self.alternates.iter()) {
+            for bundle in &db.bundles {
+                candidates.extend(
+                    bundle
+                        .index
+                        .lookup_prefix_range(prefix)
+                        .map(|index| owned::Id::from(bundle.index.oid_at_index(index))),
+                );
+            }
+            match db.loose.ids_with_prefix(prefix) {
+                Ok(ids) => candidates.extend(ids),
+                Err(err) => return Some(Err(err)),
+            }
+        }
+        prefix::disambiguate(prefix, candidates)
+    }
+
+    /// Compute the shortest prefix of `id` with at least `min_hex_len` hex characters that matches no other object in
+    /// the database, including alternates, like the abbreviated ids shown by git.
+    ///
+    /// `id` doesn't have to be in the database, and `min_hex_len` is raised to [`owned::Prefix::MIN_HEX_LEN`] if needed.
+    pub fn shortest_unique_prefix(&self, id: borrowed::Id, min_hex_len: usize) -> Result<owned::Prefix, prefix::Error> {
+        let mut longest_common_hex_len = 0;
+        for db in std::iter::once(self).chain(self.alternates.iter()) {
+            for bundle in &db.bundles {
+                longest_common_hex_len = longest_common_hex_len.max(bundle.index.longest_common_hex_len(id));
+            }
+            longest_common_hex_len = longest_common_hex_len.max(db.loose.longest_common_hex_len(id)?);
+        }
+        let hex_len = (longest_common_hex_len + 1)
+            .max(min_hex_len)
+            .max(owned::Prefix::MIN_HEX_LEN)
+            .min(id.kind().len_in_hex());
+        Ok(owned::Prefix::new(id, hex_len).expect("hex length to be in bounds"))
+    }
+}
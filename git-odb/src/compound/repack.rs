@@ -0,0 +1,472 @@
+use crate::{compound, loose, pack, zlib::CompressionLevel};
+use git_features::progress::{self, Progress};
+use git_object::owned;
+use quick_error::quick_error;
+use std::{
+    collections::{hash_map, HashMap},
+    fs, io,
+    path::PathBuf,
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error, path: PathBuf) {
+            display("Could not create or delete '{}'", path.display())
+            source(err)
+        }
+        LooseIter(err: loose::db::iter::Error) {
+            display("Could not enumerate the loose objects")
+            from()
+            source(err)
+        }
+        Write(err: pack::bundle::write::Error) {
+            display("Could not write the new pack and its index")
+            from()
+            source(err)
+        }
+        Index(err: pack::index::access::Error) {
+            display("Could not read the entries of an existing pack index")
+            from()
+            source(err)
+        }
+        PackEntry(err: pack::data::decode::Error) {
+            display("Could not decode the header of an entry in an existing pack")
+            from()
+            source(err)
+        }
+        ReverseIndex(err: pack::rev::init::Error) {
+            display("Could not obtain the reverse index of an existing pack")
+            from()
+            source(err)
+        }
+        Bundle(err: pack::bundle::Error) {
+            display("Could not open the newly written pack")
+            from()
+            source(err)
+        }
+        ObjectMissing(id: owned::Id) {
+            display("Object {} is missing in the newly written pack, nothing was deleted", id)
+        }
+        IncompleteEnumeration { path: PathBuf, expected: u32, actual: usize } {
+            display("Only {} of {} objects of the pack at '{}' were enumerated, nothing was deleted", actual, expected, path.display())
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Options {
+    /// The amount of threads to use at most when creating the index, or `None` to use all logical cores.
+    pub thread_limit: Option<usize>,
+    /// If set, deltas of packed objects are copied into the new pack if their base is part of it as well.
+    /// Otherwise all objects are stored undeltified.
+    ///
+    /// Either way, the compressed data of packed objects is copied verbatim if possible.
+    pub reuse_deltas: bool,
+    /// If set, all loose objects and packs that were consolidated into the new pack are deleted afterwards, along with
+    /// the multi-pack-index referring to them.
+    pub delete_redundant: bool,
+    /// The level at which objects are compressed, like git's `pack.compression`.
+    /// It doesn't affect entries copied from existing packs, which keep their compression.
+    pub compression: CompressionLevel,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            thread_limit: None,
+            reuse_deltas: true,
+            delete_redundant: false,
+            compression: CompressionLevel::default(),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Outcome {
+    /// The written pack and index, or `None` if there were no objects to pack.
+    pub write: Option<pack::bundle::write::Outcome>,
+    /// The amount of objects in the new pack
+    pub num_objects: u32,
+    /// The amount of objects stored as delta because an existing delta could be reused
+    pub num_reused_deltas: u32,
+    /// The amount of objects whose compressed data was copied from an existing pack without recompressing it
+    pub num_copied_entries: u32,
+    /// The amount of loose objects that were deleted
+    pub num_removed_loose_objects: usize,
+    /// The paths of all pack files whose pack, index and auxiliary files were deleted
+    pub removed_packs: Vec<PathBuf>,
+    /// True if the multi-pack-index was deleted as it referred to deleted packs
+    pub removed_multi_index: bool,
+}
+
+/// The amount of objects to turn into entries at a time
+const CHUNK_SIZE: usize = 1000;
+/// The amount of memory to use for caching decoded delta bases
+const CACHE_CAPACITY_IN_BYTES: usize = 64 * 1024 * 1024;
+
+enum Source {
+    Pack {
+        bundle_index: usize,
+        index_position: u32,
+        pack_offset: u64,
+    },
+    Loose,
+}
+
+struct Object {
+    id: owned::Id,
+    source: Source,
+    /// The index of the object whose delta against it is copied, which always precedes this one
+    base: Option<usize>,
+}
+
+#[derive(Default)]
+struct Counts {
+    copied_entries: u32,
+    reused_deltas: u32,
+}
+
+struct Enumeration {
+    /// All objects to write, ordered such that delta bases precede their deltas
+    objects: Vec<Object>,
+    /// All loose objects, including the ones which are also packed
+    loose_ids: Vec<owned::Id>,
+    /// For each bundle, the amount of its index entries that were enumerated, or `None` if it is kept
+    num_entries_by_bundle: Vec<Option<usize>>,
+}
+
+/// Repacking
+impl compound::Db {
+    /// Write all of our packed and loose objects into a single new pack with index in our `pack` directory, similar to
+    /// `git repack -a`, and return information about it along with the paths of all files that were removed.
+    ///
+    /// Objects of alternate object directories are not included, nor are they ever deleted. Like with `git repack -a`,
+    /// packs with a `.keep` file are left alone as well, and their objects are not added to the new pack.
+    /// The pack and index are written into temporary files first and only moved into place once complete, and
+    /// redundant objects are only deleted if [`Options::delete_redundant`] is set and after the new pack was
+    /// verified to contain all of them. This way, an interrupted run never loses objects.
+    /// Loose objects and packs that are added to the database while repacking are never deleted.
+    pub fn repack<P>(&self, mut progress: P, options: Options) -> Result<Outcome, Error>
+    where
+        P: Progress,
+        <<<P as Progress>::SubProgress as Progress>::SubProgress as Progress>::SubProgress: Send,
+    {
+        let Enumeration {
+            objects,
+            loose_ids,
+            num_entries_by_bundle,
+        } = {
+            let mut enumerate_progress = progress.add_child("enumerate objects");
+            enumerate_progress.init(None, progress::count("objects"));
+            let enumeration = self.objects_to_repack(options.reuse_deltas)?;
+            enumerate_progress.inc_by(enumeration.objects.len());
+            enumeration
+        };
+        if objects.is_empty() {
+            return Ok(Outcome {
+                write: None,
+                num_objects: 0,
+                num_reused_deltas: 0,
+                num_copied_entries: 0,
+                num_removed_loose_objects: 0,
+                removed_packs: Vec::new(),
+                removed_multi_index: false,
+            });
+        }
+
+        let pack_directory = self.loose.path.join("pack");
+        fs::create_dir_all(&pack_directory).map_err(|err| Error::Io(err, pack_directory.clone()))?;
+        let num_objects = objects.len() as u32;
+        let reverse_indices = self
+            .bundles
+            .iter()
+            .map(|bundle| bundle.index.reverse_index())
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut counts = Counts::default();
+        let write = pack::Bundle::write_entries_to_directory(
+            self.entries_for_repack(&objects, &reverse_indices, options.compression, &mut counts),
+            num_objects,
+            Some(&pack_directory),
+            progress.add_child("write"),
+            pack::bundle::write::Options {
+                thread_limit: options.thread_limit,
+                iteration_mode: pack::data::iter::Mode::Verify,
+                index_kind: pack::index::Kind::default(),
+                hash_kind: objects[0].id.kind(),
+                compression: options.compression,
+            },
+        )?;
+
+        let (mut num_removed_loose_objects, mut removed_packs, mut removed_multi_index) = (0, Vec::new(), false);
+        if options.delete_redundant {
+            for (bundle, num_entries) in self.bundles.iter().zip(&num_entries_by_bundle) {
+                match num_entries {
+                    Some(num_entries) if *num_entries != bundle.index.num_objects() as usize => {
+                        return Err(Error::IncompleteEnumeration {
+                            path: bundle.pack.path().to_owned(),
+                            expected: bundle.index.num_objects(),
+                            actual: *num_entries,
+                        })
+                    }
+                    _ => {}
+                }
+            }
+            let index_path = write.index_path.as_ref().expect("index written to directory");
+            let bundle = pack::Bundle::at(index_path)?;
+            if let Some(object) = objects
+                .iter()
+                .find(|o| bundle.index.lookup(o.id.to_borrowed()).is_none())
+            {
+                return Err(Error::ObjectMissing(object.id.clone()));
+            }
+            let mut delete_progress = progress.add_child("delete redundant objects");
+            delete_progress.init(None, progress::count("files"));
+
+            for id in &loose_ids {
+                let path = loose::db::hash_path(id.to_borrowed(), self.loose.path.clone());
+                if remove_file_if_present(&path)? {
+                    num_removed_loose_objects += 1;
+                    delete_progress.inc();
+                }
+                if let Some(fan_out_directory) = path.parent() {
+                    // Fails as long as other objects remain, which is expected
+                    fs::remove_dir(fan_out_directory).ok();
+                }
+            }
+
+            let new_pack_path = write.data_path.as_ref().expect("pack written to directory");
+            for (bundle, num_entries) in self.bundles.iter().zip(&num_entries_by_bundle) {
+                let pack_path = bundle.pack.path();
+                // A pack may have been kept while we were repacking, which is fine as its objects are in the new pack
+                if pack_path == new_pack_path || num_entries.is_none() || is_kept(bundle) {
+                    continue;
+                }
+                // Delete the index first, as an index without pack would prevent the database from being opened
+                for extension in &["idx", "rev", "bitmap", "pack"] {
+                    if remove_file_if_present(&pack_path.with_extension(extension))? {
+                        delete_progress.inc();
+                    }
+                }
+                removed_packs.push(pack_path.to_owned());
+            }
+            if !removed_packs.is_empty() {
+                // It would refer to packs that don't exist anymore
+                removed_multi_index = remove_file_if_present(&pack_directory.join(pack::multi_index::FILE_NAME))?;
+            }
+        }
+
+        Ok(Outcome {
+            write: Some(write),
+            num_objects,
+            num_reused_deltas: counts.reused_deltas,
+            num_copied_entries: counts.copied_entries,
+            num_removed_loose_objects,
+            removed_packs,
+            removed_multi_index,
+        })
+    }
+
+    /// Enumerate the objects of all packs which aren't kept in pack order, followed by all loose objects which aren't
+    /// packed, and if `reuse_deltas` is set, find the base of all packed deltas which can be copied.
+    fn objects_to_repack(&self, reuse_deltas: bool) -> Result<Enumeration, Error> {
+        let mut objects = Vec::new();
+        let mut object_index_by_id = HashMap::new();
+        let mut offsets_by_bundle = Vec::with_capacity(self.bundles.len());
+        let mut num_entries_by_bundle = Vec::with_capacity(self.bundles.len());
+        let mut kept_bundles = Vec::new();
+        for (bundle_index, bundle) in self.bundles.iter().enumerate() {
+            if is_kept(bundle) {
+                kept_bundles.push(bundle);
+                offsets_by_bundle.push(HashMap::new());
+                num_entries_by_bundle.push(None);
+                continue;
+            }
+            let mut entries: Vec<_> = (0u32..).zip(bundle.index.iter()?).collect();
+            num_entries_by_bundle.push(Some(entries.len()));
+            entries.sort_by_key(|(_, e)| e.pack_offset);
+            let mut offsets = HashMap::with_capacity(entries.len());
+            for (index_position, entry) in entries {
+                let pack_offset = entry.pack_offset;
+                let object_index = *object_index_by_id.entry(entry.oid.clone()).or_insert_with(|| {
+                    objects.push(Object {
+                        id: entry.oid,
+                        source: Source::Pack {
+                            bundle_index,
+                            index_position,
+                            pack_offset,
+                        },
+                        base: None,
+                    });
+                    objects.len() - 1
+                });
+                offsets.insert(pack_offset, object_index);
+            }
+            offsets_by_bundle.push(offsets);
+        }
+        let mut loose_ids = Vec::new();
+        for id in self.loose.iter() {
+            let id = id?;
+            loose_ids.push(id.clone());
+            if kept_bundles
+                .iter()
+                .any(|bundle| bundle.index.lookup(id.to_borrowed()).is_some())
+            {
+                continue;
+            }
+            if let hash_map::Entry::Vacant(entry) = object_index_by_id.entry(id.clone()) {
+                entry.insert(objects.len());
+                objects.push(Object {
+                    id,
+                    source: Source::Loose,
+                    base: None,
+                });
+            }
+        }
+
+        if reuse_deltas {
+            for object in &mut objects {
+                if let Source::Pack {
+                    bundle_index,
+                    pack_offset,
+                    ..
+                } = object.source
+                {
+                    let entry = self.bundles[bundle_index].pack.entry(pack_offset)?;
+                    object.base = match entry.header {
+                        pack::data::Header::OfsDelta { base_distance } => entry
+                            .base_pack_offset(base_distance)
+                            .and_then(|base_offset| offsets_by_bundle[bundle_index].get(&base_offset))
+                            .copied(),
+                        pack::data::Header::RefDelta { base_id } => object_index_by_id.get(&base_id).copied(),
+                        _ => None,
+                    };
+                }
+            }
+            objects = bases_first(objects);
+        }
+        Ok(Enumeration {
+            objects,
+            loose_ids,
+            num_entries_by_bundle,
+        })
+    }
+
+    /// Produce entries for all `objects` in order, copying the compressed data of packed objects verbatim if possible,
+    /// along with their delta if they have a base.
+    fn entries_for_repack<'a>(
+        &'a self,
+        objects: &'a [Object],
+        reverse_indices: &'a [pack::rev::File],
+        compression: CompressionLevel,
+        counts: &'a mut Counts,
+    ) -> impl Iterator<Item = Result<Vec<pack::data::output::Entry>, pack::data::output::Error>> + 'a {
+        let mut kinds = Vec::with_capacity(objects.len());
+        let mut cache = pack::cache::DecodeEntryMemoryCapped::new(CACHE_CAPACITY_IN_BYTES);
+        let mut buf = Vec::new();
+        objects.chunks(CHUNK_SIZE).map(move |chunk| {
+            let mut entries = Vec::with_capacity(chunk.len());
+            for object in chunk {
+                let copied = match object.source {
+                    Source::Pack {
+                        bundle_index,
+                        index_position,
+                        ..
+                    } => pack::data::output::Entry::from_pack_entry(
+                        &self.bundles[bundle_index],
+                        &reverse_indices[bundle_index],
+                        index_position,
+                        // the base was obtained from the same entry header
+                        |_base_id| object.base.map(|base| (base, kinds[base])),
+                    )
+                    .transpose()?,
+                    Source::Loose => None,
+                };
+                let entry = match copied {
+                    Some(entry) => {
+                        counts.copied_entries += 1;
+                        if let pack::data::output::EntryKind::DeltaRef { .. } = entry.kind {
+                            counts.reused_deltas += 1;
+                        }
+                        entry
+                    }
+                    None => {
+                        let data = self
+                            .locate(object.id.to_borrowed(), &mut buf, &mut cache)
+                            .ok_or_else(|| pack::data::output::Error::NotFound(object.id.clone()))?
+                            .map_err(|err| pack::data::output::Error::Locate(Box::new(err)))?;
+                        pack::data::output::Entry::from_data(object.id.clone(), &data, compression)?
+                    }
+                };
+                kinds.push(entry.object_kind);
+                entries.push(entry);
+            }
+            Ok(entries)
+        })
+    }
+}
+
+/// Reorder `objects` so that each one is preceded by its base, while keeping their order otherwise, and remap the
+/// bases accordingly. Bases which would form a cycle are removed.
+fn bases_first(mut objects: Vec<Object>) -> Vec<Object> {
+    const UNVISITED: u8 = 0;
+    const ON_PATH: u8 = 1;
+    const DONE: u8 = 2;
+    let mut state = vec![UNVISITED; objects.len()];
+    let mut order = Vec::with_capacity(objects.len());
+    let mut path = Vec::<usize>::new();
+    for object_index in 0..objects.len() {
+        let mut current = object_index;
+        loop {
+            match state[current] {
+                DONE => break,
+                ON_PATH => {
+                    let last = *path.last().expect("a path leads to objects on it");
+                    objects[last].base = None;
+                    break;
+                }
+                _ => {}
+            }
+            state[current] = ON_PATH;
+            path.push(current);
+            match objects[current].base {
+                Some(base) => current = base,
+                None => break,
+            }
+        }
+        for index in path.drain(..).rev() {
+            state[index] = DONE;
+            order.push(index);
+        }
+    }
+
+    let mut new_index = vec![0; objects.len()];
+    for (position, index) in order.iter().enumerate() {
+        new_index[*index] = position;
+    }
+    let mut objects: Vec<_> = objects.into_iter().map(Some).collect();
+    order
+        .into_iter()
+        .map(|index| {
+            let mut object = objects[index].take().expect("each object is ordered once");
+            object.base = object.base.map(|base| new_index[base]);
+            object
+        })
+        .collect()
+}
+
+/// Returns true if the pack of `bundle` has a `.keep` file, which protects it from being repacked and deleted.
+fn is_kept(bundle: &pack::Bundle) -> bool {
+    bundle.pack.path().with_extension("keep").is_file()
+}
+
+/// Returns true if the file at `path` was removed, or false if it didn't exist.
+fn remove_file_if_present(path: &std::path::Path) -> Result<bool, Error> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(Error::Io(err, path.to_owned())),
+    }
+}
@@ -0,0 +1,106 @@
+use crate::{compound, loose, pack};
+use git_object::borrowed;
+use std::{cell::RefCell, io};
+
+/// An implementation of [`io::Read`] yielding the data of an object, see [`compound::Db::stream()`].
+pub struct Stream<'a> {
+    kind: git_object::Kind,
+    size: u64,
+    inner: Inner<'a>,
+}
+
+enum Inner<'a> {
+    Pack(pack::data::stream::Reader<'a>),
+    Loose(loose::object::stream::Reader<'static>),
+    Cached(io::Cursor<Vec<u8>>),
+}
+
+impl<'a> Stream<'a> {
+    /// The kind of the object whose data is streamed.
+    pub fn kind(&self) -> git_object::Kind {
+        self.kind
+    }
+
+    /// The size of the object in bytes, which is the total amount of bytes that can be read.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+impl<'a> io::Read for Stream<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.inner {
+            Inner::Pack(reader) => reader.read(buf),
+            Inner::Loose(reader) => reader.read(buf),
+            Inner::Cached(reader) => reader.read(buf),
+        }
+    }
+}
+
+/// Object streaming
+impl compound::Db {
+    /// Obtain a reader for the data of the object with `id`, searching the same places in the same order as
+    /// [`locate()`][compound::Db::locate()], which decompresses the object while reading.
+    ///
+    /// Only the base of a deltified object is held in memory, see [`pack::data::File::stream_entry()`], making this
+    /// suitable for objects too large to be decoded at once. Hits in the [object cache][compound::Db::object_cache]
+    /// are served from memory, but streamed objects are never put into it.
+    pub fn stream(
+        &self,
+        id: borrowed::Id,
+        pack_cache: &mut impl pack::cache::DecodeEntry,
+    ) -> Option<Result<Stream<'_>, compound::locate::Error>> {
+        if let Some(object_cache) = &self.object_cache {
+            let mut buf = Vec::new();
+            if let Some(kind) = object_cache.get(id, &mut buf) {
+                return Some(Ok(Stream {
+                    kind,
+                    size: buf.len() as u64,
+                    inner: Inner::Cached(io::Cursor::new(buf)),
+                }));
+            }
+        }
+        for db in std::iter::once(self).chain(self.alternates.iter()) {
+            for bundle in &db.bundles {
+                if let Some(index_position) = bundle.index.lookup(id) {
+                    let entry = match bundle.entry_at_index(index_position) {
+                        Ok(entry) => entry,
+                        Err(err) => return Some(Err(pack::data::stream::Error::from(err).into())),
+                    };
+                    let base_error = RefCell::new(None);
+                    return Some(
+                        bundle
+                            .pack
+                            .stream_entry(
+                                entry,
+                                |base_id, out| {
+                                    self.resolve_base(bundle, base_id, out, 0).unwrap_or_else(|err| {
+                                        base_error.replace(Some(err));
+                                        None
+                                    })
+                                },
+                                pack_cache,
+                            )
+                            .map(|reader| Stream {
+                                kind: reader.kind(),
+                                size: reader.size(),
+                                inner: Inner::Pack(reader),
+                            })
+                            .map_err(|err| base_error.take().unwrap_or_else(|| err.into())),
+                    );
+                }
+            }
+            if let Some(res) = db.loose.stream(id) {
+                return Some(
+                    res.map(|(kind, size, reader)| Stream {
+                        kind,
+                        size,
+                        inner: Inner::Loose(reader),
+                    })
+                    .map_err(Into::into),
+                );
+            }
+        }
+        None
+    }
+}
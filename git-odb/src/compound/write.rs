@@ -0,0 +1,22 @@
+use crate::{compound, loose};
+use git_object::{owned, HashKind};
+use std::io;
+
+/// Objects are written into the loose object store, to be packed later.
+impl crate::Write for compound::Db {
+    type Error = loose::db::write::Error;
+
+    fn write_buf(&self, kind: git_object::Kind, from: &[u8], hash: HashKind) -> Result<owned::Id, Self::Error> {
+        self.loose.write_buf(kind, from, hash)
+    }
+
+    fn write_stream(
+        &self,
+        kind: git_object::Kind,
+        size: u64,
+        from: impl io::Read,
+        hash: HashKind,
+    ) -> Result<owned::Id, Self::Error> {
+        self.loose.write_stream(kind, size, from, hash)
+    }
+}
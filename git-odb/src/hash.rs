@@ -3,8 +3,38 @@ use git_features::interrupt::is_triggered;
 use git_object::{owned, HashKind};
 use std::{io, path::Path};
 
+/// A hasher for any kind of hash, producing ids of the same kind.
+#[derive(Clone)]
+pub(crate) enum Hasher {
+    Sha1(hash::Sha1),
+    Sha256(hash::Sha256),
+}
+
+impl Hasher {
+    pub fn new(kind: HashKind) -> Self {
+        match kind {
+            HashKind::Sha1 => Hasher::Sha1(hash::Sha1::default()),
+            HashKind::Sha256 => Hasher::Sha256(hash::Sha256::default()),
+        }
+    }
+
+    pub fn update(&mut self, d: &[u8]) {
+        match self {
+            Hasher::Sha1(hasher) => hasher.update(d),
+            Hasher::Sha256(hasher) => hasher.update(d),
+        }
+    }
+
+    pub fn digest(self) -> owned::Id {
+        match self {
+            Hasher::Sha1(hasher) => hasher.digest().into(),
+            Hasher::Sha256(hasher) => hasher.digest().into(),
+        }
+    }
+}
+
 pub(crate) struct Write<T> {
-    pub hash: hash::Sha1,
+    pub hash: Hasher,
     pub inner: T,
 }
 
@@ -27,11 +57,9 @@ where
     T: io::Write,
 {
     pub fn new(inner: T, kind: HashKind) -> Self {
-        match kind {
-            HashKind::Sha1 => Write {
-                inner,
-                hash: hash::Sha1::default(),
-            },
+        Write {
+            inner,
+            hash: Hasher::new(kind),
         }
     }
 }
@@ -39,9 +67,10 @@ where
 pub(crate) fn bytes_of_file(
     path: impl AsRef<Path>,
     num_bytes_from_start: usize,
+    kind: HashKind,
     progress: &mut impl git_features::progress::Progress,
 ) -> io::Result<owned::Id> {
-    let mut hasher = hash::Sha1::default();
+    let mut hasher = Hasher::new(kind);
     let start = std::time::Instant::now();
     // init progress before the possibility for failure, as convenience in case people want to recover
     progress.init(Some(num_bytes_from_start), git_features::progress::bytes());
@@ -63,7 +92,7 @@ pub(crate) fn bytes_of_file(
         }
     }
 
-    let id = owned::Id::new_sha1(hasher.digest());
+    let id = hasher.digest();
     progress.show_throughput(start);
     Ok(id)
 }
@@ -1,9 +1,15 @@
 #![deny(unsafe_code)]
 
 mod zlib;
+pub use zlib::CompressionLevel;
 
+pub mod alternate;
+pub mod chunk_file;
+pub mod commit_graph;
+pub mod compound;
 pub mod loose;
 pub mod pack;
+pub mod prefix;
 
 mod sink;
 pub use sink::{sink, Sink};
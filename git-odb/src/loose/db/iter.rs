@@ -1,5 +1,5 @@
 use crate::loose::Db;
-use git_object::owned;
+use git_object::{owned, SHA1_SIZE, SHA256_SIZE};
 use quick_error::quick_error;
 use walkdir::WalkDir;
 
@@ -28,15 +28,16 @@ impl Db {
                     let p = e.path();
                     let (c1, c2) = p.components().fold((None, None), |(_c1, c2), cn| (c2, Some(cn)));
                     if let (Some(Normal(c1)), Some(Normal(c2))) = (c1, c2) {
-                        if c1.len() == 2 && c2.len() == 38 {
+                        if c1.len() == 2 && (c2.len() == SHA1_SIZE * 2 - 2 || c2.len() == SHA256_SIZE * 2 - 2) {
                             if let (Some(c1), Some(c2)) = (c1.to_str(), c2.to_str()) {
-                                let mut buf = [0u8; 40];
+                                let mut buf = [0u8; SHA256_SIZE * 2];
+                                let hex_len = c1.len() + c2.len();
                                 {
-                                    let (first_byte, rest) = buf.split_at_mut(2);
+                                    let (first_byte, rest) = buf[..hex_len].split_at_mut(2);
                                     first_byte.copy_from_slice(c1.as_bytes());
                                     rest.copy_from_slice(c2.as_bytes());
                                 }
-                                if let Ok(b) = owned::Id::from_40_bytes_in_hex(&buf[..]) {
+                                if let Ok(b) = owned::Id::from_hex(&buf[..hex_len]) {
                                     is_valid_path = true;
                                     return b;
                                 }
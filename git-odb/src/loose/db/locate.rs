@@ -1,6 +1,10 @@
 use crate::{
-    loose::{db::sha1_path, object::header, Db, Object, HEADER_READ_COMPRESSED_BYTES, HEADER_READ_UNCOMPRESSED_BYTES},
-    zlib,
+    loose::{
+        db::hash_path,
+        object::{decode, header, stream},
+        Db, Object, HEADER_READ_COMPRESSED_BYTES, HEADER_READ_UNCOMPRESSED_BYTES,
+    },
+    pack, zlib,
 };
 use git_object as object;
 use object::borrowed;
@@ -24,6 +28,14 @@ quick_error! {
             display("Could not {} data at '{}'", action, path.display())
             source(err)
         }
+        SizeMismatch(expected: usize, actual: usize, path: PathBuf) {
+            display("Loose object at '{}' was expected to decompress to {} bytes, but got {}", path.display(), expected, actual)
+        }
+        DecompressAll(err: decode::Error) {
+            display("Could not decompress the object data")
+            from()
+            source(err)
+        }
     }
 }
 
@@ -31,6 +43,11 @@ quick_error! {
 impl Db {
     const OPEN_ACTION: &'static str = "open";
 
+    /// Returns true if an object with `id` exists in this database, without reading it.
+    pub fn contains(&self, id: borrowed::Id) -> bool {
+        hash_path(id, self.path.clone()).is_file()
+    }
+
     pub fn locate(&self, id: borrowed::Id) -> Option<Result<Object, Error>> {
         match self.locate_inner(id) {
             Ok(obj) => Some(Ok(obj)),
@@ -47,8 +64,47 @@ impl Db {
         }
     }
 
+    /// Obtain the kind and size of the object with `id` by decompressing only the beginning of its file, or `None` if
+    /// it doesn't exist.
+    pub fn header(&self, id: borrowed::Id) -> Option<Result<(object::Kind, u64), Error>> {
+        match self.header_inner(id) {
+            Err(Error::Io(_, action, _)) if action == Self::OPEN_ACTION => None,
+            res => Some(res.map(|(kind, size, _header_size)| (kind, size))),
+        }
+    }
+
+    /// Obtain a reader for the data of the object with `id` which decompresses it while reading, along with its kind
+    /// and size, or `None` if it doesn't exist.
+    pub fn stream(&self, id: borrowed::Id) -> Option<Result<(object::Kind, u64, stream::Reader<'static>), Error>> {
+        let (kind, size, header_size) = match self.header_inner(id) {
+            Err(Error::Io(_, action, _)) if action == Self::OPEN_ACTION => return None,
+            Err(err) => return Some(Err(err)),
+            Ok(header) => header,
+        };
+        let path = hash_path(id, self.path.clone());
+        Some(
+            fs::File::open(&path)
+                .map_err(|e| Error::Io(e, Self::OPEN_ACTION, path))
+                .map(|file| (kind, size, stream::Reader::from_read(header_size, file))),
+        )
+    }
+
+    fn header_inner(&self, id: borrowed::Id) -> Result<(object::Kind, u64, usize), Error> {
+        let path = hash_path(id, self.path.clone());
+        let mut compressed = [0; HEADER_READ_COMPRESSED_BYTES];
+        let bytes_read = fs::File::open(&path)
+            .map_err(|e| Error::Io(e, Self::OPEN_ACTION, path.to_owned()))?
+            .read(&mut compressed[..])
+            .map_err(|e| Error::Io(e, "read", path.to_owned()))?;
+        let mut decompressed = [0; HEADER_READ_UNCOMPRESSED_BYTES];
+        let (_status, _consumed_in, consumed_out) = zlib::Inflate::default()
+            .once(&compressed[..bytes_read], &mut decompressed[..])
+            .map_err(|e| Error::DecompressFile(e, path.to_owned()))?;
+        header::decode(&decompressed[..consumed_out]).map_err(Into::into)
+    }
+
     fn locate_inner(&self, id: borrowed::Id) -> Result<Object, Error> {
-        let path = sha1_path(id, self.path.clone());
+        let path = hash_path(id, self.path.clone());
 
         let mut inflate = zlib::Inflate::default();
         let mut decompressed = [0; HEADER_READ_UNCOMPRESSED_BYTES];
@@ -60,7 +116,7 @@ impl Db {
                 .map_err(|e| Error::Io(e, "read", path.to_owned()))?;
             (
                 inflate
-                    .once(&compressed[..bytes_read], &mut decompressed[..], true)
+                    .once(&compressed[..bytes_read], &mut decompressed[..])
                     .map_err(|e| Error::DecompressFile(e, path.to_owned()))?,
                 bytes_read,
                 istream,
@@ -68,6 +124,10 @@ impl Db {
         };
 
         let (kind, size, header_size) = header::decode(&decompressed[..consumed_out])?;
+        let size: usize = size.try_into().expect("actual size to potentially fit into memory");
+        if inflate.is_done && consumed_out != header_size + size {
+            return Err(Error::SizeMismatch(header_size + size, consumed_out, path));
+        }
         let mut decompressed = SmallVec::from_buf(decompressed);
         decompressed.resize(consumed_out, 0);
 
@@ -107,7 +167,7 @@ impl Db {
 
         Ok(Object {
             kind,
-            size: size.try_into().expect("actual size to potentially fit into memory"),
+            size,
             decompressed_data: decompressed,
             compressed_data: compressed,
             header_size,
@@ -116,3 +176,38 @@ impl Db {
         })
     }
 }
+
+impl crate::Locate for Db {
+    type Error = Error;
+
+    /// Note that the `pack_cache` is unused, as loose objects are not delta-compressed.
+    fn locate<'a>(
+        &self,
+        id: borrowed::Id,
+        buffer: &'a mut Vec<u8>,
+        _pack_cache: &mut impl pack::cache::DecodeEntry,
+    ) -> Option<Result<pack::Object<'a>, Self::Error>> {
+        let mut object = match Db::locate(self, id)? {
+            Ok(object) => object,
+            Err(err) => return Some(Err(err)),
+        };
+        if let Err(err) = object.decompress_all() {
+            return Some(Err(err.into()));
+        }
+        buffer.clear();
+        buffer.extend_from_slice(&object.decompressed_data[object.header_size..]);
+        Some(Ok(pack::Object {
+            kind: object.kind,
+            data: buffer.as_slice(),
+        }))
+    }
+
+    fn header(
+        &self,
+        id: borrowed::Id,
+        _buffer: &mut Vec<u8>,
+        _pack_cache: &mut impl pack::cache::DecodeEntry,
+    ) -> Option<Result<(object::Kind, u64), Self::Error>> {
+        Db::header(self, id)
+    }
+}
@@ -1,29 +1,38 @@
-use git_object::{borrowed, HashKind};
+use crate::zlib::CompressionLevel;
+use git_object::borrowed;
 use std::path::PathBuf;
 
 pub struct Db {
     pub path: PathBuf,
+    /// The level at which newly written objects are compressed, like git's `core.looseCompression`.
+    pub compression: CompressionLevel,
 }
 
 /// Initialization
 impl Db {
     pub fn at(path: impl Into<PathBuf>) -> Db {
-        Db { path: path.into() }
+        Db {
+            path: path.into(),
+            compression: CompressionLevel::default(),
+        }
     }
-}
 
-pub(crate) fn sha1_path(id: borrowed::Id, mut root: PathBuf) -> PathBuf {
-    match id.kind() {
-        HashKind::Sha1 => {
-            let hex = id.to_sha1_hex();
-            let buf = std::str::from_utf8(&hex).expect("ascii only in hex");
-            root.push(&buf[..2]);
-            root.push(&buf[2..]);
-            root
-        }
+    /// Compress newly written objects at the given `level`.
+    pub fn with_compression(mut self, level: CompressionLevel) -> Self {
+        self.compression = level;
+        self
     }
 }
 
+/// The path of the loose object with `id` below `root`, with the first byte of its hex id as fan-out directory.
+pub(crate) fn hash_path(id: borrowed::Id, mut root: PathBuf) -> PathBuf {
+    let hex = id.to_hex_string();
+    root.push(&hex[..2]);
+    root.push(&hex[2..]);
+    root
+}
+
 pub mod iter;
 pub mod locate;
+mod prefix;
 pub mod write;
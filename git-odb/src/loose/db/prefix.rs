@@ -0,0 +1,57 @@
+use crate::{loose::Db, prefix};
+use git_object::{borrowed, owned, SHA1_SIZE, SHA256_SIZE};
+use std::{fs, io};
+
+/// Lookup by abbreviated ids
+impl Db {
+    /// Find the only loose object whose id starts with `prefix`, or return an error listing all candidates if there
+    /// is more than one.
+    pub fn lookup_prefix(&self, prefix: &owned::Prefix) -> Option<Result<owned::Id, prefix::Error>> {
+        match self.ids_with_prefix(prefix) {
+            Ok(ids) => prefix::disambiguate(prefix, ids),
+            Err(err) => Some(Err(err)),
+        }
+    }
+
+    /// Returns the ids of all loose objects starting with `prefix`.
+    pub(crate) fn ids_with_prefix(&self, prefix: &owned::Prefix) -> Result<Vec<owned::Id>, prefix::Error> {
+        let mut ids = self.ids_with_first_byte(prefix.as_id().first_byte())?;
+        ids.retain(|id| prefix.matches(id.to_borrowed()));
+        Ok(ids)
+    }
+
+    /// Returns the amount of leading hex characters `id` has in common with the most similar other loose object.
+    pub(crate) fn longest_common_hex_len(&self, id: borrowed::Id) -> Result<usize, prefix::Error> {
+        Ok(self
+            .ids_with_first_byte(id.first_byte())?
+            .into_iter()
+            .filter(|candidate| candidate.to_borrowed() != id)
+            .map(|candidate| prefix::common_hex_len(id.as_slice(), candidate.as_slice()))
+            .max()
+            .unwrap_or(0))
+    }
+
+    /// List all loose objects in the fan-out directory for `first_byte`, which doesn't have to exist.
+    fn ids_with_first_byte(&self, first_byte: u8) -> Result<Vec<owned::Id>, prefix::Error> {
+        let directory = self.path.join(format!("{:02x}", first_byte));
+        let entries = match fs::read_dir(&directory) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(prefix::Error::Io(err, directory)),
+        };
+        let mut ids = Vec::new();
+        for entry in entries {
+            let name = entry
+                .map_err(|err| prefix::Error::Io(err, directory.clone()))?
+                .file_name();
+            let hex = format!("{:02x}{}", first_byte, name.to_string_lossy());
+            if hex.len() != SHA1_SIZE * 2 && hex.len() != SHA256_SIZE * 2 {
+                continue;
+            }
+            if let Ok(id) = owned::Id::from_hex(hex.as_bytes()) {
+                ids.push(id);
+            }
+        }
+        Ok(ids)
+    }
+}
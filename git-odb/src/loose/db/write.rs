@@ -28,15 +28,11 @@ impl crate::Write for Db {
     type Error = Error;
 
     fn write_buf(&self, kind: git_object::Kind, from: &[u8], hash: HashKind) -> Result<owned::Id, Self::Error> {
-        match hash {
-            HashKind::Sha1 => {
-                let mut to = self.write_header(kind, from.len() as u64, hash)?;
-                to.write_all(from)
-                    .map_err(|err| Error::Io(err, "stream all data into tempfile in", self.path.to_owned()))?;
-                to.flush()?;
-                self.finalize_object(to)
-            }
-        }
+        let mut to = self.write_header(kind, from.len() as u64, hash)?;
+        to.write_all(from)
+            .map_err(|err| Error::Io(err, "stream all data into tempfile in", self.path.to_owned()))?;
+        to.flush()?;
+        self.finalize_object(to)
     }
 
     fn write_stream(
@@ -46,15 +42,11 @@ impl crate::Write for Db {
         mut from: impl io::Read,
         hash: HashKind,
     ) -> Result<owned::Id, Self::Error> {
-        match hash {
-            HashKind::Sha1 => {
-                let mut to = self.write_header(kind, size, hash)?;
-                io::copy(&mut from, &mut to)
-                    .map_err(|err| Error::Io(err, "stream all data into tempfile in", self.path.to_owned()))?;
-                to.flush()?;
-                self.finalize_object(to)
-            }
-        }
+        let mut to = self.write_header(kind, size, hash)?;
+        io::copy(&mut from, &mut to)
+            .map_err(|err| Error::Io(err, "stream all data into tempfile in", self.path.to_owned()))?;
+        to.flush()?;
+        self.finalize_object(to)
     }
 }
 
@@ -68,9 +60,10 @@ impl Db {
         hash: HashKind,
     ) -> Result<hash::Write<HashAndTempFile>, Error> {
         let mut to = hash::Write::new(
-            DeflateWriter::new(
+            DeflateWriter::with_level(
                 NamedTempFile::new_in(&self.path)
                     .map_err(|err| Error::Io(err, "create named temp file in", self.path.to_owned()))?,
+                self.compression,
             ),
             hash,
         );
@@ -84,8 +77,8 @@ impl Db {
         &self,
         hash::Write { hash, inner: file }: hash::Write<HashAndTempFile>,
     ) -> Result<owned::Id, Error> {
-        let id = owned::Id::from(hash.digest());
-        let object_path = loose::db::sha1_path(id.to_borrowed(), self.path.clone());
+        let id = hash.digest();
+        let object_path = loose::db::hash_path(id.to_borrowed(), self.path.clone());
         let object_dir = object_path
             .parent()
             .expect("each object path has a 1 hex-bytes directory");
@@ -1,7 +1,6 @@
 use super::stream;
 use crate::{loose, zlib};
 use git_object as object;
-use miniz_oxide::inflate::decompress_to_vec_zlib;
 use object::borrowed;
 use quick_error::quick_error;
 use smallvec::SmallVec;
@@ -24,19 +23,27 @@ quick_error! {
             display("Could not {} data at '{}'", action, path.display())
             source(err)
         }
+        SizeMismatch(expected: usize, actual: usize) {
+            display("Object was expected to decompress to {} bytes, but got {}", expected, actual)
+        }
     }
 }
 
 impl loose::Object {
     /// **Note**: Blobs are loaded into memory and are made available that way.
     /// Consider using `stream()` if large Blobs are expected.
-    pub fn decode(&mut self) -> Result<borrowed::Object, Error> {
+    pub fn decode(&mut self) -> Result<borrowed::Object<'_>, Error> {
+        self.decode_with_hash(git_object::HashKind::Sha1)
+    }
+
+    /// Like [`decode()`][loose::Object::decode()], but expects the ids of tree entries to be of kind `hash`.
+    pub fn decode_with_hash(&mut self, hash: git_object::HashKind) -> Result<borrowed::Object<'_>, Error> {
         self.decompress_all()?;
         let bytes = &self.decompressed_data[self.header_size..];
-        Ok(borrowed::Object::from_bytes(self.kind, bytes)?)
+        Ok(borrowed::Object::from_bytes_with_hash(self.kind, bytes, hash)?)
     }
 
-    pub fn stream(&mut self) -> Result<stream::Reader, Error> {
+    pub fn stream(&mut self) -> Result<stream::Reader<'_>, Error> {
         match &self.path {
             Some(path) => Ok(stream::Reader::from_read(
                 self.header_size,
@@ -73,10 +80,15 @@ impl loose::Object {
             file.read_to_end(&mut buf).map_err(|e| Error::Io(e, "read", path))?;
             self.compressed_data = SmallVec::from(buf);
         }
-        self.decompressed_data = SmallVec::from(decompress_to_vec_zlib(&self.compressed_data[..]).unwrap());
+        let mut decompressed = vec![0; total_size];
+        let (_status, _consumed_in, consumed_out) =
+            zlib::Inflate::default().once(&self.compressed_data[..], &mut decompressed)?;
+        if consumed_out != total_size {
+            return Err(Error::SizeMismatch(total_size, consumed_out));
+        }
+        self.decompressed_data = SmallVec::from(decompressed);
         self.compressed_data = Default::default();
         self.decompressed_data.shrink_to_fit();
-        assert!(self.decompressed_data.len() == total_size);
         self.decompression_complete = true;
         Ok(())
     }
@@ -27,10 +27,10 @@ pub fn decode(input: &[u8]) -> Result<(object::Kind, u64, usize), Error> {
         .ok_or_else(|| Error::InvalidHeader("Did not find 0 byte in header"))?;
     let header = &input[..header_end];
     let mut split = header.split(|&b| b == b' ');
-    match (split.next(), split.next()) {
-        (Some(kind), Some(size)) => Ok((
+    match (split.next(), split.next(), split.next()) {
+        (Some(kind), Some(size), None) => Ok((
             object::Kind::from_bytes(kind)?,
-            btoi::btoi(size).map_err(|e| {
+            btoi::btou(size).map_err(|e| {
                 Error::ParseIntegerError("Object size in header could not be parsed", size.to_owned(), e)
             })?,
             header_end + 1, // account for 0 byte
@@ -31,7 +31,7 @@ impl loose::Object {
         loose::object::header::encode(kind, size as u64, &mut sink).expect("hash to always work");
         io::copy(&mut reader, &mut sink)?;
 
-        let actual_id = owned::Id::from(sink.hash.digest());
+        let actual_id = sink.hash.digest();
         if desired != actual_id.to_borrowed() {
             return Err(Error::ChecksumMismatch(desired.into(), actual_id));
         }
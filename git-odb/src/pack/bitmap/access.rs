@@ -0,0 +1,113 @@
+use crate::pack::{
+    self,
+    bitmap::{self, ewah, Bitmap},
+};
+use byteorder::{BigEndian, ByteOrder};
+use git_object::{borrowed, owned};
+use quick_error::quick_error;
+use std::mem::size_of;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Ewah(err: ewah::Error) {
+            display("A bitmap could not be decoded")
+            from()
+            source(err)
+        }
+        NotFound(id: owned::Id) {
+            display("Object {} is not contained in the pack", id)
+        }
+        NoBitmap(id: owned::Id) {
+            display("There is no bitmap for commit {}", id)
+        }
+        ReverseIndex(err: pack::rev::init::Error) {
+            display("The reverse index of the pack could not be loaded")
+            from()
+            source(err)
+        }
+    }
+}
+
+const N32_SIZE: usize = size_of::<u32>();
+
+/// Access to bitmaps
+impl bitmap::File {
+    /// The bitmap of all commits in the pack.
+    pub fn commits(&self) -> &ewah::Vec {
+        &self.commits
+    }
+    /// The bitmap of all trees in the pack.
+    pub fn trees(&self) -> &ewah::Vec {
+        &self.trees
+    }
+    /// The bitmap of all blobs in the pack.
+    pub fn blobs(&self) -> &ewah::Vec {
+        &self.blobs
+    }
+    /// The bitmap of all tags in the pack.
+    pub fn tags(&self) -> &ewah::Vec {
+        &self.tags
+    }
+
+    /// Returns the bitmap of all objects reachable from the commit at `index_position` in the pack index, or `None`
+    /// if there is no bitmap for it.
+    pub fn bitmap_at_index_position(&self, index_position: u32) -> Option<Result<Bitmap, Error>> {
+        self.entry_by_index_position
+            .get(&index_position)
+            .map(|entry_index| self.bitmap_at_entry(*entry_index))
+    }
+
+    /// Returns the bitmap of all objects reachable from the commit with the given `id`, using `index` to find it,
+    /// or `None` if there is no bitmap for it.
+    pub fn bitmap_for_commit(&self, id: borrowed::Id, index: &pack::index::File) -> Option<Result<Bitmap, Error>> {
+        index
+            .lookup(id)
+            .and_then(|index_position| self.bitmap_at_index_position(index_position))
+    }
+
+    /// Returns a bitmap of all objects reachable from any of the given `commits` using `index` to find them,
+    /// without traversing any trees.
+    ///
+    /// All commits must have a bitmap.
+    pub fn reachable<'a>(
+        &self,
+        index: &pack::index::File,
+        commits: impl IntoIterator<Item = borrowed::Id<'a>>,
+    ) -> Result<Bitmap, Error> {
+        let mut reachable = Bitmap::default();
+        for id in commits {
+            let index_position = index.lookup(id).ok_or_else(|| Error::NotFound(id.into()))?;
+            let bitmap = self
+                .bitmap_at_index_position(index_position)
+                .ok_or_else(|| Error::NoBitmap(id.into()))??;
+            reachable.or(&bitmap);
+        }
+        Ok(reachable)
+    }
+
+    /// Returns the hash of the path at which the object at `index_position` in the pack index was found,
+    /// if the hash cache is present.
+    pub fn name_hash_at_index_position(&self, index_position: u32) -> Option<u32> {
+        let range = self.hash_cache.as_ref()?;
+        let start = range.start + index_position as usize * N32_SIZE;
+        if start + N32_SIZE > range.end {
+            return None;
+        }
+        Some(BigEndian::read_u32(&self.data[start..]))
+    }
+
+    fn bitmap_at_entry(&self, mut entry_index: usize) -> Result<Bitmap, Error> {
+        let mut bitmap = self.entries[entry_index].bitmap.decompress()?;
+        // Bitmaps may be stored as xor against a previous bitmap, which in turn may be xored against another one
+        loop {
+            let xor_offset = self.entries[entry_index].xor_offset as usize;
+            if xor_offset == 0 {
+                break;
+            }
+            entry_index -= xor_offset;
+            bitmap.xor(&self.entries[entry_index].bitmap.decompress()?);
+        }
+        Ok(bitmap)
+    }
+}
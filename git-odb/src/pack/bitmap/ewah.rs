@@ -0,0 +1,153 @@
+//! Encoding and decoding of bitmaps compressed with the [EWAH] scheme, as used in `.bitmap` files.
+//!
+//! [EWAH]: https://github.com/git/git/blob/master/Documentation/technical/bitmap-format.txt
+use crate::pack::bitmap::Bitmap;
+use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
+use quick_error::quick_error;
+use std::{io, mem::size_of};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Corrupt(msg: &'static str) {
+            display("{}", msg)
+        }
+    }
+}
+
+const N32_SIZE: usize = size_of::<u32>();
+const N64_SIZE: usize = size_of::<u64>();
+const RUNNING_LEN_BITS: u32 = 32;
+const LITERAL_WORDS_BITS: u32 = 31;
+const MAX_RUNNING_LEN: usize = (1 << RUNNING_LEN_BITS) - 1;
+const MAX_LITERAL_WORDS: usize = (1 << LITERAL_WORDS_BITS) - 1;
+
+/// A compressed bitmap as stored on disk, consisting of run-length encoded words of all zeroes or all ones
+/// followed by literal words.
+#[derive(PartialEq, Eq, Debug, Hash, Clone)]
+pub struct Vec {
+    num_bits: u32,
+    words: std::vec::Vec<u64>,
+}
+
+impl Vec {
+    /// Decode a compressed bitmap from the beginning of `data`, returning it along with all bytes following it.
+    pub fn from_bytes(data: &[u8]) -> Result<(Vec, &[u8]), Error> {
+        if data.len() < N32_SIZE * 2 {
+            return Err(Error::Corrupt("Not enough bytes for the bitmap header"));
+        }
+        let num_bits = BigEndian::read_u32(data);
+        let num_words = BigEndian::read_u32(&data[N32_SIZE..]) as usize;
+        let data = &data[N32_SIZE * 2..];
+        let words_len = num_words
+            .checked_mul(N64_SIZE)
+            .filter(|len| len + N32_SIZE <= data.len())
+            .ok_or(Error::Corrupt("Not enough bytes for all words of the bitmap"))?;
+        let words = data[..words_len].chunks(N64_SIZE).map(BigEndian::read_u64).collect();
+        // The trailing position of the last run-length word is only needed when appending, which we don't do
+        Ok((Vec { num_bits, words }, &data[words_len + N32_SIZE..]))
+    }
+
+    /// Compress `bitmap` by run-length encoding all words whose bits are all set or all unset.
+    ///
+    /// Like git, the amount of bits is determined by the last set bit.
+    pub fn compress(bitmap: &Bitmap) -> Vec {
+        let num_bits = bitmap.iter_ones().last().map(|position| position + 1).unwrap_or(0);
+        let uncompressed = &bitmap.words[..num_bits.div_ceil(64)];
+        let mut words = std::vec::Vec::new();
+        let mut remaining = uncompressed;
+        loop {
+            let rlw_index = words.len();
+            words.push(0);
+            let running_word = match remaining.first() {
+                Some(word) if *word == 0 || *word == u64::MAX => Some(*word),
+                _ => None,
+            };
+            let running_len = running_word
+                .map(|running_word| {
+                    remaining
+                        .iter()
+                        .take(MAX_RUNNING_LEN)
+                        .take_while(|word| **word == running_word)
+                        .count()
+                })
+                .unwrap_or(0);
+            remaining = &remaining[running_len..];
+            let literal_words = remaining
+                .iter()
+                .take(MAX_LITERAL_WORDS)
+                .take_while(|word| **word != 0 && **word != u64::MAX)
+                .count();
+            words.extend_from_slice(&remaining[..literal_words]);
+            remaining = &remaining[literal_words..];
+            words[rlw_index] = (running_word == Some(u64::MAX)) as u64
+                | (running_len as u64) << 1
+                | (literal_words as u64) << (1 + RUNNING_LEN_BITS);
+            if remaining.is_empty() {
+                break;
+            }
+        }
+        Vec {
+            num_bits: num_bits as u32,
+            words,
+        }
+    }
+
+    /// Write this bitmap to `out` in the format understood by [`from_bytes()`][Vec::from_bytes()].
+    pub fn write_to(&self, mut out: impl io::Write) -> io::Result<()> {
+        out.write_u32::<BigEndian>(self.num_bits)?;
+        out.write_u32::<BigEndian>(self.words.len() as u32)?;
+        for word in &self.words {
+            out.write_u64::<BigEndian>(*word)?;
+        }
+        out.write_u32::<BigEndian>(self.last_rlw_index() as u32)
+    }
+
+    /// The amount of bits represented by this bitmap.
+    pub fn num_bits(&self) -> u32 {
+        self.num_bits
+    }
+
+    /// The amount of compressed words, which determines the size of this bitmap on disk.
+    pub fn num_words(&self) -> usize {
+        self.words.len()
+    }
+
+    /// Expand all run-length encoded words into a bitmap that can be accessed directly.
+    pub fn decompress(&self) -> Result<Bitmap, Error> {
+        let max_words = (self.num_bits as usize).div_ceil(64);
+        let mut words = std::vec::Vec::with_capacity(max_words);
+        let mut remaining = &self.words[..];
+        while let Some((rlw, rest)) = remaining.split_first() {
+            let running_bit = rlw & 1 == 1;
+            let running_len = (rlw >> 1) & ((1 << RUNNING_LEN_BITS) - 1);
+            let literal_words = (rlw >> (1 + RUNNING_LEN_BITS)) & ((1 << LITERAL_WORDS_BITS) - 1);
+            if literal_words as usize > rest.len() {
+                return Err(Error::Corrupt(
+                    "A run-length word refers to more literal words than available",
+                ));
+            }
+            if words.len() + running_len as usize + literal_words as usize > max_words {
+                return Err(Error::Corrupt("The bitmap has more words than announced"));
+            }
+            words.extend(std::iter::repeat_n(
+                if running_bit { u64::MAX } else { 0 },
+                running_len as usize,
+            ));
+            let (literals, rest) = rest.split_at(literal_words as usize);
+            words.extend_from_slice(literals);
+            remaining = rest;
+        }
+        Ok(Bitmap::from_words(words, self.num_bits as usize))
+    }
+
+    fn last_rlw_index(&self) -> usize {
+        let mut rlw_index = 0;
+        let mut next_rlw_index = 0;
+        while let Some(rlw) = self.words.get(next_rlw_index) {
+            rlw_index = next_rlw_index;
+            next_rlw_index += 1 + ((rlw >> (1 + RUNNING_LEN_BITS)) & ((1 << LITERAL_WORDS_BITS) - 1)) as usize;
+        }
+        rlw_index
+    }
+}
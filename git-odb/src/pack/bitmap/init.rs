@@ -0,0 +1,134 @@
+use crate::pack::bitmap::{self, ewah, flags, SIGNATURE, VERSION};
+use byteorder::{BigEndian, ByteOrder};
+use filebuffer::FileBuffer;
+use git_object::{owned, SHA1_SIZE};
+use quick_error::quick_error;
+use std::{collections::HashMap, convert::TryFrom, mem::size_of, path::Path};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: std::io::Error, path: std::path::PathBuf) {
+            display("Could not open bitmap file at '{}'", path.display())
+            source(err)
+        }
+        Corrupt(msg: String) {
+            display("{}", msg)
+        }
+        UnsupportedVersion(version: u16) {
+            display("Unsupported bitmap version: {}", version)
+        }
+        Ewah(err: ewah::Error) {
+            display("A bitmap could not be decoded")
+            from()
+            source(err)
+        }
+        PackMismatch { expected: owned::Id, actual: owned::Id } {
+            display("The bitmap belongs to pack {}, but was expected to belong to pack {}", actual, expected)
+        }
+    }
+}
+
+const N16_SIZE: usize = size_of::<u16>();
+const N32_SIZE: usize = size_of::<u32>();
+const HEADER_LEN: usize = SIGNATURE.len() + N16_SIZE * 2 + N32_SIZE + SHA1_SIZE;
+const LOOKUP_TABLE_ENTRY_LEN: usize = N32_SIZE + size_of::<u64>() + N32_SIZE;
+
+/// Instantiation
+impl bitmap::File {
+    pub fn at(path: impl AsRef<Path>) -> Result<bitmap::File, Error> {
+        Self::try_from(path.as_ref())
+    }
+}
+
+impl TryFrom<&Path> for bitmap::File {
+    type Error = Error;
+
+    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+        let data = FileBuffer::open(path).map_err(|e| Error::Io(e, path.to_owned()))?;
+        if data.len() < HEADER_LEN + SHA1_SIZE {
+            return Err(Error::Corrupt(format!(
+                "Bitmap file of size {} is too small for even an empty bitmap",
+                data.len()
+            )));
+        }
+        let (sig, header) = data[..HEADER_LEN].split_at(SIGNATURE.len());
+        if sig != SIGNATURE {
+            return Err(Error::Corrupt("Invalid signature, expected a bitmap file".into()));
+        }
+        let version = BigEndian::read_u16(header);
+        if version != VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+        let flags = BigEndian::read_u16(&header[N16_SIZE..]);
+        let num_entries = BigEndian::read_u32(&header[N16_SIZE * 2..]) as usize;
+        let pack_checksum = owned::Id::from_20_bytes(&header[N16_SIZE * 2 + N32_SIZE..]);
+
+        let data_end = data.len() - SHA1_SIZE;
+        let d = &data[HEADER_LEN..data_end];
+        let (commits, d) = ewah::Vec::from_bytes(d)?;
+        let (trees, d) = ewah::Vec::from_bytes(d)?;
+        let (blobs, d) = ewah::Vec::from_bytes(d)?;
+        let (tags, mut d) = ewah::Vec::from_bytes(d)?;
+
+        let mut entries = Vec::with_capacity(num_entries.min(d.len()));
+        let mut entry_by_index_position = HashMap::with_capacity(entries.capacity());
+        for entry_index in 0..num_entries {
+            if d.len() < N32_SIZE + 2 {
+                return Err(Error::Corrupt(format!(
+                    "Bitmap entry {} of {} is truncated",
+                    entry_index, num_entries
+                )));
+            }
+            let index_position = BigEndian::read_u32(d);
+            let xor_offset = d[N32_SIZE];
+            let entry_flags = d[N32_SIZE + 1];
+            if xor_offset as usize > entry_index {
+                return Err(Error::Corrupt(format!(
+                    "Bitmap entry {} refers to a non-existing entry {} entries before it",
+                    entry_index, xor_offset
+                )));
+            }
+            let (bitmap, rest) = ewah::Vec::from_bytes(&d[N32_SIZE + 2..])?;
+            d = rest;
+            entry_by_index_position.insert(index_position, entries.len());
+            entries.push(bitmap::Entry {
+                index_position,
+                xor_offset,
+                flags: entry_flags,
+                bitmap,
+            });
+        }
+
+        let hash_cache = if flags & flags::HASH_CACHE != 0 {
+            let start = data_end - d.len();
+            let lookup_table_len = if flags & flags::LOOKUP_TABLE != 0 {
+                num_entries * LOOKUP_TABLE_ENTRY_LEN
+            } else {
+                0
+            };
+            let end = data_end
+                .checked_sub(lookup_table_len)
+                .filter(|end| *end >= start && (end - start).is_multiple_of(N32_SIZE))
+                .ok_or_else(|| Error::Corrupt("The name hash cache has an invalid size".into()))?;
+            Some(start..end)
+        } else {
+            None
+        };
+
+        Ok(bitmap::File {
+            path: path.to_owned(),
+            version,
+            flags,
+            pack_checksum,
+            commits,
+            trees,
+            blobs,
+            tags,
+            entries,
+            entry_by_index_position,
+            hash_cache,
+            data,
+        })
+    }
+}
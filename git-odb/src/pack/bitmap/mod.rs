@@ -0,0 +1,173 @@
+//! Reachability bitmaps as stored in `.bitmap` files next to a pack, which allow to determine all objects reachable
+//! from a commit without traversing any trees.
+//!
+//! Each bit refers to an object by its position in the pack, ordered by pack offset.
+use filebuffer::FileBuffer;
+use git_object::owned;
+use std::{collections::HashMap, path::PathBuf};
+
+const SIGNATURE: &[u8] = b"BITM";
+const VERSION: u16 = 1;
+
+/// Flags in the header of a `.bitmap` file.
+pub(crate) mod flags {
+    /// Bitmaps describe the full graph of objects in the pack
+    pub const FULL_DAG: u16 = 0x1;
+    /// The name hash of each object is stored after the bitmap entries
+    pub const HASH_CACHE: u16 = 0x4;
+    /// A lookup table for the bitmap entries follows the hash cache
+    pub const LOOKUP_TABLE: u16 = 0x10;
+}
+
+pub mod ewah;
+
+/// An uncompressed bitmap with one bit per object in a pack.
+#[derive(PartialEq, Eq, Debug, Hash, Clone, Default)]
+pub struct Bitmap {
+    words: Vec<u64>,
+    num_bits: usize,
+}
+
+impl Bitmap {
+    pub(crate) fn from_words(mut words: Vec<u64>, num_bits: usize) -> Self {
+        words.resize(num_bits.div_ceil(64), 0);
+        if !num_bits.is_multiple_of(64) {
+            if let Some(last) = words.last_mut() {
+                *last &= (1 << (num_bits % 64)) - 1;
+            }
+        }
+        Bitmap { words, num_bits }
+    }
+
+    /// The amount of bits in this bitmap, including unset ones.
+    pub fn len(&self) -> usize {
+        self.num_bits
+    }
+
+    /// Returns true if there are no bits in this bitmap.
+    pub fn is_empty(&self) -> bool {
+        self.num_bits == 0
+    }
+
+    /// Returns true if the bit at `position` is set.
+    pub fn contains(&self, position: usize) -> bool {
+        self.words
+            .get(position / 64)
+            .map(|word| word & (1 << (position % 64)) != 0)
+            .unwrap_or(false)
+    }
+
+    /// Set the bit at `position`, growing the bitmap as needed, and return true if it wasn't set before.
+    pub fn insert(&mut self, position: usize) -> bool {
+        if position >= self.num_bits {
+            self.num_bits = position + 1;
+            self.words.resize(self.num_bits.div_ceil(64), 0);
+        }
+        let word = &mut self.words[position / 64];
+        let mask = 1 << (position % 64);
+        let was_set = *word & mask != 0;
+        *word |= mask;
+        !was_set
+    }
+
+    /// The amount of set bits.
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// Iterate the positions of all set bits in ascending order.
+    pub fn iter_ones<'a>(&'a self) -> impl Iterator<Item = usize> + 'a {
+        self.words.iter().enumerate().flat_map(|(word_index, word)| {
+            let word = *word;
+            (0..64)
+                .filter(move |bit| word & (1 << bit) != 0)
+                .map(move |bit| word_index * 64 + bit)
+        })
+    }
+
+    /// Set all bits that are set in `other`.
+    pub fn or(&mut self, other: &Bitmap) {
+        self.combine(other, |a, b| a | b)
+    }
+
+    /// Flip all bits that are set in `other`.
+    pub fn xor(&mut self, other: &Bitmap) {
+        self.combine(other, |a, b| a ^ b)
+    }
+
+    /// Clear all bits that are not set in `other`.
+    pub fn and(&mut self, other: &Bitmap) {
+        self.combine(other, |a, b| a & b)
+    }
+
+    fn combine(&mut self, other: &Bitmap, op: impl Fn(u64, u64) -> u64) {
+        if other.num_bits > self.num_bits {
+            self.num_bits = other.num_bits;
+            self.words.resize(other.words.len(), 0);
+        }
+        for (index, word) in self.words.iter_mut().enumerate() {
+            *word = op(*word, other.words.get(index).copied().unwrap_or(0));
+        }
+    }
+}
+
+/// A single bitmap of all objects reachable from a commit.
+#[derive(PartialEq, Eq, Debug, Hash, Clone)]
+pub struct Entry {
+    /// The position of the commit in the pack index
+    pub index_position: u32,
+    /// If not 0, the bitmap needs to be xored with the bitmap of the entry this many entries before this one
+    pub xor_offset: u8,
+    pub flags: u8,
+    pub bitmap: ewah::Vec,
+}
+
+/// A `.bitmap` file with reachability bitmaps for some of the commits in the pack it belongs to.
+pub struct File {
+    path: PathBuf,
+    version: u16,
+    flags: u16,
+    pack_checksum: owned::Id,
+    commits: ewah::Vec,
+    trees: ewah::Vec,
+    blobs: ewah::Vec,
+    tags: ewah::Vec,
+    entries: Vec<Entry>,
+    /// A mapping from a commits position in the pack index to its position in `entries`
+    entry_by_index_position: HashMap<u32, usize>,
+    /// The bytes holding the name hash of each object in pack index order, if present
+    hash_cache: Option<std::ops::Range<usize>>,
+    data: FileBuffer,
+}
+
+impl File {
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+    /// The checksum of the pack this file belongs to.
+    pub fn pack_checksum(&self) -> owned::Id {
+        self.pack_checksum.clone()
+    }
+    /// The amount of commits with a bitmap.
+    pub fn num_entries(&self) -> usize {
+        self.entries.len()
+    }
+    /// All commits with a bitmap, in the order in which they are stored.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+    /// Returns true if the bitmaps describe the full graph of objects in the pack.
+    pub fn is_full_dag(&self) -> bool {
+        self.flags & flags::FULL_DAG != 0
+    }
+}
+
+pub mod init;
+
+mod access;
+pub use access::Error;
+
+pub mod write;
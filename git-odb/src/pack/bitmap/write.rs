@@ -0,0 +1,445 @@
+use crate::{
+    hash,
+    pack::{
+        self,
+        bitmap::{self, ewah, flags, Bitmap, SIGNATURE, VERSION},
+    },
+};
+use byteorder::{BigEndian, WriteBytesExt};
+use git_features::progress::{self, Progress};
+use git_object::{borrowed, owned, TreeMode};
+use quick_error::quick_error;
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, Write},
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error) {
+            display("An IO error occurred when writing the bitmap")
+            from()
+            source(err)
+        }
+        Persist(err: tempfile::PersistError) {
+            display("Could not move the bitmap file into its desired place")
+            from()
+            source(err)
+        }
+        TipNotFound(id: owned::Id) {
+            display("The tip {} is not contained in the pack", id)
+        }
+        MissingObject(id: owned::Id) {
+            display("Object {} is reachable from a tip, but not contained in the pack", id)
+        }
+        Locate(err: pack::bundle::locate::Error, id: owned::Id) {
+            display("Object {} could not be decoded", id)
+            source(err)
+        }
+        ReverseIndex(err: pack::rev::init::Error) {
+            display("The reverse index of the pack could not be loaded")
+            from()
+            source(err)
+        }
+        Parse(err: git_object::borrowed::Error, id: owned::Id) {
+            display("Object {} could not be parsed", id)
+            source(err)
+        }
+        InvalidParent(id: owned::Id) {
+            display("A parent of commit {} is not a valid object id", id)
+        }
+        UnsupportedHashKind(kind: git_object::HashKind) {
+            display("Bitmaps can only be written for packs with Sha1 object ids, but the pack uses {:?}", kind)
+        }
+    }
+}
+
+/// The maximum distance to a previous bitmap to xor against, matching the one used by git.
+const MAX_XOR_OFFSET: usize = 10;
+
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Options {
+    /// Besides all tips, a bitmap is written for every commit this many commits apart when walking the history.
+    /// A value of 1 writes a bitmap for every commit.
+    pub commit_spacing: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options { commit_spacing: 100 }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Outcome {
+    /// The hash over all bytes of the bitmap file, also found in its trailing bytes
+    pub bitmap_hash: owned::Id,
+    /// The amount of commits reachable from the tips
+    pub num_commits: u32,
+    /// The amount of commits with a bitmap
+    pub num_entries: u32,
+}
+
+struct Commit {
+    tree: usize,
+    parents: Vec<usize>,
+}
+
+/// Objects of a pack, addressed by their position in the pack, which is the position of their bit.
+struct Objects<'a> {
+    bundle: &'a pack::Bundle,
+    index_position_by_pack_position: Vec<u32>,
+    pack_position_by_index_position: Vec<u32>,
+    buf: Vec<u8>,
+    cache: pack::cache::DecodeEntryLRU,
+}
+
+impl<'a> Objects<'a> {
+    fn new(bundle: &'a pack::Bundle) -> Result<Self, Error> {
+        let index_position_by_pack_position: Vec<_> = bundle.index.reverse_index()?.iter().collect();
+        let mut pack_position_by_index_position = vec![0; index_position_by_pack_position.len()];
+        for (pack_position, index_position) in index_position_by_pack_position.iter().enumerate() {
+            pack_position_by_index_position[*index_position as usize] = pack_position as u32;
+        }
+        Ok(Objects {
+            bundle,
+            index_position_by_pack_position,
+            pack_position_by_index_position,
+            buf: Vec::new(),
+            cache: Default::default(),
+        })
+    }
+
+    fn pack_position(&self, id: borrowed::Id) -> Option<usize> {
+        self.bundle
+            .index
+            .lookup(id)
+            .map(|index_position| self.pack_position_by_index_position[index_position as usize] as usize)
+    }
+
+    fn id(&self, pack_position: usize) -> borrowed::Id<'a> {
+        self.bundle
+            .index
+            .oid_at_index(self.index_position_by_pack_position[pack_position])
+    }
+
+    /// Determine the kind of the object at `pack_position` without decoding it, following delta chains to their base.
+    fn kind(&self, pack_position: usize) -> Option<git_object::Kind> {
+        self.bundle
+            .kind_at_index(self.index_position_by_pack_position[pack_position])
+    }
+
+    fn decode(&mut self, pack_position: usize) -> Result<(git_object::Kind, &[u8]), Error> {
+        let id = self.id(pack_position);
+        let object = decode(self.bundle, id, &mut self.buf, &mut self.cache)?;
+        Ok((object.kind, object.data))
+    }
+
+    fn commit(&mut self, pack_position: usize) -> Result<Commit, Error> {
+        let id = self.id(pack_position);
+        let (tree, parents) = {
+            let (_, data) = self.decode(pack_position)?;
+            let commit = borrowed::Commit::from_bytes(data).map_err(|err| Error::Parse(err, id.into()))?;
+            let parents = commit
+                .parents
+                .iter()
+                .map(|hex| owned::Id::from_hex(hex).map_err(|_| Error::InvalidParent(id.into())))
+                .collect::<Result<Vec<_>, _>>()?;
+            (commit.tree(), parents)
+        };
+        let position = |id: owned::Id| self.pack_position(id.to_borrowed()).ok_or(Error::MissingObject(id));
+        Ok(Commit {
+            tree: position(tree)?,
+            parents: parents.into_iter().map(position).collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Peel tags until a commit is found, or return `None` if the tag points to another kind of object.
+    /// Like git, the name hash of tags is derived from their name.
+    fn peel_to_commit(
+        &mut self,
+        mut pack_position: usize,
+        name_hashes: &mut [Option<u32>],
+    ) -> Result<Option<usize>, Error> {
+        loop {
+            let id = self.id(pack_position);
+            let (kind, data) = self.decode(pack_position)?;
+            match kind {
+                git_object::Kind::Commit => return Ok(Some(pack_position)),
+                git_object::Kind::Tag => {
+                    let tag = borrowed::Tag::from_bytes(data).map_err(|err| Error::Parse(err, id.into()))?;
+                    name_hashes[pack_position].get_or_insert_with(|| pack::data::output::name_hash(tag.name));
+                    let target = tag.target();
+                    pack_position = self
+                        .pack_position(target.to_borrowed())
+                        .ok_or(Error::MissingObject(target))?;
+                }
+                _ => return Ok(None),
+            }
+        }
+    }
+
+    /// Set the bits of the tree at `pack_position` and all trees and blobs it contains, noting the name hash
+    /// of each object the first time it is seen.
+    ///
+    /// Trees whose bit is already set are skipped along with their contents.
+    fn insert_tree(
+        &mut self,
+        pack_position: usize,
+        bitmap: &mut Bitmap,
+        name_hashes: &mut [Option<u32>],
+    ) -> Result<(), Error> {
+        if !bitmap.insert(pack_position) {
+            return Ok(());
+        }
+        name_hashes[pack_position].get_or_insert(0);
+        // Keep the decoded tree while looking up its entries
+        let mut buf = std::mem::take(&mut self.buf);
+        let res = self.insert_tree_entries(pack_position, bitmap, name_hashes, &mut buf);
+        self.buf = buf;
+        res
+    }
+
+    fn insert_tree_entries(
+        &mut self,
+        pack_position: usize,
+        bitmap: &mut Bitmap,
+        name_hashes: &mut [Option<u32>],
+        buf: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        let mut trees = vec![(pack_position, Vec::new())];
+        while let Some((pack_position, path)) = trees.pop() {
+            let id = self.id(pack_position);
+            let object = decode(self.bundle, id, buf, &mut self.cache)?;
+            let tree = borrowed::Tree::from_bytes_with_hash(object.data, id.kind())
+                .map_err(|err| Error::Parse(err, id.into()))?;
+            for entry in tree.entries {
+                if entry.mode == TreeMode::Commit {
+                    // submodules are not part of the pack
+                    continue;
+                }
+                let entry_position = self
+                    .pack_position(entry.oid)
+                    .ok_or_else(|| Error::MissingObject(entry.oid.into()))?;
+                let mut entry_path = path.clone();
+                if !entry_path.is_empty() {
+                    entry_path.push(b'/');
+                }
+                entry_path.extend_from_slice(entry.filename);
+                name_hashes[entry_position].get_or_insert_with(|| pack::data::output::name_hash(&entry_path));
+                if bitmap.insert(entry_position) && entry.mode == TreeMode::Tree {
+                    trees.push((entry_position, entry_path));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn decode<'b>(
+    bundle: &pack::Bundle,
+    id: borrowed::Id,
+    buf: &'b mut Vec<u8>,
+    cache: &mut pack::cache::DecodeEntryLRU,
+) -> Result<pack::Object<'b>, Error> {
+    bundle
+        .locate(id, buf, cache)
+        .ok_or_else(|| Error::MissingObject(id.into()))?
+        .map_err(|err| Error::Locate(err, id.into()))
+}
+
+/// Writing bitmaps for existing packs
+impl bitmap::File {
+    /// Write a bitmap file for the pack in `bundle` to `out`, which is expected to be placed next to the pack with
+    /// the `.bitmap` extension.
+    ///
+    /// Bitmaps are written for all `tips`, with tags being peeled to the commit they point to, and for commits spaced
+    /// out along the history as configured in `options`. All objects reachable from the tips must be contained in the
+    /// pack, and the name hash of each object is derived from the path at which it was first found.
+    ///
+    /// Only packs with SHA1 object ids are supported, as bitmaps for other kinds of hashes can't be read yet.
+    pub fn write_from_bundle<'a>(
+        bundle: &pack::Bundle,
+        tips: impl IntoIterator<Item = borrowed::Id<'a>>,
+        out: impl io::Write,
+        mut progress: impl Progress,
+        Options { commit_spacing }: Options,
+    ) -> Result<Outcome, Error> {
+        let hash_kind = bundle.index.hash_kind();
+        if hash_kind != git_object::HashKind::Sha1 {
+            return Err(Error::UnsupportedHashKind(hash_kind));
+        }
+        let mut objects = Objects::new(bundle)?;
+        let num_objects = bundle.index.num_objects() as usize;
+
+        progress.init(Some(4), progress::steps());
+        let type_bitmaps = {
+            let mut info = progress.add_child("classifying objects");
+            info.init(Some(num_objects), progress::count("objects"));
+            let mut type_bitmaps = [
+                Bitmap::default(),
+                Bitmap::default(),
+                Bitmap::default(),
+                Bitmap::default(),
+            ];
+            for pack_position in 0..num_objects {
+                let slot = match objects.kind(pack_position) {
+                    Some(git_object::Kind::Commit) => 0,
+                    Some(git_object::Kind::Tree) => 1,
+                    Some(git_object::Kind::Blob) => 2,
+                    Some(git_object::Kind::Tag) => 3,
+                    None => return Err(Error::MissingObject(objects.id(pack_position).into())),
+                };
+                type_bitmaps[slot].insert(pack_position);
+                info.inc();
+            }
+            type_bitmaps
+        };
+
+        progress.inc();
+        let mut name_hashes = vec![None; num_objects];
+        let (commits, commits_parents_first, tips) = {
+            let mut info = progress.add_child("walking commits");
+            info.init(None, progress::count("commits"));
+            let mut tip_positions = HashSet::new();
+            for id in tips {
+                let pack_position = objects.pack_position(id).ok_or_else(|| Error::TipNotFound(id.into()))?;
+                if let Some(commit_position) = objects.peel_to_commit(pack_position, &mut name_hashes)? {
+                    tip_positions.insert(commit_position);
+                }
+            }
+            let mut tips: Vec<_> = tip_positions.into_iter().collect();
+            tips.sort_unstable();
+
+            let mut commits = HashMap::new();
+            let mut commits_parents_first = Vec::new();
+            let mut stack: Vec<_> = tips.iter().rev().map(|tip| (*tip, false)).collect();
+            while let Some((pack_position, parents_done)) = stack.pop() {
+                if parents_done {
+                    commits_parents_first.push(pack_position);
+                    continue;
+                }
+                if commits.contains_key(&pack_position) {
+                    continue;
+                }
+                let commit = objects.commit(pack_position)?;
+                stack.push((pack_position, true));
+                stack.extend(
+                    commit
+                        .parents
+                        .iter()
+                        .rev()
+                        .filter(|parent| !commits.contains_key(*parent))
+                        .map(|parent| (*parent, false)),
+                );
+                commits.insert(pack_position, commit);
+                info.inc();
+            }
+            (commits, commits_parents_first, tips)
+        };
+
+        progress.inc();
+        let bitmaps = {
+            let commit_spacing = commit_spacing.max(1);
+            let selected: HashSet<_> = commits_parents_first
+                .iter()
+                .rev()
+                .enumerate()
+                .filter(|(nth, pack_position)| nth % commit_spacing == 0 || tips.binary_search(pack_position).is_ok())
+                .map(|(_, pack_position)| *pack_position)
+                .collect();
+            let mut info = progress.add_child("computing reachability");
+            info.init(Some(selected.len()), progress::count("bitmaps"));
+
+            let mut bitmaps: Vec<(usize, Bitmap)> = Vec::with_capacity(selected.len());
+            let mut bitmap_by_commit: HashMap<usize, usize> = HashMap::new();
+            for commit_position in commits_parents_first.iter().filter(|c| selected.contains(c)) {
+                let mut bitmap = Bitmap::default();
+                let mut stack = vec![*commit_position];
+                while let Some(pack_position) = stack.pop() {
+                    if bitmap.contains(pack_position) {
+                        continue;
+                    }
+                    if let Some(ancestor_bitmap) = bitmap_by_commit.get(&pack_position).map(|idx| &bitmaps[*idx].1) {
+                        bitmap.or(ancestor_bitmap);
+                        continue;
+                    }
+                    bitmap.insert(pack_position);
+                    name_hashes[pack_position].get_or_insert(0);
+                    let commit = &commits[&pack_position];
+                    objects.insert_tree(commit.tree, &mut bitmap, &mut name_hashes)?;
+                    stack.extend(commit.parents.iter().copied());
+                }
+                bitmap_by_commit.insert(*commit_position, bitmaps.len());
+                bitmaps.push((*commit_position, bitmap));
+                info.inc();
+            }
+            bitmaps
+        };
+
+        progress.inc();
+        let _info = progress.add_child("writing bitmap");
+        let entries: Vec<_> = bitmaps
+            .iter()
+            .enumerate()
+            .map(|(entry_index, (commit_position, bitmap))| {
+                let (xor_offset, compressed) = xor_compress(bitmap, &bitmaps[..entry_index]);
+                bitmap::Entry {
+                    index_position: objects.index_position_by_pack_position[*commit_position],
+                    xor_offset,
+                    flags: 0,
+                    bitmap: compressed,
+                }
+            })
+            .collect();
+
+        let mut out = io::BufWriter::with_capacity(8 * 4096, hash::Write::new(out, hash_kind));
+        out.write_all(SIGNATURE)?;
+        out.write_u16::<BigEndian>(VERSION)?;
+        out.write_u16::<BigEndian>(flags::FULL_DAG | flags::HASH_CACHE)?;
+        out.write_u32::<BigEndian>(entries.len() as u32)?;
+        out.write_all(bundle.pack.checksum().as_slice())?;
+        for bitmap in &type_bitmaps {
+            ewah::Vec::compress(bitmap).write_to(&mut out)?;
+        }
+        for entry in &entries {
+            out.write_u32::<BigEndian>(entry.index_position)?;
+            out.write_all(&[entry.xor_offset, entry.flags])?;
+            entry.bitmap.write_to(&mut out)?;
+        }
+        // The name hash cache is ordered like the pack index
+        for pack_position in objects.pack_position_by_index_position.iter() {
+            out.write_u32::<BigEndian>(name_hashes[*pack_position as usize].unwrap_or(0))?;
+        }
+
+        let mut out = out.into_inner().map_err(|err| err.into_error())?;
+        let bitmap_hash: owned::Id = out.hash.digest();
+        out.inner.write_all(bitmap_hash.as_slice())?;
+        out.inner.flush()?;
+
+        progress.inc();
+        Ok(Outcome {
+            bitmap_hash,
+            num_commits: commits.len() as u32,
+            num_entries: entries.len() as u32,
+        })
+    }
+}
+
+/// Compress `bitmap` on its own or xored against one of the most recent `previous` bitmaps, whichever is smallest,
+/// returning the distance to the chosen previous bitmap or 0 along with the compressed bitmap.
+fn xor_compress(bitmap: &Bitmap, previous: &[(usize, Bitmap)]) -> (u8, ewah::Vec) {
+    let mut best = (0, ewah::Vec::compress(bitmap));
+    for (xor_offset, (_, base)) in previous.iter().rev().take(MAX_XOR_OFFSET).enumerate() {
+        let mut xored = bitmap.clone();
+        xored.xor(base);
+        let compressed = ewah::Vec::compress(&xored);
+        if compressed.num_words() < best.1.num_words() {
+            best = (xor_offset as u8 + 1, compressed);
+        }
+    }
+    best
+}
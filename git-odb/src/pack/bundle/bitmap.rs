@@ -0,0 +1,72 @@
+use crate::pack;
+use git_features::progress::Progress;
+use git_object::{borrowed, owned};
+use std::{
+    io::{self, Write},
+    path::Path,
+};
+use tempfile::NamedTempFile;
+
+/// Reachability bitmaps
+impl pack::Bundle {
+    /// Open the `.bitmap` file next to our pack, or return `None` if there is none.
+    pub fn bitmap(&self) -> Option<Result<pack::bitmap::File, pack::bitmap::init::Error>> {
+        let path = self.pack.path().with_extension("bitmap");
+        if !path.is_file() {
+            return None;
+        }
+        Some(pack::bitmap::File::at(path).and_then(|bitmap| {
+            let expected = self.pack.checksum();
+            if bitmap.pack_checksum() == expected {
+                Ok(bitmap)
+            } else {
+                Err(pack::bitmap::init::Error::PackMismatch {
+                    expected,
+                    actual: bitmap.pack_checksum(),
+                })
+            }
+        }))
+    }
+
+    /// Returns the ids of all objects reachable from any of the given `commits` in the order they appear in our pack,
+    /// using the reachability `bitmap` of our pack instead of traversing trees.
+    pub fn reachable_objects<'a>(
+        &self,
+        bitmap: &pack::bitmap::File,
+        commits: impl IntoIterator<Item = borrowed::Id<'a>>,
+    ) -> Result<Vec<owned::Id>, pack::bitmap::Error> {
+        let reachable = bitmap.reachable(&self.index, commits)?;
+        let rev = self.index.reverse_index()?;
+        Ok(reachable
+            .iter_ones()
+            .filter(|pack_position| *pack_position < rev.num_objects() as usize)
+            .map(|pack_position| {
+                let index_position = rev.index_position_at_pack_position(pack_position as u32);
+                self.index.oid_at_index(index_position).into()
+            })
+            .collect())
+    }
+
+    /// Write a `.bitmap` file next to our pack with bitmaps for all `tips` and commits spaced out along their history,
+    /// replacing an existing one. See [`pack::bitmap::File::write_from_bundle()`] for details.
+    ///
+    /// This is typically called on the bundle of a pack that was just created to make serving it faster.
+    pub fn write_bitmap<'a>(
+        &self,
+        tips: impl IntoIterator<Item = borrowed::Id<'a>>,
+        progress: impl Progress,
+        options: pack::bitmap::write::Options,
+    ) -> Result<pack::bitmap::write::Outcome, pack::bitmap::write::Error> {
+        let path = self.pack.path().with_extension("bitmap");
+        let directory = path
+            .parent()
+            .filter(|directory| !directory.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
+        let mut file = NamedTempFile::new_in(directory)?;
+        let outcome =
+            pack::bitmap::File::write_from_bundle(self, tips, io::BufWriter::new(&mut file), progress, options)?;
+        file.flush()?;
+        file.persist(&path)?;
+        Ok(outcome)
+    }
+}
@@ -16,7 +16,8 @@ impl pack::Bundle {
     ///
     /// Note that ref deltas are automatically resolved within this pack only, which makes this implementation unusable
     /// for thin packs.
-    /// For the latter, pack streams are required.
+    /// These can be made self-contained when writing them with [`pack::Bundle::write_to_directory()`] by providing
+    /// a lookup for the missing base objects.
     pub fn locate<'a>(
         &self,
         id: borrowed::Id,
@@ -24,19 +25,15 @@ impl pack::Bundle {
         cache: &mut impl pack::cache::DecodeEntry,
     ) -> Option<Result<pack::Object<'a>, Error>> {
         let idx = self.index.lookup(id)?;
-        let ofs = self.index.pack_offset_at_index(idx);
-        let pack_entry = self.pack.entry(ofs);
-        self.pack
-            .decode_entry(
-                pack_entry,
-                out,
-                |id, _out| {
-                    self.index.lookup(id).map(|idx| {
-                        pack::data::decode::ResolvedBase::InPack(self.pack.entry(self.index.pack_offset_at_index(idx)))
-                    })
-                },
-                cache,
-            )
+        self.entry_at_index(idx)
+            .and_then(|pack_entry| {
+                self.pack.decode_entry(
+                    pack_entry,
+                    out,
+                    |id, _out| self.resolve_in_pack(id).map(pack::data::decode::ResolvedBase::InPack),
+                    cache,
+                )
+            })
             .map_err(Error::Decode)
             .map(move |r| pack::Object {
                 kind: r.kind,
@@ -45,3 +42,75 @@ impl pack::Bundle {
             .into()
     }
 }
+
+/// Object headers
+impl pack::Bundle {
+    /// Obtain the kind and size of the object with `id` without decoding it, or `None` if it isn't in this pack.
+    ///
+    /// Delta chains are followed only as far as needed to learn the kind of their base object, and like with
+    /// [`locate()`][pack::Bundle::locate()], ref delta bases must be in this pack.
+    pub fn header(&self, id: borrowed::Id) -> Option<Result<(git_object::Kind, u64), Error>> {
+        let idx = self.index.lookup(id)?;
+        self.entry_at_index(idx)
+            .and_then(|pack_entry| {
+                self.pack.decode_header(pack_entry, |id| {
+                    self.resolve_in_pack(id)
+                        .map(pack::data::decode::ResolvedHeaderBase::InPack)
+                })
+            })
+            .map_err(Error::Decode)
+            .into()
+    }
+}
+
+impl crate::Locate for pack::Bundle {
+    type Error = Error;
+
+    fn locate<'a>(
+        &self,
+        id: borrowed::Id,
+        buffer: &'a mut Vec<u8>,
+        pack_cache: &mut impl pack::cache::DecodeEntry,
+    ) -> Option<Result<pack::Object<'a>, Self::Error>> {
+        pack::Bundle::locate(self, id, buffer, pack_cache)
+    }
+
+    fn header(
+        &self,
+        id: borrowed::Id,
+        _buffer: &mut Vec<u8>,
+        _pack_cache: &mut impl pack::cache::DecodeEntry,
+    ) -> Option<Result<(git_object::Kind, u64), Self::Error>> {
+        pack::Bundle::header(self, id)
+    }
+}
+
+/// Object streaming
+impl pack::Bundle {
+    /// Obtain a reader for the data of the object with `id`, or `None` if it isn't in this pack, see
+    /// [`pack::data::File::stream_entry()`].
+    ///
+    /// Like with [`locate()`][pack::Bundle::locate()], ref delta bases must be in this pack.
+    pub fn stream(
+        &self,
+        id: borrowed::Id,
+        cache: &mut impl pack::cache::DecodeEntry,
+    ) -> Option<Result<pack::data::stream::Reader<'_>, pack::data::stream::Error>> {
+        let idx = self.index.lookup(id)?;
+        self.entry_at_index(idx)
+            .map_err(pack::data::stream::Error::from)
+            .and_then(|pack_entry| {
+                self.pack.stream_entry(
+                    pack_entry,
+                    |id, _out| self.resolve_in_pack(id).map(pack::data::decode::ResolvedBase::InPack),
+                    cache,
+                )
+            })
+            .into()
+    }
+
+    /// The entry of the object with `id` if it is in this pack and its header can be decoded.
+    fn resolve_in_pack(&self, id: borrowed::Id) -> Option<pack::data::Entry> {
+        self.index.lookup(id).and_then(|idx| self.entry_at_index(idx).ok())
+    }
+}
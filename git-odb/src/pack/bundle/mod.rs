@@ -5,6 +5,7 @@ use std::{
     path::{Path, PathBuf},
 };
 
+mod bitmap;
 pub mod locate;
 pub mod write;
 
@@ -38,6 +39,36 @@ impl Bundle {
     pub fn at(path: impl AsRef<Path>) -> Result<Self, Error> {
         Self::try_from(path.as_ref())
     }
+
+    /// The entry of the object at `index_position` in our index, with only its header decoded.
+    pub fn entry_at_index(&self, index_position: u32) -> Result<pack::data::Entry, pack::data::decode::Error> {
+        let pack_offset = self
+            .index
+            .pack_offset_at_index(index_position)
+            .ok_or(pack::data::decode::Error::IndexOffsetOutOfBounds { index_position })?;
+        self.pack.entry(pack_offset)
+    }
+
+    /// Determine the kind of the object at `index_position` in our index without decoding it, following delta chains
+    /// to their base.
+    ///
+    /// Returns `None` if the base of a `RefDelta` isn't contained in this pack, if the delta chain never ends, or if
+    /// an entry on the way is malformed.
+    pub fn kind_at_index(&self, index_position: u32) -> Option<git_object::Kind> {
+        let mut entry = self.entry_at_index(index_position).ok()?;
+        for _ in 0..self.index.num_objects() {
+            entry = match entry.header {
+                pack::data::Header::OfsDelta { base_distance } => {
+                    self.pack.entry(entry.base_pack_offset(base_distance)?).ok()?
+                }
+                pack::data::Header::RefDelta { base_id } => {
+                    self.entry_at_index(self.index.lookup(base_id.to_borrowed())?).ok()?
+                }
+                header => return header.to_kind(),
+            };
+        }
+        None
+    }
 }
 
 impl TryFrom<&Path> for Bundle {
@@ -48,16 +79,14 @@ impl TryFrom<&Path> for Bundle {
             .extension()
             .and_then(|e| e.to_str())
             .ok_or_else(|| Error::InvalidPath(path.to_owned()))?;
-        Ok(match ext {
-            "idx" => Self {
-                index: pack::index::File::at(path)?,
-                pack: pack::data::File::at(path.with_extension("pack"))?,
-            },
-            "pack" => Self {
-                pack: pack::data::File::at(path)?,
-                index: pack::index::File::at(path.with_extension("idx"))?,
-            },
+        let (index_path, pack_path) = match ext {
+            "idx" => (path.to_owned(), path.with_extension("pack")),
+            "pack" => (path.with_extension("idx"), path.to_owned()),
             _ => return Err(Error::InvalidPath(path.to_owned())),
-        })
+        };
+        // Only the index knows the kind of hash used by both.
+        let index = pack::index::File::at(index_path)?;
+        let pack = pack::data::File::at_with_hash(pack_path, index.hash_kind())?;
+        Ok(Self { pack, index })
     }
 }
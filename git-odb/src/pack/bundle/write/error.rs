@@ -20,10 +20,20 @@ quick_error! {
             from()
             source(err)
         }
+        PackWrite(err: pack::data::output::bytes::Error) {
+            display("The pack could not be written from its entries")
+            from()
+            source(err)
+        }
         IndexWrite(err: pack::index::write::Error) {
             display("The index file could not be written")
             from()
             source(err)
         }
+        IndexInit(err: pack::index::init::Error) {
+            display("The written index file could not be read to create its reverse index")
+            from()
+            source(err)
+        }
     }
 }
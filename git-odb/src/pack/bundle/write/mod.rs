@@ -10,11 +10,11 @@ use std::{
 use tempfile::NamedTempFile;
 
 mod error;
-use error::Error;
+pub use error::Error;
 
 mod types;
 pub use types::Outcome;
-use types::PassThrough;
+use types::{LockWriter, PassThrough};
 
 #[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
@@ -22,25 +22,39 @@ pub struct Options {
     pub thread_limit: Option<usize>,
     pub iteration_mode: pack::data::iter::Mode,
     pub index_kind: pack::index::Kind,
+    /// The kind of hash used for object ids and checksums of the pack and its index
+    pub hash_kind: git_object::HashKind,
+    /// The level at which the bases added to thin packs are compressed
+    pub compression: crate::zlib::CompressionLevel,
 }
 
 impl pack::Bundle {
-    /// If `directory` is `None`, the output will be written to a sink
-    pub fn write_to_directory<P>(
+    /// Read a pack from `pack` and write it along with a newly created index into `directory`.
+    /// If `directory` is `None`, the output will be written to a sink.
+    ///
+    /// `thin_pack_base_object_lookup` is used to find the bases of `RefDelta` objects which are not contained in the pack,
+    /// as is the case for thin packs received over the wire. These bases are appended to the written pack to make it
+    /// self-contained.
+    /// Without it, thin packs cannot be indexed.
+    pub fn write_to_directory<P, L>(
         pack: impl io::Read + Send + 'static,
         pack_size: Option<u64>,
         directory: Option<impl AsRef<Path>>,
         mut progress: P,
+        thin_pack_base_object_lookup: Option<L>,
         Options {
             thread_limit,
             iteration_mode,
             index_kind,
+            hash_kind,
+            compression,
         }: Options,
     ) -> Result<Outcome, Error>
     where
         P: Progress,
         <P as Progress>::SubProgress: std::marker::Send + 'static,
         <<P as Progress>::SubProgress as Progress>::SubProgress: Send,
+        L: crate::Locate,
     {
         let mut read_progress = progress.add_child("read pack");
         read_progress.init(pack_size.map(|s| s as usize), progress::bytes());
@@ -55,43 +69,81 @@ impl pack::Bundle {
             None => NamedTempFile::new()?,
         }));
         let data_path: PathBuf = data_file.lock().path().into();
+        let is_thin_pack_lookup_enabled = thin_pack_base_object_lookup.is_some();
         let pack = PassThrough {
             reader: interrupt::Read { inner: pack },
-            writer: Some(data_file.clone()),
+            // thin packs are altered, so we write them ourselves
+            writer: if is_thin_pack_lookup_enabled {
+                None
+            } else {
+                Some(data_file.clone())
+            },
         };
         let eight_pages = 4096 * 8;
         let buffered_pack = io::BufReader::with_capacity(eight_pages, pack);
-        let pack_entries_iter = pack::data::Iter::new_from_header(
+        let pack_entries_iter = pack::data::Iter::new_from_header_with_hash(
             buffered_pack,
             iteration_mode,
-            pack::data::iter::CompressedBytesMode::CRC32,
+            if is_thin_pack_lookup_enabled {
+                pack::data::iter::CompressedBytesMode::KeepAndCRC32
+            } else {
+                pack::data::iter::CompressedBytesMode::CRC32
+            },
+            hash_kind,
         )?;
         let pack_kind = pack_entries_iter.kind();
         let num_objects = pack_entries_iter.size_hint().0;
         let pack_entries_iter =
             git_features::parallel::EagerIterIf::new(|| num_objects > 25_000, pack_entries_iter, 5_000, 5);
 
-        let (outcome, data_path, index_path) = match directory {
-            Some(directory) => {
-                let directory = directory.as_ref();
-                let mut index_file = NamedTempFile::new_in(directory)?;
-
-                let outcome = pack::index::File::write_data_iter_to_stream(
-                    index_kind,
-                    move || new_pack_file_resolver(data_path),
-                    pack_entries_iter,
-                    thread_limit,
-                    indexing_progress,
-                    &mut index_file,
-                )?;
-
-                let data_path = directory.join(format!("{}.pack", outcome.data_hash.to_sha1_hex_string()));
+        let (outcome, index_file) = match thin_pack_base_object_lookup {
+            Some(lookup) => write_index(
+                index_kind,
+                pack::data::thin::EntriesToBytesIter::new(
+                    pack::data::thin::LookupRefDeltaObjectsIter::new(
+                        pack_entries_iter,
+                        lookup,
+                        {
+                            let data_path = data_path.clone();
+                            move || new_pack_file_resolver(data_path)
+                        },
+                        hash_kind,
+                        compression,
+                    ),
+                    LockWriter {
+                        writer: data_file.clone(),
+                    },
+                    pack_kind,
+                    hash_kind,
+                ),
+                data_path,
+                directory.as_ref().map(|d| d.as_ref()),
+                thread_limit,
+                indexing_progress,
+            )?,
+            None => write_index(
+                index_kind,
+                pack_entries_iter,
+                data_path,
+                directory.as_ref().map(|d| d.as_ref()),
+                thread_limit,
+                indexing_progress,
+            )?,
+        };
+
+        let (data_path, index_path, reverse_index_path) = match (directory, index_file) {
+            (Some(directory), Some(index_file)) => {
+                let data_path = directory
+                    .as_ref()
+                    .join(format!("{}.pack", outcome.data_hash.to_hex_string()));
                 let index_path = data_path.with_extension("idx");
+                let reverse_index_path = data_path.with_extension("rev");
 
                 Arc::try_unwrap(data_file)
                     .expect("only one handle left after pack was consumed")
                     .into_inner()
                     .persist(&data_path)?;
+                write_reverse_index(&index_file, directory.as_ref(), &reverse_index_path)?;
                 index_file
                     .persist(&index_path)
                     .map_err(|err| {
@@ -101,20 +153,9 @@ impl pack::Bundle {
                         ));
                         err
                     })?;
-                (outcome, Some(data_path), Some(index_path))
+                (Some(data_path), Some(index_path), Some(reverse_index_path))
             }
-            None => (
-                pack::index::File::write_data_iter_to_stream(
-                    index_kind,
-                    move || new_pack_file_resolver(data_path),
-                    pack_entries_iter,
-                    thread_limit,
-                    indexing_progress,
-                    io::sink(),
-                )?,
-                None,
-                None,
-            ),
+            _ => (None, None, None),
         };
 
         Ok(Outcome {
@@ -122,10 +163,143 @@ impl pack::Bundle {
             pack_kind,
             data_path,
             index_path,
+            reverse_index_path,
         })
     }
 }
 
+/// Write an index for `pack_entries` into a temporary file in `directory`, or into a sink if `directory` is `None`.
+fn write_index<P>(
+    index_kind: pack::index::Kind,
+    pack_entries: impl Iterator<Item = Result<pack::data::iter::Entry, pack::data::iter::Error>>,
+    data_path: PathBuf,
+    directory: Option<&Path>,
+    thread_limit: Option<usize>,
+    progress: P,
+) -> Result<(pack::index::write::Outcome, Option<NamedTempFile>), Error>
+where
+    P: Progress,
+    <P as Progress>::SubProgress: Send,
+{
+    Ok(match directory {
+        Some(directory) => {
+            let mut index_file = NamedTempFile::new_in(directory)?;
+            let outcome = pack::index::File::write_data_iter_to_stream(
+                index_kind,
+                move || new_pack_file_resolver(data_path),
+                pack_entries,
+                thread_limit,
+                progress,
+                &mut index_file,
+            )?;
+            (outcome, Some(index_file))
+        }
+        None => (
+            pack::index::File::write_data_iter_to_stream(
+                index_kind,
+                move || new_pack_file_resolver(data_path),
+                pack_entries,
+                thread_limit,
+                progress,
+                io::sink(),
+            )?,
+            None,
+        ),
+    })
+}
+
+impl pack::Bundle {
+    /// Write a new pack made from `num_entries` of `entries` along with its index into `directory`, or into a sink if it is `None`.
+    /// `entries` are typically produced by [`pack::data::output::objects_to_entries_iter()`].
+    ///
+    /// Note that the `iteration_mode` and `compression` of `options` are ignored as the pack is not read, but written
+    /// from entries which are already compressed.
+    /// To make the new pack faster to serve, use [`pack::Bundle::write_bitmap()`] on the bundle at the returned paths.
+    pub fn write_entries_to_directory<P>(
+        entries: impl Iterator<Item = Result<Vec<pack::data::output::Entry>, pack::data::output::Error>>,
+        num_entries: u32,
+        directory: Option<impl AsRef<Path>>,
+        mut progress: P,
+        Options {
+            thread_limit,
+            iteration_mode: _,
+            index_kind,
+            hash_kind,
+            compression: _,
+        }: Options,
+    ) -> Result<Outcome, Error>
+    where
+        P: Progress,
+        <<P as Progress>::SubProgress as Progress>::SubProgress: Send,
+    {
+        let mut data_file = match directory.as_ref() {
+            Some(directory) => NamedTempFile::new_in(directory.as_ref())?,
+            None => NamedTempFile::new()?,
+        };
+        let data_path: PathBuf = data_file.path().into();
+
+        let pack_entries = {
+            let mut write_progress = progress.add_child("write pack");
+            write_progress.init(Some(num_entries as usize), progress::count("entries"));
+            let mut pack_entries = Vec::with_capacity(num_entries as usize);
+            for chunk in pack::data::output::bytes::FromEntriesIter::new(
+                entries,
+                io::BufWriter::with_capacity(4096 * 8, &mut data_file),
+                num_entries,
+                hash_kind,
+            ) {
+                let chunk = chunk?;
+                write_progress.inc_by(chunk.len());
+                pack_entries.extend(chunk);
+            }
+            pack_entries
+        };
+
+        let (outcome, index_file) = write_index(
+            index_kind,
+            pack_entries.into_iter().map(Ok),
+            data_path,
+            directory.as_ref().map(|d| d.as_ref()),
+            thread_limit,
+            progress.add_child("create index file"),
+        )?;
+        let (data_path, index_path, reverse_index_path) = match (directory, index_file) {
+            (Some(directory), Some(index_file)) => {
+                let data_path = directory
+                    .as_ref()
+                    .join(format!("{}.pack", outcome.data_hash.to_hex_string()));
+                let index_path = data_path.with_extension("idx");
+                let reverse_index_path = data_path.with_extension("rev");
+
+                data_file.persist(&data_path)?;
+                write_reverse_index(&index_file, directory.as_ref(), &reverse_index_path)?;
+                index_file.persist(&index_path)?;
+                (Some(data_path), Some(index_path), Some(reverse_index_path))
+            }
+            _ => (None, None, None),
+        };
+
+        Ok(Outcome {
+            index: outcome,
+            pack_kind: pack::data::Kind::V2,
+            data_path,
+            index_path,
+            reverse_index_path,
+        })
+    }
+}
+
+/// Write the reverse index of the not yet persisted `index_file` to `reverse_index_path` within `directory`.
+///
+/// Like git, we do this before moving the index into place, as only then the pack is considered usable.
+fn write_reverse_index(index_file: &NamedTempFile, directory: &Path, reverse_index_path: &Path) -> Result<(), Error> {
+    let index = pack::index::File::at(index_file.path())?;
+    let mut reverse_index_file = NamedTempFile::new_in(directory)?;
+    pack::rev::File::write_from_index(&index, &mut reverse_index_file)?;
+    reverse_index_file.persist(reverse_index_path)?;
+    Ok(())
+}
+
 fn new_pack_file_resolver(
     data_path: PathBuf,
 ) -> io::Result<impl Fn(pack::data::EntrySlice, &mut Vec<u8>) -> Option<()> + Send + Sync> {
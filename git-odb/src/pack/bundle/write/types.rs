@@ -1,5 +1,9 @@
 use crate::pack;
-use std::{io, path::PathBuf, sync::Arc};
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+    sync::Arc,
+};
 use tempfile::NamedTempFile;
 
 #[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
@@ -10,6 +14,8 @@ pub struct Outcome {
 
     pub index_path: Option<PathBuf>,
     pub data_path: Option<PathBuf>,
+    /// The path of the reverse index written alongside the index
+    pub reverse_index_path: Option<PathBuf>,
 }
 
 impl Outcome {
@@ -30,9 +36,35 @@ where
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         let bytes_read = self.reader.read(buf)?;
         if let Some(writer) = self.writer.as_mut() {
-            use io::Write;
             writer.lock().write_all(&buf[..bytes_read])?;
         }
         Ok(bytes_read)
     }
 }
+
+/// Provides access to a shared temporary file, locking it for each operation.
+pub(crate) struct LockWriter {
+    pub writer: Arc<parking_lot::Mutex<NamedTempFile>>,
+}
+
+impl io::Write for LockWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.lock().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.lock().flush()
+    }
+}
+
+impl io::Read for LockWriter {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.writer.lock().read(buf)
+    }
+}
+
+impl io::Seek for LockWriter {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        self.writer.lock().seek(pos)
+    }
+}
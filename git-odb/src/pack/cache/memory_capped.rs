@@ -0,0 +1,148 @@
+use super::DecodeEntry;
+use std::collections::{BTreeSet, HashMap};
+
+/// A cache of decoded pack entries bounded by the total amount of bytes it holds, which can be shared among threads
+/// as `&DecodeEntryMemoryCapped` implements [`DecodeEntry`] as well.
+///
+/// If space is needed, the entries which are cheapest to decode again are evicted first, with the cost of an entry
+/// being the length of the delta chain that produced it. Evicting an entry ages all others, so that expensive entries
+/// which aren't used anymore are evicted eventually, similar to the _GreedyDual_ algorithm.
+pub struct DecodeEntryMemoryCapped {
+    capacity: usize,
+    state: parking_lot::Mutex<State>,
+}
+
+struct Entry {
+    data: Vec<u8>,
+    kind: git_object::Kind,
+    compressed_size: usize,
+    cost: u64,
+    priority: Priority,
+}
+
+/// The value of an entry and when it was last used, to evict the least recently used one among entries of the same value.
+type Priority = (u64, u64);
+
+#[derive(Default)]
+struct State {
+    entries: HashMap<u64, Entry>,
+    by_priority: BTreeSet<(Priority, u64)>,
+    /// The value of the entry evicted last, which is the base value of all entries that are inserted or used afterwards.
+    inflation: u64,
+    clock: u64,
+    bytes: usize,
+}
+
+impl DecodeEntryMemoryCapped {
+    /// Create a new cache holding no more than `capacity_in_bytes` of decoded object data.
+    pub fn new(capacity_in_bytes: usize) -> Self {
+        DecodeEntryMemoryCapped {
+            capacity: capacity_in_bytes,
+            state: Default::default(),
+        }
+    }
+
+    /// The maximum amount of bytes of object data this cache holds.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The amount of bytes of object data currently held.
+    pub fn bytes_used(&self) -> usize {
+        self.state.lock().bytes
+    }
+
+    /// The amount of objects currently held.
+    pub fn num_entries(&self) -> usize {
+        self.state.lock().entries.len()
+    }
+}
+
+impl State {
+    fn next_priority(&mut self, cost: u64) -> Priority {
+        self.clock += 1;
+        (self.inflation + cost, self.clock)
+    }
+
+    fn remove(&mut self, offset: u64) {
+        if let Some(entry) = self.entries.remove(&offset) {
+            self.by_priority.remove(&(entry.priority, offset));
+            self.bytes -= entry.data.len();
+        }
+    }
+
+    fn evict_one(&mut self) {
+        let (priority, offset) = *self
+            .by_priority
+            .iter()
+            .next()
+            .expect("an entry to evict if bytes are used");
+        self.inflation = priority.0;
+        self.remove(offset);
+    }
+}
+
+impl DecodeEntry for &DecodeEntryMemoryCapped {
+    fn put(
+        &mut self,
+        offset: u64,
+        data: &[u8],
+        kind: git_object::Kind,
+        compressed_size: usize,
+        delta_chain_length: u32,
+    ) {
+        if data.len() > self.capacity {
+            return;
+        }
+        let mut state = self.state.lock();
+        state.remove(offset);
+        while state.bytes + data.len() > self.capacity {
+            state.evict_one();
+        }
+        let cost = delta_chain_length as u64 + 1;
+        let priority = state.next_priority(cost);
+        state.by_priority.insert((priority, offset));
+        state.bytes += data.len();
+        state.entries.insert(
+            offset,
+            Entry {
+                data: data.to_owned(),
+                kind,
+                compressed_size,
+                cost,
+                priority,
+            },
+        );
+    }
+
+    fn get(&mut self, offset: u64, out: &mut Vec<u8>) -> Option<(git_object::Kind, usize)> {
+        let mut state = self.state.lock();
+        let state = &mut *state;
+        let entry = state.entries.get_mut(&offset)?;
+        state.by_priority.remove(&(entry.priority, offset));
+        state.clock += 1;
+        entry.priority = (state.inflation + entry.cost, state.clock);
+        state.by_priority.insert((entry.priority, offset));
+
+        out.resize(entry.data.len(), 0);
+        out.copy_from_slice(&entry.data);
+        Some((entry.kind, entry.compressed_size))
+    }
+}
+
+impl DecodeEntry for DecodeEntryMemoryCapped {
+    fn put(
+        &mut self,
+        offset: u64,
+        data: &[u8],
+        kind: git_object::Kind,
+        compressed_size: usize,
+        delta_chain_length: u32,
+    ) {
+        (&*self).put(offset, data, kind, compressed_size, delta_chain_length)
+    }
+
+    fn get(&mut self, offset: u64, out: &mut Vec<u8>) -> Option<(git_object::Kind, usize)> {
+        (&*self).get(offset, out)
+    }
+}
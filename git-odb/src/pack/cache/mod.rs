@@ -0,0 +1,126 @@
+/// A cache for decoded objects, keyed by the offset of their entry in a pack, to speed up the decoding of delta chains.
+pub trait DecodeEntry {
+    /// Store the decoded `data` of the entry at `offset`, whose `kind` and `compressed_size` are returned by `get()`.
+    ///
+    /// `delta_chain_length` is the amount of deltas that were applied to produce `data`, or 0 for undeltified
+    /// objects, and serves as measure of how costly it is to decode the object again.
+    fn put(
+        &mut self,
+        offset: u64,
+        data: &[u8],
+        kind: git_object::Kind,
+        compressed_size: usize,
+        delta_chain_length: u32,
+    );
+    fn get(&mut self, offset: u64, out: &mut Vec<u8>) -> Option<(git_object::Kind, usize)>;
+}
+
+pub struct DecodeEntryNoop;
+
+impl DecodeEntry for DecodeEntryNoop {
+    fn put(
+        &mut self,
+        _offset: u64,
+        _data: &[u8],
+        _kind: git_object::Kind,
+        _compressed_size: usize,
+        _delta_chain_length: u32,
+    ) {
+    }
+    fn get(&mut self, _offset: u64, _out: &mut Vec<u8>) -> Option<(git_object::Kind, usize)> {
+        None
+    }
+}
+
+struct LRUEntry {
+    offset: u64,
+    data: Vec<u8>,
+    kind: git_object::Kind,
+    compressed_size: usize,
+}
+
+#[derive(Default)]
+pub struct DecodeEntryLRU(uluru::LRUCache<[uluru::Entry<LRUEntry>; 64]>);
+
+impl DecodeEntry for DecodeEntryLRU {
+    fn put(
+        &mut self,
+        offset: u64,
+        data: &[u8],
+        kind: git_object::Kind,
+        compressed_size: usize,
+        _delta_chain_length: u32,
+    ) {
+        self.0.insert(LRUEntry {
+            offset,
+            data: Vec::from(data),
+            kind,
+            compressed_size,
+        })
+    }
+
+    fn get(&mut self, offset: u64, out: &mut Vec<u8>) -> Option<(git_object::Kind, usize)> {
+        self.0.lookup(|e: &mut LRUEntry| {
+            if e.offset == offset {
+                out.resize(e.data.len(), 0);
+                out.copy_from_slice(&e.data);
+                Some((e.kind, e.compressed_size))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+mod memory_capped;
+pub use memory_capped::DecodeEntryMemoryCapped;
+
+/// The amount of lookups in a cache that could or could not be answered.
+#[derive(Default, Debug, PartialEq, Eq, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Statistics {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// A cache counting the hits and misses of the `inner` cache, to learn how well it works for any implementation.
+pub(crate) struct Counting<C> {
+    pub inner: C,
+    pub statistics: Statistics,
+}
+
+impl<C> Counting<C> {
+    pub fn new(inner: C) -> Self {
+        Counting {
+            inner,
+            statistics: Statistics::default(),
+        }
+    }
+
+    /// Return the statistics gathered so far and start counting from zero.
+    pub fn take_statistics(&mut self) -> Statistics {
+        std::mem::take(&mut self.statistics)
+    }
+}
+
+impl<C: DecodeEntry> DecodeEntry for Counting<C> {
+    fn put(
+        &mut self,
+        offset: u64,
+        data: &[u8],
+        kind: git_object::Kind,
+        compressed_size: usize,
+        delta_chain_length: u32,
+    ) {
+        self.inner.put(offset, data, kind, compressed_size, delta_chain_length)
+    }
+
+    fn get(&mut self, offset: u64, out: &mut Vec<u8>) -> Option<(git_object::Kind, usize)> {
+        let res = self.inner.get(offset, out);
+        match res {
+            Some(_) => self.statistics.hits += 1,
+            None => self.statistics.misses += 1,
+        }
+        res
+    }
+}
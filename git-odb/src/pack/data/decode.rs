@@ -5,7 +5,7 @@ use crate::{
 use git_object::{self as object, borrowed, owned};
 use quick_error::quick_error;
 use smallvec::SmallVec;
-use std::{convert::TryInto, io, ops::Range};
+use std::{convert::TryInto, ops::Range};
 
 quick_error! {
     #[derive(Debug)]
@@ -17,9 +17,34 @@ quick_error! {
         DeltaBaseUnresolved(id: owned::Id) {
             display("A delta chain could not be applied as the ref base with id {} could not be found", id)
         }
+        EntryHeader(err: pack::data::header::Error, pack_offset: u64) {
+            display("The header of the entry at offset {} could not be decoded", pack_offset)
+            source(err)
+        }
+        OffsetOutOfBounds(pack_offset: u64) {
+            display("The pack offset {} lies outside of the pack", pack_offset)
+        }
+        IndexOffsetOutOfBounds { index_position: u32 } {
+            display("The pack index refers to a large offset it doesn't contain for the object at index {}", index_position)
+        }
+        DeltaBaseOffsetOutOfBounds { pack_offset: u64, distance: u64 } {
+            display("The delta at offset {} refers to a base at distance {}, which lies outside of the pack", pack_offset, distance)
+        }
+        DeltaBaseSizeMismatch { expected: u64, actual: u64 } {
+            display("The delta expects a base of {} bytes, but the base has {} bytes", expected, actual)
+        }
+        Delta(msg: &'static str) {
+            display("Invalid delta: {}", msg)
+        }
+        ImplausibleSize { pack_offset: u64, size: u64 } {
+            display("The entry at offset {} claims to decompress to {} bytes, more than its compressed data can hold", pack_offset, size)
+        }
     }
 }
 
+/// Deflate can't compress data by more than this factor, which bounds the size an entry can claim to have.
+const MAX_DEFLATE_RATIO: u64 = 1032;
+
 #[derive(Debug)]
 struct Delta {
     data: Range<usize>,
@@ -37,6 +62,17 @@ pub enum ResolvedBase {
     OutOfPack { kind: object::Kind, end: usize },
 }
 
+/// The base of a `RefDelta` entry as far as needed to learn the kind of an object, see [`File::decode_header()`].
+#[derive(Debug, PartialEq, Eq, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum ResolvedHeaderBase {
+    InPack(pack::data::Entry),
+    OutOfPack { kind: object::Kind },
+}
+
+/// The most bytes the base and result size at the beginning of a delta can occupy, as each is a 64 bit varint.
+const DELTA_SIZES_MAX_LEN: usize = 2 * 10;
+
 #[derive(Debug, PartialEq, Eq, Hash, Ord, PartialOrd, Clone)]
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 pub struct Outcome {
@@ -83,24 +119,29 @@ impl File {
         self.decompress_entry_from_data_offset(entry.data_offset, out)
     }
 
-    fn assure_v2(&self) {
-        assert!(
-            if let crate::pack::data::Kind::V2 = self.kind {
-                true
-            } else {
-                false
-            },
-            "Only V2 is implemented"
-        );
+    /// Parse the header of the entry at pack `offset`, which fails if it lies outside of the pack or is malformed.
+    ///
+    /// Note that V3 packs are read like V2 packs, as their entries are the same.
+    pub fn entry(&self, offset: u64) -> Result<pack::data::Entry, Error> {
+        let object_data = offset
+            .try_into()
+            .ok()
+            .filter(|offset| *offset >= pack::data::File::HEADER_LEN)
+            .and_then(|offset: usize| self.data.get(offset..self.pack_end()))
+            .ok_or(Error::OffsetOutOfBounds(offset))?;
+        pack::data::Entry::from_bytes(object_data, offset, self.hash_kind)
+            .map_err(|err| Error::EntryHeader(err, offset))
     }
 
-    pub fn entry(&self, offset: u64) -> pack::data::Entry {
-        self.assure_v2();
-        let pack_offset: usize = offset.try_into().expect("offset representable by machine");
-        assert!(pack_offset <= self.data.len(), "offset out of bounds");
-
-        let object_data = &self.data[pack_offset..];
-        pack::data::Entry::from_bytes(object_data, offset)
+    /// The entry at the base of the `OfsDelta` `entry` which is at `distance`.
+    pub(crate) fn base_entry(&self, entry: &pack::data::Entry, distance: u64) -> Result<pack::data::Entry, Error> {
+        let base_offset = entry
+            .base_pack_offset(distance)
+            .ok_or_else(|| Error::DeltaBaseOffsetOutOfBounds {
+                pack_offset: entry.pack_offset(),
+                distance,
+            })?;
+        self.entry(base_offset)
     }
 
     /// Decompress the object expected at the given data offset, sans pack header. This information is only
@@ -109,15 +150,37 @@ impl File {
     /// `out` is expected to be large enough to hold `entry.size` bytes.
     /// Returns the amount of packed bytes there were decompressed into `out`
     fn decompress_entry_from_data_offset(&self, data_offset: u64, out: &mut [u8]) -> Result<usize, Error> {
-        let offset: usize = data_offset.try_into().expect("offset representable by machine");
-        assert!(offset < self.data.len(), "entry offset out of bounds");
-
+        let data = self.data_at(data_offset)?;
         zlib::Inflate::default()
-            .once(&self.data[offset..], out, true)
+            .once(data, out)
             .map_err(|e| Error::ZlibInflate(e, "Failed to decompress pack entry"))
             .map(|(_, consumed_in, _)| consumed_in)
     }
 
+    /// The decompressed size of `entry`, as long as the compressed data following it could possibly produce it.
+    ///
+    /// This prevents allocating huge buffers for entries whose header claims sizes they can't have.
+    fn plausible_decompressed_size(&self, entry: &pack::data::Entry) -> Result<usize, Error> {
+        let implausible = || Error::ImplausibleSize {
+            pack_offset: entry.pack_offset(),
+            size: entry.decompressed_size,
+        };
+        let compressed_len = self.data_at(entry.data_offset)?.len() as u64;
+        if entry.decompressed_size > compressed_len.saturating_mul(MAX_DEFLATE_RATIO) {
+            return Err(implausible());
+        }
+        entry.decompressed_size.try_into().map_err(|_| implausible())
+    }
+
+    /// All data from `data_offset` to the end of the last entry.
+    pub(crate) fn data_at(&self, data_offset: u64) -> Result<&[u8], Error> {
+        data_offset
+            .try_into()
+            .ok()
+            .and_then(|offset: usize| self.data.get(offset..self.pack_end()))
+            .ok_or(Error::OffsetOutOfBounds(data_offset))
+    }
+
     /// Decode an entry, resolving delta's as needed, while growing the output vector if there is not enough
     /// space to hold the result object.
     /// Returns (object_kind, compressed_size), referring to the `entry` in-pack size for use with CRC32 checks
@@ -132,13 +195,7 @@ impl File {
         use crate::pack::data::header::Header::*;
         match entry.header {
             Tree | Blob | Commit | Tag => {
-                out.resize(
-                    entry
-                        .decompressed_size
-                        .try_into()
-                        .expect("size representable by machine"),
-                    0,
-                );
+                out.resize(self.plausible_decompressed_size(&entry)?, 0);
                 self.decompress_entry(&entry, out.as_mut_slice()).map(|consumed_input| {
                     Outcome::from_object_entry(
                         entry.header.to_kind().expect("a non-delta entry"),
@@ -151,6 +208,53 @@ impl File {
         }
     }
 
+    /// Obtain the kind and size of the object stored in `entry` without decoding it.
+    ///
+    /// For deltified entries, the delta chain is followed only to learn the kind of its base object, and the size
+    /// is read from the header of the first delta, which requires decompressing only a few bytes of it.
+    /// `resolve` is called with the ids of `RefDelta` bases to learn where they are.
+    pub fn decode_header(
+        &self,
+        entry: pack::data::Entry,
+        resolve: impl Fn(borrowed::Id) -> Option<ResolvedHeaderBase>,
+    ) -> Result<(object::Kind, u64), Error> {
+        use crate::pack::data::header::Header::*;
+        if let Some(kind) = entry.header.to_kind() {
+            return Ok((kind, entry.decompressed_size));
+        }
+        let size = self.delta_result_size(&entry)?;
+        let mut cursor = entry;
+        let mut chain_len = 0;
+        let kind = loop {
+            chain_len += 1;
+            if chain_len > self.num_objects {
+                return Err(Error::Delta("the delta chain never ends"));
+            }
+            cursor = match cursor.header {
+                Tree | Blob | Commit | Tag => break cursor.header.to_kind().expect("a non-delta entry"),
+                OfsDelta { base_distance } => self.base_entry(&cursor, base_distance)?,
+                RefDelta { base_id } => match resolve(base_id.to_borrowed()) {
+                    Some(ResolvedHeaderBase::InPack(entry)) => entry,
+                    Some(ResolvedHeaderBase::OutOfPack { kind }) => break kind,
+                    None => return Err(Error::DeltaBaseUnresolved(*base_id)),
+                },
+            };
+        };
+        Ok((kind, size))
+    }
+
+    /// Decompress only the beginning of the delta in `entry` to read the size of the object it produces.
+    fn delta_result_size(&self, entry: &pack::data::Entry) -> Result<u64, Error> {
+        let mut sizes = [0u8; DELTA_SIZES_MAX_LEN];
+        let (_status, _consumed_in, consumed_out) = zlib::Inflate::default()
+            .once(self.data_at(entry.data_offset)?, &mut sizes)
+            .map_err(|e| Error::ZlibInflate(e, "Failed to decompress the delta header"))?;
+        let sizes = &sizes[..consumed_out];
+        let (_base_size, consumed) = delta_header_size_ofs(sizes)?;
+        let (result_size, _consumed) = delta_header_size_ofs(&sizes[consumed..])?;
+        Ok(result_size)
+    }
+
     /// resolve: technically, this shoudln't ever be required as stored local packs don't refer to objects by id
     /// that are outside of the pack. Unless, of course, the ref refers to an object within this pack, which means
     /// it's very, very large as 20bytes are smaller than the corresponding MSB encoded number
@@ -182,11 +286,10 @@ impl File {
                 }
                 break;
             }
-            total_delta_data_size += cursor.decompressed_size;
-            let decompressed_size = cursor
-                .decompressed_size
-                .try_into()
-                .expect("a single delta size small enough to fit a usize");
+            total_delta_data_size = total_delta_data_size
+                .checked_add(cursor.decompressed_size)
+                .ok_or(Error::Delta("the deltas of the chain are too large"))?;
+            let decompressed_size = self.plausible_decompressed_size(&cursor)?;
             chain.push(Delta {
                 data: Range {
                     start: 0,
@@ -197,9 +300,12 @@ impl File {
                 decompressed_size,
                 data_offset: cursor.data_offset,
             });
+            if chain.len() > self.num_objects as usize {
+                return Err(Error::Delta("the delta chain never ends"));
+            }
             use pack::data::Header;
-            cursor = match cursor.header {
-                Header::OfsDelta { base_distance } => self.entry(cursor.base_pack_offset(base_distance)),
+            cursor = match &cursor.header {
+                Header::OfsDelta { base_distance } => self.base_entry(&cursor, *base_distance)?,
                 Header::RefDelta { base_id } => match resolve(base_id.to_borrowed(), out) {
                     Some(ResolvedBase::InPack(entry)) => entry,
                     Some(ResolvedBase::OutOfPack { end, kind }) => {
@@ -207,7 +313,7 @@ impl File {
                         object_kind = Some(kind);
                         break;
                     }
-                    None => return Err(Error::DeltaBaseUnresolved(base_id)),
+                    None => return Err(Error::DeltaBaseUnresolved(base_id.as_ref().clone())),
                 },
                 _ => unreachable!("cursor.is_delta() only allows deltas here"),
             };
@@ -226,12 +332,19 @@ impl File {
         // First pass will decompress all delta data and keep it in our output buffer
         // [<possibly resolved base object>]<delta-1..delta-n>...
         // so that we can find the biggest result size.
-        let total_delta_data_size: usize = total_delta_data_size.try_into().expect("delta data to fit in memory");
+        let total_delta_data_size: usize = total_delta_data_size
+            .try_into()
+            .map_err(|_| Error::Delta("the deltas of the chain are too large to fit into memory"))?;
 
         let chain_len = chain.len();
         let (first_buffer_end, second_buffer_end) = {
             let delta_start = base_buffer_size.unwrap_or(0);
-            out.resize(delta_start + total_delta_data_size, 0);
+            out.resize(
+                delta_start
+                    .checked_add(total_delta_data_size)
+                    .ok_or(Error::Delta("the deltas of the chain are too large to fit into memory"))?,
+                0,
+            );
 
             let delta_range = Range {
                 start: delta_start,
@@ -249,15 +362,20 @@ impl File {
                     consumed_input = Some(consumed_from_data_offset);
                 }
 
-                let (base_size, offset) = delta_header_size_ofs(instructions);
+                let delta_data = &instructions[..delta.decompressed_size];
+                let (base_size, offset) = delta_header_size_ofs(delta_data)?;
                 let mut bytes_consumed_by_header = offset;
                 biggest_result_size = biggest_result_size.max(base_size);
-                delta.base_size = base_size.try_into().expect("base size fits into usize");
+                delta.base_size = base_size
+                    .try_into()
+                    .map_err(|_| Error::Delta("the base size is too large to fit into memory"))?;
 
-                let (result_size, offset) = delta_header_size_ofs(&instructions[offset..]);
+                let (result_size, offset) = delta_header_size_ofs(&delta_data[offset..])?;
                 bytes_consumed_by_header += offset;
                 biggest_result_size = biggest_result_size.max(result_size);
-                delta.result_size = result_size.try_into().expect("result size fits into usize");
+                delta.result_size = result_size
+                    .try_into()
+                    .map_err(|_| Error::Delta("the result size is too large to fit into memory"))?;
 
                 // the absolute location into the instructions buffer, so we keep track of the end point of the last
                 delta.data.start = relative_delta_start + bytes_consumed_by_header;
@@ -267,15 +385,44 @@ impl File {
                 instructions = &mut instructions[delta.decompressed_size..];
             }
 
+            // Each delta must be applied to a base of the size it expects, starting with the base of the chain
+            let base_size = match base_buffer_size {
+                Some(size) => size as u64,
+                None => self.plausible_decompressed_size(&cursor)? as u64,
+            };
+            let mut expected_base_size = base_size;
+            for delta in chain.iter().rev() {
+                if delta.base_size as u64 != expected_base_size {
+                    return Err(Error::DeltaBaseSizeMismatch {
+                        expected: delta.base_size as u64,
+                        actual: expected_base_size,
+                    });
+                }
+                if delta.result_size as u64
+                    > max_delta_result_size(delta.data.end - delta.data.start, expected_base_size)
+                {
+                    return Err(Error::Delta(
+                        "the result size can't be produced by the delta instructions",
+                    ));
+                }
+                expected_base_size = delta.result_size as u64;
+            }
+
             // Now we can produce a buffer like this
             // [<biggest-result-buffer, possibly filled with resolved base object data>]<biggest-result-buffer><delta-1..delta-n>
             // from [<possibly resolved base object>]<delta-1..delta-n>...
             let biggest_result_size: usize = biggest_result_size
                 .try_into()
-                .expect("biggest result size small enough to fit into usize");
+                .map_err(|_| Error::Delta("the result is too large to fit into memory"))?;
             let first_buffer_size = biggest_result_size;
             let second_buffer_size = first_buffer_size;
-            out.resize(first_buffer_size + second_buffer_size + total_delta_data_size, 0);
+            out.resize(
+                first_buffer_size
+                    .checked_mul(2)
+                    .and_then(|size| size.checked_add(total_delta_data_size))
+                    .ok_or(Error::Delta("the result is too large to fit into memory"))?,
+                0,
+            );
 
             // Now 'rescue' the deltas, because in the next step we possibly overwrite that portion
             // of memory with the base object (in the majority of cases)
@@ -305,12 +452,10 @@ impl File {
                 let packed_size = self.decompress_entry_from_data_offset(base_entry.data_offset, out)?;
                 cache.put(
                     base_entry.data_offset,
-                    &out[..base_entry
-                        .decompressed_size
-                        .try_into()
-                        .expect("successful decompression should make this successful too")],
+                    &out[..base_entry.decompressed_size as usize],
                     object_kind.expect("non-delta object"),
                     packed_size,
+                    0,
                 );
             }
 
@@ -340,7 +485,7 @@ impl File {
             if delta_idx + 1 == chain_len {
                 last_result_size = Some(result_size);
             }
-            apply_delta(&source_buf[..base_size], &mut target_buf[..result_size], data);
+            apply_delta(&source_buf[..base_size], &mut target_buf[..result_size], data)?;
             // use the target as source for the next delta
             std::mem::swap(&mut source_buf, &mut target_buf);
         }
@@ -362,7 +507,13 @@ impl File {
 
         let object_kind = object_kind.expect("a base object as root of any delta chain that we are here to resolve");
         let consumed_input = consumed_input.expect("at least one decompressed delta object");
-        cache.put(first_entry.data_offset, out.as_slice(), object_kind, consumed_input);
+        cache.put(
+            first_entry.data_offset,
+            out.as_slice(),
+            object_kind,
+            consumed_input,
+            chain_len as u32,
+        );
         Ok(Outcome {
             kind: object_kind,
             // technically depending on the cache, the chain size is not correct as it might
@@ -376,71 +527,90 @@ impl File {
     }
 }
 
-pub(crate) fn apply_delta(base: &[u8], mut target: &mut [u8], data: &[u8]) {
+/// The largest object `num_instruction_bytes` of delta instructions can produce from a base of `base_size`.
+///
+/// Each instruction takes at least one byte and copies at most the whole base or inserts 127 bytes.
+pub(crate) fn max_delta_result_size(num_instruction_bytes: usize, base_size: u64) -> u64 {
+    (num_instruction_bytes as u64).saturating_mul(base_size.max(0x7f))
+}
+
+/// Apply the delta instructions in `data` to `base` to produce `target`, which must be filled exactly.
+pub(crate) fn apply_delta(base: &[u8], mut target: &mut [u8], data: &[u8]) -> Result<(), Error> {
     let mut i = 0;
+    let next_byte = |i: &mut usize| -> Result<u32, Error> {
+        let byte = *data.get(*i).ok_or(Error::Delta("copy instruction is truncated"))?;
+        *i += 1;
+        Ok(byte as u32)
+    };
     while let Some(cmd) = data.get(i) {
         i += 1;
-        match cmd {
+        let bytes = match cmd {
             cmd if cmd & 0b1000_0000 != 0 => {
                 let (mut ofs, mut size): (u32, u32) = (0, 0);
                 if cmd & 0b0000_0001 != 0 {
-                    ofs = data[i] as u32;
-                    i += 1;
+                    ofs = next_byte(&mut i)?;
                 }
                 if cmd & 0b0000_0010 != 0 {
-                    ofs |= (data[i] as u32) << 8;
-                    i += 1;
+                    ofs |= next_byte(&mut i)? << 8;
                 }
                 if cmd & 0b0000_0100 != 0 {
-                    ofs |= (data[i] as u32) << 16;
-                    i += 1;
+                    ofs |= next_byte(&mut i)? << 16;
                 }
                 if cmd & 0b0000_1000 != 0 {
-                    ofs |= (data[i] as u32) << 24;
-                    i += 1;
+                    ofs |= next_byte(&mut i)? << 24;
                 }
                 if cmd & 0b0001_0000 != 0 {
-                    size = data[i] as u32;
-                    i += 1;
+                    size = next_byte(&mut i)?;
                 }
                 if cmd & 0b0010_0000 != 0 {
-                    size |= (data[i] as u32) << 8;
-                    i += 1;
+                    size |= next_byte(&mut i)? << 8;
                 }
                 if cmd & 0b0100_0000 != 0 {
-                    size |= (data[i] as u32) << 16;
-                    i += 1;
+                    size |= next_byte(&mut i)? << 16;
                 }
                 if size == 0 {
                     size = 0x10000; // 65536
                 }
                 let ofs = ofs as usize;
-                io::Write::write(&mut target, &base[ofs..ofs + size as usize])
-                    .expect("delta copy from base: byte slices must match");
+                ofs.checked_add(size as usize)
+                    .and_then(|end| base.get(ofs..end))
+                    .ok_or(Error::Delta("copy from beyond the end of the base"))?
             }
-            0 => panic!("encountered unsupported command code: 0"),
+            0 => return Err(Error::Delta("encountered unsupported command code: 0")),
             size => {
-                io::Write::write(&mut target, &data[i..i + *size as usize])
-                    .expect("delta copy data: slice sizes to match up");
+                let bytes = data
+                    .get(i..i + *size as usize)
+                    .ok_or(Error::Delta("insert instruction is truncated"))?;
                 i += *size as usize;
+                bytes
             }
+        };
+        if bytes.len() > target.len() {
+            return Err(Error::Delta("produces more data than announced"));
         }
+        let (head, tail) = target.split_at_mut(bytes.len());
+        head.copy_from_slice(bytes);
+        target = tail;
+    }
+    if !target.is_empty() {
+        return Err(Error::Delta("produces less data than announced"));
     }
-    assert_eq!(i, data.len());
-    assert_eq!(target.len(), 0);
+    Ok(())
 }
 
-pub(crate) fn delta_header_size_ofs(d: &[u8]) -> (u64, usize) {
+/// Decode a size at the beginning of a delta, returning it along with the amount of bytes it occupied.
+pub(crate) fn delta_header_size_ofs(d: &[u8]) -> Result<(u64, usize), Error> {
     let mut i = 0;
     let mut size = 0u64;
-    let mut consumed = 0;
-    for cmd in d.iter() {
-        consumed += 1;
+    for (consumed, cmd) in d.iter().enumerate() {
+        if i >= 64 {
+            break;
+        }
         size |= (*cmd as u64 & 0x7f) << i;
         i += 7;
         if *cmd & 0x80 == 0 {
-            break;
+            return Ok((size, consumed + 1));
         }
     }
-    (size, consumed)
+    Err(Error::Delta("the size in the delta header is truncated or too large"))
 }
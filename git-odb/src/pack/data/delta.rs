@@ -0,0 +1,205 @@
+//! Computation of git-compatible deltas, consisting of instructions to copy from a base object or insert new data.
+use crate::pack::data::decode::{self, apply_delta, delta_header_size_ofs, max_delta_result_size};
+use std::{collections::HashMap, convert::TryInto};
+
+/// The size of the blocks of the base object that are indexed, which is also the shortest copy we can find.
+const BLOCK_SIZE: usize = 16;
+/// The maximum amount of bytes a single insert instruction can carry.
+const MAX_INSERT_SIZE: usize = 0x7f;
+/// The maximum amount of bytes we copy with a single copy instruction, matching the one used by git.
+const MAX_COPY_SIZE: usize = 0x10000;
+/// The maximum amount of base offsets to compare per block hash, keeping runtime in check for repetitive data.
+const MAX_CANDIDATES_PER_HASH: usize = 64;
+/// The multiplier of the polynomial rolling hash.
+const PRIME: u32 = 0x0100_0193;
+
+/// An index over all blocks of a base object, making it cheap to compute deltas of many targets against it.
+pub struct Index {
+    base: Vec<u8>,
+    blocks: HashMap<u32, Vec<u32>>,
+    /// PRIME to the power of BLOCK_SIZE - 1, to remove the oldest byte from a rolling hash
+    prime_pow: u32,
+}
+
+impl Index {
+    /// Create a new index for the given `base` object data.
+    pub fn new(base: Vec<u8>) -> Self {
+        let mut blocks: HashMap<u32, Vec<u32>> = HashMap::with_capacity(base.len() / BLOCK_SIZE);
+        if base.len() <= u32::MAX as usize {
+            for (block_index, block) in base.chunks_exact(BLOCK_SIZE).enumerate() {
+                let offsets = blocks.entry(block_hash(block)).or_default();
+                if offsets.len() < MAX_CANDIDATES_PER_HASH {
+                    offsets.push((block_index * BLOCK_SIZE) as u32);
+                }
+            }
+        }
+        Index {
+            base,
+            blocks,
+            prime_pow: (1..BLOCK_SIZE).fold(1u32, |acc, _| acc.wrapping_mul(PRIME)),
+        }
+    }
+
+    /// The base object data this index was created from.
+    pub fn base(&self) -> &[u8] {
+        &self.base
+    }
+
+    /// Write delta instructions to `out` to turn our base into `target`, returning `false` and stopping early if the
+    /// delta would be larger than `max_size` bytes.
+    ///
+    /// `out` is cleared before writing.
+    pub fn encode(&self, target: &[u8], out: &mut Vec<u8>, max_size: usize) -> bool {
+        out.clear();
+        encode_size(self.base.len(), out);
+        encode_size(target.len(), out);
+
+        let mut insert_start = 0;
+        let mut pos = 0;
+        let mut hash = None;
+        while pos + BLOCK_SIZE <= target.len() {
+            let current_hash = match hash {
+                Some(hash) => hash,
+                None => block_hash(&target[pos..pos + BLOCK_SIZE]),
+            };
+            match self.longest_match(current_hash, target, pos) {
+                Some((mut base_ofs, mut len)) => {
+                    // Grow backwards into bytes we would otherwise insert
+                    while pos > insert_start && base_ofs > 0 && self.base[base_ofs - 1] == target[pos - 1] {
+                        base_ofs -= 1;
+                        pos -= 1;
+                        len += 1;
+                    }
+                    encode_insert(&target[insert_start..pos], out);
+                    encode_copy(base_ofs, len, out);
+                    pos += len;
+                    insert_start = pos;
+                    hash = None;
+                }
+                None => {
+                    if pos + BLOCK_SIZE < target.len() {
+                        let removed = (target[pos] as u32).wrapping_mul(self.prime_pow);
+                        hash = Some(
+                            current_hash
+                                .wrapping_sub(removed)
+                                .wrapping_mul(PRIME)
+                                .wrapping_add(target[pos + BLOCK_SIZE] as u32),
+                        );
+                    }
+                    pos += 1;
+                }
+            }
+            if out.len() > max_size {
+                return false;
+            }
+        }
+        encode_insert(&target[insert_start..], out);
+        out.len() <= max_size
+    }
+
+    fn longest_match(&self, hash: u32, target: &[u8], pos: usize) -> Option<(usize, usize)> {
+        let candidates = self.blocks.get(&hash)?;
+        let mut best: Option<(usize, usize)> = None;
+        for &base_ofs in candidates {
+            let base_ofs = base_ofs as usize;
+            let len = self.base[base_ofs..]
+                .iter()
+                .zip(&target[pos..])
+                .take_while(|(a, b)| a == b)
+                .count();
+            if len >= BLOCK_SIZE && len > best.map(|(_, best_len)| best_len).unwrap_or(0) {
+                best = Some((base_ofs, len));
+            }
+        }
+        best
+    }
+}
+
+/// Compute the delta instructions to turn `base` into `target` and write them to `out`, which is cleared beforehand.
+///
+/// Use an [`Index`] instead if many targets are to be compared against the same base.
+pub fn encode(base: &[u8], target: &[u8], out: &mut Vec<u8>) {
+    Index::new(base.to_owned()).encode(target, out, usize::MAX);
+}
+
+/// Apply the `delta` instructions to `base`, placing the result into `out`, which is resized to fit.
+///
+/// Fails if the delta is malformed or was made for a base of a different size.
+pub fn apply(base: &[u8], delta: &[u8], out: &mut Vec<u8>) -> Result<(), decode::Error> {
+    let (base_size, consumed) = delta_header_size_ofs(delta)?;
+    if base_size != base.len() as u64 {
+        return Err(decode::Error::DeltaBaseSizeMismatch {
+            expected: base_size,
+            actual: base.len() as u64,
+        });
+    }
+    let (result_size, consumed_result) = delta_header_size_ofs(&delta[consumed..])?;
+    let instructions = &delta[consumed + consumed_result..];
+    if result_size > max_delta_result_size(instructions.len(), base_size) {
+        return Err(decode::Error::Delta(
+            "the result size can't be produced by the delta instructions",
+        ));
+    }
+    out.resize(
+        result_size
+            .try_into()
+            .map_err(|_| decode::Error::Delta("the result is too large to fit into memory"))?,
+        0,
+    );
+    apply_delta(base, out, instructions)
+}
+
+#[inline]
+fn block_hash(block: &[u8]) -> u32 {
+    block
+        .iter()
+        .fold(0u32, |hash, b| hash.wrapping_mul(PRIME).wrapping_add(*b as u32))
+}
+
+fn encode_size(mut size: usize, out: &mut Vec<u8>) {
+    loop {
+        let byte = (size & 0x7f) as u8;
+        size >>= 7;
+        if size == 0 {
+            out.push(byte);
+            break;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn encode_insert(data: &[u8], out: &mut Vec<u8>) {
+    for chunk in data.chunks(MAX_INSERT_SIZE) {
+        out.push(chunk.len() as u8);
+        out.extend_from_slice(chunk);
+    }
+}
+
+fn encode_copy(mut ofs: usize, mut len: usize, out: &mut Vec<u8>) {
+    while len > 0 {
+        let size = len.min(MAX_COPY_SIZE);
+        let cmd_pos = out.len();
+        let mut cmd = 0b1000_0000u8;
+        out.push(cmd);
+        for byte_index in 0..4 {
+            let byte = (ofs >> (byte_index * 8)) as u8;
+            if byte != 0 {
+                cmd |= 1 << byte_index;
+                out.push(byte);
+            }
+        }
+        // A size of zero encodes MAX_COPY_SIZE
+        if size != MAX_COPY_SIZE {
+            for byte_index in 0..3 {
+                let byte = (size >> (byte_index * 8)) as u8;
+                if byte != 0 {
+                    cmd |= 1 << (4 + byte_index);
+                    out.push(byte);
+                }
+            }
+        }
+        out[cmd_pos] = cmd;
+        ofs += size;
+        len -= size;
+    }
+}
@@ -1,6 +1,28 @@
-use git_object::{owned, SHA1_SIZE};
+use git_object::{owned, HashKind, SHA256_SIZE};
+use quick_error::quick_error;
 use std::io;
 
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Truncated {
+            display("The entry header ends prematurely")
+        }
+        UnknownType(type_id: u8) {
+            display("Unknown or unsupported object type id {}", type_id)
+        }
+        Overflow(what: &'static str) {
+            display("The {} in the entry header does not fit into 64 bits", what)
+        }
+    }
+}
+
+impl Error {
+    fn into_io(self) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, self)
+    }
+}
+
 const _TYPE_EXT1: u8 = 0;
 const COMMIT: u8 = 1;
 const TREE: u8 = 2;
@@ -18,33 +40,37 @@ pub struct Entry {
     pub decompressed_size: u64,
     /// absolute offset to compressed object data in the pack, just behind the header
     pub data_offset: u64,
+    /// The amount of bytes the header took in the pack, which can be more than needed to encode it
+    header_size: u16,
 }
 
 /// Access
 impl Entry {
-    pub fn base_pack_offset(&self, distance: u64) -> u64 {
-        let pack_offset = self.data_offset - self.header_size() as u64;
-        pack_offset.checked_sub(distance).expect("in-bound distance of deltas")
+    /// The pack offset of the base of this `OfsDelta` entry at `distance`, or `None` if it would lie before the
+    /// beginning of the pack or at this very entry.
+    pub fn base_pack_offset(&self, distance: u64) -> Option<u64> {
+        Header::verified_base_pack_offset(self.pack_offset(), distance)
     }
     pub fn pack_offset(&self) -> u64 {
         self.data_offset - self.header_size() as u64
     }
     pub fn header_size(&self) -> usize {
-        self.header
-            .to_write(self.decompressed_size, io::sink())
-            .expect("io::sink() to never fail")
+        self.header_size as usize
     }
 }
 
 /// Decoding
 impl Entry {
-    pub fn from_bytes(d: &[u8], pack_offset: u64) -> Entry {
-        let (type_id, size, mut consumed) = parse_header_info(d);
+    /// Parse the entry at the beginning of `d`, found at `pack_offset`, whose `RefDelta` bases are ids of `hash_kind`.
+    ///
+    /// Fails if `d` ends before the header does or if the header is malformed.
+    pub fn from_bytes(d: &[u8], pack_offset: u64, hash_kind: HashKind) -> Result<Entry, Error> {
+        let (type_id, size, mut consumed) = parse_header_info(d)?;
 
         use self::Header::*;
         let object = match type_id {
             OFS_DELTA => {
-                let (distance, leb_bytes) = leb64decode(&d[consumed..]);
+                let (distance, leb_bytes) = leb64decode(&d[consumed..])?;
                 let delta = OfsDelta {
                     base_distance: distance,
                 };
@@ -52,26 +78,34 @@ impl Entry {
                 delta
             }
             REF_DELTA => {
+                let hash_len = hash_kind.len_in_bytes();
                 let delta = RefDelta {
-                    base_id: owned::Id::from_20_bytes(&d[consumed..consumed + SHA1_SIZE]),
+                    base_id: Box::new(
+                        owned::Id::from_bytes(d.get(consumed..consumed + hash_len).ok_or(Error::Truncated)?)
+                            .expect("hash_len bytes to make an id"),
+                    ),
                 };
-                consumed += SHA1_SIZE;
+                consumed += hash_len;
                 delta
             }
             BLOB => Blob,
             TREE => Tree,
             COMMIT => Commit,
             TAG => Tag,
-            _ => panic!("We currently don't support any V3 features or extensions"),
+            _ => return Err(Error::UnknownType(type_id)),
         };
-        Entry {
+        Ok(Entry {
             header: object,
             decompressed_size: size,
             data_offset: pack_offset + consumed as u64,
-        }
+            header_size: consumed as u16,
+        })
     }
 
-    pub fn from_read(mut r: impl io::Read, pack_offset: u64) -> Result<Entry, io::Error> {
+    /// Read the entry at `pack_offset` from `r`, whose `RefDelta` bases are ids of `hash_kind`.
+    ///
+    /// Malformed headers yield an error of kind [`io::ErrorKind::InvalidData`] with an [`Error`] inside.
+    pub fn from_read(mut r: impl io::Read, pack_offset: u64, hash_kind: HashKind) -> Result<Entry, io::Error> {
         let (type_id, size, mut consumed) = streaming_parse_header_info(&mut r)?;
 
         use self::Header::*;
@@ -85,39 +119,43 @@ impl Entry {
                 delta
             }
             REF_DELTA => {
-                let mut buf = [0u8; SHA1_SIZE];
-                r.read_exact(&mut buf)?;
+                let mut buf = [0u8; SHA256_SIZE];
+                let buf = &mut buf[..hash_kind.len_in_bytes()];
+                r.read_exact(buf)?;
                 let delta = RefDelta {
-                    base_id: owned::Id::new_sha1(buf),
+                    base_id: Box::new(owned::Id::from_bytes(buf).expect("hash_len bytes to make an id")),
                 };
-                consumed += SHA1_SIZE;
+                consumed += buf.len();
                 delta
             }
             BLOB => Blob,
             TREE => Tree,
             COMMIT => Commit,
             TAG => Tag,
-            _ => panic!("We currently don't support any V3 features or extensions"),
+            _ => return Err(Error::UnknownType(type_id).into_io()),
         };
         Ok(Entry {
             header: object,
             decompressed_size: size,
             data_offset: pack_offset + consumed as u64,
+            header_size: consumed as u16,
         })
     }
 }
 
-#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 pub enum Header {
     Commit,
     Tree,
     Blob,
     Tag,
-    /// An object within this pack if the LSB encoded offset would be larger than 20 bytes
+    /// An object within this pack if the LSB encoded offset would be larger than the id
     /// Alternatively an object stored in the repository, if this is a thin pack
+    ///
+    /// As these are rare outside of thin packs, the id is kept on the heap to keep headers small.
     RefDelta {
-        base_id: owned::Id,
+        base_id: Box<owned::Id>,
     },
     /// The distance to the pack offset of the base object, measured from this objects pack offset, so that
     /// base_pack_offset = pack_offset - distance
@@ -207,18 +245,17 @@ impl Header {
 }
 
 #[inline]
-fn leb64decode(d: &[u8]) -> (u64, usize) {
+fn leb64decode(d: &[u8]) -> Result<(u64, usize), Error> {
     let mut i = 0;
-    let mut c = d[i];
+    let mut c = *d.get(i).ok_or(Error::Truncated)?;
     i += 1;
     let mut value = c as u64 & 0x7f;
     while c & 0x80 != 0 {
-        c = d[i];
+        c = *d.get(i).ok_or(Error::Truncated)?;
         i += 1;
-        value += 1;
-        value = (value << 7) + (c as u64 & 0x7f)
+        value = leb64_shift_in(value, c)?;
     }
-    (value, i)
+    Ok((value, i))
 }
 
 #[inline]
@@ -231,27 +268,46 @@ fn streaming_leb64decode(mut r: impl io::Read) -> Result<(u64, usize), io::Error
     while b[0] & 0x80 != 0 {
         r.read_exact(&mut b)?;
         i += 1;
-        value += 1;
-        value = (value << 7) + (b[0] as u64 & 0x7f)
+        value = leb64_shift_in(value, b[0]).map_err(Error::into_io)?;
     }
     Ok((value, i))
 }
 
+/// Add the 7 bits of `c` to `value` like git's offset encoding does, which adds one for each continuation byte.
+#[inline]
+fn leb64_shift_in(value: u64, c: u8) -> Result<u64, Error> {
+    value
+        .checked_add(1)
+        .filter(|v| v.leading_zeros() >= 7)
+        .map(|v| (v << 7) + (c as u64 & 0x7f))
+        .ok_or(Error::Overflow("delta base distance"))
+}
+
+/// Add the 7 bits of `c` at bit position `shift` to `size`, failing if they don't fit into 64 bits.
+#[inline]
+fn size_shift_in(size: u64, c: u8, shift: u32) -> Result<u64, Error> {
+    let bits = (c & 0b0111_1111) as u64;
+    if shift >= 64 || (bits << shift) >> shift != bits {
+        return Err(Error::Overflow("object size"));
+    }
+    Ok(size | bits << shift)
+}
+
 /// Parses the header of a pack-entry, yielding object type id, decompressed object size, and consumed bytes
 #[inline]
-fn parse_header_info(data: &[u8]) -> (u8, u64, usize) {
-    let mut c = data[0];
+fn parse_header_info(data: &[u8]) -> Result<(u8, u64, usize), Error> {
+    let mut c = *data.first().ok_or(Error::Truncated)?;
     let mut i = 1;
     let type_id = (c >> 4) & 0b0000_0111;
     let mut size = c as u64 & 0b0000_1111;
     let mut s = 4;
     while c & 0b1000_0000 != 0 {
-        c = data[i];
+        c = *data.get(i).ok_or(Error::Truncated)?;
         i += 1;
-        size += ((c & 0b0111_1111) as u64) << s;
+        size = size_shift_in(size, c, s)?;
         s += 7
     }
-    (type_id, size, i)
+    Ok((type_id, size, i))
 }
 
 #[inline]
@@ -267,7 +323,7 @@ fn streaming_parse_header_info(mut read: impl io::Read) -> Result<(u8, u64, usiz
         read.read_exact(&mut byte)?;
         c = byte[0];
         i += 1;
-        size += ((c & 0b0111_1111) as u64) << s;
+        size = size_shift_in(size, c, s).map_err(Error::into_io)?;
         s += 7
     }
     Ok((type_id, size, i))
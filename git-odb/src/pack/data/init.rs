@@ -1,28 +1,39 @@
 use crate::pack::data;
 use filebuffer::FileBuffer;
-use git_object::SHA1_SIZE;
+use git_object::HashKind;
 use std::{convert::TryFrom, convert::TryInto, path::Path};
 
 /// Instantiation
 impl data::File {
+    /// Open the pack at `path`, assuming it refers to objects by SHA1.
     pub fn at(path: impl AsRef<Path>) -> Result<data::File, data::parse::Error> {
         data::File::try_from(path.as_ref())
     }
-}
-
-impl TryFrom<&Path> for data::File {
-    type Error = data::parse::Error;
 
-    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+    /// Open the pack at `path`, which refers to objects by ids produced by the `hash_kind`.
+    ///
+    /// Packs don't record their kind of hash, but their index does, see [`pack::index::File::hash_kind()`][crate::pack::index::File::hash_kind()].
+    pub fn at_with_hash(path: impl AsRef<Path>, hash_kind: HashKind) -> Result<data::File, data::parse::Error> {
+        let path = path.as_ref();
         use data::parse::N32_SIZE;
 
-        let data = FileBuffer::open(path).map_err(|e| data::parse::Error::Io(e, path.to_owned()))?;
-        let pack_len = data.len();
-        if pack_len < N32_SIZE * 3 + SHA1_SIZE {
-            return Err(data::parse::Error::Corrupt(format!(
+        let too_small = |pack_len: u64| {
+            data::parse::Error::Corrupt(format!(
                 "Pack data of size {} is too small for even an empty pack",
                 pack_len
-            )));
+            ))
+        };
+        let min_len = (N32_SIZE * 3 + hash_kind.len_in_bytes()) as u64;
+        // Empty files can't be mapped, so check the size before mapping the file
+        let file_len = std::fs::metadata(path)
+            .map_err(|e| data::parse::Error::Io(e, path.to_owned()))?
+            .len();
+        if file_len < min_len {
+            return Err(too_small(file_len));
+        }
+        let data = FileBuffer::open(path).map_err(|e| data::parse::Error::Io(e, path.to_owned()))?;
+        if (data.len() as u64) < min_len {
+            return Err(too_small(data.len() as u64));
         }
         let (kind, num_objects) =
             data::parse::header(&data[..12].try_into().expect("enough data after previous check"))?;
@@ -31,6 +42,15 @@ impl TryFrom<&Path> for data::File {
             path: path.to_owned(),
             kind,
             num_objects,
+            hash_kind,
         })
     }
 }
+
+impl TryFrom<&Path> for data::File {
+    type Error = data::parse::Error;
+
+    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+        data::File::at_with_hash(path, HashKind::Sha1)
+    }
+}
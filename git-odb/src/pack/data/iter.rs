@@ -1,7 +1,6 @@
 use crate::zlib::stream::inflate::InflateReaderBoxed;
 use crate::{hash, pack, zlib::stream::inflate::Inflate};
-use git_features::hash::Sha1;
-use git_object::owned;
+use git_object::{owned, HashKind};
 use quick_error::quick_error;
 use std::{fs, io};
 
@@ -21,9 +20,18 @@ quick_error! {
         ChecksumMismatch { expected: owned::Id, actual: owned::Id } {
             display("pack checksum in trailer was {}, but actual checksum was {}", expected, actual)
         }
+        SizeMismatch { pack_offset: u64, expected: u64, actual: u64 } {
+            display("The entry at offset {} should decompress to {} bytes, but got {}", pack_offset, expected, actual)
+        }
+        CompressedBytesMissing { pack_offset: u64 } {
+            display("The compressed bytes of the entry at offset {} were not kept, see CompressedBytesMode", pack_offset)
+        }
     }
 }
 
+/// The most bytes to reserve up front for keeping compressed data, as the size in the entry header can't be trusted.
+const MAX_COMPRESSED_PREALLOCATION: u64 = 1024 * 1024;
+
 #[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 pub struct Entry {
@@ -31,6 +39,10 @@ pub struct Entry {
     /// amount of bytes used to encode the `header`. `pack_offset + header_size` is the beginning of the compressed data in the pack.
     pub header_size: u16,
     pub pack_offset: u64,
+    /// The `header_size` bytes of the encoded header as read from the pack, which aren't necessarily the ones produced
+    /// when encoding `header`.
+    /// Kept along with the `compressed` bytes
+    pub header_bytes: Option<Vec<u8>>,
     /// The bytes consumed while producing `decompressed`
     /// These do not contain the header, which makes it possible to easily replace a RefDelta with offset deltas
     /// when resolving thin packs.
@@ -55,10 +67,12 @@ pub struct Iter<R> {
     had_error: bool,
     kind: pack::data::Kind,
     objects_left: u32,
-    hash: Option<Sha1>,
+    hash_kind: HashKind,
+    hash: Option<hash::Hasher>,
     mode: Mode,
     compressed: CompressedBytesMode,
     compressed_buf: Option<Vec<u8>>,
+    header_buf: Vec<u8>,
 }
 
 #[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
@@ -118,20 +132,31 @@ where
         self.mode
     }
 
+    /// The kind of hash used for ids of `RefDelta` bases and the trailer of the pack.
+    pub fn hash_kind(&self) -> HashKind {
+        self.hash_kind
+    }
+
     /// Note that `read` is expected at the beginning of a valid pack file with header and trailer
     /// If `verify` is true, we will assert the SHA1 is actually correct before returning the last entry.
     /// Otherwise bit there is a chance that some kinds of bitrot or inconsistencies will not be detected.
-    pub fn new_from_header(mut read: R, trailer: Mode, compressed: CompressedBytesMode) -> Result<Iter<R>, Error> {
+    pub fn new_from_header(read: R, trailer: Mode, compressed: CompressedBytesMode) -> Result<Iter<R>, Error> {
+        Self::new_from_header_with_hash(read, trailer, compressed, HashKind::Sha1)
+    }
+
+    /// Like [`new_from_header()`][Iter::new_from_header()], but for packs whose ids are of kind `hash_kind`.
+    pub fn new_from_header_with_hash(
+        mut read: R,
+        trailer: Mode,
+        compressed: CompressedBytesMode,
+        hash_kind: HashKind,
+    ) -> Result<Iter<R>, Error> {
         let mut header_data = [0u8; 12];
         read.read_exact(&mut header_data)?;
 
+        // V3 packs are read like V2 packs, as their entries are the same.
         let (kind, num_objects) = pack::data::parse::header(&header_data)?;
-        assert_eq!(
-            kind,
-            pack::data::Kind::V2,
-            "let's stop here if we see undocumented pack formats"
-        );
-        Ok(Iter {
+        let mut iter = Iter {
             read,
             decompressor: None,
             compressed,
@@ -139,8 +164,9 @@ where
             had_error: false,
             kind,
             objects_left: num_objects,
+            hash_kind,
             hash: if trailer != Mode::AsIs {
-                let mut hash = Sha1::default();
+                let mut hash = hash::Hasher::new(hash_kind);
                 hash.update(&header_data);
                 Some(hash)
             } else {
@@ -148,29 +174,52 @@ where
             },
             mode: trailer,
             compressed_buf: None,
-        })
+            header_buf: Vec::with_capacity(32),
+        };
+        if num_objects == 0 {
+            // There is no last entry to read the trailer with, so it's consumed and verified right away.
+            iter.read_trailer()?;
+        }
+        Ok(iter)
+    }
+
+    /// Read the trailer following the last entry and verify it according to our mode.
+    fn read_trailer(&mut self) -> Result<owned::Id, Error> {
+        let mut id = owned::Id::null_of(self.hash_kind);
+        if let Err(err) = self.read.read_exact(id.as_mut_slice()) {
+            if self.mode != Mode::Restore {
+                return Err(err.into());
+            }
+        }
+
+        if let Some(hash) = self.hash.take() {
+            let actual_id = hash.digest();
+            if self.mode == Mode::Restore {
+                id = actual_id.clone();
+            }
+            if id != actual_id {
+                return Err(Error::ChecksumMismatch {
+                    actual: actual_id,
+                    expected: id,
+                });
+            }
+        }
+        Ok(id)
     }
 
     fn next_inner(&mut self) -> Result<Entry, Error> {
         self.objects_left -= 1; // even an error counts as objects
 
         // Read header
-        let entry = match self.hash.take() {
-            Some(hash) => {
-                let mut read = read_and_pass_to(
-                    &mut self.read,
-                    hash::Write {
-                        inner: io::sink(),
-                        hash,
-                    },
-                );
-                let res = pack::data::Entry::from_read(&mut read, self.offset);
-                self.hash = Some(read.write.hash);
-                res
-            }
-            None => pack::data::Entry::from_read(&mut self.read, self.offset),
+        self.header_buf.clear();
+        let entry = pack::data::Entry::from_read(
+            read_and_pass_to(&mut self.read, &mut self.header_buf),
+            self.offset,
+            self.hash_kind,
+        )?;
+        if let Some(hash) = self.hash.as_mut() {
+            hash.update(&self.header_buf);
         }
-        .map_err(Error::from)?;
 
         // Decompress object to learn it's compressed bytes
         let mut decompressor = self.decompressor.take().unwrap_or_default();
@@ -180,7 +229,7 @@ where
             inner: read_and_pass_to(
                 &mut self.read,
                 if self.compressed.keep() {
-                    Vec::with_capacity(entry.decompressed_size as usize)
+                    Vec::with_capacity(entry.decompressed_size.min(MAX_COMPRESSED_PREALLOCATION) as usize)
                 } else {
                     compressed_buf
                 },
@@ -189,14 +238,16 @@ where
         };
 
         let bytes_copied = io::copy(&mut decompressed_reader, &mut io::sink())?;
-        debug_assert_eq!(
-            bytes_copied, entry.decompressed_size,
-            "We should have decompressed {} bytes, but got {} instead",
-            entry.decompressed_size, bytes_copied
-        );
+        if bytes_copied != entry.decompressed_size {
+            return Err(Error::SizeMismatch {
+                pack_offset: self.offset,
+                expected: entry.decompressed_size,
+                actual: bytes_copied,
+            });
+        }
 
         let pack_offset = self.offset;
-        let compressed_size = decompressed_reader.decompressor.total_in;
+        let compressed_size = decompressed_reader.decompressor.total_in();
         self.offset += entry.header_size() as u64 + compressed_size;
         self.decompressor = Some(decompressed_reader.decompressor);
 
@@ -211,54 +262,34 @@ where
         }
 
         let crc32 = if self.compressed.crc32() {
-            let mut header_buf = [0u8; 32];
-            let header_len = entry.header.to_write(bytes_copied, header_buf.as_mut())?;
-            let state = git_features::hash::crc32_update(0, &header_buf[..header_len]);
+            let state = git_features::hash::crc32(&self.header_buf);
             Some(git_features::hash::crc32_update(state, &compressed))
         } else {
             None
         };
 
-        let compressed = if self.compressed.keep() {
-            Some(compressed)
+        let (header_bytes, compressed) = if self.compressed.keep() {
+            (Some(self.header_buf.clone()), Some(compressed))
         } else {
             compressed.clear();
             self.compressed_buf = Some(compressed);
-            None
+            (None, None)
         };
 
         // Last objects gets trailer (which is potentially verified)
         let trailer = if self.objects_left == 0 {
-            let mut id = owned::Id::from([0; 20]);
-            if let Err(err) = self.read.read_exact(id.as_mut_slice()) {
-                if self.mode != Mode::Restore {
-                    return Err(err.into());
-                }
-            }
-
-            if let Some(hash) = self.hash.take() {
-                let actual_id = owned::Id::from(hash.digest());
-                if self.mode == Mode::Restore {
-                    id = actual_id;
-                }
-                if id != actual_id {
-                    return Err(Error::ChecksumMismatch {
-                        actual: actual_id,
-                        expected: id,
-                    });
-                }
-            }
-            Some(id)
+            Some(self.read_trailer()?)
         } else if self.mode == Mode::Restore {
             let hash = self.hash.clone().expect("in restore mode a hash is set");
-            Some(owned::Id::from(hash.digest()))
+            Some(hash.digest())
         } else {
             None
         };
 
         Ok(Entry {
-            header: entry.header,
             header_size: entry.header_size() as u16,
+            header: entry.header,
+            header_bytes,
             compressed,
             compressed_size,
             crc32,
@@ -347,6 +378,6 @@ impl pack::data::File {
     /// If an index is available, use the `traverse(…)` method instead for maximum performance.
     pub fn streaming_iter(&self) -> Result<Iter<impl io::BufRead>, Error> {
         let reader = io::BufReader::with_capacity(4096 * 8, fs::File::open(&self.path)?);
-        Iter::new_from_header(reader, Mode::Verify, CompressedBytesMode::KeepAndCRC32)
+        Iter::new_from_header_with_hash(reader, Mode::Verify, CompressedBytesMode::KeepAndCRC32, self.hash_kind)
     }
 }
@@ -3,15 +3,19 @@ use filebuffer::FileBuffer;
 use std::{convert::TryInto, path::Path};
 
 pub mod decode;
-mod header;
-pub use header::*;
+pub mod delta;
+pub mod header;
+pub use header::{Entry, Header};
 
 pub mod init;
 pub mod parse;
+pub mod stream;
+pub mod thin;
 pub mod verify;
 
 pub mod iter;
-use git_object::SHA1_SIZE;
+pub mod output;
+use git_object::HashKind;
 pub use iter::Iter;
 
 pub type EntrySlice = std::ops::Range<u64>;
@@ -28,6 +32,7 @@ pub struct File {
     path: std::path::PathBuf,
     kind: Kind,
     num_objects: u32,
+    hash_kind: HashKind,
 }
 
 impl File {
@@ -38,6 +43,10 @@ impl File {
     pub fn num_objects(&self) -> u32 {
         self.num_objects
     }
+    /// The kind of hash used for ids of `RefDelta` bases and the trailing checksum
+    pub fn hash_kind(&self) -> HashKind {
+        self.hash_kind
+    }
     /// The length of all mapped data, including the pack header and the pack trailer
     pub fn data_len(&self) -> usize {
         self.data.len()
@@ -45,7 +54,7 @@ impl File {
 
     /// The position of the byte one past the last entry, or in other terms, the first byte of the trailing hash.
     pub fn pack_end(&self) -> usize {
-        self.data.len() - SHA1_SIZE
+        self.data.len() - self.hash_kind.len_in_bytes()
     }
 
     pub fn path(&self) -> &Path {
@@ -0,0 +1,200 @@
+use crate::{hash, pack, pack::data::output};
+use git_object::{owned, HashKind};
+use quick_error::quick_error;
+use std::io::{self, Write};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error) {
+            display("Could not write pack data to the output")
+            from()
+            source(err)
+        }
+        Input(err: output::Error) {
+            display("The input entries could not be produced")
+            from()
+            source(err)
+        }
+        DeltaBaseNotWritten { object_index: usize, entry_index: u32 } {
+            display("Entry {} refers to base entry {} which wasn't written before it", entry_index, object_index)
+        }
+        EntryCountMismatch { expected: u32, actual: u32 } {
+            display("The pack header announced {} entries, but {} were provided", expected, actual)
+        }
+    }
+}
+
+/// An implementation of [`Iterator`] to write [encoded entries][output::Entry] to an inner implementation of [`io::Write`],
+/// yielding descriptions of all entries written so far in the format produced when reading a pack.
+///
+/// This makes it possible to write the pack index right away with [`pack::index::File::write_data_iter_to_stream`].
+pub struct FromEntriesIter<I, W> {
+    /// An iterator for input [`output::Entry`] instances
+    pub input: I,
+    /// A way of writing encoded bytes.
+    output: hash::Write<W>,
+    /// The amount of entries announced in the pack header
+    num_entries: u32,
+    /// The amount of entries seen so far
+    entries_written: u32,
+    /// The amount of bytes written so far, which is the pack offset of the next entry
+    written: u64,
+    /// The pack offsets of all entries written so far, to be able to refer to them as delta base
+    pack_offsets: Vec<u64>,
+    /// The id of the pack once the trailer was written
+    trailer: Option<owned::Id>,
+    is_done: bool,
+}
+
+impl<I, W> FromEntriesIter<I, W>
+where
+    I: Iterator<Item = Result<Vec<output::Entry>, output::Error>>,
+    W: io::Write,
+{
+    /// Create a new instance reading [entries][output::Entry] from an `input` iterator and write pack data bytes to
+    /// `output` writer, resembling a pack of `num_entries` with a trailer hashed with `hash_kind`.
+    ///
+    /// `num_entries` must match the amount of entries yielded by `input`, or an error is returned once the mismatch is noticed.
+    pub fn new(input: I, output: W, num_entries: u32, hash_kind: HashKind) -> Self {
+        FromEntriesIter {
+            input,
+            output: hash::Write::new(output, hash_kind),
+            num_entries,
+            entries_written: 0,
+            written: 0,
+            pack_offsets: Vec::with_capacity(num_entries as usize),
+            trailer: None,
+            is_done: false,
+        }
+    }
+
+    /// Consume this instance to obtain the inner writer. Note that it might not be flushed or complete if the
+    /// iteration was not completed.
+    pub fn into_write(self) -> W {
+        self.output.inner
+    }
+
+    /// Returns the trailing hash over all written bytes, which is only available once the iteration was completed.
+    pub fn digest(&self) -> Option<owned::Id> {
+        self.trailer.clone()
+    }
+
+    fn next_inner(&mut self) -> Result<Vec<pack::data::iter::Entry>, Error> {
+        if self.written == 0 {
+            let header = pack::data::parse::encode_header(pack::data::Kind::V2, self.num_entries);
+            self.output.write_all(&header)?;
+            self.written += header.len() as u64;
+        }
+        let mut out = Vec::new();
+        match self.input.next() {
+            Some(entries) => {
+                let entries = entries?;
+                out.reserve(entries.len());
+                let mut header_buf = Vec::with_capacity(20);
+                for entry in entries {
+                    if self.entries_written == self.num_entries {
+                        return Err(Error::EntryCountMismatch {
+                            expected: self.num_entries,
+                            actual: self.entries_written + 1,
+                        });
+                    }
+                    if let output::EntryKind::DeltaRef { object_index } = entry.kind {
+                        if object_index >= self.pack_offsets.len() {
+                            return Err(Error::DeltaBaseNotWritten {
+                                object_index,
+                                entry_index: self.entries_written,
+                            });
+                        }
+                    }
+                    let pack_offset = self.written;
+                    let pack_offsets = &self.pack_offsets;
+                    let header = entry.to_entry_header(|index| pack_offset - pack_offsets[index]);
+                    header_buf.clear();
+                    let header_size = header.to_write(entry.decompressed_size as u64, &mut header_buf)?;
+                    self.output.write_all(&header_buf)?;
+                    self.output.write_all(&entry.compressed_data)?;
+                    let crc32 = git_features::hash::crc32_update(
+                        git_features::hash::crc32(&header_buf),
+                        &entry.compressed_data,
+                    );
+
+                    out.push(pack::data::iter::Entry {
+                        header,
+                        header_size: header_size as u16,
+                        pack_offset,
+                        header_bytes: None,
+                        compressed: None,
+                        compressed_size: entry.compressed_data.len() as u64,
+                        crc32: Some(crc32),
+                        decompressed_size: entry.decompressed_size as u64,
+                        trailer: None,
+                    });
+                    self.pack_offsets.push(pack_offset);
+                    self.written += (header_size + entry.compressed_data.len()) as u64;
+                    self.entries_written += 1;
+                }
+                if self.entries_written == self.num_entries {
+                    self.assert_input_is_exhausted()?;
+                }
+            }
+            None => {
+                if self.entries_written != self.num_entries {
+                    return Err(Error::EntryCountMismatch {
+                        expected: self.num_entries,
+                        actual: self.entries_written,
+                    });
+                }
+            }
+        }
+
+        if self.entries_written == self.num_entries {
+            let trailer = self.output.hash.clone().digest();
+            self.output.inner.write_all(trailer.as_slice())?;
+            self.output.inner.flush()?;
+            self.written += trailer.as_slice().len() as u64;
+            if let Some(last) = out.last_mut() {
+                last.trailer = Some(trailer.clone());
+            }
+            self.trailer = Some(trailer);
+            self.is_done = true;
+        }
+        Ok(out)
+    }
+
+    /// Once all announced entries were written, the input must not provide any more of them as these would be lost.
+    fn assert_input_is_exhausted(&mut self) -> Result<(), Error> {
+        for entries in &mut self.input {
+            let num_entries = entries?.len() as u32;
+            if num_entries != 0 {
+                return Err(Error::EntryCountMismatch {
+                    expected: self.num_entries,
+                    actual: self.entries_written + num_entries,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<I, W> Iterator for FromEntriesIter<I, W>
+where
+    I: Iterator<Item = Result<Vec<output::Entry>, output::Error>>,
+    W: io::Write,
+{
+    /// The entries written by this iteration, with the last entry of the last chunk holding the pack trailer.
+    type Item = Result<Vec<pack::data::iter::Entry>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.is_done {
+            return None;
+        }
+        Some(match self.next_inner() {
+            Err(err) => {
+                self.is_done = true;
+                Err(err)
+            }
+            Ok(entries) => Ok(entries),
+        })
+    }
+}
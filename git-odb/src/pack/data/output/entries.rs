@@ -0,0 +1,278 @@
+use crate::{
+    pack::{self, data::delta, data::output},
+    zlib::CompressionLevel,
+};
+use git_features::{
+    parallel,
+    progress::{self, Progress},
+};
+use std::collections::VecDeque;
+
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Options {
+    /// The amount of threads to use at most when compressing objects, or `None` to use all logical cores.
+    /// Unused if deltas are computed, which happens on the current thread.
+    pub thread_limit: Option<usize>,
+    /// The amount of objects per chunk, which is the unit of work handed to a thread.
+    /// When computing deltas, the window of delta bases slides across chunks, which are produced one at a time.
+    pub chunk_size: usize,
+    /// If set, objects will be stored as deltas against similar objects if that saves space.
+    pub delta: Option<DeltaOptions>,
+    /// The level at which objects and deltas are compressed, like git's `pack.compression`.
+    pub compression: CompressionLevel,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            thread_limit: None,
+            chunk_size: 10,
+            delta: None,
+            compression: CompressionLevel::default(),
+        }
+    }
+}
+
+/// Configure how delta bases are found, similar to `git pack-objects --window=<n> --depth=<n>`
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeltaOptions {
+    /// The amount of preceding objects to try as delta base for each object.
+    pub window: usize,
+    /// The maximum length of delta chains, which trades pack size for decoding performance.
+    pub max_depth: u32,
+}
+
+impl Default for DeltaOptions {
+    fn default() -> Self {
+        DeltaOptions {
+            window: 10,
+            max_depth: 50,
+        }
+    }
+}
+
+/// Given a known list of `objects`, look each one up in `db` and compress it into an [`output::Entry`], using
+/// a cache created with `make_cache()` per thread.
+///
+/// Without delta compression, the returned iterator yields chunks of entries in the order of `objects`.
+/// Otherwise all objects are sorted by kind, [name hash][output::name_hash()] and size before the iteration begins
+/// to try the most similar objects as delta bases, which is why the first chunk will take longer to produce. Sorting
+/// only needs the [header][crate::Locate::header()] of each object, which doesn't require decoding it for most databases.
+/// In any case, the entries should be written to a pack using [`output::bytes::FromEntriesIter`].
+pub fn objects_to_entries_iter<'a, Locate, Iter, Cache>(
+    db: &'a Locate,
+    make_cache: impl Fn() -> Cache + Send + Sync + 'a,
+    objects: Iter,
+    mut progress: impl Progress + 'a,
+    Options {
+        thread_limit,
+        chunk_size,
+        delta,
+        compression,
+    }: Options,
+) -> impl Iterator<Item = Result<Vec<output::Entry>, output::Error>> + 'a
+where
+    Locate: crate::Locate + Sync,
+    Iter: Iterator + 'a,
+    Iter::Item: Into<output::Input>,
+    Cache: pack::cache::DecodeEntry + 'a,
+{
+    let (chunk_size, thread_limit, num_threads) = match delta {
+        Some(_) => (chunk_size.max(1), thread_limit, 1),
+        None => parallel::optimize_chunk_size_and_thread_limit(chunk_size, objects.size_hint().1, thread_limit, None),
+    };
+    progress.init(objects.size_hint().1, progress::count("objects"));
+    let mut objects: Box<dyn Iterator<Item = output::Input> + 'a> = Box::new(objects.map(Into::into).fuse());
+    let mut delta_search = delta.map(|options| DeltaSearch::new(options, make_cache()));
+    let mut needs_sorting = delta.is_some();
+    let mut objects_seen = 0;
+
+    std::iter::from_fn(move || {
+        if needs_sorting {
+            needs_sorting = false;
+            let search = delta_search.as_mut().expect("delta search if sorting is needed");
+            match sorted_for_delta_compression(db, &mut objects, &mut search.buf, &mut search.cache) {
+                Ok(sorted) => objects = Box::new(sorted.into_iter()),
+                Err(err) => return Some(Err(err)),
+            }
+        }
+        let inputs: Vec<_> = objects.by_ref().take(chunk_size * num_threads).collect();
+        if inputs.is_empty() {
+            return None;
+        }
+        let first_object_index = objects_seen;
+        objects_seen += inputs.len();
+        let entries = match delta_search.as_mut() {
+            Some(search) => search.entries(db, &inputs, first_object_index, compression),
+            None => {
+                let make_cache = &make_cache;
+                parallel::in_parallel_if(
+                    || inputs.len() > chunk_size,
+                    inputs.chunks(chunk_size).enumerate(),
+                    thread_limit,
+                    |_thread_index| (Vec::new(), make_cache()),
+                    |(chunk_index, inputs), (buf, cache)| -> Result<_, output::Error> {
+                        let mut entries = Vec::with_capacity(inputs.len());
+                        for input in inputs {
+                            let object = locate(db, input, buf, cache)?;
+                            entries.push(output::Entry::from_data(input.id.clone(), &object, compression)?);
+                        }
+                        Ok((chunk_index, entries))
+                    },
+                    InOrder::default(),
+                )
+            }
+        };
+        if entries.is_ok() {
+            progress.inc_by(inputs.len());
+        }
+        Some(entries)
+    })
+}
+
+fn locate<'a>(
+    db: &impl crate::Locate,
+    input: &output::Input,
+    buf: &'a mut Vec<u8>,
+    cache: &mut impl pack::cache::DecodeEntry,
+) -> Result<pack::Object<'a>, output::Error> {
+    db.locate(input.id.to_borrowed(), buf, cache)
+        .ok_or_else(|| output::Error::NotFound(input.id.clone()))?
+        .map_err(|err| output::Error::Locate(Box::new(err)))
+}
+
+/// Sort objects by kind and name hash, with the largest objects first, so that similar objects are close to each other.
+/// Larger objects are good delta bases as deleting data from them is cheap.
+fn sorted_for_delta_compression(
+    db: &impl crate::Locate,
+    objects: impl Iterator<Item = output::Input>,
+    buf: &mut Vec<u8>,
+    cache: &mut impl pack::cache::DecodeEntry,
+) -> Result<Vec<output::Input>, output::Error> {
+    let mut objects = objects
+        .map(|input| {
+            let (kind, size) = db
+                .header(input.id.to_borrowed(), buf, cache)
+                .ok_or_else(|| output::Error::NotFound(input.id.clone()))?
+                .map_err(|err| output::Error::Locate(Box::new(err)))?;
+            Ok((kind, input.name_hash, size, input))
+        })
+        .collect::<Result<Vec<_>, output::Error>>()?;
+    objects.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(&b.1)).then(b.2.cmp(&a.2)));
+    Ok(objects.into_iter().map(|(_, _, _, input)| input).collect())
+}
+
+struct WindowEntry {
+    object_index: usize,
+    kind: git_object::Kind,
+    depth: u32,
+    index: delta::Index,
+}
+
+/// The state of the delta search, which is kept across chunks so the window of delta bases slides over all objects.
+struct DeltaSearch<Cache> {
+    options: DeltaOptions,
+    window: VecDeque<WindowEntry>,
+    buf: Vec<u8>,
+    cache: Cache,
+}
+
+impl<Cache: pack::cache::DecodeEntry> DeltaSearch<Cache> {
+    fn new(options: DeltaOptions, cache: Cache) -> Self {
+        DeltaSearch {
+            options,
+            window: VecDeque::with_capacity(options.window + 1),
+            buf: Vec::new(),
+            cache,
+        }
+    }
+
+    /// Produce entries for `inputs`, the first of which is at `first_object_index` in the pack, trying the objects
+    /// in the window as delta base for each of them.
+    fn entries(
+        &mut self,
+        db: &impl crate::Locate,
+        inputs: &[output::Input],
+        first_object_index: usize,
+        compression: CompressionLevel,
+    ) -> Result<Vec<output::Entry>, output::Error> {
+        let DeltaSearch {
+            options,
+            window,
+            buf,
+            cache,
+        } = self;
+        let mut entries = Vec::with_capacity(inputs.len());
+        let (mut delta_buf, mut best_delta) = (Vec::new(), Vec::new());
+        for (input_index, input) in inputs.iter().enumerate() {
+            let object = locate(db, input, buf, cache)?;
+            // Like git, only accept deltas which are at most half the size of the object itself
+            let mut max_size = (object.data.len() / 2).saturating_sub(20);
+            let mut best_base = None;
+            for base in window.iter().rev() {
+                let base_size = base.index.base().len();
+                if base.kind != object.kind || base.depth >= options.max_depth || object.data.len() < base_size / 32 {
+                    continue;
+                }
+                if base.index.encode(object.data, &mut delta_buf, max_size) {
+                    max_size = delta_buf.len().saturating_sub(1);
+                    std::mem::swap(&mut delta_buf, &mut best_delta);
+                    best_base = Some((base.object_index, base.depth));
+                }
+            }
+            let depth = match best_base {
+                Some((base_object_index, base_depth)) => {
+                    entries.push(output::Entry::from_delta(
+                        input.id.clone(),
+                        object.kind,
+                        base_object_index,
+                        &best_delta,
+                        compression,
+                    )?);
+                    base_depth + 1
+                }
+                None => {
+                    entries.push(output::Entry::from_data(input.id.clone(), &object, compression)?);
+                    0
+                }
+            };
+            if options.window == 0 {
+                continue;
+            }
+            if window.len() == options.window {
+                window.pop_front();
+            }
+            window.push_back(WindowEntry {
+                object_index: first_object_index + input_index,
+                kind: object.kind,
+                depth,
+                index: delta::Index::new(object.data.to_owned()),
+            });
+        }
+        Ok(entries)
+    }
+}
+
+/// Assemble chunks of entries in the order they were submitted in.
+#[derive(Default)]
+struct InOrder {
+    chunks: Vec<(usize, Vec<output::Entry>)>,
+}
+
+impl parallel::Reducer for InOrder {
+    type Input = Result<(usize, Vec<output::Entry>), output::Error>;
+    type Output = Vec<output::Entry>;
+    type Error = output::Error;
+
+    fn feed(&mut self, input: Self::Input) -> Result<(), Self::Error> {
+        self.chunks.push(input?);
+        Ok(())
+    }
+
+    fn finalize(mut self) -> Result<Self::Output, Self::Error> {
+        self.chunks.sort_by_key(|(chunk_index, _)| *chunk_index);
+        Ok(self.chunks.into_iter().flat_map(|(_, entries)| entries).collect())
+    }
+}
@@ -0,0 +1,212 @@
+//! Creation of new packs from a set of objects
+use crate::{
+    pack,
+    zlib::{stream::DeflateWriter, CompressionLevel},
+};
+use git_object::{borrowed, owned};
+use quick_error::quick_error;
+use std::io::{self, Write};
+
+mod entries;
+pub use entries::{objects_to_entries_iter, DeltaOptions, Options};
+
+pub mod bytes;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        NotFound(id: owned::Id) {
+            display("The object {} could not be found in the object database", id)
+        }
+        Locate(err: Box<dyn std::error::Error + Send + Sync>) {
+            display("An object could not be located")
+            source(&**err)
+        }
+        Io(err: io::Error) {
+            display("An object could not be compressed")
+            from()
+            source(err)
+        }
+        PackEntry(err: pack::data::decode::Error) {
+            display("The header of an entry in an existing pack could not be decoded")
+            from()
+            source(err)
+        }
+        Crc32Mismatch { id: owned::Id, expected: u32, actual: u32 } {
+            display("The pack entry of object {} has CRC32 {:08x}, but its index expects {:08x}", id, actual, expected)
+        }
+    }
+}
+
+/// An object to be placed into a pack, along with a hint about where it is located in the tree
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Input {
+    /// The id of the object
+    pub id: owned::Id,
+    /// The [`name_hash()`] of the path at which the object was found, or 0 if unknown.
+    /// It's used to find good delta bases, as objects at the same path tend to be similar.
+    pub name_hash: u32,
+}
+
+impl From<owned::Id> for Input {
+    fn from(id: owned::Id) -> Self {
+        Input { id, name_hash: 0 }
+    }
+}
+
+/// Hash the `path` of an object similarly to git, so that the hash is mostly determined by the last 16 characters
+/// of the path, sorting objects with similar file names close to each other.
+pub fn name_hash(path: &[u8]) -> u32 {
+    path.iter()
+        .filter(|b| !b.is_ascii_whitespace())
+        .fold(0u32, |hash, b| (hash >> 2).wrapping_add((*b as u32) << 24))
+}
+
+/// Describes how an [`Entry`] is stored in the pack
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum EntryKind {
+    /// The entry holds the compressed object data as is
+    Base,
+    /// The entry holds compressed delta instructions against the base entry at `object_index`, which is the
+    /// index of the base entry among all entries written to the same pack.
+    DeltaRef { object_index: usize },
+}
+
+/// An entry to be written to a pack, with its data already compressed
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Entry {
+    /// The id of the object this entry represents
+    pub id: owned::Id,
+    /// The kind of the object this entry represents
+    pub object_kind: git_object::Kind,
+    /// The way the data of this entry is stored
+    pub kind: EntryKind,
+    /// The size of the decompressed data in bytes, which are delta instructions for delta entries
+    pub decompressed_size: usize,
+    /// The zlib compressed data, ready to be written into the pack right after the entry header
+    pub compressed_data: Vec<u8>,
+}
+
+impl Entry {
+    /// Create a new entry for the object identified by `id` by compressing its `data` at the given `level`.
+    pub fn from_data(id: owned::Id, object: &pack::Object<'_>, level: CompressionLevel) -> Result<Self, io::Error> {
+        Ok(Entry {
+            id,
+            object_kind: object.kind,
+            kind: EntryKind::Base,
+            decompressed_size: object.data.len(),
+            compressed_data: compress(object.data, level)?,
+        })
+    }
+
+    /// Create a new entry for the object identified by `id` of `object_kind`, represented by `delta` instructions against
+    /// the entry at `base_object_index`, compressed at the given `level`.
+    pub fn from_delta(
+        id: owned::Id,
+        object_kind: git_object::Kind,
+        base_object_index: usize,
+        delta: &[u8],
+        level: CompressionLevel,
+    ) -> Result<Self, io::Error> {
+        Ok(Entry {
+            id,
+            object_kind,
+            kind: EntryKind::DeltaRef {
+                object_index: base_object_index,
+            },
+            decompressed_size: delta.len(),
+            compressed_data: compress(delta, level)?,
+        })
+    }
+
+    /// Create a new entry for the object at `index_position` in `bundle` by copying its compressed bytes verbatim, which
+    /// is much faster than decompressing and compressing them again. `reverse_index` must belong to `bundle` and is
+    /// used to find where the entry ends.
+    ///
+    /// Deltas are only copied if `base_object_index` returns the index and kind of their base among the entries written
+    /// before, given the id of the base. Their header is rewritten to refer to the new position of the base, which turns
+    /// `RefDelta` entries into `OfsDelta` entries.
+    ///
+    /// Returns `None` if the entry can't be copied, in which case the object should be written in full using
+    /// [`Entry::from_data()`]. This is also the case if `bundle` has no CRC32 to verify the copied bytes with, and if
+    /// it doesn't match the copied bytes, an error is returned.
+    pub fn from_pack_entry(
+        bundle: &pack::Bundle,
+        reverse_index: &pack::rev::File,
+        index_position: u32,
+        base_object_index: impl FnOnce(borrowed::Id<'_>) -> Option<(usize, git_object::Kind)>,
+    ) -> Option<Result<Self, Error>> {
+        let expected = bundle.index.crc32_at_index(index_position)?;
+        let entry = match bundle.entry_at_index(index_position) {
+            Ok(entry) => entry,
+            Err(err) => return Some(Err(err.into())),
+        };
+        let pack_offset = entry.pack_offset();
+        let (object_kind, kind) = match entry.header {
+            pack::data::Header::OfsDelta { base_distance } => {
+                let base_pack_position =
+                    reverse_index.pack_position_by_offset(&bundle.index, entry.base_pack_offset(base_distance)?)?;
+                let (object_index, object_kind) = base_object_index(
+                    bundle
+                        .index
+                        .oid_at_index(reverse_index.index_position_at_pack_position(base_pack_position)),
+                )?;
+                (object_kind, EntryKind::DeltaRef { object_index })
+            }
+            pack::data::Header::RefDelta { base_id } => {
+                let (object_index, object_kind) = base_object_index(base_id.to_borrowed())?;
+                (object_kind, EntryKind::DeltaRef { object_index })
+            }
+            header => (header.to_kind()?, EntryKind::Base),
+        };
+
+        let next_pack_position = reverse_index.pack_position_by_offset(&bundle.index, pack_offset)? + 1;
+        let entry_end = if next_pack_position < reverse_index.num_objects() {
+            bundle
+                .index
+                .pack_offset_at_index(reverse_index.index_position_at_pack_position(next_pack_position))?
+        } else {
+            bundle.pack.pack_end() as u64
+        };
+        let entry_data = bundle.pack.entry_slice(pack_offset..entry_end)?;
+        let id = bundle.index.oid_at_index(index_position).into();
+        let actual = git_features::hash::crc32(entry_data);
+        if actual != expected {
+            return Some(Err(Error::Crc32Mismatch { id, expected, actual }));
+        }
+        Some(Ok(Entry {
+            id,
+            object_kind,
+            kind,
+            decompressed_size: entry.decompressed_size as usize,
+            compressed_data: entry_data[(entry.data_offset - pack_offset) as usize..].to_owned(),
+        }))
+    }
+
+    /// The header to use when writing this entry into a pack, with `distance_to_base` producing the distance in bytes
+    /// between the pack offset of this entry and the one of the base entry at the given object index.
+    pub fn to_entry_header(&self, distance_to_base: impl FnOnce(usize) -> u64) -> pack::data::Header {
+        use git_object::Kind::*;
+        match self.kind {
+            EntryKind::DeltaRef { object_index } => pack::data::Header::OfsDelta {
+                base_distance: distance_to_base(object_index),
+            },
+            EntryKind::Base => match self.object_kind {
+                Tree => pack::data::Header::Tree,
+                Blob => pack::data::Header::Blob,
+                Commit => pack::data::Header::Commit,
+                Tag => pack::data::Header::Tag,
+            },
+        }
+    }
+}
+
+fn compress(data: &[u8], level: CompressionLevel) -> Result<Vec<u8>, io::Error> {
+    let mut out = DeflateWriter::with_level(Vec::new(), level);
+    out.write_all(data)?;
+    out.flush()?;
+    Ok(out.into_inner())
+}
@@ -37,3 +37,18 @@ pub fn header(data: &[u8; 12]) -> Result<(data::Kind, u32), Error> {
 
     Ok((kind, num_objects))
 }
+
+/// Write a pack data header at `kind` and `num_objects` into a buffer of 12 bytes.
+pub fn encode_header(kind: data::Kind, num_objects: u32) -> [u8; 12] {
+    let mut out = [0u8; 12];
+    out[..b"PACK".len()].copy_from_slice(b"PACK");
+    BigEndian::write_u32(
+        &mut out[4..8],
+        match kind {
+            data::Kind::V2 => 2,
+            data::Kind::V3 => 3,
+        },
+    );
+    BigEndian::write_u32(&mut out[8..], num_objects);
+    out
+}
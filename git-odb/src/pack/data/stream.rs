@@ -0,0 +1,243 @@
+//! Streaming decoding of pack entries, for objects too large to be held in memory comfortably.
+use crate::{
+    pack::{self, cache, data::decode, data::File},
+    zlib::stream::InflateReader,
+};
+use git_object::borrowed;
+use quick_error::quick_error;
+use std::io::{self, BufRead, Read};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Decode(err: decode::Error) {
+            display("The base of the delta could not be decoded")
+            from()
+            source(err)
+        }
+        DeltaHeader(err: io::Error) {
+            display("The sizes at the beginning of the delta could not be read")
+            source(err)
+        }
+        DeltaBaseSizeMismatch { expected: u64, actual: usize } {
+            display("The delta expects a base of {} bytes, but the base has {} bytes", expected, actual)
+        }
+    }
+}
+
+/// An implementation of [`io::Read`] yielding the data of a pack entry, see [`File::stream_entry()`].
+pub struct Reader<'a> {
+    kind: git_object::Kind,
+    size: u64,
+    inner: Inner<'a>,
+}
+
+enum Inner<'a> {
+    Object(io::Take<InflateReader<&'a [u8]>>),
+    Delta(DeltaReader<'a>),
+}
+
+impl<'a> Reader<'a> {
+    /// The kind of the object whose data is streamed.
+    pub fn kind(&self) -> git_object::Kind {
+        self.kind
+    }
+
+    /// The size of the object in bytes, which is the total amount of bytes that can be read.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+impl<'a> io::Read for Reader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.inner {
+            Inner::Object(reader) => reader.read(buf),
+            Inner::Delta(reader) => reader.read(buf),
+        }
+    }
+}
+
+/// A delta instruction which wasn't completely applied yet.
+#[derive(Clone, Copy)]
+enum Instruction {
+    Copy { offset: usize, len: usize },
+    Insert { len: usize },
+}
+
+/// Applies the instructions of a delta to its base while they are decompressed.
+struct DeltaReader<'a> {
+    base: Vec<u8>,
+    instructions: io::BufReader<InflateReader<&'a [u8]>>,
+    current: Option<Instruction>,
+    /// The amount of bytes of the result that weren't produced yet
+    remaining: u64,
+}
+
+impl<'a> DeltaReader<'a> {
+    fn next_instruction(&mut self) -> io::Result<Option<Instruction>> {
+        let cmd = match read_byte(&mut self.instructions)? {
+            Some(cmd) => cmd,
+            None => return Ok(None),
+        };
+        let instruction = if cmd & 0b1000_0000 != 0 {
+            let (mut offset, mut len) = (0usize, 0usize);
+            for (bit, shift) in (0..4).map(|n| (1 << n, n * 8)) {
+                if cmd & bit != 0 {
+                    offset |= (read_instruction_byte(&mut self.instructions)? as usize) << shift;
+                }
+            }
+            for (bit, shift) in (4..7).map(|n| (1 << n, (n - 4) * 8)) {
+                if cmd & bit != 0 {
+                    len |= (read_instruction_byte(&mut self.instructions)? as usize) << shift;
+                }
+            }
+            if len == 0 {
+                len = 0x10000;
+            }
+            if offset.checked_add(len).filter(|&end| end <= self.base.len()).is_none() {
+                return Err(invalid_data("delta copies data from beyond the end of its base"));
+            }
+            Instruction::Copy { offset, len }
+        } else if cmd == 0 {
+            return Err(invalid_data("encountered unsupported delta command code: 0"));
+        } else {
+            Instruction::Insert { len: cmd as usize }
+        };
+        Ok(Some(instruction))
+    }
+}
+
+impl<'a> io::Read for DeltaReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let instruction = match self.current.take() {
+            Some(instruction) => instruction,
+            None => match self.next_instruction()? {
+                Some(instruction) => instruction,
+                None if self.remaining == 0 => return Ok(0),
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "delta instructions ended before the entire object was produced",
+                    ))
+                }
+            },
+        };
+        let len = match instruction {
+            Instruction::Copy { len, .. } | Instruction::Insert { len } => len,
+        };
+        if len as u64 > self.remaining {
+            return Err(invalid_data("delta produces more data than announced"));
+        }
+
+        let n = len.min(buf.len());
+        match instruction {
+            Instruction::Copy { offset, .. } => buf[..n].copy_from_slice(&self.base[offset..offset + n]),
+            Instruction::Insert { .. } => self.instructions.read_exact(&mut buf[..n])?,
+        }
+        self.current = match instruction {
+            _ if n == len => None,
+            Instruction::Copy { offset, len } => Some(Instruction::Copy {
+                offset: offset + n,
+                len: len - n,
+            }),
+            Instruction::Insert { len } => Some(Instruction::Insert { len: len - n }),
+        };
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+fn read_byte(read: &mut impl BufRead) -> io::Result<Option<u8>> {
+    let byte = read.fill_buf()?.first().copied();
+    if byte.is_some() {
+        read.consume(1);
+    }
+    Ok(byte)
+}
+
+fn read_instruction_byte(read: &mut impl BufRead) -> io::Result<u8> {
+    read_byte(read)?.ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "delta instruction is truncated"))
+}
+
+fn read_size(read: &mut impl BufRead) -> io::Result<u64> {
+    let mut size = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = read_instruction_byte(read)?;
+        size |= (byte as u64 & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(size);
+        }
+    }
+    Err(invalid_data("delta size is too large"))
+}
+
+fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Streaming
+impl File {
+    /// Obtain a reader for the data of the object stored in `entry`, decompressing it while reading.
+    ///
+    /// Undeltified objects are streamed straight from the pack. For deltified objects, only the base of the delta in
+    /// `entry` is decoded into memory, similar to [`decode_entry()`][File::decode_entry()] with `resolve` and
+    /// `delta_cache`, while the result of applying the delta is streamed. This keeps memory usage proportional to the
+    /// size of the base instead of the size of base and object together.
+    pub fn stream_entry(
+        &self,
+        entry: pack::data::Entry,
+        resolve: impl Fn(borrowed::Id, &mut Vec<u8>) -> Option<decode::ResolvedBase>,
+        delta_cache: &mut impl cache::DecodeEntry,
+    ) -> Result<Reader<'_>, Error> {
+        use pack::data::Header::*;
+        let data = self.data_at(entry.data_offset)?;
+        let mut base = Vec::new();
+        let base_kind = match entry.header {
+            Tree | Blob | Commit | Tag => {
+                return Ok(Reader {
+                    kind: entry.header.to_kind().expect("a non-delta entry"),
+                    size: entry.decompressed_size,
+                    inner: Inner::Object(InflateReader::from_read(data).take(entry.decompressed_size)),
+                })
+            }
+            OfsDelta { base_distance } => {
+                let base_entry = self.base_entry(&entry, base_distance)?;
+                self.decode_entry(base_entry, &mut base, &resolve, delta_cache)?.kind
+            }
+            RefDelta { base_id } => match resolve(base_id.to_borrowed(), &mut base) {
+                Some(decode::ResolvedBase::InPack(base_entry)) => {
+                    self.decode_entry(base_entry, &mut base, &resolve, delta_cache)?.kind
+                }
+                Some(decode::ResolvedBase::OutOfPack { kind, end }) => {
+                    base.truncate(end);
+                    kind
+                }
+                None => return Err(decode::Error::DeltaBaseUnresolved(*base_id).into()),
+            },
+        };
+
+        let mut instructions = io::BufReader::new(InflateReader::from_read(data));
+        let base_size = read_size(&mut instructions).map_err(Error::DeltaHeader)?;
+        if base_size != base.len() as u64 {
+            return Err(Error::DeltaBaseSizeMismatch {
+                expected: base_size,
+                actual: base.len(),
+            });
+        }
+        let size = read_size(&mut instructions).map_err(Error::DeltaHeader)?;
+        Ok(Reader {
+            kind: base_kind,
+            size,
+            inner: Inner::Delta(DeltaReader {
+                base,
+                instructions,
+                current: None,
+                remaining: size,
+            }),
+        })
+    }
+}
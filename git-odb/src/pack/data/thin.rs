@@ -0,0 +1,315 @@
+//! Utilities to turn thin packs, whose ref-delta bases are not contained in the pack itself, into self-contained ones.
+use crate::{
+    hash, pack,
+    pack::tree::Tree,
+    zlib::{stream::DeflateWriter, CompressionLevel},
+};
+use git_object::{owned, HashKind};
+use std::{
+    io::{self, Write},
+    iter::Peekable,
+};
+
+/// An iterator over [entries][pack::data::iter::Entry] which looks up the bases of `RefDelta` entries that aren't
+/// contained in the pack in an object database, and appends them after the last entry like `git index-pack --fix-thin`.
+/// All entries of the input are passed on unchanged, which is why the deltas keep referring to their bases by id.
+///
+/// Bases are considered missing if they aren't an object of the pack, which is determined by resolving all deltas of the
+/// pack once the input is depleted. For that, the entries passed on so far are read back using the function produced by
+/// `make_resolver()`, as is possible when they are written with [`EntriesToBytesIter`].
+/// Like git, bases which are deltas whose own base is missing may be appended even though they are part of the pack.
+/// Missing bases which can't be found aren't appended, leaving it to the consumer to deal with the incomplete pack.
+///
+/// The input iterator must keep the compressed bytes of each entry, see [`pack::data::iter::CompressedBytesMode`].
+pub struct LookupRefDeltaObjectsIter<I, L, M> {
+    /// The iterator providing entries of the input pack
+    pub inner: I,
+    lookup: L,
+    make_resolver: Option<M>,
+    hash_kind: HashKind,
+    compression: CompressionLevel,
+    cache: pack::cache::DecodeEntryLRU,
+    buf: Vec<u8>,
+    /// The pack offset of the next entry we return
+    next_offset: u64,
+    /// The delta tree of all entries passed on so far
+    tree: Option<Tree<()>>,
+    /// The pack offset and base id of all `RefDelta` entries
+    ref_deltas: Vec<(u64, owned::Id)>,
+    /// The bases to append, known once `inner` is depleted
+    missing_bases: Option<std::vec::IntoIter<owned::Id>>,
+    num_inserted_bases: usize,
+}
+
+impl<I, L, M, F> LookupRefDeltaObjectsIter<I, L, M>
+where
+    I: Iterator<Item = Result<pack::data::iter::Entry, pack::data::iter::Error>>,
+    L: crate::Locate,
+    M: FnOnce() -> io::Result<F>,
+    F: for<'r> Fn(pack::data::EntrySlice, &'r mut Vec<u8>) -> Option<()>,
+{
+    /// Create a new instance wrapping `inner` entries of a pack with objects identified by `hash_kind`, using `lookup`
+    /// to find bases that are not in the pack, which are compressed at the given `compression` level.
+    /// `make_resolver()` is only called once `inner` is depleted and if there are `RefDelta` entries, to produce a function
+    /// providing the bytes of the entries passed on so far.
+    pub fn new(inner: I, lookup: L, make_resolver: M, hash_kind: HashKind, compression: CompressionLevel) -> Self {
+        LookupRefDeltaObjectsIter {
+            inner,
+            lookup,
+            make_resolver: Some(make_resolver),
+            hash_kind,
+            compression,
+            cache: Default::default(),
+            buf: Vec::new(),
+            next_offset: pack::data::File::HEADER_LEN as u64,
+            tree: None,
+            ref_deltas: Vec::new(),
+            missing_bases: None,
+            num_inserted_bases: 0,
+        }
+    }
+
+    /// The amount of base objects inserted so far
+    pub fn num_inserted_bases(&self) -> usize {
+        self.num_inserted_bases
+    }
+
+    /// Remember where `entry` belongs in the delta tree, and pass it on.
+    fn handle(
+        &mut self,
+        mut entry: pack::data::iter::Entry,
+    ) -> Result<pack::data::iter::Entry, pack::data::iter::Error> {
+        self.add_to_tree(&entry)
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+        self.next_offset = entry.pack_offset + entry.header_size as u64 + entry.compressed_size;
+        entry.trailer = None;
+        Ok(entry)
+    }
+
+    fn add_to_tree(&mut self, entry: &pack::data::iter::Entry) -> Result<(), pack::tree::Error> {
+        let tree = match self.tree.as_mut() {
+            Some(tree) => tree,
+            None => self
+                .tree
+                .get_or_insert(Tree::with_capacity(self.inner.size_hint().0 + 1)?),
+        };
+        match &entry.header {
+            pack::data::Header::RefDelta { base_id } => {
+                tree.add_unlinked_child(entry.pack_offset, ())?;
+                self.ref_deltas.push((entry.pack_offset, (**base_id).clone()));
+                Ok(())
+            }
+            pack::data::Header::OfsDelta { base_distance } => {
+                // invalid distances point to where there is no entry, which the tree doesn't accept
+                tree.add_child(entry.pack_offset.saturating_sub(*base_distance), entry.pack_offset, ())
+            }
+            _ => tree.add_root(entry.pack_offset, ()),
+        }
+    }
+
+    /// Resolve all deltas of the pack to learn which bases of `RefDelta` entries are not contained in it.
+    fn find_missing_bases(&mut self) -> Result<Vec<owned::Id>, pack::data::iter::Error> {
+        let ref_deltas = std::mem::take(&mut self.ref_deltas);
+        let (mut tree, make_resolver) = match (self.tree.take(), self.make_resolver.take()) {
+            (Some(tree), Some(make_resolver)) if !ref_deltas.is_empty() => (tree, make_resolver),
+            _ => return Ok(Vec::new()),
+        };
+        let resolver = make_resolver()?;
+        let unresolved =
+            pack::index::write::link_ref_deltas(&mut tree, ref_deltas, resolver, self.next_offset, self.hash_kind)
+                .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+        let mut base_ids: Vec<_> = unresolved.into_iter().map(|(_, base_id)| base_id).collect();
+        base_ids.sort();
+        base_ids.dedup();
+        Ok(base_ids)
+    }
+
+    /// Look up the base with `base_id` and turn it into an entry placed at our next offset, or `None` if it wasn't found.
+    fn new_base_entry(&mut self, base_id: owned::Id) -> Result<Option<pack::data::iter::Entry>, io::Error> {
+        let object = match self
+            .lookup
+            .locate(base_id.to_borrowed(), &mut self.buf, &mut self.cache)
+        {
+            Some(object) => object.map_err(|err| io::Error::new(io::ErrorKind::Other, err))?,
+            None => return Ok(None),
+        };
+        let header = match object.kind {
+            git_object::Kind::Tree => pack::data::Header::Tree,
+            git_object::Kind::Blob => pack::data::Header::Blob,
+            git_object::Kind::Commit => pack::data::Header::Commit,
+            git_object::Kind::Tag => pack::data::Header::Tag,
+        };
+        let mut compressed = DeflateWriter::with_level(Vec::new(), self.compression);
+        compressed.write_all(object.data)?;
+        compressed.flush()?;
+        let compressed = compressed.into_inner();
+        let decompressed_size = object.data.len() as u64;
+
+        let mut header_buf = Vec::with_capacity(32);
+        let header_size = header.to_write(decompressed_size, &mut header_buf)?;
+        let crc32 = git_features::hash::crc32_update(git_features::hash::crc32(&header_buf), &compressed);
+        let entry = pack::data::iter::Entry {
+            header,
+            header_size: header_size as u16,
+            pack_offset: self.next_offset,
+            header_bytes: Some(header_buf),
+            compressed_size: compressed.len() as u64,
+            crc32: Some(crc32),
+            compressed: Some(compressed),
+            decompressed_size,
+            trailer: None,
+        };
+        self.next_offset += header_size as u64 + entry.compressed_size;
+        self.num_inserted_bases += 1;
+        Ok(Some(entry))
+    }
+}
+
+impl<I, L, M, F> Iterator for LookupRefDeltaObjectsIter<I, L, M>
+where
+    I: Iterator<Item = Result<pack::data::iter::Entry, pack::data::iter::Error>>,
+    L: crate::Locate,
+    M: FnOnce() -> io::Result<F>,
+    F: for<'r> Fn(pack::data::EntrySlice, &'r mut Vec<u8>) -> Option<()>,
+{
+    type Item = Result<pack::data::iter::Entry, pack::data::iter::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.missing_bases.is_none() {
+            match self.inner.next() {
+                Some(entry) => return Some(entry.and_then(|entry| self.handle(entry))),
+                None => match self.find_missing_bases() {
+                    Ok(base_ids) => self.missing_bases = Some(base_ids.into_iter()),
+                    Err(err) => {
+                        self.missing_bases = Some(Vec::new().into_iter());
+                        return Some(Err(err));
+                    }
+                },
+            }
+        }
+        loop {
+            let base_id = self.missing_bases.as_mut().and_then(Iterator::next)?;
+            match self.new_base_entry(base_id).transpose() {
+                Some(res) => return Some(res.map_err(Into::into)),
+                None => continue,
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, _upper) = self.inner.size_hint();
+        (lower, None)
+    }
+}
+
+/// An iterator which writes all [entries][pack::data::iter::Entry] it sees to `output`, including pack header and trailer,
+/// passing them on unchanged except for the trailer of the last entry, which is set to the hash of the written pack.
+/// Without any entries, only the pack header and trailer are written.
+///
+/// This is useful if entries were altered and thus can't be written as they are read, as the pack header is only
+/// finalized once all entries were seen. The header bytes and compressed bytes of each entry must be kept, and are
+/// written as they are to keep pack offsets and CRC32s of the entries valid.
+pub struct EntriesToBytesIter<I: Iterator, W> {
+    /// The entries to write
+    pub input: Peekable<I>,
+    output: W,
+    kind: pack::data::Kind,
+    hash_kind: HashKind,
+    num_entries: u32,
+    is_done: bool,
+}
+
+impl<I, W> EntriesToBytesIter<I, W>
+where
+    I: Iterator<Item = Result<pack::data::iter::Entry, pack::data::iter::Error>>,
+    W: io::Read + io::Write + io::Seek,
+{
+    /// Create a new instance to write all entries of `input` to `output`, which is expected to be empty, as a pack of
+    /// `kind` using `hash_kind` for the trailer.
+    pub fn new(input: I, output: W, kind: pack::data::Kind, hash_kind: HashKind) -> Self {
+        EntriesToBytesIter {
+            input: input.peekable(),
+            output,
+            kind,
+            hash_kind,
+            num_entries: 0,
+            is_done: false,
+        }
+    }
+
+    /// Consume this instance to obtain the output.
+    pub fn into_write(self) -> W {
+        self.output
+    }
+
+    fn write_entry(
+        &mut self,
+        mut entry: pack::data::iter::Entry,
+    ) -> Result<pack::data::iter::Entry, pack::data::iter::Error> {
+        if self.num_entries == 0 {
+            self.output
+                .write_all(&pack::data::parse::encode_header(self.kind, self.num_entries))?;
+        }
+        self.num_entries += 1;
+        match (entry.header_bytes.as_ref(), entry.compressed.as_ref()) {
+            (Some(header), Some(compressed)) => {
+                self.output.write_all(header)?;
+                self.output.write_all(compressed)?;
+            }
+            _ => {
+                return Err(pack::data::iter::Error::CompressedBytesMissing {
+                    pack_offset: entry.pack_offset,
+                })
+            }
+        }
+
+        if self.input.peek().is_none() {
+            entry.trailer = Some(self.finalize()?);
+        }
+        Ok(entry)
+    }
+
+    /// Write the correct amount of entries into the header and append the hash over all bytes in the pack.
+    fn finalize(&mut self) -> Result<owned::Id, io::Error> {
+        self.output.seek(io::SeekFrom::Start(0))?;
+        self.output
+            .write_all(&pack::data::parse::encode_header(self.kind, self.num_entries))?;
+        self.output.flush()?;
+
+        self.output.seek(io::SeekFrom::Start(0))?;
+        let mut hash = hash::Write::new(io::sink(), self.hash_kind);
+        io::copy(&mut self.output, &mut hash)?;
+        let id = hash.hash.digest();
+        self.output.write_all(id.as_slice())?;
+        self.output.flush()?;
+        Ok(id)
+    }
+}
+
+impl<I, W> Iterator for EntriesToBytesIter<I, W>
+where
+    I: Iterator<Item = Result<pack::data::iter::Entry, pack::data::iter::Error>>,
+    W: io::Read + io::Write + io::Seek,
+{
+    type Item = Result<pack::data::iter::Entry, pack::data::iter::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.is_done {
+            return None;
+        }
+        if self.num_entries == 0 && self.input.peek().is_none() {
+            self.is_done = true;
+            return self.finalize().err().map(|err| Err(err.into()));
+        }
+        let res = match self.input.next()? {
+            Ok(entry) => self.write_entry(entry),
+            Err(err) => Err(err),
+        };
+        self.is_done = res.is_err() || res.as_ref().map(|e| e.trailer.is_some()).unwrap_or(false);
+        Some(res)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
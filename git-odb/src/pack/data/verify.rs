@@ -1,6 +1,6 @@
 use crate::pack::data::File;
 use git_features::progress::Progress;
-use git_object::{owned, SHA1_SIZE};
+use git_object::owned;
 use quick_error::quick_error;
 
 quick_error! {
@@ -20,19 +20,19 @@ quick_error! {
 /// Checksums and verify checksums
 impl File {
     pub fn checksum(&self) -> owned::Id {
-        owned::Id::from_20_bytes(&self.data[self.data.len() - SHA1_SIZE..])
+        owned::Id::from_bytes(&self.data[self.pack_end()..]).expect("trailer to be a full hash")
     }
     pub fn verify_checksum(&self, mut progress: impl Progress) -> Result<owned::Id, Error> {
-        let right_before_trailer = self.data.len() - SHA1_SIZE;
-        let actual = match crate::hash::bytes_of_file(&self.path, right_before_trailer, &mut progress) {
+        let right_before_trailer = self.pack_end();
+        let actual = match crate::hash::bytes_of_file(&self.path, right_before_trailer, self.hash_kind, &mut progress) {
             Ok(id) => id,
             Err(_io_err) => {
                 let start = std::time::Instant::now();
-                let mut hasher = git_features::hash::Sha1::default();
+                let mut hasher = crate::hash::Hasher::new(self.hash_kind);
                 hasher.update(&self.data[..right_before_trailer]);
                 progress.inc_by(right_before_trailer);
                 progress.show_throughput(start);
-                owned::Id::new_sha1(hasher.digest())
+                hasher.digest()
             }
         };
 
@@ -1,9 +1,15 @@
-use crate::pack::index::{self, FAN_LEN};
+use crate::{
+    pack::index::{self, FAN_LEN},
+    prefix,
+};
 use byteorder::{BigEndian, ByteOrder};
 use git_object::{borrowed, owned, SHA1_SIZE};
+use quick_error::quick_error;
 use std::{
+    cmp::Ordering,
     convert::{TryFrom, TryInto},
     mem::size_of,
+    ops::Range,
 };
 
 const N32_SIZE: usize = size_of::<u32>();
@@ -14,6 +20,15 @@ const N32_HIGH_BIT: u32 = 1 << 31;
 
 pub(crate) type PackOffset = u64;
 
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        LargeOffsetOutOfBounds { index_position: u32 } {
+            display("The object at index {} refers to a large offset the index doesn't contain", index_position)
+        }
+    }
+}
+
 #[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 pub struct Entry {
@@ -42,38 +57,45 @@ impl index::File {
         }
     }
 
-    pub fn iter_v2<'a>(&'a self) -> impl Iterator<Item = Entry> + 'a {
+    /// Fails if an entry refers to a large offset the index doesn't contain, before yielding any entry.
+    pub fn iter_v2<'a>(&'a self) -> Result<impl Iterator<Item = Entry> + 'a, Error> {
+        self.verify_large_offsets()?;
         let pack64_offset = self.offset_pack_offset64_v2();
-        match self.kind {
+        Ok(match self.kind {
             index::Kind::V2 => izip!(
-                self.data[V2_HEADER_SIZE..].chunks(SHA1_SIZE),
+                self.data[V2_HEADER_SIZE..].chunks(self.hash_kind.len_in_bytes()),
                 self.data[self.offset_crc32_v2()..].chunks(N32_SIZE),
                 self.data[self.offset_pack_offset_v2()..].chunks(N32_SIZE)
             )
             .take(self.num_objects as usize)
             .map(move |(oid, crc32, ofs32)| Entry {
-                oid: owned::Id::from_20_bytes(oid),
-                pack_offset: self.pack_offset_from_offset_v2(ofs32, pack64_offset),
+                oid: owned::Id::from_bytes(oid).expect("hash_len bytes to make an id"),
+                pack_offset: self
+                    .pack_offset_from_offset_v2(ofs32, pack64_offset)
+                    .expect("large offsets to be verified"),
                 crc32: Some(BigEndian::read_u32(crc32)),
             }),
             _ => panic!("Cannot use iter_v2() on index of type {:?}", self.kind),
-        }
+        })
     }
 
-    /// Returns 20 bytes sha1 at the given index in our list of (sorted) sha1 hashes.
+    /// Returns the id at the given index in our list of (sorted) ids, being 20 bytes for SHA1 and 32 bytes for SHA256.
     /// The index ranges from 0 to self.num_objects()
     pub fn oid_at_index(&self, index: u32) -> borrowed::Id {
         let index: usize = index
             .try_into()
             .expect("an architecture able to hold 32 bits of integer");
+        let hash_len = self.hash_kind.len_in_bytes();
         let start = match self.kind {
-            index::Kind::V2 => V2_HEADER_SIZE + index * SHA1_SIZE,
+            index::Kind::V2 => V2_HEADER_SIZE + index * hash_len,
             index::Kind::V1 => V1_HEADER_SIZE + index * (N32_SIZE + SHA1_SIZE) + N32_SIZE,
         };
-        borrowed::Id::try_from(&self.data[start..start + SHA1_SIZE]).expect("20 bytes SHA1 to be alright")
+        borrowed::Id::try_from(&self.data[start..start + hash_len]).expect("ids of the index's hash kind")
     }
 
-    pub fn pack_offset_at_index(&self, index: u32) -> PackOffset {
+    /// Returns the offset into the pack of the object at the given index, or `None` if the index is corrupt and
+    /// refers to a large offset it doesn't contain.
+    pub fn pack_offset_at_index(&self, index: u32) -> Option<PackOffset> {
         let index: usize = index
             .try_into()
             .expect("an architecture able to hold 32 bits of integer");
@@ -84,7 +106,7 @@ impl index::File {
             }
             index::Kind::V1 => {
                 let start = V1_HEADER_SIZE + index * (N32_SIZE + SHA1_SIZE);
-                BigEndian::read_u32(&self.data[start..start + N32_SIZE]) as u64
+                Some(BigEndian::read_u32(&self.data[start..start + N32_SIZE]) as u64)
             }
         }
     }
@@ -102,7 +124,7 @@ impl index::File {
         }
     }
 
-    /// Returns the offset of the given SHA1 for use with the `(oid|pack_offset|crc32)_at_index()`
+    /// Returns the offset of the given id for use with the `(oid|pack_offset|crc32)_at_index()`
     pub fn lookup(&self, id: borrowed::Id) -> Option<u32> {
         let first_byte = id.first_byte() as usize;
         let mut upper_bound = self.fan[first_byte];
@@ -126,33 +148,96 @@ impl index::File {
         None
     }
 
-    pub fn iter<'a>(&'a self) -> Box<dyn Iterator<Item = Entry> + 'a> {
-        match self.kind {
-            index::Kind::V2 => Box::new(self.iter_v2()),
-            index::Kind::V1 => Box::new(self.iter_v1()),
+    /// Returns the index positions of all objects whose id starts with `prefix`, which is empty if there is none.
+    pub fn lookup_prefix_range(&self, prefix: &owned::Prefix) -> Range<u32> {
+        let first_byte = prefix.as_id().first_byte();
+        let start = self.partition_point(first_byte, |id| prefix.cmp_id(id) == Ordering::Greater);
+        let end = self.partition_point(first_byte, |id| prefix.cmp_id(id) != Ordering::Less);
+        start..end
+    }
+
+    /// Find the only object whose id starts with `prefix` and return its index position, or an error listing all
+    /// candidates if there is more than one.
+    pub fn lookup_prefix(&self, prefix: &owned::Prefix) -> Option<Result<u32, prefix::Error>> {
+        let range = self.lookup_prefix_range(prefix);
+        match range.len() {
+            0 => None,
+            1 => Some(Ok(range.start)),
+            _ => Some(Err(prefix::Error::Ambiguous(
+                prefix.clone(),
+                range.map(|index| self.oid_at_index(index).into()).collect(),
+            ))),
         }
     }
 
-    pub fn sorted_offsets(&self) -> Vec<PackOffset> {
-        let mut ofs: Vec<_> = match self.kind {
-            index::Kind::V1 => self.iter().map(|e| e.pack_offset).collect(),
-            index::Kind::V2 => {
-                let mut v = Vec::with_capacity(self.num_objects as usize);
-                let mut ofs32 = &self.data[self.offset_pack_offset_v2()..];
-                let pack_offset_64 = self.offset_pack_offset64_v2();
-                for _ in 0..self.num_objects {
-                    v.push(self.pack_offset_from_offset_v2(ofs32, pack_offset_64));
-                    ofs32 = &ofs32[4..];
-                }
-                v
-            }
+    /// Returns the amount of leading hex characters `id` has in common with the most similar other object in this
+    /// index, whether `id` is contained in it or not.
+    pub(crate) fn longest_common_hex_len(&self, id: borrowed::Id) -> usize {
+        let position = self.partition_point(id.first_byte(), |candidate| candidate < id);
+        let next = if position < self.num_objects() && self.oid_at_index(position) == id {
+            position + 1
+        } else {
+            position
         };
-        ofs.sort();
-        ofs
+        position
+            .checked_sub(1)
+            .into_iter()
+            .chain(Some(next).filter(|next| *next < self.num_objects()))
+            .map(|index| prefix::common_hex_len(id.as_slice(), self.oid_at_index(index).as_slice()))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Bisect the objects starting with `first_byte` and return the index of the first one for which `is_before`
+    /// returns false, with `is_before` being true for all objects up to that point.
+    fn partition_point(&self, first_byte: u8, mut is_before: impl FnMut(borrowed::Id) -> bool) -> u32 {
+        let first_byte = first_byte as usize;
+        let mut upper_bound = self.fan[first_byte];
+        let mut lower_bound = if first_byte != 0 { self.fan[first_byte - 1] } else { 0 };
+        while lower_bound < upper_bound {
+            let mid = (lower_bound + upper_bound) / 2;
+            if is_before(self.oid_at_index(mid)) {
+                lower_bound = mid + 1;
+            } else {
+                upper_bound = mid;
+            }
+        }
+        lower_bound
+    }
+
+    /// Fails if an entry refers to a large offset the index doesn't contain, before yielding any entry.
+    pub fn iter<'a>(&'a self) -> Result<Box<dyn Iterator<Item = Entry> + 'a>, Error> {
+        Ok(match self.kind {
+            index::Kind::V2 => Box::new(self.iter_v2()?),
+            index::Kind::V1 => Box::new(self.iter_v1()),
+        })
+    }
+
+    /// Like [`iter()`][index::File::iter()], this fails if an entry refers to a large offset the index doesn't contain.
+    pub fn sorted_offsets(&self) -> Result<Vec<PackOffset>, Error> {
+        let mut ofs: Vec<_> = self.iter()?.map(|e| e.pack_offset).collect();
+        ofs.sort_unstable();
+        Ok(ofs)
+    }
+
+    /// Assure that all entries referring to large offsets stay within the table of large offsets of the index, which
+    /// is otherwise only checked when accessing them.
+    pub fn verify_large_offsets(&self) -> Result<(), Error> {
+        if self.kind != index::Kind::V2 {
+            return Ok(());
+        }
+        let pack64_offset = self.offset_pack_offset64_v2();
+        let offsets = &self.data[self.offset_pack_offset_v2()..][..self.num_objects as usize * N32_SIZE];
+        for (index_position, ofs32) in (0..).zip(offsets.chunks(N32_SIZE)) {
+            if self.pack_offset_from_offset_v2(ofs32, pack64_offset).is_none() {
+                return Err(Error::LargeOffsetOutOfBounds { index_position });
+            }
+        }
+        Ok(())
     }
 
     fn offset_crc32_v2(&self) -> usize {
-        V2_HEADER_SIZE + self.num_objects as usize * SHA1_SIZE
+        V2_HEADER_SIZE + self.num_objects as usize * self.hash_kind.len_in_bytes()
     }
 
     fn offset_pack_offset_v2(&self) -> usize {
@@ -163,14 +248,19 @@ impl index::File {
         self.offset_pack_offset_v2() + self.num_objects as usize * N32_SIZE
     }
 
-    fn pack_offset_from_offset_v2(&self, offset: &[u8], pack64_offset: usize) -> PackOffset {
+    /// Large offsets are only bounds-checked here, as checking them all when opening the index is too costly.
+    fn pack_offset_from_offset_v2(&self, offset: &[u8], pack64_offset: usize) -> Option<PackOffset> {
         debug_assert_eq!(self.kind, index::Kind::V2);
         let ofs32 = BigEndian::read_u32(offset);
         if (ofs32 & N32_HIGH_BIT) == N32_HIGH_BIT {
             let from = pack64_offset + (ofs32 ^ N32_HIGH_BIT) as usize * N64_SIZE;
-            BigEndian::read_u64(&self.data[from..from + N64_SIZE])
+            let large_offsets_end = self.data.len() - self.hash_kind.len_in_bytes() * 2;
+            if from + N64_SIZE > large_offsets_end {
+                return None;
+            }
+            Some(BigEndian::read_u64(&self.data[from..from + N64_SIZE]))
         } else {
-            ofs32 as u64
+            Some(ofs32 as u64)
         }
     }
 }
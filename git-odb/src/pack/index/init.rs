@@ -1,7 +1,7 @@
 use crate::pack::index::{self, Kind, FAN_LEN, V2_SIGNATURE};
 use byteorder::{BigEndian, ByteOrder};
 use filebuffer::FileBuffer;
-use git_object::SHA1_SIZE;
+use git_object::HashKind;
 use quick_error::quick_error;
 use std::{convert::TryFrom, mem::size_of, path::Path};
 
@@ -22,7 +22,9 @@ quick_error! {
 }
 
 const N32_SIZE: usize = size_of::<u32>();
-const FOOTER_SIZE: usize = SHA1_SIZE * 2;
+const N64_SIZE: usize = size_of::<u64>();
+/// The header of a V2 index, consisting of signature, version and the fan-out table.
+const V2_HEADER_SIZE: usize = N32_SIZE * 2 + FAN_LEN * N32_SIZE;
 
 /// Instantiation
 impl index::File {
@@ -35,13 +37,24 @@ impl TryFrom<&Path> for index::File {
     type Error = Error;
 
     fn try_from(path: &Path) -> Result<Self, Self::Error> {
-        let data = FileBuffer::open(&path).map_err(|e| Error::Io(e, path.to_owned()))?;
-        let idx_len = data.len();
-        if idx_len < FAN_LEN * N32_SIZE + FOOTER_SIZE {
-            return Err(Error::Corrupt(format!(
+        let too_small = |idx_len: usize| {
+            Error::Corrupt(format!(
                 "Pack index of size {} is too small for even an empty index",
                 idx_len
-            )));
+            ))
+        };
+        let min_len = FAN_LEN * N32_SIZE + HashKind::Sha1.len_in_bytes() * 2;
+        // Empty files can't be mapped, so check the size before mapping the file
+        let file_len = std::fs::metadata(path)
+            .map_err(|e| Error::Io(e, path.to_owned()))?
+            .len() as usize;
+        if file_len < min_len {
+            return Err(too_small(file_len));
+        }
+        let data = FileBuffer::open(&path).map_err(|e| Error::Io(e, path.to_owned()))?;
+        let idx_len = data.len();
+        if idx_len < min_len {
+            return Err(too_small(idx_len));
         }
         let (kind, version, fan, num_objects) = {
             let (kind, d) = {
@@ -67,13 +80,36 @@ impl TryFrom<&Path> for index::File {
             let (fan, bytes_read) = read_fan(d);
             let (_, _d) = d.split_at(bytes_read);
             let num_objects = fan[FAN_LEN - 1];
+            if fan.windows(2).any(|w| w[0] > w[1]) {
+                return Err(Error::Corrupt("Pack index fan-out table must not decrease".to_string()));
+            }
 
             (kind, version, fan, num_objects)
         };
+        let hash_kind = match kind {
+            Kind::V1 => {
+                let hash_len = HashKind::Sha1.len_in_bytes();
+                let expected_size = FAN_LEN * N32_SIZE + num_objects as usize * (N32_SIZE + hash_len) + hash_len * 2;
+                if idx_len < expected_size {
+                    return Err(Error::Corrupt(format!(
+                        "Pack index of size {} is too small to hold {} objects",
+                        idx_len, num_objects
+                    )));
+                }
+                HashKind::Sha1
+            }
+            Kind::V2 => v2_hash_kind(idx_len, num_objects).ok_or_else(|| {
+                Error::Corrupt(format!(
+                    "Pack index of size {} does not fit {} objects with either SHA1 or SHA256 ids",
+                    idx_len, num_objects
+                ))
+            })?,
+        };
         Ok(index::File {
             data,
             path: path.to_owned(),
             kind,
+            hash_kind,
             num_objects,
             version,
             fan,
@@ -88,3 +124,17 @@ fn read_fan(d: &[u8]) -> ([u32; FAN_LEN], usize) {
     }
     (fan, FAN_LEN * N32_SIZE)
 }
+
+/// V2 indices don't store the kind of hash they use, but as there can be at most one large offset less than there are
+/// objects, only one kind of hash can explain the size of the index.
+///
+/// Like git, we accept any size in that range without requiring it to hold a whole number of large offsets.
+fn v2_hash_kind(idx_len: usize, num_objects: u32) -> Option<HashKind> {
+    let num_objects = num_objects as usize;
+    [HashKind::Sha1, HashKind::Sha256].iter().copied().find(|hash| {
+        let hash_len = hash.len_in_bytes();
+        let min_size = V2_HEADER_SIZE + num_objects * (hash_len + N32_SIZE * 2) + hash_len * 2;
+        let max_size = min_size + num_objects.saturating_sub(1) * N64_SIZE;
+        (min_size..=max_size).contains(&idx_len)
+    })
+}
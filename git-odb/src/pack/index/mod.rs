@@ -88,21 +88,13 @@ impl Default for Kind {
     }
 }
 
-impl Kind {
-    /// The kind of hash to produce to be compatible to this kind of index
-    pub fn hash(&self) -> git_object::HashKind {
-        match self {
-            Kind::V1 | Kind::V2 => git_object::HashKind::Sha1,
-        }
-    }
-}
-
 const FAN_LEN: usize = 256;
 
 pub struct File {
     pub(crate) data: FileBuffer,
     path: std::path::PathBuf,
     kind: Kind,
+    hash_kind: git_object::HashKind,
     version: u32,
     num_objects: u32,
     fan: [u32; FAN_LEN],
@@ -112,6 +104,10 @@ impl File {
     pub fn kind(&self) -> Kind {
         self.kind
     }
+    /// The kind of hash used for the ids of objects in this index and for its checksums.
+    pub fn hash_kind(&self) -> git_object::HashKind {
+        self.hash_kind
+    }
     pub fn path(&self) -> &std::path::Path {
         &self.path
     }
@@ -126,7 +122,7 @@ impl File {
 const V2_SIGNATURE: &[u8] = b"\xfftOc";
 pub mod init;
 
-pub(crate) mod access;
+pub mod access;
 pub use access::Entry;
 
 pub mod traverse;
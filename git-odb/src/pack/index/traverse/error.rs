@@ -15,6 +15,11 @@ quick_error! {
             source(err)
             from()
         }
+        Index(err: index::access::Error) {
+            display("The entries of the index file could not be read")
+            source(err)
+            from()
+        }
         Tree(err: pack::tree::from_offsets::Error) {
             display("The pack delta tree index could not be built")
             from()
@@ -43,13 +43,14 @@ impl index::File {
             },
             || -> Result<_, Error> {
                 let sorted_entries =
-                    index_entries_sorted_by_offset_ascending(self, root.add_child("collecting sorted index"));
+                    index_entries_sorted_by_offset_ascending(self, root.add_child("collecting sorted index"))?;
                 let tree = pack::tree::Tree::from_offsets_in_pack(
                     sorted_entries.into_iter().map(EntryWithDefault::from),
                     |e| e.index_entry.pack_offset,
                     pack.path(),
+                    self.hash_kind(),
                     root.add_child("indexing"),
-                    |id| self.lookup(id).map(|idx| self.pack_offset_at_index(idx)),
+                    |id| self.lookup(id).and_then(|idx| self.pack_offset_at_index(idx)),
                 )?;
                 let there_are_enough_objects = || self.num_objects > 10_000;
                 let mut outcome = digest_statistics(tree.traverse(
@@ -59,6 +60,7 @@ impl index::File {
                     root.add_child("Decoding"),
                     thread_limit,
                     pack.pack_end() as u64,
+                    self.hash_kind(),
                     || (new_processor(), [0u8; 64]),
                     |data,
                      progress,
@@ -128,7 +130,7 @@ impl Default for EntryWithDefault {
             index_entry: pack::index::Entry {
                 pack_offset: 0,
                 crc32: None,
-                oid: git_object::owned::Id::null(),
+                oid: git_object::owned::Id::null_sha1(),
             },
             level: 0,
             _object_kind: git_object::Kind::Tree,
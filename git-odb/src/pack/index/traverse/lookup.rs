@@ -46,7 +46,7 @@ impl index::File {
             },
             || {
                 let index_entries =
-                    util::index_entries_sorted_by_offset_ascending(self, root.add_child("collecting sorted index"));
+                    util::index_entries_sorted_by_offset_ascending(self, root.add_child("collecting sorted index"))?;
 
                 let (chunk_size, thread_limit, available_cores) =
                     parallel::optimize_chunk_size_and_thread_limit(1000, Some(index_entries.len()), thread_limit, None);
@@ -59,7 +59,7 @@ impl index::File {
                 });
                 let state_per_thread = |index| {
                     (
-                        new_cache(),
+                        pack::cache::Counting::new(new_cache()),
                         new_processor(),
                         Vec::with_capacity(2048), // decode buffer
                         reduce_progress.lock().add_child(format!("thread {}", index)), // per thread progress
@@ -73,7 +73,7 @@ impl index::File {
                     state_per_thread,
                     |entries: &[index::Entry],
                      (cache, ref mut processor, buf, progress)|
-                     -> Result<Vec<decode::Outcome>, Error> {
+                     -> Result<(Vec<decode::Outcome>, pack::cache::Statistics), Error> {
                         progress.init(
                             Some(entries.len()),
                             Some(unit::dynamic(unit::Human::new(
@@ -104,7 +104,7 @@ impl index::File {
                             }?;
                             stats.push(stat);
                         }
-                        Ok(stats)
+                        Ok((stats, cache.take_statistics()))
                     },
                     Reducer::from_progress(&reduce_progress, pack.data_len(), check),
                 )
@@ -98,20 +98,23 @@ impl index::File {
         P: Progress,
         E: std::error::Error + Send + Sync + 'static,
     {
-        let pack_entry = pack.entry(index_entry.pack_offset);
+        let pack_entry = pack
+            .entry(index_entry.pack_offset)
+            .map_err(|e| Error::PackDecode(e, index_entry.oid.clone(), index_entry.pack_offset))?;
         let pack_entry_data_offset = pack_entry.data_offset;
         let entry_stats = pack
             .decode_entry(
                 pack_entry,
                 buf,
                 |id, _| {
-                    self.lookup(id).map(|index| {
-                        pack::data::decode::ResolvedBase::InPack(pack.entry(self.pack_offset_at_index(index)))
-                    })
+                    self.lookup(id)
+                        .and_then(|index| self.pack_offset_at_index(index))
+                        .and_then(|pack_offset| pack.entry(pack_offset).ok())
+                        .map(pack::data::decode::ResolvedBase::InPack)
                 },
                 cache,
             )
-            .map_err(|e| Error::PackDecode(e, index_entry.oid, index_entry.pack_offset))?;
+            .map_err(|e| Error::PackDecode(e, index_entry.oid.clone(), index_entry.pack_offset))?;
         let object_kind = entry_stats.kind;
         let header_size = (pack_entry_data_offset - index_entry.pack_offset) as usize;
         let entry_len = header_size + entry_stats.compressed_size;
@@ -149,15 +152,15 @@ where
         let header_size =
             crate::loose::object::header::encode(object_kind, decompressed.len() as u64, &mut header_buf[..])
                 .expect("header buffer to be big enough");
-        let mut hasher = git_features::hash::Sha1::default();
+        let mut hasher = crate::hash::Hasher::new(index_entry.oid.kind());
         hasher.update(&header_buf[..header_size]);
         hasher.update(decompressed);
 
-        let actual_oid = owned::Id::new_sha1(hasher.digest());
+        let actual_oid = hasher.digest();
         if actual_oid != index_entry.oid {
             return Err(Error::PackObjectMismatch {
                 actual: actual_oid,
-                expected: index_entry.oid,
+                expected: index_entry.oid.clone(),
                 offset: index_entry.pack_offset,
                 kind: object_kind,
             });
@@ -1,4 +1,4 @@
-use crate::pack::{data::decode, index::traverse};
+use crate::pack::{cache, data::decode, index::traverse};
 use git_features::{interrupt::is_triggered, parallel, progress::Progress};
 use std::time::Instant;
 
@@ -51,12 +51,12 @@ impl<'a, P> parallel::Reducer for Reducer<'a, P>
 where
     P: Progress,
 {
-    type Input = Result<Vec<decode::Outcome>, traverse::Error>;
+    type Input = Result<(Vec<decode::Outcome>, cache::Statistics), traverse::Error>;
     type Output = traverse::Outcome;
     type Error = traverse::Error;
 
     fn feed(&mut self, input: Self::Input) -> Result<(), Self::Error> {
-        let chunk_stats: Vec<_> = match input {
+        let (chunk_stats, cache_stats) = match input {
             Err(err @ traverse::Error::PackDecode(_, _, _)) if !self.check.fatal_decode_error() => {
                 self.progress.lock().info(format!("Ignoring decode error: {}", err));
                 return Ok(());
@@ -64,6 +64,8 @@ where
             res => res,
         }?;
         self.entries_seen += chunk_stats.len();
+        self.stats.cache.hits += cache_stats.hits;
+        self.stats.cache.misses += cache_stats.misses;
 
         let chunk_total = chunk_stats.into_iter().fold(
             decode::Outcome::default_from_kind(git_object::Kind::Tree),
@@ -18,6 +18,8 @@ pub struct Outcome {
     pub num_trees: u32,
     pub num_tags: u32,
     pub num_blobs: u32,
+    /// The hits and misses of the pack cache while decoding objects, which is only used by [`Algorithm::Lookup`]
+    pub cache: pack::cache::Statistics,
 }
 
 impl Default for Outcome {
@@ -33,6 +35,7 @@ impl Default for Outcome {
             num_commits: 0,
             num_trees: 0,
             num_tags: 0,
+            cache: Default::default(),
         }
     }
 }
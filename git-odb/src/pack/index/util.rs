@@ -5,19 +5,39 @@ use std::{io, time::Instant};
 pub(crate) fn index_entries_sorted_by_offset_ascending(
     idx: &pack::index::File,
     mut progress: impl Progress,
-) -> Vec<pack::index::Entry> {
+) -> Result<Vec<pack::index::Entry>, pack::index::access::Error> {
+    idx.verify_large_offsets()?;
     progress.init(Some(idx.num_objects as usize), progress::count("entries"));
     let start = Instant::now();
 
-    let mut v = Vec::with_capacity(idx.num_objects as usize);
-    for entry in idx.iter() {
-        v.push(entry);
-        progress.inc();
-    }
-    v.sort_by_key(|e| e.pack_offset);
+    let v = match idx.reverse_index() {
+        Ok(rev) if rev.path().is_some() => rev
+            .iter()
+            .map(|index_position| {
+                progress.inc();
+                pack::index::Entry {
+                    oid: idx.oid_at_index(index_position).into(),
+                    pack_offset: idx
+                        .pack_offset_at_index(index_position)
+                        .expect("large offsets to be verified"),
+                    crc32: idx.crc32_at_index(index_position),
+                }
+            })
+            .collect(),
+        // Without a usable `.rev` file, sort all entries ourselves. It's not our job to validate it here.
+        _ => {
+            let mut v = Vec::with_capacity(idx.num_objects as usize);
+            for entry in idx.iter()? {
+                v.push(entry);
+                progress.inc();
+            }
+            v.sort_by_key(|e| e.pack_offset);
+            v
+        }
+    };
 
     progress.show_throughput(start);
-    v
+    Ok(v)
 }
 
 pub(crate) struct Chunks<I> {
@@ -3,7 +3,7 @@ use git_features::progress::{self, Progress};
 use git_object::{
     borrowed,
     bstr::{BString, ByteSlice},
-    owned, SHA1_SIZE,
+    owned,
 };
 use quick_error::quick_error;
 
@@ -40,27 +40,30 @@ pub enum Mode {
 /// Verify and validate the content of the index file
 impl index::File {
     pub fn index_checksum(&self) -> owned::Id {
-        owned::Id::from_20_bytes(&self.data[self.data.len() - SHA1_SIZE..])
+        owned::Id::from_bytes(&self.data[self.data.len() - self.hash_kind.len_in_bytes()..])
+            .expect("hash_len bytes to make an id")
     }
 
     pub fn pack_checksum(&self) -> owned::Id {
-        let from = self.data.len() - SHA1_SIZE * 2;
-        owned::Id::from_20_bytes(&self.data[from..from + SHA1_SIZE])
+        let hash_len = self.hash_kind.len_in_bytes();
+        let from = self.data.len() - hash_len * 2;
+        owned::Id::from_bytes(&self.data[from..from + hash_len]).expect("hash_len bytes to make an id")
     }
 
     pub fn verify_checksum(&self, mut progress: impl Progress) -> Result<owned::Id, Error> {
-        let data_len_without_trailer = self.data.len() - SHA1_SIZE;
-        let actual = match crate::hash::bytes_of_file(&self.path, data_len_without_trailer, &mut progress) {
-            Ok(id) => id,
-            Err(_io_err) => {
-                let start = std::time::Instant::now();
-                let mut hasher = git_features::hash::Sha1::default();
-                hasher.update(&self.data[..data_len_without_trailer]);
-                progress.inc_by(data_len_without_trailer);
-                progress.show_throughput(start);
-                owned::Id::new_sha1(hasher.digest())
-            }
-        };
+        let data_len_without_trailer = self.data.len() - self.hash_kind.len_in_bytes();
+        let actual =
+            match crate::hash::bytes_of_file(&self.path, data_len_without_trailer, self.hash_kind, &mut progress) {
+                Ok(id) => id,
+                Err(_io_err) => {
+                    let start = std::time::Instant::now();
+                    let mut hasher = crate::hash::Hasher::new(self.hash_kind);
+                    hasher.update(&self.data[..data_len_without_trailer]);
+                    progress.inc_by(data_len_without_trailer);
+                    progress.show_throughput(start);
+                    hasher.digest()
+                }
+            };
 
         let expected = self.index_checksum();
         if actual == expected {
@@ -74,6 +77,7 @@ impl index::File {
     /// It will be used to validate internal integrity of the pack before checking each objects integrity
     /// is indeed as advertised via its SHA1 as stored in this index, as well as the CRC32 hash.
     /// redoing a lot of work across multiple objects.
+    /// Entries referring to large offsets the index doesn't contain fail the verification before anything else.
     pub fn verify_integrity<P, C>(
         &self,
         pack: Option<(&pack::data::File, Mode, index::traverse::Algorithm)>,
@@ -87,6 +91,7 @@ impl index::File {
         <<P as Progress>::SubProgress as Progress>::SubProgress: Send,
         C: pack::cache::DecodeEntry,
     {
+        self.verify_large_offsets()?;
         let mut root = progress::DoOrDiscard::from(progress);
         match pack {
             None => self
@@ -130,8 +135,9 @@ impl index::File {
             use git_object::Kind::*;
             match object_kind {
                 Tree | Commit | Tag => {
-                    let borrowed_object = borrowed::Object::from_bytes(object_kind, buf)
-                        .map_err(|err| Error::ObjectDecode(err, object_kind, index_entry.oid))?;
+                    let borrowed_object =
+                        borrowed::Object::from_bytes_with_hash(object_kind, buf, index_entry.oid.kind())
+                            .map_err(|err| Error::ObjectDecode(err, object_kind, index_entry.oid.clone()))?;
                     if let Mode::Sha1CRC32DecodeEncode = mode {
                         let object = owned::Object::from(borrowed_object);
                         encode_buf.clear();
@@ -147,7 +153,7 @@ impl index::File {
                             if should_return_error {
                                 return Err(Error::ObjectEncodeMismatch(
                                     object_kind,
-                                    index_entry.oid,
+                                    index_entry.oid.clone(),
                                     buf.into(),
                                     encode_buf.clone().into(),
                                 ));
@@ -15,10 +15,6 @@ pub(crate) fn to_write(
     mut progress: impl Progress,
 ) -> io::Result<owned::Id> {
     use io::Write;
-    assert!(
-        !entries_sorted_by_oid.is_empty(),
-        "Empty packs do not exists, or so I think"
-    );
     assert_eq!(kind, pack::index::Kind::V2, "Can only write V2 packs right now");
     assert!(
         entries_sorted_by_oid.len() <= u32::MAX as usize,
@@ -28,7 +24,7 @@ pub(crate) fn to_write(
     // Write header
     let mut out = Count::new(std::io::BufWriter::with_capacity(
         8 * 4096,
-        hash::Write::new(out, kind.hash()),
+        hash::Write::new(out, pack_hash.kind()),
     ));
     out.write_all(V2_SIGNATURE)?;
     out.write_u32::<BigEndian>(kind as u32)?;
@@ -37,7 +33,7 @@ pub(crate) fn to_write(
     const HIGH_BIT: u32 = 0x8000_0000;
 
     let needs_64bit_offsets =
-        entries_sorted_by_oid.last().expect("at least one pack entry").offset > LARGE_OFFSET_THRESHOLD;
+        matches!(entries_sorted_by_oid.last(), Some(entry) if entry.offset > LARGE_OFFSET_THRESHOLD);
     let mut fan_out_be = [0u32; 256];
     progress.init(Some(4), progress::steps());
     let start = std::time::Instant::now();
@@ -114,7 +110,7 @@ pub(crate) fn to_write(
 
     let bytes_written_without_trailer = out.bytes;
     let mut out = out.inner.into_inner()?;
-    let index_hash: owned::Id = out.hash.digest().into();
+    let index_hash: owned::Id = out.hash.digest();
     out.inner.write_all(index_hash.as_slice())?;
     out.inner.flush()?;
 
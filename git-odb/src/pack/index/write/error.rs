@@ -1,4 +1,5 @@
 use crate::pack;
+use git_object::owned;
 use quick_error::quick_error;
 use std::io;
 
@@ -18,8 +19,8 @@ quick_error! {
         Unsupported(kind: pack::index::Kind) {
             display("Indices of type {} cannot be written, only {} are supported", *kind as usize, pack::index::Kind::default() as usize)
         }
-        IteratorInvariantNoRefDelta {
-            display("Ref delta objects are not supported as there is no way to look them up. Resolve them beforehand.")
+        IteratorInvariantRefDeltaBase(pack_offset: u64, base_id: owned::Id) {
+            display("The base {} of the ref delta at pack offset {} is not an object of the pack. Add the bases of thin packs beforehand.", base_id, pack_offset)
         }
         IteratorInvariantTrailer {
             display("The iterator failed to set a trailing hash over all prior pack entries in the last provided entry")
@@ -4,7 +4,7 @@ use crate::{
 };
 use git_features::progress::{self, Progress};
 use git_object::{owned, HashKind};
-use std::{convert::Infallible, convert::TryInto, io};
+use std::{collections::HashMap, convert::Infallible, convert::TryInto, io, rc::Rc};
 
 mod encode;
 mod error;
@@ -18,7 +18,7 @@ pub struct TreeEntry {
 impl Default for TreeEntry {
     fn default() -> Self {
         TreeEntry {
-            id: owned::Id::null(),
+            id: owned::Id::null_sha1(),
             crc32: 0,
         }
     }
@@ -37,7 +37,8 @@ pub struct Outcome {
 
 /// Various ways of writing an index file from pack entries
 impl pack::index::File {
-    /// Note that neither in-pack nor out-of-pack Ref Deltas are supported here, these must have been resolved beforehand.
+    /// Note that Ref Deltas are only supported if their base is an object of the pack, which is why the bases of thin packs
+    /// must have been added beforehand, for example using [`pack::data::thin::LookupRefDeltaObjectsIter`].
     /// `make_resolver()`:  It will only be called after the iterator stopped returning elements and produces a function that
     /// provides all bytes belonging to an entry.
     pub fn write_data_iter_to_stream<F, F2, P>(
@@ -61,8 +62,9 @@ impl pack::index::File {
         let mut bytes_to_process = 0u64;
         let mut last_seen_trailer = None;
         let mut last_base_index = None;
+        let mut ref_deltas = Vec::new();
         let anticipated_num_objects = entries.size_hint().0;
-        let mut tree = Tree::with_capacity(anticipated_num_objects)?;
+        let mut tree = Tree::with_capacity(anticipated_num_objects.max(1))?;
         let indexing_start = std::time::Instant::now();
 
         root_progress.init(Some(4), progress::steps());
@@ -78,6 +80,7 @@ impl pack::index::File {
                 pack_offset,
                 crc32,
                 header_size,
+                header_bytes: _,
                 compressed: _,
                 compressed_size,
                 decompressed_size,
@@ -99,12 +102,21 @@ impl pack::index::File {
                     tree.add_root(
                         pack_offset,
                         TreeEntry {
-                            id: owned::Id::null(),
+                            id: owned::Id::null_sha1(),
                             crc32,
                         },
                     )?;
                 }
-                RefDelta { .. } => return Err(Error::IteratorInvariantNoRefDelta),
+                RefDelta { base_id } => {
+                    tree.add_unlinked_child(
+                        pack_offset,
+                        TreeEntry {
+                            id: owned::Id::null_sha1(),
+                            crc32,
+                        },
+                    )?;
+                    ref_deltas.push((pack_offset, *base_id));
+                }
                 OfsDelta { base_distance } => {
                     let base_pack_offset = pack::data::Header::verified_base_pack_offset(pack_offset, base_distance)
                         .ok_or_else(|| Error::IteratorInvariantBaseOffset(pack_offset, base_distance))?;
@@ -112,7 +124,7 @@ impl pack::index::File {
                         base_pack_offset,
                         pack_offset,
                         TreeEntry {
-                            id: owned::Id::null(),
+                            id: owned::Id::null_sha1(),
                             crc32,
                         },
                     )?;
@@ -131,6 +143,22 @@ impl pack::index::File {
         let num_objects: u32 = num_objects
             .try_into()
             .map_err(|_| Error::IteratorInvariantTooManyObjects(num_objects))?;
+        if num_objects == 0 {
+            let pack_hash = empty_pack_hash(make_resolver()?)?;
+            let index_hash = encode::to_write(
+                out,
+                Vec::new(),
+                &pack_hash,
+                kind,
+                root_progress.add_child("writing index file"),
+            )?;
+            return Ok(Outcome {
+                index_kind: kind,
+                index_hash,
+                data_hash: pack_hash,
+                num_objects,
+            });
+        }
         last_base_index.ok_or(Error::IteratorInvariantBasesPresent)?;
 
         objects_progress.show_throughput(indexing_start);
@@ -140,7 +168,15 @@ impl pack::index::File {
 
         root_progress.inc();
 
+        let pack_hash = last_seen_trailer.ok_or(Error::IteratorInvariantTrailer)?;
+        let hash_kind = pack_hash.kind();
         let resolver = make_resolver()?;
+        if !ref_deltas.is_empty() {
+            let unresolved = link_ref_deltas(&mut tree, ref_deltas, &resolver, pack_entries_end, hash_kind)?;
+            if let Some((pack_offset, base_id)) = unresolved.into_iter().next() {
+                return Err(Error::IteratorInvariantRefDeltaBase(pack_offset, base_id));
+            }
+        }
         let sorted_pack_offsets_by_oid = {
             let in_parallel_if_pack_is_big_enough = || bytes_to_process > 5_000_000;
             let mut items = tree.traverse(
@@ -150,6 +186,7 @@ impl pack::index::File {
                 root_progress.add_child("Decoding"),
                 thread_limit,
                 pack_entries_end,
+                hash_kind,
                 || (),
                 |data,
                  _progress,
@@ -157,20 +194,19 @@ impl pack::index::File {
                      entry,
                      decompressed: bytes,
                      ..
-                 }| modify_base(data, entry, bytes, kind.hash()),
+                 }| modify_base(data, entry, bytes, hash_kind),
             )?;
             root_progress.inc();
 
             {
                 let _progress = root_progress.add_child("sorting by id");
-                items.sort_by_key(|e| e.data.id);
+                items.sort_by(|a, b| a.data.id.cmp(&b.data.id));
             }
 
             root_progress.inc();
             items
         };
 
-        let pack_hash = last_seen_trailer.ok_or(Error::IteratorInvariantTrailer)?;
         let index_hash = encode::to_write(
             out,
             sorted_pack_offsets_by_oid,
@@ -192,20 +228,111 @@ impl pack::index::File {
     }
 }
 
+/// Make each of the `ref_deltas`, pairs of pack offset and base id, a child of its base in `tree` and return the ones whose
+/// base isn't an object of the pack.
+///
+/// Bases which are deltas themselves are only known once the deltas leading to them were resolved, which is why the
+/// tree is traversed until no more `ref_deltas` can be linked. As ref deltas usually refer to undeltified objects,
+/// the first round only looks at the roots.
+pub(crate) fn link_ref_deltas<T, F>(
+    tree: &mut Tree<T>,
+    mut ref_deltas: Vec<(u64, owned::Id)>,
+    resolve: F,
+    pack_entries_end: u64,
+    hash_kind: HashKind,
+) -> Result<Vec<(u64, owned::Id)>, pack::tree::traverse::Error>
+where
+    F: for<'r> Fn(pack::data::EntrySlice, &'r mut Vec<u8>) -> Option<()>,
+{
+    let mut buf = Vec::new();
+    let mut offset_by_id = HashMap::new();
+    let mut roots_only = true;
+    loop {
+        let items = tree.entry_slices_with_children(pack_entries_end);
+        let mut stack: Vec<_> = items
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, is_root, _))| *is_root)
+            .map(|(index, _)| (index, None::<(git_object::Kind, Rc<Vec<u8>>)>))
+            .collect();
+        while let Some((index, base)) = stack.pop() {
+            let (slice, _, children) = &items[index];
+            let (entry, decompressed) =
+                pack::tree::traverse::decompress_entry(slice.clone(), &resolve, &mut buf, hash_kind)?;
+            let (kind, object) = match base {
+                None => (
+                    entry.header.to_kind().expect("roots are undeltified objects"),
+                    decompressed,
+                ),
+                Some((kind, base_object)) => {
+                    let mut object = Vec::new();
+                    pack::data::delta::apply(&base_object, &decompressed, &mut object)
+                        .map_err(|err| pack::tree::traverse::Error::Delta(err, slice.start))?;
+                    (kind, object)
+                }
+            };
+            offset_by_id
+                .entry(compute_hash(kind, &object, hash_kind))
+                .or_insert(slice.start);
+            if !roots_only && !children.is_empty() {
+                let object = Rc::new(object);
+                stack.extend(children.iter().map(|child| (*child, Some((kind, Rc::clone(&object))))));
+            }
+        }
+
+        let num_unlinked = ref_deltas.len();
+        ref_deltas.retain(|(pack_offset, base_id)| match offset_by_id.get(base_id) {
+            Some(base_offset) => {
+                tree.link_child(*base_offset, *pack_offset);
+                false
+            }
+            None => true,
+        });
+        if ref_deltas.is_empty() || (!roots_only && ref_deltas.len() == num_unlinked) {
+            return Ok(ref_deltas);
+        }
+        roots_only = false;
+    }
+}
+
+/// Obtain the trailer of an empty pack using `resolve`, as there is no last entry to provide it.
+fn empty_pack_hash<F>(resolve: F) -> Result<owned::Id, Error>
+where
+    F: for<'r> Fn(pack::data::EntrySlice, &'r mut Vec<u8>) -> Option<()>,
+{
+    let header_len = pack::data::File::HEADER_LEN as u64;
+    let mut header = vec![0; header_len as usize];
+    resolve(0..header_len, &mut header).ok_or(Error::IteratorInvariantTrailer)?;
+    // Without entries the kind of hash isn't known, but only the right one reproduces the trailer.
+    for hash_kind in &[HashKind::Sha1, HashKind::Sha256] {
+        let mut trailer = vec![0; hash_kind.len_in_bytes()];
+        if resolve(header_len..header_len + trailer.len() as u64, &mut trailer).is_none() {
+            continue;
+        }
+        let mut hash = crate::hash::Hasher::new(*hash_kind);
+        hash.update(&header);
+        let id = hash.digest();
+        if id.as_slice() == trailer.as_slice() {
+            return Ok(id);
+        }
+    }
+    Err(Error::IteratorInvariantTrailer)
+}
+
+/// Compute the id of an object of `kind` with the given decompressed `bytes`.
+pub(crate) fn compute_hash(kind: git_object::Kind, bytes: &[u8], hash_kind: HashKind) -> owned::Id {
+    let mut write = crate::hash::Write::new(io::sink(), hash_kind);
+    loose::object::header::encode(kind, bytes.len() as u64, &mut write).expect("write to sink and hash cannot fail");
+    write.hash.update(bytes);
+    write.hash.digest()
+}
+
 pub fn modify_base(
     entry: &mut pack::index::write::TreeEntry,
     pack_entry: &pack::data::Entry,
     decompressed: &[u8],
     hash: HashKind,
 ) -> Result<(), Infallible> {
-    fn compute_hash(kind: git_object::Kind, bytes: &[u8], hash_kind: HashKind) -> owned::Id {
-        let mut write = crate::hash::Write::new(io::sink(), hash_kind);
-        loose::object::header::encode(kind, bytes.len() as u64, &mut write)
-            .expect("write to sink and hash cannot fail");
-        write.hash.update(bytes);
-        owned::Id::from(write.hash.digest())
-    }
-
     let object_kind = pack_entry.header.to_kind().expect("base object as source of iteration");
     let id = compute_hash(object_kind, &decompressed, hash);
     entry.id = id;
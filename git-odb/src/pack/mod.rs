@@ -1,7 +1,10 @@
+pub mod bitmap;
 pub mod bundle;
 pub mod cache;
 pub mod data;
 pub mod index;
+pub mod multi_index;
+pub mod rev;
 pub mod tree;
 
 mod object;
@@ -0,0 +1,108 @@
+use crate::pack::{index::access::PackOffset, multi_index};
+use byteorder::{BigEndian, ByteOrder};
+use git_object::{borrowed, owned, SHA1_SIZE};
+use std::{
+    convert::{TryFrom, TryInto},
+    mem::size_of,
+};
+
+const N32_SIZE: usize = size_of::<u32>();
+const N64_SIZE: usize = size_of::<u64>();
+const N32_HIGH_BIT: u32 = 1 << 31;
+
+/// The id of a pack as position in the list of [index names][multi_index::File::index_names()].
+pub type PackIndex = u32;
+
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Entry {
+    pub oid: owned::Id,
+    /// The offset to the object's header in the pack
+    pub pack_offset: PackOffset,
+    /// The id of the pack containing the object
+    pub pack_index: PackIndex,
+}
+
+/// Iteration and access
+impl multi_index::File {
+    /// Returns 20 bytes sha1 at the given index in our list of (sorted) sha1 hashes.
+    /// The index ranges from 0 to self.num_objects()
+    pub fn oid_at_index(&self, index: u32) -> borrowed::Id<'_> {
+        let start = self.lookup_ofs + to_usize(index) * SHA1_SIZE;
+        borrowed::Id::try_from(&self.data[start..start + SHA1_SIZE]).expect("20 bytes SHA1 to be alright")
+    }
+
+    /// Returns the id of the pack containing the object at `index`, along with the object's offset in that pack.
+    ///
+    /// Returns `None` if the file is corrupt and refers to a large offset it doesn't contain.
+    pub fn pack_id_and_pack_offset_at_index(&self, index: u32) -> Option<(PackIndex, PackOffset)> {
+        let start = self.offsets_ofs + to_usize(index) * multi_index::init::OFFSET_ENTRY_LEN;
+        let pack_index = BigEndian::read_u32(&self.data[start..]);
+        let ofs32 = BigEndian::read_u32(&self.data[start + N32_SIZE..]);
+        let pack_offset = if ofs32 & N32_HIGH_BIT == N32_HIGH_BIT {
+            let large_offsets = self.large_offsets.as_ref()?;
+            let from = large_offsets.start + (ofs32 ^ N32_HIGH_BIT) as usize * N64_SIZE;
+            if from + N64_SIZE > large_offsets.end {
+                return None;
+            }
+            BigEndian::read_u64(&self.data[from..from + N64_SIZE])
+        } else {
+            ofs32 as u64
+        };
+        Some((pack_index, pack_offset))
+    }
+
+    /// Returns the index of the given SHA1 for use with the `(oid|pack_id_and_pack_offset)_at_index()` methods.
+    pub fn lookup_index(&self, id: borrowed::Id) -> Option<u32> {
+        let first_byte = id.first_byte() as usize;
+        let mut upper_bound = self.fan[first_byte];
+        let mut lower_bound = if first_byte != 0 { self.fan[first_byte - 1] } else { 0 };
+
+        while lower_bound < upper_bound {
+            let mid = (lower_bound + upper_bound) / 2;
+            let mid_sha = self.oid_at_index(mid);
+
+            use std::cmp::Ordering::*;
+            match id.cmp(&mid_sha) {
+                Less => upper_bound = mid,
+                Equal => return Some(mid),
+                Greater => lower_bound = mid + 1,
+            }
+        }
+        None
+    }
+
+    /// Returns the id of the pack containing the object with the given SHA1 along with its offset in that pack,
+    /// or `None` if it is not contained in any of our packs.
+    pub fn lookup(&self, id: borrowed::Id) -> Option<(PackIndex, PackOffset)> {
+        self.lookup_index(id)
+            .and_then(|index| self.pack_id_and_pack_offset_at_index(index))
+    }
+
+    /// Note that iteration stops early if an entry refers to a large offset the file doesn't contain.
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = Entry> + 'a {
+        (0..self.num_objects).map_while(move |index| {
+            let (pack_index, pack_offset) = self.pack_id_and_pack_offset_at_index(index)?;
+            Some(Entry {
+                oid: self.oid_at_index(index).into(),
+                pack_offset,
+                pack_index,
+            })
+        })
+    }
+
+    /// The path to the pack index with the given id, next to this file.
+    pub fn index_path(&self, pack_index: PackIndex) -> std::path::PathBuf {
+        let name = &self.index_names[pack_index as usize];
+        match self.path.parent() {
+            Some(dir) => dir.join(name),
+            None => name.to_owned(),
+        }
+    }
+}
+
+fn to_usize(index: u32) -> usize {
+    index
+        .try_into()
+        .expect("an architecture able to hold 32 bits of integer")
+}
@@ -0,0 +1,172 @@
+use crate::{
+    chunk_file,
+    pack::multi_index::{self, chunk, FAN_LEN, OBJECT_HASH_SHA1, SIGNATURE, VERSION},
+};
+use byteorder::{BigEndian, ByteOrder};
+use filebuffer::FileBuffer;
+use git_object::{bstr::ByteSlice, HashKind, SHA1_SIZE};
+use quick_error::quick_error;
+use std::{
+    convert::TryFrom,
+    mem::size_of,
+    path::{Path, PathBuf},
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: std::io::Error, path: std::path::PathBuf) {
+            display("Could not open multi-pack-index file at '{}'", path.display())
+            source(err)
+        }
+        Corrupt(msg: String) {
+            display("{}", msg)
+        }
+        UnsupportedVersion(version: u8) {
+            display("Unsupported multi-pack-index version: {}", version)
+        }
+        UnsupportedObjectHash(id: u8) {
+            display("Unsupported object hash with id {}", id)
+        }
+        Chunk(err: chunk_file::Error) {
+            display("Could not read the chunks of the multi-pack-index file")
+            from()
+            source(err)
+        }
+    }
+}
+
+const N32_SIZE: usize = size_of::<u32>();
+const N64_SIZE: usize = size_of::<u64>();
+pub(crate) const HEADER_LEN: usize = SIGNATURE.len() + 4 + N32_SIZE;
+pub(crate) const OFFSET_ENTRY_LEN: usize = N32_SIZE * 2;
+
+/// Instantiation
+impl multi_index::File {
+    pub fn at(path: impl AsRef<Path>) -> Result<multi_index::File, Error> {
+        Self::try_from(path.as_ref())
+    }
+}
+
+impl TryFrom<&Path> for multi_index::File {
+    type Error = Error;
+
+    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+        let too_small = |len: usize| {
+            Error::Corrupt(format!(
+                "multi-pack-index of size {} is too small for even an empty index",
+                len
+            ))
+        };
+        let min_len = HEADER_LEN + chunk_file::TABLE_ENTRY_LEN + SHA1_SIZE;
+        // Empty files can't be mapped, so check the size before mapping the file
+        let file_len = std::fs::metadata(path)
+            .map_err(|e| Error::Io(e, path.to_owned()))?
+            .len() as usize;
+        if file_len < min_len {
+            return Err(too_small(file_len));
+        }
+        let data = FileBuffer::open(path).map_err(|e| Error::Io(e, path.to_owned()))?;
+        if data.len() < min_len {
+            return Err(too_small(data.len()));
+        }
+        let (sig, header) = data[..HEADER_LEN].split_at(SIGNATURE.len());
+        if sig != SIGNATURE {
+            return Err(Error::Corrupt(
+                "Invalid signature, expected a multi-pack-index file".into(),
+            ));
+        }
+        let version = header[0];
+        if version != VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+        let hash_kind = match header[1] {
+            OBJECT_HASH_SHA1 => HashKind::Sha1,
+            id => return Err(Error::UnsupportedObjectHash(id)),
+        };
+        let num_chunks = header[2] as usize;
+        let num_packs = BigEndian::read_u32(&header[4..]);
+
+        let chunks = chunk_file::Table::from_bytes(&data, HEADER_LEN, num_chunks, hash_kind)?;
+
+        let fan_range = chunks.required_range(chunk::OID_FANOUT)?;
+        if fan_range.len() != FAN_LEN * N32_SIZE {
+            return Err(Error::Corrupt(format!(
+                "The fan-out table has {} bytes, but {} were expected",
+                fan_range.len(),
+                FAN_LEN * N32_SIZE
+            )));
+        }
+        let mut fan = [0; FAN_LEN];
+        for (c, f) in data[fan_range].chunks(N32_SIZE).zip(fan.iter_mut()) {
+            *f = BigEndian::read_u32(c);
+        }
+        let num_objects = fan[FAN_LEN - 1];
+        if fan.windows(2).any(|w| w[0] > w[1]) {
+            return Err(Error::Corrupt("The fan-out table must not decrease".to_string()));
+        }
+
+        let lookup_range = chunks.required_range(chunk::OID_LOOKUP)?;
+        if lookup_range.len() != num_objects as usize * SHA1_SIZE {
+            return Err(Error::Corrupt(format!(
+                "The object id lookup table has {} bytes, but {} objects need {} bytes",
+                lookup_range.len(),
+                num_objects,
+                num_objects as usize * SHA1_SIZE
+            )));
+        }
+        let offsets_range = chunks.required_range(chunk::OBJECT_OFFSETS)?;
+        if offsets_range.len() != num_objects as usize * OFFSET_ENTRY_LEN {
+            return Err(Error::Corrupt(format!(
+                "The object offsets table has {} bytes, but {} objects need {} bytes",
+                offsets_range.len(),
+                num_objects,
+                num_objects as usize * OFFSET_ENTRY_LEN
+            )));
+        }
+        let large_offsets = match chunks.range(chunk::LARGE_OFFSETS) {
+            Some(range) if range.len() % N64_SIZE != 0 => {
+                return Err(Error::Corrupt(format!(
+                    "The large offsets table size of {} bytes is not a multiple of {}",
+                    range.len(),
+                    N64_SIZE
+                )))
+            }
+            range => range,
+        };
+
+        let index_names = read_index_names(&data[chunks.required_range(chunk::PACK_NAMES)?])?;
+        if index_names.len() != num_packs as usize {
+            return Err(Error::Corrupt(format!(
+                "The header announces {} packs, but {} pack names were found",
+                num_packs,
+                index_names.len()
+            )));
+        }
+
+        Ok(multi_index::File {
+            path: path.to_owned(),
+            version,
+            hash_kind,
+            num_objects,
+            fan,
+            index_names,
+            lookup_ofs: lookup_range.start,
+            offsets_ofs: offsets_range.start,
+            large_offsets,
+            data,
+        })
+    }
+}
+
+fn read_index_names(data: &[u8]) -> Result<Vec<PathBuf>, Error> {
+    // Names are separated by null bytes, with more null bytes padding the chunk at the end
+    data.split(|b| *b == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            name.to_str()
+                .map(PathBuf::from)
+                .map_err(|_| Error::Corrupt(format!("Pack index name {:?} is not valid UTF-8", name.as_bstr())))
+        })
+        .collect()
+}
@@ -0,0 +1,69 @@
+//! A multi-pack-index, which maps objects to their offsets in any of multiple packs, sparing the lookup in
+//! many individual pack indices.
+use filebuffer::FileBuffer;
+use git_object::HashKind;
+use std::path::PathBuf;
+
+/// The name of the multi-pack-index file in the `pack` directory of an object database.
+pub const FILE_NAME: &str = "multi-pack-index";
+
+const SIGNATURE: &[u8] = b"MIDX";
+const VERSION: u8 = 1;
+const OBJECT_HASH_SHA1: u8 = 1;
+const FAN_LEN: usize = 256;
+
+/// The identifiers of all chunks we know, with the ones we ignore being skipped.
+pub(crate) mod chunk {
+    pub use crate::chunk_file::Id;
+
+    pub const PACK_NAMES: Id = *b"PNAM";
+    pub const OID_FANOUT: Id = *b"OIDF";
+    pub const OID_LOOKUP: Id = *b"OIDL";
+    pub const OBJECT_OFFSETS: Id = *b"OOFF";
+    pub const LARGE_OFFSETS: Id = *b"LOFF";
+}
+
+/// A memory mapped multi-pack-index file, usually found at `objects/pack/multi-pack-index`.
+pub struct File {
+    data: FileBuffer,
+    path: PathBuf,
+    version: u8,
+    hash_kind: HashKind,
+    num_objects: u32,
+    fan: [u32; FAN_LEN],
+    index_names: Vec<PathBuf>,
+    lookup_ofs: usize,
+    offsets_ofs: usize,
+    large_offsets: Option<std::ops::Range<usize>>,
+}
+
+impl File {
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+    pub fn hash_kind(&self) -> HashKind {
+        self.hash_kind
+    }
+    pub fn num_objects(&self) -> u32 {
+        self.num_objects
+    }
+    pub fn num_packs(&self) -> u32 {
+        self.index_names.len() as u32
+    }
+    /// The file names of all pack indices covered by this multi-pack-index, relative to its own directory.
+    /// The position of a name is the id of the pack it refers to.
+    pub fn index_names(&self) -> &[PathBuf] {
+        &self.index_names
+    }
+}
+
+pub mod init;
+
+mod access;
+pub use access::{Entry, PackIndex};
+
+pub mod verify;
+pub mod write;
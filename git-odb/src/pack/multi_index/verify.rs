@@ -0,0 +1,161 @@
+use crate::pack::{self, index, multi_index};
+use git_features::progress::{self, Progress};
+use git_object::{owned, SHA1_SIZE};
+use quick_error::quick_error;
+use std::path::PathBuf;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Mismatch { expected: owned::Id, actual: owned::Id } {
+            display("multi-pack-index checksum mismatch: expected {}, got {}", expected, actual)
+        }
+        OutOfOrder { index: u32 } {
+            display("The object id at index {} is not sorted correctly", index)
+        }
+        LargeOffsetOutOfBounds { index: u32 } {
+            display("The object at index {} refers to a large offset which doesn't exist", index)
+        }
+        PackIndexOutOfBounds { index: u32, pack_index: multi_index::PackIndex } {
+            display("The object at index {} refers to pack {}, which doesn't exist", index, pack_index)
+        }
+        BundleInit(err: pack::bundle::Error, path: PathBuf) {
+            display("The pack and index at '{}' could not be opened", path.display())
+            source(err)
+        }
+        ObjectNotFoundInPack { id: owned::Id, path: PathBuf } {
+            display("Object {} is not contained in the pack index at '{}'", id, path.display())
+        }
+        PackOffsetOutOfBounds { id: owned::Id, path: PathBuf } {
+            display("The pack index at '{}' refers to a large offset it doesn't contain for object {}", path.display(), id)
+        }
+        OffsetMismatch { id: owned::Id, expected: u64, actual: u64 } {
+            display("Object {} is expected at pack offset {}, but the pack index has it at {}", id, expected, actual)
+        }
+        Index(err: index::traverse::Error, path: PathBuf) {
+            display("The pack at '{}' failed to verify", path.display())
+            source(err)
+        }
+    }
+}
+
+/// Verify and validate the content of the multi-pack-index file
+impl multi_index::File {
+    pub fn checksum(&self) -> owned::Id {
+        owned::Id::from_20_bytes(&self.data[self.data.len() - SHA1_SIZE..])
+    }
+
+    pub fn verify_checksum(&self, mut progress: impl Progress) -> Result<owned::Id, Error> {
+        let data_len_without_trailer = self.data.len() - SHA1_SIZE;
+        let actual = match crate::hash::bytes_of_file(
+            &self.path,
+            data_len_without_trailer,
+            git_object::HashKind::Sha1,
+            &mut progress,
+        ) {
+            Ok(id) => id,
+            Err(_io_err) => {
+                let start = std::time::Instant::now();
+                let mut hasher = git_features::hash::Sha1::default();
+                hasher.update(&self.data[..data_len_without_trailer]);
+                progress.inc_by(data_len_without_trailer);
+                progress.show_throughput(start);
+                owned::Id::new_sha1(hasher.digest())
+            }
+        };
+
+        let expected = self.checksum();
+        if actual == expected {
+            Ok(actual)
+        } else {
+            Err(Error::Mismatch { actual, expected })
+        }
+    }
+
+    /// Verify the checksum of this file and assure each object is contained in the pack we claim it to be in,
+    /// at the offset we claim.
+    ///
+    /// If `pack` is provided, all packs are fully verified with the given [mode][index::verify::Mode] and
+    /// [algorithm][index::traverse::Algorithm], returning one [`index::traverse::Outcome`] per pack in the order of
+    /// our [index names][multi_index::File::index_names()].
+    #[allow(clippy::type_complexity)]
+    pub fn verify_integrity<P, C>(
+        &self,
+        pack: Option<(index::verify::Mode, index::traverse::Algorithm)>,
+        thread_limit: Option<usize>,
+        progress: Option<P>,
+        make_cache: impl Fn() -> C + Send + Sync,
+    ) -> Result<(owned::Id, Option<Vec<index::traverse::Outcome>>, Option<P>), Error>
+    where
+        P: Progress + Send,
+        <P as Progress>::SubProgress: Send,
+        <<P as Progress>::SubProgress as Progress>::SubProgress: Send,
+        <<<P as Progress>::SubProgress as Progress>::SubProgress as Progress>::SubProgress: Send,
+        C: pack::cache::DecodeEntry,
+    {
+        let mut root = progress::DoOrDiscard::from(progress);
+        let id = self.verify_checksum(root.add_child("Sha1 of multi-pack-index"))?;
+
+        let mut progress = root.add_child("Checking objects");
+        progress.init(Some(self.num_objects as usize), progress::count("objects"));
+        let mut bundles = Vec::with_capacity(self.index_names.len());
+        for (pack_index, name) in self.index_names.iter().enumerate() {
+            let path = self.index_path(pack_index as multi_index::PackIndex);
+            let bundle = pack::Bundle::at(&path).map_err(|err| Error::BundleInit(err, path.clone()))?;
+            bundles.push((name, bundle));
+        }
+        for index in 0..self.num_objects {
+            if index != 0 && self.oid_at_index(index - 1) >= self.oid_at_index(index) {
+                return Err(Error::OutOfOrder { index });
+            }
+            let (pack_index, expected) = self
+                .pack_id_and_pack_offset_at_index(index)
+                .ok_or(Error::LargeOffsetOutOfBounds { index })?;
+            let (_, bundle) = bundles
+                .get(pack_index as usize)
+                .ok_or(Error::PackIndexOutOfBounds { index, pack_index })?;
+            let id = self.oid_at_index(index);
+            let pack_index_entry = bundle.index.lookup(id).ok_or_else(|| Error::ObjectNotFoundInPack {
+                id: id.into(),
+                path: bundle.index.path().to_owned(),
+            })?;
+            let actual =
+                bundle
+                    .index
+                    .pack_offset_at_index(pack_index_entry)
+                    .ok_or_else(|| Error::PackOffsetOutOfBounds {
+                        id: id.into(),
+                        path: bundle.index.path().to_owned(),
+                    })?;
+            if actual != expected {
+                return Err(Error::OffsetMismatch {
+                    id: id.into(),
+                    expected,
+                    actual,
+                });
+            }
+            progress.inc();
+        }
+
+        let outcomes = match pack {
+            None => None,
+            Some((mode, algorithm)) => {
+                let mut outcomes = Vec::with_capacity(bundles.len());
+                for (name, bundle) in &bundles {
+                    let (_, outcome, _) = bundle
+                        .index
+                        .verify_integrity(
+                            Some((&bundle.pack, mode, algorithm)),
+                            thread_limit,
+                            root.add_child(name.display().to_string()).into_inner(),
+                            &make_cache,
+                        )
+                        .map_err(|err| Error::Index(err, bundle.index.path().to_owned()))?;
+                    outcomes.push(outcome.expect("outcome to be present when verifying the pack"));
+                }
+                Some(outcomes)
+            }
+        };
+        Ok((id, outcomes, root.into_inner()))
+    }
+}
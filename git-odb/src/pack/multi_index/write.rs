@@ -0,0 +1,233 @@
+use crate::{
+    chunk_file, hash,
+    pack::{self, multi_index},
+};
+use byteorder::{BigEndian, WriteBytesExt};
+use git_features::progress::{self, Progress};
+use git_object::{owned, SHA1_SIZE};
+use quick_error::quick_error;
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+    time::SystemTime,
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error) {
+            display("An IO error occurred when writing the multi-pack-index")
+            from()
+            source(err)
+        }
+        InvalidIndexPath(path: PathBuf) {
+            display("The pack index at '{}' needs a valid UTF-8 file name", path.display())
+        }
+        UnsupportedHashKind { path: PathBuf, kind: git_object::HashKind } {
+            display("The pack index at '{}' uses {:?} object ids, but only Sha1 can be written into a multi-pack-index", path.display(), kind)
+        }
+        DuplicateIndexName(path: PathBuf) {
+            display("The pack index at '{}' was provided more than once", path.display())
+        }
+        Index(err: pack::index::access::Error, path: PathBuf) {
+            display("The entries of the pack index at '{}' could not be read", path.display())
+            source(err)
+        }
+        TooManyObjects(num_objects: usize) {
+            display("Only u32::MAX objects can be stored in a multi-pack-index, found {}", num_objects)
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Outcome {
+    /// The hash over all bytes of the multi-pack-index, also found in its trailing bytes
+    pub multi_index_hash: owned::Id,
+    pub num_objects: u32,
+    pub num_packs: u32,
+    /// The amount of objects that were contained in more than one pack, but are only referenced once
+    pub num_duplicates: u32,
+}
+
+const LARGE_OFFSET_THRESHOLD: u64 = 0x7fff_ffff;
+const HIGH_BIT: u32 = 0x8000_0000;
+/// Chunks are padded to be aligned to this amount of bytes.
+const CHUNK_ALIGNMENT: usize = 4;
+
+struct Entry {
+    id: owned::Id,
+    pack_index: multi_index::PackIndex,
+    pack_offset: u64,
+    pack_mtime: SystemTime,
+}
+
+/// Writing a multi-pack-index from existing pack indices
+impl multi_index::File {
+    /// Write a multi-pack-index for all `indices` to `out`, which is expected to end up in the same directory as
+    /// all of them and usually be named `multi-pack-index`.
+    ///
+    /// Objects contained in multiple packs are only referenced in the newest pack, as determined by the modification
+    /// time of the pack next to its index.
+    ///
+    /// Only indices of packs with SHA1 object ids can be written, as multi-pack-indices of other kinds of hashes
+    /// are not supported yet.
+    pub fn write_from_indices(
+        indices: Vec<pack::index::File>,
+        out: impl io::Write,
+        mut progress: impl Progress,
+    ) -> Result<Outcome, Error> {
+        let mut indices = indices
+            .into_iter()
+            .map(|index| {
+                if index.hash_kind() != git_object::HashKind::Sha1 {
+                    return Err(Error::UnsupportedHashKind {
+                        path: index.path().to_owned(),
+                        kind: index.hash_kind(),
+                    });
+                }
+                let name = index
+                    .path()
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .map(ToOwned::to_owned)
+                    .ok_or_else(|| Error::InvalidIndexPath(index.path().to_owned()))?;
+                Ok((name, index))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        // The pack ids are the position of their name in the sorted list of names
+        indices.sort_by(|a, b| a.0.cmp(&b.0));
+        if let Some(pair) = indices.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(Error::DuplicateIndexName(pair[1].1.path().to_owned()));
+        }
+
+        progress.init(Some(3), progress::steps());
+        let mut entries = {
+            let _info = progress.add_child("collecting entries");
+            let mut entries = Vec::with_capacity(indices.iter().map(|(_, index)| index.num_objects() as usize).sum());
+            for (pack_index, (_, index)) in indices.iter().enumerate() {
+                let pack_mtime = pack_mtime(index);
+                let index_entries = index.iter().map_err(|err| Error::Index(err, index.path().to_owned()))?;
+                entries.extend(index_entries.map(|entry| Entry {
+                    id: entry.oid,
+                    pack_index: pack_index as multi_index::PackIndex,
+                    pack_offset: entry.pack_offset,
+                    pack_mtime,
+                }));
+            }
+            entries
+        };
+
+        progress.inc();
+        let num_duplicates = {
+            let _info = progress.add_child("sorting and de-duplicating entries");
+            let num_entries = entries.len();
+            entries.sort_by(|a, b| {
+                a.id.cmp(&b.id)
+                    .then_with(|| b.pack_mtime.cmp(&a.pack_mtime))
+                    .then_with(|| a.pack_index.cmp(&b.pack_index))
+            });
+            entries.dedup_by(|a, b| a.id == b.id);
+            num_entries - entries.len()
+        };
+        if entries.len() > u32::MAX as usize {
+            return Err(Error::TooManyObjects(entries.len()));
+        }
+
+        progress.inc();
+        let _info = progress.add_child("writing multi-pack-index");
+        let names: Vec<_> = indices.iter().map(|(name, _)| name.as_str()).collect();
+        let multi_index_hash = write_chunks(&names, &entries, out)?;
+
+        progress.inc();
+        Ok(Outcome {
+            multi_index_hash,
+            num_objects: entries.len() as u32,
+            num_packs: indices.len() as u32,
+            num_duplicates: num_duplicates as u32,
+        })
+    }
+}
+
+fn pack_mtime(index: &pack::index::File) -> SystemTime {
+    let path = index.path();
+    std::fs::metadata(path.with_extension("pack"))
+        .or_else(|_| std::fs::metadata(path))
+        .and_then(|meta| meta.modified())
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+fn write_chunks(names: &[&str], entries_sorted_by_oid: &[Entry], out: impl io::Write) -> io::Result<owned::Id> {
+    let num_large_offsets = entries_sorted_by_oid
+        .iter()
+        .filter(|entry| entry.pack_offset > LARGE_OFFSET_THRESHOLD)
+        .count();
+    let pack_names_len = {
+        let len = names.iter().map(|name| name.len() + 1).sum::<usize>();
+        len + (CHUNK_ALIGNMENT - len % CHUNK_ALIGNMENT) % CHUNK_ALIGNMENT
+    };
+    let mut chunks = vec![
+        (multi_index::chunk::PACK_NAMES, pack_names_len),
+        (multi_index::chunk::OID_FANOUT, multi_index::FAN_LEN * 4),
+        (multi_index::chunk::OID_LOOKUP, entries_sorted_by_oid.len() * SHA1_SIZE),
+        (
+            multi_index::chunk::OBJECT_OFFSETS,
+            entries_sorted_by_oid.len() * multi_index::init::OFFSET_ENTRY_LEN,
+        ),
+    ];
+    if num_large_offsets != 0 {
+        chunks.push((multi_index::chunk::LARGE_OFFSETS, num_large_offsets * 8));
+    }
+
+    let mut out = io::BufWriter::with_capacity(8 * 4096, hash::Write::new(out, git_object::HashKind::Sha1));
+    out.write_all(multi_index::SIGNATURE)?;
+    out.write_all(&[
+        multi_index::VERSION,
+        multi_index::OBJECT_HASH_SHA1,
+        chunks.len() as u8,
+        0, /* base multi-pack-index files */
+    ])?;
+    out.write_u32::<BigEndian>(names.len() as u32)?;
+
+    chunk_file::write_table(&mut out, multi_index::init::HEADER_LEN, &chunks)?;
+
+    for name in names {
+        out.write_all(name.as_bytes())?;
+        out.write_all(&[0])?;
+    }
+    let padding = pack_names_len - names.iter().map(|name| name.len() + 1).sum::<usize>();
+    out.write_all(&[0; CHUNK_ALIGNMENT][..padding])?;
+
+    let mut entries_so_far = 0;
+    for byte in 0u8..=255 {
+        entries_so_far += entries_sorted_by_oid[entries_so_far..]
+            .iter()
+            .take_while(|entry| entry.id.as_slice()[0] == byte)
+            .count();
+        out.write_u32::<BigEndian>(entries_so_far as u32)?;
+    }
+
+    for entry in entries_sorted_by_oid {
+        out.write_all(entry.id.as_slice())?;
+    }
+
+    let mut large_offsets = Vec::with_capacity(num_large_offsets);
+    for entry in entries_sorted_by_oid {
+        out.write_u32::<BigEndian>(entry.pack_index)?;
+        out.write_u32::<BigEndian>(if entry.pack_offset > LARGE_OFFSET_THRESHOLD {
+            large_offsets.push(entry.pack_offset);
+            (large_offsets.len() - 1) as u32 | HIGH_BIT
+        } else {
+            entry.pack_offset as u32
+        })?;
+    }
+    for offset in large_offsets {
+        out.write_u64::<BigEndian>(offset)?;
+    }
+
+    let mut out = out.into_inner()?;
+    let hash: owned::Id = out.hash.digest();
+    out.inner.write_all(hash.as_slice())?;
+    out.inner.flush()?;
+    Ok(hash)
+}
@@ -1,4 +1,4 @@
-use git_object::borrowed;
+use git_object::{borrowed, HashKind};
 
 pub struct Object<'a> {
     pub kind: git_object::Kind,
@@ -6,13 +6,13 @@ pub struct Object<'a> {
 }
 
 impl<'a> Object<'a> {
-    pub fn decode(&self) -> Result<borrowed::Object, borrowed::Error> {
-        Ok(match self.kind {
-            git_object::Kind::Tree => borrowed::Object::Tree(borrowed::Tree::from_bytes(self.data)?),
-            git_object::Kind::Blob => borrowed::Object::Blob(borrowed::Blob { data: self.data }),
-            git_object::Kind::Commit => borrowed::Object::Commit(borrowed::Commit::from_bytes(self.data)?),
-            git_object::Kind::Tag => borrowed::Object::Tag(borrowed::Tag::from_bytes(self.data)?),
-        })
+    pub fn decode(&self) -> Result<borrowed::Object<'a>, borrowed::Error> {
+        self.decode_with_hash(HashKind::Sha1)
+    }
+
+    /// Decode the object, expecting the ids of tree entries to be of kind `hash`.
+    pub fn decode_with_hash(&self, hash: HashKind) -> Result<borrowed::Object<'a>, borrowed::Error> {
+        borrowed::Object::from_bytes_with_hash(self.kind, self.data, hash)
     }
 }
 
@@ -38,7 +38,7 @@ pub mod verify {
             loose::object::header::encode(self.kind, self.data.len() as u64, &mut sink).expect("hash to always work");
             sink.hash.update(&self.data);
 
-            let actual_id = owned::Id::from(sink.hash.digest());
+            let actual_id = sink.hash.digest();
             if desired != actual_id.to_borrowed() {
                 return Err(Error::ChecksumMismatch(desired.into(), actual_id));
             }
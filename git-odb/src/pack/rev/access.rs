@@ -0,0 +1,72 @@
+use crate::pack::{
+    self,
+    index::access::PackOffset,
+    rev::{self, init::HEADER_LEN, Data},
+};
+use byteorder::{BigEndian, ByteOrder};
+use git_object::owned;
+use std::mem::size_of;
+
+const N32_SIZE: usize = size_of::<u32>();
+
+/// Access to the mapping from pack positions to index positions
+impl rev::File {
+    /// Returns the position in the pack index of the object at `pack_position`, that is the object with the
+    /// `pack_position`-th smallest pack offset.
+    ///
+    /// Panics if `pack_position` is not smaller than [`num_objects()`][rev::File::num_objects()].
+    pub fn index_position_at_pack_position(&self, pack_position: u32) -> u32 {
+        assert!(
+            pack_position < self.num_objects,
+            "pack position {} is out of bounds",
+            pack_position
+        );
+        match &self.data {
+            Data::Mapped(data) => BigEndian::read_u32(&data[HEADER_LEN + pack_position as usize * N32_SIZE..]),
+            Data::InMemory(positions) => positions[pack_position as usize],
+        }
+    }
+
+    /// Iterate the index positions of all objects in the order in which they appear in the pack.
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = u32> + 'a {
+        (0..self.num_objects).map(move |pack_position| self.index_position_at_pack_position(pack_position))
+    }
+
+    /// Returns the position in the pack of the object starting at `pack_offset`, using `index` to obtain the offsets
+    /// of objects.
+    pub fn pack_position_by_offset(&self, index: &pack::index::File, pack_offset: PackOffset) -> Option<u32> {
+        let (mut lower, mut upper) = (0, self.num_objects);
+        while lower < upper {
+            let mid = lower + (upper - lower) / 2;
+            let mid_offset = index.pack_offset_at_index(self.index_position_at_pack_position(mid))?;
+            if mid_offset < pack_offset {
+                lower = mid + 1;
+            } else if mid_offset > pack_offset {
+                upper = mid;
+            } else {
+                return Some(mid);
+            }
+        }
+        None
+    }
+
+    /// The checksum of the pack this reverse index belongs to, or `None` if it was computed in memory.
+    pub fn pack_checksum(&self) -> Option<owned::Id> {
+        match &self.data {
+            Data::Mapped(data) => {
+                let hash_len = self.hash_kind.len_in_bytes();
+                let from = data.len() - hash_len * 2;
+                owned::Id::from_bytes(&data[from..from + hash_len])
+            }
+            Data::InMemory(_) => None,
+        }
+    }
+
+    /// The checksum over all bytes of the `.rev` file, or `None` if it was computed in memory.
+    pub fn checksum(&self) -> Option<owned::Id> {
+        match &self.data {
+            Data::Mapped(data) => owned::Id::from_bytes(&data[data.len() - self.hash_kind.len_in_bytes()..]),
+            Data::InMemory(_) => None,
+        }
+    }
+}
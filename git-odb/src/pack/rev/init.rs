@@ -0,0 +1,124 @@
+use crate::pack::{
+    self,
+    rev::{self, Data, OBJECT_HASH_SHA1, OBJECT_HASH_SHA256, SIGNATURE, VERSION},
+};
+use byteorder::{BigEndian, ByteOrder};
+use filebuffer::FileBuffer;
+use git_object::{owned, HashKind, SHA1_SIZE};
+use quick_error::quick_error;
+use std::{convert::TryFrom, mem::size_of, path::Path};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: std::io::Error, path: std::path::PathBuf) {
+            display("Could not open reverse index file at '{}'", path.display())
+            source(err)
+        }
+        Corrupt(msg: String) {
+            display("{}", msg)
+        }
+        UnsupportedVersion(version: u32) {
+            display("Unsupported reverse index version: {}", version)
+        }
+        UnsupportedObjectHash(kind: u32) {
+            display("Unsupported object hash kind: {}", kind)
+        }
+        IndexMismatch { expected: owned::Id, actual: owned::Id } {
+            display("The reverse index belongs to pack {}, but was expected to belong to pack {}", actual, expected)
+        }
+    }
+}
+
+const N32_SIZE: usize = size_of::<u32>();
+pub(crate) const HEADER_LEN: usize = SIGNATURE.len() + N32_SIZE * 2;
+
+/// Instantiation
+impl rev::File {
+    pub fn at(path: impl AsRef<Path>) -> Result<rev::File, Error> {
+        Self::try_from(path.as_ref())
+    }
+
+    /// Compute the reverse index of `index` in memory, which is what a `.rev` file would contain.
+    pub fn from_index(index: &pack::index::File) -> rev::File {
+        let mut positions: Vec<_> = (0..index.num_objects()).collect();
+        positions.sort_by_key(|index_position| index.pack_offset_at_index(*index_position));
+        rev::File {
+            data: Data::InMemory(positions),
+            path: None,
+            version: VERSION,
+            hash_kind: index.hash_kind(),
+            num_objects: index.num_objects(),
+        }
+    }
+}
+
+impl TryFrom<&Path> for rev::File {
+    type Error = Error;
+
+    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+        let data = FileBuffer::open(path).map_err(|e| Error::Io(e, path.to_owned()))?;
+        if data.len() < HEADER_LEN + SHA1_SIZE * 2 {
+            return Err(Error::Corrupt(format!(
+                "Reverse index file of size {} is too small for even an empty index",
+                data.len()
+            )));
+        }
+        let (sig, header) = data[..HEADER_LEN].split_at(SIGNATURE.len());
+        if sig != SIGNATURE {
+            return Err(Error::Corrupt(
+                "Invalid signature, expected a reverse index file".into(),
+            ));
+        }
+        let version = BigEndian::read_u32(header);
+        if version != VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+        let hash_kind = match BigEndian::read_u32(&header[N32_SIZE..]) {
+            OBJECT_HASH_SHA1 => HashKind::Sha1,
+            OBJECT_HASH_SHA256 => HashKind::Sha256,
+            unknown => return Err(Error::UnsupportedObjectHash(unknown)),
+        };
+        let positions_len = data
+            .len()
+            .checked_sub(HEADER_LEN + hash_kind.len_in_bytes() * 2)
+            .ok_or_else(|| Error::Corrupt(format!("Reverse index file of size {} is truncated", data.len())))?;
+        if positions_len % N32_SIZE != 0 {
+            return Err(Error::Corrupt(format!(
+                "The reverse index table of {} bytes is not a multiple of {}",
+                positions_len, N32_SIZE
+            )));
+        }
+        Ok(rev::File {
+            num_objects: (positions_len / N32_SIZE) as u32,
+            data: Data::Mapped(data),
+            path: Some(path.to_owned()),
+            version,
+            hash_kind,
+        })
+    }
+}
+
+/// Access to the reverse index of a pack index
+impl pack::index::File {
+    /// Open the `.rev` file next to this index, or compute the reverse index in memory if there is none.
+    ///
+    /// Fails if the `.rev` file exists, but is unusable or doesn't belong to the same pack.
+    pub fn reverse_index(&self) -> Result<rev::File, Error> {
+        let path = self.path().with_extension("rev");
+        if !path.is_file() {
+            return Ok(rev::File::from_index(self));
+        }
+        let rev = rev::File::at(path)?;
+        let expected = self.pack_checksum();
+        match rev.pack_checksum() {
+            Some(actual) if actual != expected => Err(Error::IndexMismatch { expected, actual }),
+            _ if rev.num_objects() != self.num_objects() => Err(Error::Corrupt(format!(
+                "The reverse index has {} objects, but its pack index has {}",
+                rev.num_objects(),
+                self.num_objects()
+            ))),
+            _ => Ok(rev),
+        }
+    }
+}
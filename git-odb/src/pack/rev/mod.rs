@@ -0,0 +1,50 @@
+//! Reverse indices as stored in `.rev` files next to a pack index, which map positions in the pack, that is objects
+//! ordered by their pack offset, to their position in the pack index.
+//!
+//! If there is no such file, the mapping can be computed in memory instead.
+use filebuffer::FileBuffer;
+use git_object::HashKind;
+use std::path::PathBuf;
+
+const SIGNATURE: &[u8] = b"RIDX";
+const VERSION: u32 = 1;
+const OBJECT_HASH_SHA1: u32 = 1;
+const OBJECT_HASH_SHA256: u32 = 2;
+
+enum Data {
+    /// A memory mapped `.rev` file
+    Mapped(FileBuffer),
+    /// The index position of each object in pack order, computed from the pack index
+    InMemory(Vec<u32>),
+}
+
+/// A mapping from positions of objects in a pack to their positions in its pack index.
+pub struct File {
+    data: Data,
+    path: Option<PathBuf>,
+    version: u32,
+    hash_kind: HashKind,
+    num_objects: u32,
+}
+
+impl File {
+    /// The path of the `.rev` file we were read from, or `None` if we were computed in memory.
+    pub fn path(&self) -> Option<&std::path::Path> {
+        self.path.as_deref()
+    }
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+    pub fn hash_kind(&self) -> HashKind {
+        self.hash_kind
+    }
+    pub fn num_objects(&self) -> u32 {
+        self.num_objects
+    }
+}
+
+pub mod init;
+
+mod access;
+
+pub mod write;
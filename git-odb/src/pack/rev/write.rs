@@ -0,0 +1,36 @@
+use crate::{
+    hash,
+    pack::{
+        self,
+        rev::{self, OBJECT_HASH_SHA1, OBJECT_HASH_SHA256, SIGNATURE, VERSION},
+    },
+};
+use byteorder::{BigEndian, WriteBytesExt};
+use git_object::{owned, HashKind};
+use std::io::{self, Write};
+
+/// Writing reverse indices
+impl rev::File {
+    /// Write the reverse index of `index` to `out`, which is expected to be placed next to `index` with the `.rev`
+    /// extension, returning the checksum over all written bytes, which is also written as trailer.
+    pub fn write_from_index(index: &pack::index::File, out: impl io::Write) -> io::Result<owned::Id> {
+        let rev = rev::File::from_index(index);
+        let mut out = io::BufWriter::with_capacity(8 * 4096, hash::Write::new(out, index.hash_kind()));
+        out.write_all(SIGNATURE)?;
+        out.write_u32::<BigEndian>(VERSION)?;
+        out.write_u32::<BigEndian>(match index.hash_kind() {
+            HashKind::Sha1 => OBJECT_HASH_SHA1,
+            HashKind::Sha256 => OBJECT_HASH_SHA256,
+        })?;
+        for index_position in rev.iter() {
+            out.write_u32::<BigEndian>(index_position)?;
+        }
+        out.write_all(index.pack_checksum().as_slice())?;
+
+        let mut out = out.into_inner()?;
+        let hash: owned::Id = out.hash.digest();
+        out.inner.write_all(hash.as_slice())?;
+        out.inner.flush()?;
+        Ok(hash)
+    }
+}
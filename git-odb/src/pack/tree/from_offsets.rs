@@ -39,11 +39,13 @@ const PACK_HEADER_LEN: usize = 12;
 
 /// Generate tree from certain input
 impl<T> Tree<T> {
-    /// The sort order is ascending. The given packfile path must match the provided offsets.
+    /// The sort order is ascending. The given packfile path must match the provided offsets, and its ids must be
+    /// of kind `hash_kind`.
     pub fn from_offsets_in_pack(
         data_sorted_by_offsets: impl Iterator<Item = T>,
         get_pack_offset: impl Fn(&T) -> PackOffset,
         pack_path: impl AsRef<std::path::Path>,
+        hash_kind: git_object::HashKind,
         mut progress: impl Progress,
         resolve_in_pack_id: impl Fn(git_object::borrowed::Id) -> Option<PackOffset>,
     ) -> Result<Self, Error> {
@@ -75,13 +77,15 @@ impl<T> Tree<T> {
         let then = Instant::now();
 
         let mut previous_cursor_position = None::<u64>;
+        // Ref deltas whose base comes after them, as is the case for bases appended to thin packs
+        let mut deltas_before_their_base = Vec::new();
 
         for (idx, data) in data_sorted_by_offsets.enumerate() {
             let pack_offset = get_pack_offset(&data);
             if let Some(previous_offset) = previous_cursor_position {
                 Self::advance_cursor_to_pack_offset(&mut r, pack_offset, previous_offset)?;
             };
-            let entry = pack::data::Entry::from_read(&mut r, pack_offset)
+            let entry = pack::data::Entry::from_read(&mut r, pack_offset, hash_kind)
                 .map_err(|err| Error::Io(err, "EOF while parsing header"))?;
             previous_cursor_position = Some(pack_offset + entry.header_size() as u64);
 
@@ -91,11 +95,14 @@ impl<T> Tree<T> {
                     tree.add_root(pack_offset, data)?;
                 }
                 RefDelta { base_id } => {
-                    resolve_in_pack_id(base_id.to_borrowed())
-                        .ok_or_else(|| Error::UnresolvedRefDelta(base_id))
-                        .and_then(|base_pack_offset| {
-                            tree.add_child(base_pack_offset, pack_offset, data).map_err(Into::into)
-                        })?;
+                    let base_pack_offset =
+                        resolve_in_pack_id(base_id.to_borrowed()).ok_or_else(|| Error::UnresolvedRefDelta(*base_id))?;
+                    if base_pack_offset < pack_offset {
+                        tree.add_child(base_pack_offset, pack_offset, data)?;
+                    } else {
+                        tree.add_unlinked_child(pack_offset, data)?;
+                        deltas_before_their_base.push((base_pack_offset, pack_offset));
+                    }
                 }
                 OfsDelta { base_distance } => {
                     let base_pack_offset = pack_offset
@@ -110,6 +117,10 @@ impl<T> Tree<T> {
             }
         }
 
+        for (base_pack_offset, pack_offset) in deltas_before_their_base {
+            tree.link_child(base_pack_offset, pack_offset);
+        }
+
         progress.show_throughput(then);
         Ok(tree)
     }
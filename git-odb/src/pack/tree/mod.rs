@@ -101,6 +101,52 @@ impl<T> Tree<T> {
         Ok(())
     }
 
+    /// Add the delta at `offset` whose base isn't known yet. It won't be traversed unless it is made the child of its
+    /// base using [`link_child()`][Tree::link_child()].
+    pub fn add_unlinked_child(&mut self, offset: u64, data: T) -> Result<(), Error> {
+        let offset = self.assert_is_incrementing(offset)?;
+        self.items.get_mut().push(Item {
+            is_root: false,
+            offset,
+            data,
+            children: Default::default(),
+        });
+        Ok(())
+    }
+
+    /// Make the delta at `offset`, previously added with [`add_unlinked_child()`][Tree::add_unlinked_child()], a child
+    /// of the item at `base_offset`.
+    pub fn link_child(&mut self, base_offset: u64, offset: u64) {
+        let items = self.items.get_mut();
+        let index_of = |offset| {
+            items
+                .binary_search_by_key(&offset, |e: &Item<T>| e.offset)
+                .expect("linked items to be part of the tree (BUG)")
+        };
+        let (base_index, child_index) = (index_of(base_offset), index_of(offset));
+        items[base_index].children.push(child_index);
+    }
+
+    /// The entry of each item along with whether it is a root and the indices of its children, with the one of the last item
+    /// in the pack ending at `pack_entries_end`.
+    pub fn entry_slices_with_children(
+        &mut self,
+        pack_entries_end: u64,
+    ) -> Vec<(crate::pack::data::EntrySlice, bool, Vec<usize>)> {
+        let items = self.items.get_mut();
+        items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| {
+                (
+                    item.offset..items.get(index + 1).map_or(pack_entries_end, |next| next.offset),
+                    item.is_root,
+                    item.children.clone(),
+                )
+            })
+            .collect()
+    }
+
     pub fn into_items(self) -> Vec<Item<T>> {
         self.items.into_inner()
     }
@@ -12,6 +12,7 @@ use git_features::{
 use quick_error::quick_error;
 
 mod resolve;
+pub(crate) use resolve::decompress_entry;
 
 quick_error! {
     #[derive(Debug)]
@@ -20,9 +21,20 @@ quick_error! {
             display("{}", msg)
             source(err)
         }
+        SizeMismatch(expected: u64, actual: u64) {
+            display("An entry was expected to decompress to {} bytes, but got {}", expected, actual)
+        }
         ResolveFailed(pack_offset: u64) {
             display("The resolver failed to obtain the pack entry bytes for the entry at {}", pack_offset)
         }
+        EntryHeader(err: pack::data::header::Error, pack_offset: u64) {
+            display("The header of the entry at offset {} could not be decoded", pack_offset)
+            source(err)
+        }
+        Delta(err: pack::data::decode::Error, pack_offset: u64) {
+            display("The delta at offset {} could not be applied", pack_offset)
+            source(err)
+        }
         Inspect(err: Box<dyn std::error::Error + Send + Sync>) {
             display("One of the object inspectors failed")
             source(&**err)
@@ -55,6 +67,7 @@ where
         size_progress: P,
         thread_limit: Option<usize>,
         pack_entries_end: u64,
+        hash_kind: git_object::HashKind,
         new_thread_state: impl Fn() -> S + Send + Sync,
         inspect_object: MBFN,
     ) -> Result<Vec<Item<T>>, Error>
@@ -82,7 +95,7 @@ where
                     new_thread_state(),
                 )
             },
-            |root_nodes, state| resolve::deltas(root_nodes, state, &resolve, &inspect_object),
+            |root_nodes, state| resolve::deltas(root_nodes, state, &resolve, hash_kind, &inspect_object),
             Reducer::new(num_objects, &object_progress, size_progress),
         )?;
         Ok(self.into_items())
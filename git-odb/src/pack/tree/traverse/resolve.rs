@@ -3,12 +3,13 @@ use crate::{
     zlib,
 };
 use git_features::progress::{unit, Progress};
-use std::{cell::RefCell, collections::BTreeMap};
+use std::{cell::RefCell, collections::BTreeMap, convert::TryInto};
 
 pub(crate) fn deltas<T, F, P, MBFN, S, E>(
     nodes: Vec<pack::tree::Node<T>>,
     (bytes_buf, ref mut progress, state): &mut (Vec<u8>, P, S),
     resolve: F,
+    hash_kind: git_object::HashKind,
     modify_base: MBFN,
 ) -> Result<(usize, u64), Error>
 where
@@ -23,13 +24,9 @@ where
     let mut num_objects = 0;
     let mut decompressed_bytes: u64 = 0;
     let decompress_from_resolver = |slice: EntrySlice| -> Result<(pack::data::Entry, u64, Vec<u8>), Error> {
-        let mut bytes_buf = bytes_buf.borrow_mut();
-        bytes_buf.resize((slice.end - slice.start) as usize, 0);
-        resolve(slice.clone(), &mut bytes_buf).ok_or_else(|| Error::ResolveFailed(slice.start))?;
-        let entry = pack::data::Entry::from_bytes(&bytes_buf, slice.start);
-        let compressed = &bytes_buf[entry.header_size() as usize..];
-        let decompressed_len = entry.decompressed_size as usize;
-        Ok((entry, slice.end, decompress_all_at_once(compressed, decompressed_len)?))
+        let entry_end = slice.end;
+        let (entry, decompressed) = decompress_entry(slice, &resolve, &mut bytes_buf.borrow_mut(), hash_kind)?;
+        Ok((entry, entry_end, decompressed))
     };
 
     // Traverse the tree breadth first and loose the data produced for the base as it won't be needed anymore.
@@ -71,23 +68,11 @@ where
         progress.inc();
         for child in base.store_changes_then_into_child_iter() {
             let (mut child_entry, entry_end, delta_bytes) = decompress_from_resolver(child.entry_slice())?;
-            let (base_size, consumed) = pack::data::decode::delta_header_size_ofs(&delta_bytes);
-            let mut header_ofs = consumed;
-            assert_eq!(
-                base_bytes.len(),
-                base_size as usize,
-                "recorded base size in delta does not match"
-            );
-            let (result_size, consumed) = pack::data::decode::delta_header_size_ofs(&delta_bytes[consumed..]);
-            header_ofs += consumed;
-
             let mut fully_resolved_delta_bytes = bytes_buf.borrow_mut();
-            fully_resolved_delta_bytes.resize(result_size as usize, 0);
-            pack::data::decode::apply_delta(&base_bytes, &mut fully_resolved_delta_bytes, &delta_bytes[header_ofs..]);
+            pack::data::delta::apply(&base_bytes, &delta_bytes, &mut fully_resolved_delta_bytes)
+                .map_err(|err| Error::Delta(err, child.offset()))?;
 
-            // FIXME: this actually invalidates the "pack_offset()" computation, which is not obvious to consumers
-            // at all
-            child_entry.header = base_entry.header;
+            child_entry.header = base_entry.header.clone();
             decompressed_bytes_by_pack_offset.insert(
                 child.offset(),
                 (child_entry, entry_end, fully_resolved_delta_bytes.to_owned()),
@@ -99,11 +84,40 @@ where
     Ok((num_objects, decompressed_bytes))
 }
 
-fn decompress_all_at_once(b: &[u8], decompressed_len: usize) -> Result<Vec<u8>, Error> {
+/// Obtain the bytes of the entry at `slice` using `resolve` and `bytes_buf`, and decompress its data.
+pub(crate) fn decompress_entry<F>(
+    slice: EntrySlice,
+    resolve: F,
+    bytes_buf: &mut Vec<u8>,
+    hash_kind: git_object::HashKind,
+) -> Result<(pack::data::Entry, Vec<u8>), Error>
+where
+    F: for<'r> Fn(EntrySlice, &'r mut Vec<u8>) -> Option<()>,
+{
+    bytes_buf.resize((slice.end - slice.start) as usize, 0);
+    resolve(slice.clone(), bytes_buf).ok_or_else(|| Error::ResolveFailed(slice.start))?;
+    let entry = pack::data::Entry::from_bytes(bytes_buf, slice.start, hash_kind)
+        .map_err(|err| Error::EntryHeader(err, slice.start))?;
+    let compressed = bytes_buf
+        .get(entry.header_size() as usize..)
+        .ok_or_else(|| Error::ResolveFailed(slice.start))?;
+    let decompressed = decompress_all_at_once(compressed, entry.decompressed_size)?;
+    Ok((entry, decompressed))
+}
+
+fn decompress_all_at_once(b: &[u8], decompressed_len: u64) -> Result<Vec<u8>, Error> {
     let mut out = Vec::new();
-    out.resize(decompressed_len, 0);
-    zlib::Inflate::default()
-        .once(&b, &mut out, true)
+    out.resize(
+        decompressed_len
+            .try_into()
+            .map_err(|_| Error::SizeMismatch(decompressed_len, 0))?,
+        0,
+    );
+    let (_status, _consumed_in, consumed_out) = zlib::Inflate::default()
+        .once(&b, &mut out)
         .map_err(|err| Error::ZlibInflate(err, "Failed to decompress entry"))?;
+    if consumed_out as u64 != decompressed_len {
+        return Err(Error::SizeMismatch(decompressed_len, consumed_out as u64));
+    }
     Ok(out)
 }
@@ -0,0 +1,39 @@
+//! Lookup of objects by an abbreviated id, a [`Prefix`][git_object::owned::Prefix] of their full id.
+use git_object::owned;
+use quick_error::quick_error;
+use std::{io, path::PathBuf};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Ambiguous(prefix: owned::Prefix, candidates: Vec<owned::Id>) {
+            display("The short id {} is ambiguous, candidates are: {}",
+                prefix,
+                candidates.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))
+        }
+        Io(err: io::Error, path: PathBuf) {
+            display("Could not list the loose objects in '{}'", path.display())
+            source(err)
+        }
+    }
+}
+
+/// Turn all `candidates` matching `prefix` into a unique match, or into an error listing all of them.
+pub(crate) fn disambiguate(prefix: &owned::Prefix, mut candidates: Vec<owned::Id>) -> Option<Result<owned::Id, Error>> {
+    candidates.sort();
+    candidates.dedup();
+    match candidates.len() {
+        0 => None,
+        1 => Some(Ok(candidates.remove(0))),
+        _ => Some(Err(Error::Ambiguous(prefix.clone(), candidates))),
+    }
+}
+
+/// The amount of leading hex characters `a` and `b` have in common.
+pub(crate) fn common_hex_len(a: &[u8], b: &[u8]) -> usize {
+    a.iter()
+        .zip(b)
+        .position(|(a, b)| a != b)
+        .map(|byte| byte * 2 + ((a[byte] ^ b[byte]) & 0xf0 == 0) as usize)
+        .unwrap_or_else(|| a.len() * 2)
+}
@@ -1,4 +1,7 @@
-use crate::{loose, zlib::stream::DeflateWriter};
+use crate::{
+    hash, loose,
+    zlib::{stream::DeflateWriter, CompressionLevel},
+};
 use git_object::{owned::Id, HashKind};
 use std::{
     cell::RefCell,
@@ -8,21 +11,32 @@ use std::{
 
 pub struct Sink {
     compressor: Option<RefCell<DeflateWriter<io::Sink>>>,
+    level: CompressionLevel,
 }
 
 impl Sink {
     pub fn compress(mut self, enable: bool) -> Self {
         if enable {
-            self.compressor = Some(RefCell::new(DeflateWriter::new(io::sink())));
+            self.compressor = Some(RefCell::new(DeflateWriter::with_level(io::sink(), self.level)));
         } else {
             self.compressor = None;
         }
         self
     }
+
+    /// Set the `level` at which to compress objects if compression is enabled.
+    pub fn compression_level(mut self, level: CompressionLevel) -> Self {
+        self.level = level;
+        let enabled = self.compressor.is_some();
+        self.compress(enabled)
+    }
 }
 
 pub fn sink() -> Sink {
-    Sink { compressor: None }
+    Sink {
+        compressor: None,
+        level: CompressionLevel::default(),
+    }
 }
 
 impl crate::Write for Sink {
@@ -35,7 +49,6 @@ impl crate::Write for Sink {
         mut from: impl io::Read,
         hash: HashKind,
     ) -> Result<Id, Self::Error> {
-        use git_features::hash::Sha1;
         let mut buf = [0u8; 8096];
 
         let possibly_compress = |buf: &[u8]| -> io::Result<()> {
@@ -44,29 +57,25 @@ impl crate::Write for Sink {
             }
             Ok(())
         };
-        match hash {
-            HashKind::Sha1 => {
-                let mut hasher = Sha1::default();
-                let header_len = loose::object::header::encode(kind, size, &mut buf[..])?;
-                hasher.update(&buf[..header_len]);
-                possibly_compress(&buf[..header_len])?;
+        let mut hasher = hash::Hasher::new(hash);
+        let header_len = loose::object::header::encode(kind, size, &mut buf[..])?;
+        hasher.update(&buf[..header_len]);
+        possibly_compress(&buf[..header_len])?;
 
-                let mut size: usize = size.try_into().expect("object size to fit into usize");
-                while size != 0 {
-                    let bytes = size.min(buf.len());
-                    from.read_exact(&mut buf[..bytes])?;
-                    hasher.update(&buf[..bytes]);
-                    possibly_compress(&buf[..bytes])?;
-                    size -= bytes;
-                }
-                if let Some(compressor) = self.compressor.as_ref() {
-                    let mut c = compressor.borrow_mut();
-                    c.flush()?;
-                    c.reset();
-                }
-
-                Ok(hasher.digest().into())
-            }
+        let mut size: usize = size.try_into().expect("object size to fit into usize");
+        while size != 0 {
+            let bytes = size.min(buf.len());
+            from.read_exact(&mut buf[..bytes])?;
+            hasher.update(&buf[..bytes]);
+            possibly_compress(&buf[..bytes])?;
+            size -= bytes;
         }
+        if let Some(compressor) = self.compressor.as_ref() {
+            let mut c = compressor.borrow_mut();
+            c.flush()?;
+            c.reset();
+        }
+
+        Ok(hasher.digest())
     }
 }
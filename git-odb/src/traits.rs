@@ -1,4 +1,5 @@
-use git_object::{owned, HashKind};
+use crate::pack;
+use git_object::{borrowed, owned, HashKind};
 use std::io;
 
 pub trait Write {
@@ -20,3 +21,59 @@ pub trait Write {
         hash: HashKind,
     ) -> Result<owned::Id, Self::Error>;
 }
+
+/// Describe how objects can be located in an object store, decoding them fully into `buffer`.
+pub trait Locate {
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Find an object matching `id` in the database while placing its raw, decoded data into `buffer`.
+    /// A `pack_cache` can be used to speed up subsequent lookups, set it to `pack::cache::DecodeEntryNoop` if it's not needed.
+    ///
+    /// Returns `Some` object if it was present in the database, or the error that occurred during lookup or object
+    /// retrieval.
+    fn locate<'a>(
+        &self,
+        id: borrowed::Id,
+        buffer: &'a mut Vec<u8>,
+        pack_cache: &mut impl pack::cache::DecodeEntry,
+    ) -> Option<Result<pack::Object<'a>, Self::Error>>;
+
+    /// Obtain the kind and size of the object matching `id`, or `None` if it isn't present in the database.
+    ///
+    /// The default implementation decodes the object into `buffer` using `pack_cache`, implementors should
+    /// override it if they can learn an object's header without decoding it.
+    fn header(
+        &self,
+        id: borrowed::Id,
+        buffer: &mut Vec<u8>,
+        pack_cache: &mut impl pack::cache::DecodeEntry,
+    ) -> Option<Result<(git_object::Kind, u64), Self::Error>> {
+        self.locate(id, buffer, pack_cache)
+            .map(|res| res.map(|object| (object.kind, object.data.len() as u64)))
+    }
+}
+
+impl<T> Locate for &T
+where
+    T: Locate,
+{
+    type Error = T::Error;
+
+    fn locate<'a>(
+        &self,
+        id: borrowed::Id,
+        buffer: &'a mut Vec<u8>,
+        pack_cache: &mut impl pack::cache::DecodeEntry,
+    ) -> Option<Result<pack::Object<'a>, Self::Error>> {
+        (*self).locate(id, buffer, pack_cache)
+    }
+
+    fn header(
+        &self,
+        id: borrowed::Id,
+        buffer: &mut Vec<u8>,
+        pack_cache: &mut impl pack::cache::DecodeEntry,
+    ) -> Option<Result<(git_object::Kind, u64), Self::Error>> {
+        (*self).header(id, buffer, pack_cache)
+    }
+}
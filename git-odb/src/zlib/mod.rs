@@ -1,12 +1,9 @@
-use miniz_oxide::{
-    inflate::core::DecompressorOxide,
-    inflate::{
-        core::inflate_flags::{
-            TINFL_FLAG_HAS_MORE_INPUT, TINFL_FLAG_PARSE_ZLIB_HEADER, TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF,
-        },
-        TINFLStatus,
-    },
-};
+//! zlib compression and decompression through `flate2`, whose backend is selected with cargo features.
+//!
+//! The pure-Rust `miniz_oxide` is used by default, while the `zlib-ng-compat` and `zlib` features switch to zlib-ng
+//! or libz respectively, for all decompression of packs and loose objects as well as all compression when writing.
+pub use flate2::Status;
+use flate2::{Compression, Decompress, FlushDecompress};
 use quick_error::quick_error;
 
 quick_error! {
@@ -17,54 +14,95 @@ quick_error! {
             from()
             source(err)
         }
-        Inflate(status: miniz_oxide::inflate::TINFLStatus) {
-            display("Could not decode zip stream, status was '{:?}'", status)
+        Inflate(err: flate2::DecompressError) {
+            display("Could not decode zip stream")
+            from()
+            source(err)
+        }
+    }
+}
+
+/// The level of compression to use when writing objects, from 0 for no compression to 9 for the best compression,
+/// like git's `core.compression`, `core.looseCompression` and `pack.compression`.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompressionLevel(u8);
+
+impl CompressionLevel {
+    /// Store data as is, which is fastest but takes the most space.
+    pub const NONE: CompressionLevel = CompressionLevel(0);
+    /// Compress as fast as possible.
+    pub const FASTEST: CompressionLevel = CompressionLevel(1);
+    /// Compress as well as possible, which is slowest.
+    pub const BEST: CompressionLevel = CompressionLevel(9);
+
+    /// Create a new level from `level` between 0 and 9, or `None` if it is out of range.
+    pub fn new(level: u32) -> Option<Self> {
+        if level <= 9 {
+            Some(CompressionLevel(level as u8))
+        } else {
+            None
+        }
+    }
+
+    /// Interpret `value` like git does in its configuration, where -1 is the default of zlib and all other values
+    /// must be between 0 and 9.
+    pub fn from_git_config(value: i32) -> Option<Self> {
+        match value {
+            -1 => Some(CompressionLevel::default()),
+            value if value >= 0 => CompressionLevel::new(value as u32),
+            _ => None,
         }
     }
+
+    /// The level as number between 0 and 9.
+    pub fn get(&self) -> u32 {
+        self.0 as u32
+    }
+}
+
+/// The default level of zlib, which is a good compromise between speed and size.
+impl Default for CompressionLevel {
+    fn default() -> Self {
+        CompressionLevel(6)
+    }
+}
+
+impl From<CompressionLevel> for Compression {
+    fn from(level: CompressionLevel) -> Self {
+        Compression::new(level.get())
+    }
 }
 
 /// Decompress a few bytes of a zlib stream without allocation
 pub struct Inflate {
-    state: DecompressorOxide,
+    state: Decompress,
     pub is_done: bool,
 }
 
 impl Default for Inflate {
     fn default() -> Self {
         Inflate {
-            state: DecompressorOxide::default(),
+            state: Decompress::new(true),
             is_done: false,
         }
     }
 }
 
 impl Inflate {
-    /// Run the decompressor exactly once. Cannot be run mutliple times
-    pub fn once(
-        &mut self,
-        input: &[u8],
-        out: &mut [u8],
-        parse_header: bool,
-    ) -> Result<(TINFLStatus, usize, usize), Error> {
-        let (status, in_consumed, out_consumed) = miniz_oxide::inflate::core::decompress(
-            &mut self.state,
-            input,
-            out,
-            0,
-            if parse_header { TINFL_FLAG_PARSE_ZLIB_HEADER } else { 0 }
-                | TINFL_FLAG_HAS_MORE_INPUT
-                | TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF,
-        );
-
-        use miniz_oxide::inflate::TINFLStatus::*;
-        match status {
-            Failed | FailedCannotMakeProgress | BadParam | Adler32Mismatch => return Err(Error::Inflate(status)),
-            HasMoreOutput | NeedsMoreInput => {}
-            Done => {
-                self.is_done = true;
-            }
-        };
-        Ok((status, in_consumed, out_consumed))
+    /// Run the decompressor exactly once, returning the status along with the amount of bytes consumed from `input`
+    /// and written to `out`. Cannot be run mutliple times
+    pub fn once(&mut self, input: &[u8], out: &mut [u8]) -> Result<(Status, usize, usize), Error> {
+        let (before_in, before_out) = (self.state.total_in(), self.state.total_out());
+        let status = self.state.decompress(input, out, FlushDecompress::None)?;
+        if status == Status::StreamEnd {
+            self.is_done = true;
+        }
+        Ok((
+            status,
+            (self.state.total_in() - before_in) as usize,
+            (self.state.total_out() - before_out) as usize,
+        ))
     }
 }
 
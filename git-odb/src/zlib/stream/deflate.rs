@@ -1,57 +1,46 @@
-use super::Status;
-use miniz_oxide::{deflate, deflate::core::CompressorOxide, MZError, MZFlush, MZStatus};
+use crate::zlib::CompressionLevel;
+use flate2::{Compress, FlushCompress, Status};
 use quick_error::quick_error;
 use std::io;
 
 quick_error! {
     #[derive(Debug)]
     pub enum Error {
-        Compression {
-            display("The compression failed due to an unknown error")
-        }
-        ZLibNeedDict {
-            display("Need dictionary")
-        }
-        Error(err: MZError) {
-            display("A compression error occurred: {:?}", err)
+        Error(err: flate2::CompressError) {
+            display("A compression error occurred")
+            source(err)
         }
     }
 }
 
 pub struct Deflate {
-    inner: CompressorOxide,
-    total_in: u64,
-    total_out: u64,
+    inner: Compress,
 }
 
 impl Default for Deflate {
     fn default() -> Self {
-        Deflate {
-            inner: CompressorOxide::default(),
-            total_in: 0,
-            total_out: 0,
-        }
+        Deflate::new(CompressionLevel::default())
     }
 }
 
 impl Deflate {
-    fn compress(&mut self, input: &[u8], output: &mut [u8], flush: MZFlush) -> Result<Status, Error> {
-        let res = deflate::stream::deflate(&mut self.inner, input, output, flush);
-        self.total_in += res.bytes_consumed as u64;
-        self.total_out += res.bytes_written as u64;
-
-        match res.status {
-            Ok(status) => match status {
-                MZStatus::Ok => Ok(Status::Ok),
-                MZStatus::StreamEnd => Ok(Status::StreamEnd),
-                MZStatus::NeedDict => Err(Error::ZLibNeedDict),
-            },
-            Err(status) => match status {
-                MZError::Buf => Ok(Status::BufError),
-                _ => Err(Error::Error(status)),
-            },
+    pub fn new(level: CompressionLevel) -> Self {
+        Deflate {
+            inner: Compress::new(level.into(), true),
         }
     }
+
+    fn compress(&mut self, input: &[u8], output: &mut [u8], flush: FlushCompress) -> Result<Status, Error> {
+        self.inner.compress(input, output, flush).map_err(Error::Error)
+    }
+
+    fn total_in(&self) -> u64 {
+        self.inner.total_in()
+    }
+
+    fn total_out(&self) -> u64 {
+        self.inner.total_out()
+    }
 }
 
 const BUF_SIZE: usize = 4096 * 8;
@@ -66,8 +55,13 @@ where
     W: io::Write,
 {
     pub fn new(inner: W) -> DeflateWriter<W> {
+        Self::with_level(inner, CompressionLevel::default())
+    }
+
+    /// Compress all data written to us at the given `level` into `inner`.
+    pub fn with_level(inner: W, level: CompressionLevel) -> DeflateWriter<W> {
         DeflateWriter {
-            compressor: Default::default(),
+            compressor: Deflate::new(level),
             inner,
             buf: [0; BUF_SIZE],
         }
@@ -81,38 +75,38 @@ where
         self.inner
     }
 
-    fn write_inner(&mut self, mut buf: &[u8], flush: MZFlush) -> io::Result<usize> {
-        let total_in_when_start = self.compressor.total_in;
+    fn write_inner(&mut self, mut buf: &[u8], flush: FlushCompress) -> io::Result<usize> {
+        let total_in_when_start = self.compressor.total_in();
         loop {
-            let last_total_in = self.compressor.total_in;
-            let last_total_out = self.compressor.total_out;
+            let last_total_in = self.compressor.total_in();
+            let last_total_out = self.compressor.total_out();
 
             let status = self
                 .compressor
                 .compress(buf, &mut self.buf, flush)
                 .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
 
-            let written = self.compressor.total_out - last_total_out;
+            let written = self.compressor.total_out() - last_total_out;
             if written > 0 {
                 self.inner.write_all(&self.buf[..written as usize])?;
             }
 
             match status {
-                Status::StreamEnd => return Ok((self.compressor.total_in - total_in_when_start) as usize),
+                Status::StreamEnd => return Ok((self.compressor.total_in() - total_in_when_start) as usize),
                 Status::Ok | Status::BufError => {
-                    let consumed = self.compressor.total_in - last_total_in;
+                    let consumed = self.compressor.total_in() - last_total_in;
                     buf = &buf[consumed as usize..];
 
                     // output buffer still makes progress
-                    if self.compressor.total_out > last_total_out {
+                    if self.compressor.total_out() > last_total_out {
                         continue;
                     }
                     // input still makes progress
-                    if self.compressor.total_in > last_total_in {
+                    if self.compressor.total_in() > last_total_in {
                         continue;
                     }
                     // input also makes no progress anymore, need more so leave with what we have
-                    return Ok((self.compressor.total_in - total_in_when_start) as usize);
+                    return Ok((self.compressor.total_in() - total_in_when_start) as usize);
                 }
             }
         }
@@ -121,11 +115,11 @@ where
 
 impl<W: io::Write> io::Write for DeflateWriter<W> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.write_inner(buf, MZFlush::None)
+        self.write_inner(buf, FlushCompress::None)
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        self.write_inner(&[], MZFlush::Finish).map(|_| ())
+        self.write_inner(&[], FlushCompress::Finish).map(|_| ())
     }
 }
 
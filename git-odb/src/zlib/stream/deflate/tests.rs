@@ -49,16 +49,18 @@ mod deflate_stream {
     }
 }
 
+/// These assert the exact buffering of `miniz_oxide`, while other backends for instance emit the zlib header right away.
+#[cfg(not(any(feature = "zlib", feature = "zlib-ng-compat")))]
 mod deflate {
-    use crate::zlib::stream::{deflate::Deflate, Status};
-    use miniz_oxide::MZFlush;
+    use crate::zlib::stream::deflate::Deflate;
+    use flate2::{FlushCompress, Status};
 
     #[test]
     fn compress_all_data_at_once() {
         let mut buf = [0u8; 16];
         assert_eq!(
             Deflate::default()
-                .compress(b"hello", &mut buf, MZFlush::Finish)
+                .compress(b"hello", &mut buf, FlushCompress::Finish)
                 .expect("compression to memory to work"),
             Status::StreamEnd
         );
@@ -69,20 +71,20 @@ mod deflate {
         let mut buf = [0u8; 6];
         let mut deflate = Deflate::default();
         let input = b"hello";
-        assert_eq!(deflate.compress(input, &mut buf, MZFlush::Finish)?, Status::Ok);
-        assert_eq!(deflate.total_in, 5);
-        assert_eq!(deflate.total_out, 6);
+        assert_eq!(deflate.compress(input, &mut buf, FlushCompress::Finish)?, Status::Ok);
+        assert_eq!(deflate.total_in(), 5);
+        assert_eq!(deflate.total_out(), 6);
         assert_eq!(
-            deflate.compress(&input[deflate.total_in as usize..], &mut buf, MZFlush::Finish)?,
+            deflate.compress(&input[deflate.total_in() as usize..], &mut buf, FlushCompress::Finish)?,
             Status::Ok
         );
-        assert_eq!(deflate.total_in, 5);
-        assert!(deflate.total_out == 13 || deflate.total_out == 12);
+        assert_eq!(deflate.total_in(), 5);
+        assert!(deflate.total_out() == 13 || deflate.total_out() == 12);
         assert_eq!(
-            deflate.compress(&input[deflate.total_in as usize..], &mut buf, MZFlush::Finish)?,
+            deflate.compress(&input[deflate.total_in() as usize..], &mut buf, FlushCompress::Finish)?,
             Status::StreamEnd
         );
-        assert!(deflate.total_out == 13 || deflate.total_out == 12);
+        assert!(deflate.total_out() == 13 || deflate.total_out() == 12);
         Ok(())
     }
 
@@ -91,32 +93,32 @@ mod deflate {
         let mut buf = [0u8; 6];
         let mut deflate = Deflate::default();
         let input = b"hellohellohellohellohellohellohellohellhellohellohellohellohellohellohellohellhellohellohellohellohellohellohellohellooohellohellohellohellohellohellohellohello";
-        assert_eq!(deflate.compress(input, &mut buf, MZFlush::None)?, Status::Ok);
-        assert_eq!(deflate.total_in, 160);
-        assert_eq!(deflate.total_out, 0);
+        assert_eq!(deflate.compress(input, &mut buf, FlushCompress::None)?, Status::Ok);
+        assert_eq!(deflate.total_in(), 160);
+        assert_eq!(deflate.total_out(), 0);
         assert_eq!(
-            deflate.compress(&input[deflate.total_in as usize..], &mut buf, MZFlush::None)?,
+            deflate.compress(&input[deflate.total_in() as usize..], &mut buf, FlushCompress::None)?,
             Status::BufError,
             "the output buffer is too small to drop any information"
         );
         let mut buf = [0u8; 32];
         assert_eq!(
-            deflate.compress(&input[deflate.total_in as usize..], &mut buf, MZFlush::None)?,
+            deflate.compress(&input[deflate.total_in() as usize..], &mut buf, FlushCompress::None)?,
             Status::BufError,
             "after the first buf error, unless providing more input, probably nothing can be done"
         );
-        assert_eq!(deflate.total_out, 0);
+        assert_eq!(deflate.total_out(), 0);
         assert_eq!(
-            deflate.compress(&input[deflate.total_in as usize..], &mut buf, MZFlush::Finish)?,
+            deflate.compress(&input[deflate.total_in() as usize..], &mut buf, FlushCompress::Finish)?,
             Status::Ok,
             "it wrote some data, but not all"
         );
-        assert!(deflate.total_out == 31 || deflate.total_out == 32);
+        assert!(deflate.total_out() == 31 || deflate.total_out() == 32);
         assert_eq!(
-            deflate.compress(&input[deflate.total_in as usize..], &mut buf, MZFlush::Finish)?,
+            deflate.compress(&input[deflate.total_in() as usize..], &mut buf, FlushCompress::Finish)?,
             Status::StreamEnd,
         );
-        assert_eq!(deflate.total_out, 35);
+        assert_eq!(deflate.total_out(), 35);
         Ok(())
     }
 
@@ -128,25 +130,25 @@ mod deflate {
         let step = 2;
         let mut cur = 0;
         assert_eq!(
-            deflate.compress(&input[cur..cur + step], &mut buf, MZFlush::None)?,
+            deflate.compress(&input[cur..cur + step], &mut buf, FlushCompress::None)?,
             Status::Ok
         );
-        assert_eq!(deflate.total_in, 2);
-        assert_eq!(deflate.total_out, 0);
+        assert_eq!(deflate.total_in(), 2);
+        assert_eq!(deflate.total_out(), 0);
         cur += step;
         assert_eq!(
-            deflate.compress(&input[cur..cur + step], &mut buf, MZFlush::None)?,
+            deflate.compress(&input[cur..cur + step], &mut buf, FlushCompress::None)?,
             Status::Ok
         );
-        assert_eq!(deflate.total_in, 4);
-        assert_eq!(deflate.total_out, 0);
+        assert_eq!(deflate.total_in(), 4);
+        assert_eq!(deflate.total_out(), 0);
         cur += step;
         assert_eq!(
-            deflate.compress(&input[cur..], &mut buf, MZFlush::Finish)?,
+            deflate.compress(&input[cur..], &mut buf, FlushCompress::Finish)?,
             Status::StreamEnd
         );
-        assert_eq!(deflate.total_in, 5);
-        assert!(deflate.total_out == 13 || deflate.total_out == 12);
+        assert_eq!(deflate.total_in(), 5);
+        assert!(deflate.total_out() == 13 || deflate.total_out() == 12);
         Ok(())
     }
 }
@@ -1,5 +1,4 @@
-use super::Status;
-use miniz_oxide::{inflate, inflate::stream::InflateState, DataFormat, MZError, MZFlush, MZStatus};
+use flate2::{Decompress, FlushDecompress, Status};
 use quick_error::quick_error;
 use std::{io, io::BufRead};
 
@@ -16,44 +15,38 @@ quick_error! {
 }
 
 pub(crate) struct Inflate {
-    state: InflateState,
-    pub(crate) total_in: u64,
-    total_out: u64,
+    state: Decompress,
 }
 
 impl Default for Inflate {
     fn default() -> Self {
         Inflate {
-            state: InflateState::new(DataFormat::Zlib),
-            total_in: 0,
-            total_out: 0,
+            state: Decompress::new(true),
         }
     }
 }
 
 impl Inflate {
     pub fn reset(&mut self) {
-        self.state.reset(DataFormat::Zlib);
-        self.total_in = 0;
-        self.total_out = 0;
+        self.state.reset(true);
     }
 
-    fn decompress(&mut self, input: &[u8], output: &mut [u8], flush: MZFlush) -> Result<Status, Error> {
-        let res = inflate::stream::inflate(&mut self.state, input, output, flush);
-        self.total_in += res.bytes_consumed as u64;
-        self.total_out += res.bytes_written as u64;
-
-        match res.status {
-            Ok(status) => match status {
-                MZStatus::Ok => Ok(Status::Ok),
-                MZStatus::StreamEnd => Ok(Status::StreamEnd),
-                MZStatus::NeedDict => Err(Error::ZLibNeedDict(self.state.decompressor().adler32().unwrap_or(0))),
-            },
-            Err(status) => match status {
-                MZError::Buf => Ok(Status::BufError),
-                _ => Err(Error::Decompression),
-            },
-        }
+    /// The amount of compressed bytes consumed since the last reset.
+    pub(crate) fn total_in(&self) -> u64 {
+        self.state.total_in()
+    }
+
+    fn total_out(&self) -> u64 {
+        self.state.total_out()
+    }
+
+    fn decompress(&mut self, input: &[u8], output: &mut [u8], flush: FlushDecompress) -> Result<Status, Error> {
+        self.state
+            .decompress(input, output, flush)
+            .map_err(|err| match err.needs_dictionary() {
+                Some(adler) => Error::ZLibNeedDict(adler),
+                None => Error::Decompression,
+            })
     }
 }
 
@@ -116,12 +109,16 @@ where
         {
             let input = obj.fill_buf()?;
             eof = input.is_empty();
-            let before_out = data.total_out;
-            let before_in = data.total_in;
-            let flush = if eof { MZFlush::Finish } else { MZFlush::None };
+            let before_out = data.total_out();
+            let before_in = data.total_in();
+            let flush = if eof {
+                FlushDecompress::Finish
+            } else {
+                FlushDecompress::None
+            };
             ret = data.decompress(input, dst, flush);
-            read = (data.total_out - before_out) as usize;
-            consumed = (data.total_in - before_in) as usize;
+            read = (data.total_out() - before_out) as usize;
+            consumed = (data.total_in() - before_in) as usize;
         }
         obj.consume(consumed);
 
@@ -131,6 +128,13 @@ where
             // return that 0 bytes of data have been read then it will
             // be interpreted as EOF.
             Ok(Status::Ok) | Ok(Status::BufError) if read == 0 && !eof && !dst.is_empty() => continue,
+            // Not all backends fail if the input ends before the stream does, so we have to.
+            Ok(Status::Ok) | Ok(Status::BufError) if read == 0 && eof && !dst.is_empty() => {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "deflate stream ended prematurely",
+                ))
+            }
             Ok(Status::Ok) | Ok(Status::BufError) | Ok(Status::StreamEnd) => return Ok(read),
 
             Err(..) => return Err(io::Error::new(io::ErrorKind::InvalidInput, "corrupt deflate stream")),
@@ -159,4 +163,15 @@ mod tests {
         assert!(bytes.next().is_none());
         Ok(())
     }
+
+    #[test]
+    fn truncated_stream_is_an_error() -> Result<(), Box<dyn std::error::Error>> {
+        let compressed = std::fs::read(fixture_path("objects/37/d4e6c5c48ba0d245164c4e10d5f41140cab980"))?;
+        let mut out = Vec::new();
+        let err = InflateReader::from_read(&compressed[..compressed.len() / 2])
+            .read_to_end(&mut out)
+            .expect_err("the stream is incomplete");
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        Ok(())
+    }
 }
@@ -1,33 +1,3 @@
-/// From Flate2
-/// Possible status results of compressing some data or successfully
-/// decompressing a block of data.
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
-enum Status {
-    /// Indicates success.
-    ///
-    /// Means that more input may be needed but isn't available
-    /// and/or there's more output to be written but the output buffer is full.
-    Ok,
-
-    /// Indicates that forward progress is not possible due to input or output
-    /// buffers being empty.
-    ///
-    /// For compression it means the input buffer needs some more data or the
-    /// output buffer needs to be freed up before trying again.
-    ///
-    /// For decompression this means that more input is needed to continue or
-    /// the output buffer isn't large enough to contain the result. The function
-    /// can be called again after fixing both.
-    BufError,
-
-    /// Indicates that all input has been consumed and all output bytes have
-    /// been written. Decompression/compression should not be called again.
-    ///
-    /// For decompression with zlib streams the adler-32 of the decompressed
-    /// data has also been verified.
-    StreamEnd,
-}
-
 pub mod inflate;
 #[doc(inline)]
 pub use inflate::InflateReader;
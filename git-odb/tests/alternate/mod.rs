@@ -0,0 +1,159 @@
+use crate::{fixture_path, hex_to_id};
+use git_odb::{alternate, compound, pack};
+use std::path::{Path, PathBuf};
+
+fn canonical(path: &str) -> PathBuf {
+    fixture_path(path).canonicalize().expect("fixture exists")
+}
+
+mod parse {
+    use git_odb::alternate::parse;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn comments_empty_lines_and_quoted_paths() -> Result<(), parse::Error> {
+        let base = Path::new("repo/objects");
+        assert_eq!(
+            parse::content(
+                b"# comment\n\n/absolute/objects\n../../other/objects\n\"with\\040space/\\\"quoted\\\"\\\\\"\n",
+                base
+            )?,
+            vec![
+                PathBuf::from("/absolute/objects"),
+                base.join("../../other/objects"),
+                base.join("with space/\"quoted\"\\"),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn illformed_quotes_are_rejected() {
+        for input in &[&b"\"unterminated"[..], b"\"trailing\" garbage", b"\"bad octal \\08\""] {
+            assert!(
+                matches!(parse::content(input, Path::new(".")), Err(parse::Error::Unquote(_))),
+                "{:?}",
+                input
+            );
+        }
+    }
+}
+
+#[test]
+fn resolve_recursively_and_ignore_cycles() -> Result<(), alternate::Error> {
+    assert_eq!(
+        alternate::resolve_with(fixture_path("alternate/repo-a/objects"), None)?,
+        vec![
+            canonical("alternate/repo-b/objects"),
+            canonical("alternate/repo-c/objects"),
+            canonical("compound/objects"),
+        ],
+        "alternates of alternates come first, and each directory is listed once"
+    );
+    assert_eq!(
+        alternate::resolve_with(fixture_path("alternate/repo-b/objects"), None)?,
+        vec![
+            canonical("alternate/repo-c/objects"),
+            canonical("alternate/repo-a/objects"),
+            canonical("compound/objects"),
+        ],
+        "the cycle back to repo-b is ignored"
+    );
+    Ok(())
+}
+
+#[test]
+fn resolve_with_additional_directories_first() -> Result<(), alternate::Error> {
+    assert_eq!(
+        alternate::resolve_with(
+            fixture_path("alternate/repo-c/objects"),
+            alternate::split_paths(
+                &std::env::join_paths(&[
+                    fixture_path("compound/objects"),
+                    fixture_path("alternate/repo-c/objects")
+                ])
+                .expect("valid paths")
+            )
+        )?,
+        vec![canonical("compound/objects")]
+    );
+    Ok(())
+}
+
+#[test]
+fn missing_alternates_are_skipped() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::TempDir::new()?;
+    std::fs::create_dir(dir.path().join("info"))?;
+    std::fs::write(dir.path().join("not-a-directory"), "")?;
+    std::fs::write(
+        dir.path().join("info").join("alternates"),
+        format!(
+            "does-not-exist\nnot-a-directory\n{}\n",
+            canonical("alternate/repo-c/objects").display()
+        ),
+    )?;
+    assert_eq!(
+        alternate::resolve_with(dir.path(), Some(dir.path().join("missing-too")))?,
+        vec![canonical("alternate/repo-c/objects")],
+        "like git, alternates which can't be used don't fail the repository"
+    );
+
+    let db = compound::Db::at(dir.path())?;
+    assert_eq!(db.alternates.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn compound_db_locates_objects_in_alternates() -> Result<(), Box<dyn std::error::Error>> {
+    let db = compound::Db::at(fixture_path("alternate/repo-a/objects"))?;
+    assert_eq!(
+        db.alternates
+            .iter()
+            .map(|alternate| alternate.loose.path.as_path())
+            .collect::<Vec<&Path>>(),
+        vec![
+            canonical("alternate/repo-b/objects"),
+            canonical("alternate/repo-c/objects"),
+            canonical("compound/objects"),
+        ]
+    );
+    assert!(db.alternates.iter().all(|alternate| alternate.alternates.is_empty()));
+
+    let mut cache = pack::cache::DecodeEntryLRU::default();
+    for (hex, expected) in &[
+        ("61780798228d17af2d34fce4cfbdf35556832472", &b"b\n"[..]),
+        ("f2ad6c76f0115a6ba5b00456a849810e7ec0af20", b"c\n"),
+    ] {
+        let mut buf = Vec::new();
+        let object = db
+            .locate(hex_to_id(hex).to_borrowed(), &mut buf, &mut cache)
+            .expect("present in an alternate")?;
+        assert_eq!(object.kind, git_object::Kind::Blob);
+        assert_eq!(object.data, *expected);
+    }
+
+    let mut buf = Vec::new();
+    let delta = db
+        .locate(
+            hex_to_id("6e288ae61ebb815a1abac73191b064d5b4fc9a50").to_borrowed(),
+            &mut buf,
+            &mut cache,
+        )
+        .expect("present in a pack of an alternate")?;
+    assert_eq!(
+        delta.kind,
+        git_object::Kind::Blob,
+        "ref-delta bases resolve in alternates too"
+    );
+
+    assert!(
+        db.locate(
+            hex_to_id("0000000000000000000000000000000000000001").to_borrowed(),
+            &mut buf,
+            &mut cache
+        )
+        .is_none(),
+        "objects that don't exist anywhere are not found"
+    );
+    Ok(())
+}
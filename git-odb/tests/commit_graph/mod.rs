@@ -0,0 +1,288 @@
+use crate::{fixture_path, hex_to_id};
+use git_features::progress;
+use git_odb::{commit_graph, pack};
+
+const PACK_INDEX: &str = "commit-graph/pack/pack-8c943562ece4fc72f24f648fdeadfbb4c32c563d.idx";
+const SINGLE: &str = "commit-graph/single/info";
+const SPLIT: &str = "commit-graph/split/info";
+
+const ROOT: &str = "42f3424c4acd6bdee0b9ae6c5ea78fc371fb8ba6";
+const MERGE: &str = "b3bd2ffac83da9e69d38d489c0959e37946655b2";
+const OCTOPUS: &str = "673146603107d7114b7652ef6a4eda3f4206c102";
+const HEAD: &str = "217f3a60ef3ccc28ea4b99664116099bad43760c";
+
+fn parent_ids(graph: &commit_graph::Graph, id: &str) -> Vec<String> {
+    graph
+        .commit_by_id(hex_to_id(id).to_borrowed())
+        .expect("commit in graph")
+        .iter_parents()
+        .map(|parent| graph.id_at(parent.expect("valid parent")).to_string())
+        .collect()
+}
+
+fn assert_commits(graph: &commit_graph::Graph) {
+    assert_eq!(graph.num_commits(), 9);
+
+    let root = graph.commit_by_id(hex_to_id(ROOT).to_borrowed()).expect("present");
+    assert_eq!(root.id(), hex_to_id(ROOT).to_borrowed());
+    assert_eq!(
+        root.root_tree_id(),
+        hex_to_id("c953cbf72793bf7a7cd60d87a668185076b1698a").to_borrowed()
+    );
+    assert_eq!(root.iter_parents().count(), 0);
+    assert_eq!(root.generation(), 1);
+    assert_eq!(root.committer_timestamp(), 1_600_003_600);
+
+    assert_eq!(
+        parent_ids(graph, MERGE),
+        vec![
+            "5b7e87a7735078311c47a85b685c270bf532f89d",
+            "5dfc556a0363bc7549665b45c340b69f4d8c1fc3"
+        ]
+    );
+    assert_eq!(
+        parent_ids(graph, OCTOPUS),
+        vec![
+            MERGE,
+            "f69085be3e29e865f79f66888a0d87d1e6ee47fd",
+            "93aa7e566a5c2655ea6f58767ed3051dca331890"
+        ]
+    );
+    assert_eq!(parent_ids(graph, HEAD), vec![OCTOPUS]);
+
+    let head = graph.commit_by_id(hex_to_id(HEAD).to_borrowed()).expect("present");
+    assert_eq!(head.generation(), 6);
+    assert_eq!(head.committer_timestamp(), 1_600_032_400);
+    assert_eq!(graph.commit_at(head.position()).id(), head.id());
+
+    assert!(graph
+        .lookup(hex_to_id("0000000000000000000000000000000000000000").to_borrowed())
+        .is_none());
+}
+
+fn verify(graph: &commit_graph::Graph) -> Result<commit_graph::verify::Outcome, Box<dyn std::error::Error>> {
+    let bundle = pack::Bundle::at(fixture_path(PACK_INDEX))?;
+    Ok(graph.verify_integrity(&bundle, progress::Discard)?)
+}
+
+#[test]
+fn single_file() -> Result<(), Box<dyn std::error::Error>> {
+    let graph = commit_graph::Graph::at(fixture_path(SINGLE))?;
+    assert_eq!(graph.files().len(), 1);
+    assert_eq!(graph.files()[0].version(), 1);
+    assert!(graph.files()[0].base_graph_ids().is_empty());
+    assert_commits(&graph);
+
+    assert_eq!(
+        verify(&graph)?,
+        commit_graph::verify::Outcome {
+            num_commits: 9,
+            num_files: 1,
+            max_generation: 6
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn split_chain() -> Result<(), Box<dyn std::error::Error>> {
+    let graph = commit_graph::Graph::at(fixture_path(SPLIT))?;
+    let files = graph.files();
+    assert_eq!(files.iter().map(|f| f.num_commits()).collect::<Vec<_>>(), vec![5, 4]);
+    assert_eq!(files[1].base_graph_ids(), &[files[0].checksum()]);
+    assert_commits(&graph);
+
+    let octopus = graph.commit_by_id(hex_to_id(OCTOPUS).to_borrowed()).expect("present");
+    assert!(
+        octopus.position() >= files[0].num_commits(),
+        "the octopus merge is in the second graph, but has a parent in the base graph"
+    );
+    assert_eq!(
+        verify(&graph)?,
+        commit_graph::verify::Outcome {
+            num_commits: 9,
+            num_files: 2,
+            max_generation: 6
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn positions_are_consistent_with_iteration() -> Result<(), Box<dyn std::error::Error>> {
+    let graph = commit_graph::Graph::at(fixture_path(SPLIT))?;
+    for (position, commit) in graph.iter_commits().enumerate() {
+        assert_eq!(commit.position() as usize, position);
+        assert_eq!(graph.lookup(commit.id()), Some(commit.position()));
+    }
+    Ok(())
+}
+
+#[test]
+fn chain_files_out_of_order_are_rejected() -> Result<(), Box<dyn std::error::Error>> {
+    let files = commit_graph::Graph::at(fixture_path(SPLIT))?
+        .files()
+        .iter()
+        .map(|f| commit_graph::File::at(f.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert!(matches!(
+        commit_graph::Graph::new(files.into_iter().rev().collect()),
+        Err(commit_graph::init::Error::ChainMismatch(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn missing_graph_is_reported() {
+    assert!(matches!(
+        commit_graph::Graph::at(fixture_path("commit-graph/pack")),
+        Err(commit_graph::init::Error::NotFound(_))
+    ));
+}
+
+#[test]
+fn verify_fails_if_commits_are_missing() -> Result<(), Box<dyn std::error::Error>> {
+    let graph = commit_graph::Graph::at(fixture_path(SINGLE))?;
+    let bundle = pack::Bundle::at(fixture_path("packs/pack-11fdfa9e156ab73caae3b6da867192221f2089c2.idx"))?;
+    assert!(matches!(
+        graph.verify_integrity(&bundle, progress::Discard),
+        Err(commit_graph::verify::Error::ObjectNotFound(_))
+    ));
+    Ok(())
+}
+
+mod write {
+    use super::{assert_commits, HEAD, MERGE, PACK_INDEX};
+    use crate::{fixture_path, hex_to_id};
+    use git_features::progress;
+    use git_odb::{
+        commit_graph::{self, write::Mode},
+        pack,
+    };
+    use std::fs;
+
+    #[test]
+    fn single_file_from_tips_or_all_commits_in_a_pack() -> Result<(), Box<dyn std::error::Error>> {
+        let bundle = pack::Bundle::at(fixture_path(PACK_INDEX))?;
+        let dir = tempfile::TempDir::new()?;
+        let outcome = commit_graph::Graph::write_to_info_dir(
+            dir.path(),
+            &bundle,
+            Some(hex_to_id(HEAD).to_borrowed()),
+            Mode::Single,
+            progress::Discard,
+        )?
+        .expect("single files are always written");
+        assert_eq!(outcome.num_commits, 9);
+        assert_eq!(outcome.num_base_commits, 0);
+
+        let graph = commit_graph::Graph::at(dir.path())?;
+        assert_eq!(graph.files()[0].checksum(), outcome.graph_hash);
+        assert_commits(&graph);
+        graph.verify_integrity(&bundle, progress::Discard)?;
+
+        let commits = commit_graph::write::commits_in_bundle(&bundle)?;
+        assert_eq!(commits.len(), 9);
+        let mut out = Vec::new();
+        let from_pack = commit_graph::File::write_from_tips(
+            &bundle,
+            commits.iter().map(|id| id.to_borrowed()),
+            None,
+            &mut out,
+            progress::Discard,
+        )?;
+        assert_eq!(from_pack, outcome, "the same commits yield the same graph");
+        Ok(())
+    }
+
+    #[test]
+    fn split_chain_grows_by_one_file_per_write() -> Result<(), Box<dyn std::error::Error>> {
+        let bundle = pack::Bundle::at(fixture_path(PACK_INDEX))?;
+        let dir = tempfile::TempDir::new()?;
+        let write = |tip: &str| {
+            commit_graph::Graph::write_to_info_dir(
+                dir.path(),
+                &bundle,
+                Some(hex_to_id(tip).to_borrowed()),
+                Mode::Split,
+                progress::Discard,
+            )
+        };
+        let base = write(MERGE)?.expect("new commits");
+        assert_eq!((base.num_commits, base.num_base_commits), (5, 0));
+        assert!(write(MERGE)?.is_none(), "all commits are in the chain already");
+        let top = write(HEAD)?.expect("new commits");
+        assert_eq!((top.num_commits, top.num_base_commits), (4, 5));
+
+        let graph = commit_graph::Graph::at(dir.path())?;
+        assert_eq!(
+            graph.files().iter().map(|f| f.checksum()).collect::<Vec<_>>(),
+            vec![base.graph_hash, top.graph_hash]
+        );
+        assert_commits(&graph);
+        assert_eq!(graph.verify_integrity(&bundle, progress::Discard)?.num_files, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn written_graphs_pass_the_verification_of_git() -> Result<(), Box<dyn std::error::Error>> {
+        for mode in &[Mode::Single, Mode::Split] {
+            let dir = tempfile::TempDir::new()?;
+            let git = |args: &[&str]| -> Result<(), Box<dyn std::error::Error>> {
+                let output = std::process::Command::new("git")
+                    .arg("--git-dir")
+                    .arg(dir.path())
+                    .args(args)
+                    .output()?;
+                assert!(
+                    output.status.success(),
+                    "git {:?} failed: {}",
+                    args,
+                    String::from_utf8_lossy(&output.stderr)
+                );
+                Ok(())
+            };
+            git(&["init", "--bare", "--quiet"])?;
+            let index_path = dir
+                .path()
+                .join("objects")
+                .join("pack")
+                .join(fixture_path(PACK_INDEX).file_name().expect("file name"));
+            fs::copy(fixture_path(PACK_INDEX), &index_path)?;
+            fs::copy(
+                fixture_path(PACK_INDEX).with_extension("pack"),
+                index_path.with_extension("pack"),
+            )?;
+
+            let bundle = pack::Bundle::at(index_path)?;
+            let info_dir = dir.path().join("objects").join("info");
+            for tip in &[MERGE, HEAD] {
+                commit_graph::Graph::write_to_info_dir(
+                    &info_dir,
+                    &bundle,
+                    Some(hex_to_id(tip).to_borrowed()),
+                    *mode,
+                    progress::Discard,
+                )?;
+            }
+            git(&["commit-graph", "verify"])?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn fails_if_a_tip_is_missing() -> Result<(), Box<dyn std::error::Error>> {
+        let bundle = pack::Bundle::at(fixture_path(PACK_INDEX))?;
+        assert!(matches!(
+            commit_graph::File::write_from_tips(
+                &bundle,
+                Some(hex_to_id("0000000000000000000000000000000000000001").to_borrowed()),
+                None,
+                Vec::new(),
+                progress::Discard,
+            ),
+            Err(commit_graph::write::Error::NotFound(_))
+        ));
+        Ok(())
+    }
+}
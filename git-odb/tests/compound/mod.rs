@@ -0,0 +1,522 @@
+use crate::{fixture_path, hex_to_id};
+use git_object::HashKind;
+use git_odb::{compound, pack, Write};
+
+const OBJECTS: &str = "compound/objects";
+
+fn db() -> compound::Db {
+    compound::Db::at(fixture_path(OBJECTS)).expect("valid objects directory")
+}
+
+/// Locate `hex` and return its kind, asserting the decoded data hashes to its id.
+fn locate(db: &compound::Db, hex: &str) -> git_object::Kind {
+    let id = hex_to_id(hex);
+    let mut buf = Vec::new();
+    let object = db
+        .locate(id.to_borrowed(), &mut buf, &mut pack::cache::DecodeEntryLRU::default())
+        .expect("object present")
+        .expect("object decodes");
+    assert_eq!(
+        git_odb::sink()
+            .write_buf(object.kind, object.data, HashKind::Sha1)
+            .expect("infallible"),
+        id,
+        "the decoded data is the one of the object"
+    );
+    object.kind
+}
+
+#[test]
+fn init() {
+    let db = db();
+    assert_eq!(db.loose.path, fixture_path(OBJECTS));
+    assert_eq!(db.bundles.len(), 2);
+}
+
+#[test]
+fn init_without_pack_directory() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::TempDir::new()?;
+    assert_eq!(compound::Db::at(dir.path())?.bundles.len(), 0);
+    Ok(())
+}
+
+#[test]
+fn locate_in_packs_and_loose_objects() {
+    let db = db();
+    assert_eq!(
+        locate(&db, "ef039b8b280d4a2111bc13bdccdc80924b59728a"),
+        git_object::Kind::Commit
+    );
+    assert_eq!(
+        locate(&db, "4f705dc1b10a2151e193a340f23bf9dc97dc3037"),
+        git_object::Kind::Commit
+    );
+    assert_eq!(
+        locate(&db, "37d4e6c5c48ba0d245164c4e10d5f41140cab980"),
+        git_object::Kind::Blob,
+        "loose objects are found as well"
+    );
+    assert!(db
+        .locate(
+            hex_to_id("37d4e6c5c48ba0d245164c4e10d5f41140cab989").to_borrowed(),
+            &mut Vec::new(),
+            &mut pack::cache::DecodeEntryNoop
+        )
+        .is_none());
+}
+
+#[test]
+fn ref_delta_bases_in_other_packs_are_resolved() {
+    let db = db();
+    let thin_pack = db
+        .bundles
+        .iter()
+        .find(|bundle| {
+            bundle
+                .pack
+                .path()
+                .ends_with("pack-2d246990b4c4f183178506d8416e48c44cebc922.pack")
+        })
+        .expect("thin pack present");
+    let delta = hex_to_id("6e288ae61ebb815a1abac73191b064d5b4fc9a50");
+    assert!(
+        thin_pack
+            .locate(delta.to_borrowed(), &mut Vec::new(), &mut pack::cache::DecodeEntryNoop)
+            .expect("present")
+            .is_err(),
+        "the base isn't in the same pack"
+    );
+    assert_eq!(
+        locate(&db, "6e288ae61ebb815a1abac73191b064d5b4fc9a50"),
+        git_object::Kind::Blob
+    );
+}
+
+#[test]
+fn header_matches_located_objects() -> Result<(), Box<dyn std::error::Error>> {
+    let db = db();
+    for hex in &[
+        "ef039b8b280d4a2111bc13bdccdc80924b59728a",
+        "4f705dc1b10a2151e193a340f23bf9dc97dc3037",
+        "6e288ae61ebb815a1abac73191b064d5b4fc9a50",
+        "37d4e6c5c48ba0d245164c4e10d5f41140cab980",
+    ] {
+        let id = hex_to_id(hex);
+        let mut buf = Vec::new();
+        let object = db
+            .locate(id.to_borrowed(), &mut buf, &mut pack::cache::DecodeEntryNoop)
+            .expect("present")?;
+        assert_eq!(
+            db.header(id.to_borrowed()).expect("present")?,
+            (object.kind, object.data.len() as u64),
+            "{}",
+            hex
+        );
+    }
+    assert!(db
+        .header(hex_to_id("37d4e6c5c48ba0d245164c4e10d5f41140cab989").to_borrowed())
+        .is_none());
+    Ok(())
+}
+
+#[test]
+fn stream_matches_located_objects() -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::Read;
+    let db = db();
+    for hex in &[
+        "ef039b8b280d4a2111bc13bdccdc80924b59728a",
+        "4f705dc1b10a2151e193a340f23bf9dc97dc3037",
+        "6e288ae61ebb815a1abac73191b064d5b4fc9a50",
+        "37d4e6c5c48ba0d245164c4e10d5f41140cab980",
+    ] {
+        let id = hex_to_id(hex);
+        let mut buf = Vec::new();
+        let object = db
+            .locate(id.to_borrowed(), &mut buf, &mut pack::cache::DecodeEntryNoop)
+            .expect("present")?;
+        let mut stream = db
+            .stream(id.to_borrowed(), &mut pack::cache::DecodeEntryNoop)
+            .expect("present")?;
+        assert_eq!(
+            (stream.kind(), stream.size()),
+            (object.kind, object.data.len() as u64),
+            "{}",
+            hex
+        );
+        let mut streamed = Vec::new();
+        stream.read_to_end(&mut streamed)?;
+        assert_eq!(streamed, object.data, "{}", hex);
+    }
+    assert!(db
+        .stream(
+            hex_to_id("37d4e6c5c48ba0d245164c4e10d5f41140cab989").to_borrowed(),
+            &mut pack::cache::DecodeEntryNoop
+        )
+        .is_none());
+    Ok(())
+}
+
+#[test]
+fn write_goes_to_loose_objects() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::TempDir::new()?;
+    let db = compound::Db::at(dir.path())?;
+    let id = db.write_buf(git_object::Kind::Blob, b"hi there\n", HashKind::Sha1)?;
+    assert_eq!(id, hex_to_id("37d4e6c5c48ba0d245164c4e10d5f41140cab980"));
+    assert!(db.loose.locate(id.to_borrowed()).is_some());
+    assert_eq!(
+        locate(&db, "37d4e6c5c48ba0d245164c4e10d5f41140cab980"),
+        git_object::Kind::Blob
+    );
+    Ok(())
+}
+
+mod ref_delta_cycles {
+    use crate::hex_to_id;
+    use git_features::hash::{crc32, Sha1};
+    use git_object::owned;
+    use git_odb::{compound, pack, CompressionLevel};
+    use std::{io::Read, path::Path};
+
+    /// Write a pack holding nothing but a `RefDelta` entry for `id` whose base is `base_id`, along with its index.
+    fn write_ref_delta_pack(
+        pack_dir: &Path,
+        id: &owned::Id,
+        base_id: &owned::Id,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut delta = Vec::new();
+        pack::data::delta::encode(b"base", b"object", &mut delta);
+        let entry = pack::data::output::Entry::from_delta(
+            id.clone(),
+            git_object::Kind::Blob,
+            0,
+            &delta,
+            CompressionLevel::default(),
+        )?;
+
+        let mut pack = b"PACK".to_vec();
+        pack.extend_from_slice(&2u32.to_be_bytes());
+        pack.extend_from_slice(&1u32.to_be_bytes());
+        let pack_offset = pack.len();
+        pack::data::Header::RefDelta {
+            base_id: Box::new(base_id.clone()),
+        }
+        .to_write(delta.len() as u64, &mut pack)?;
+        pack.extend_from_slice(&entry.compressed_data);
+        let entry_crc32 = crc32(&pack[pack_offset..]);
+        let pack_checksum = sha1(&pack);
+        pack.extend_from_slice(&pack_checksum);
+
+        let mut index = b"\xfftOc".to_vec();
+        index.extend_from_slice(&2u32.to_be_bytes());
+        for byte in 0..=u8::MAX {
+            let count: u32 = if byte >= id.as_slice()[0] { 1 } else { 0 };
+            index.extend_from_slice(&count.to_be_bytes());
+        }
+        index.extend_from_slice(id.as_slice());
+        index.extend_from_slice(&entry_crc32.to_be_bytes());
+        index.extend_from_slice(&(pack_offset as u32).to_be_bytes());
+        index.extend_from_slice(&pack_checksum);
+        let index_checksum = sha1(&index);
+        index.extend_from_slice(&index_checksum);
+
+        let name = format!("pack-{}", id);
+        std::fs::write(pack_dir.join(&name).with_extension("pack"), pack)?;
+        std::fs::write(pack_dir.join(name).with_extension("idx"), index)?;
+        Ok(())
+    }
+
+    fn sha1(data: &[u8]) -> [u8; 20] {
+        let mut hasher = Sha1::default();
+        hasher.update(data);
+        hasher.digest()
+    }
+
+    #[test]
+    fn objects_in_different_packs_which_are_each_others_base_are_an_error() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::TempDir::new()?;
+        let pack_dir = dir.path().join("pack");
+        std::fs::create_dir(&pack_dir)?;
+        let a = hex_to_id("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa");
+        let b = hex_to_id("bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb");
+        write_ref_delta_pack(&pack_dir, &a, &b)?;
+        write_ref_delta_pack(&pack_dir, &b, &a)?;
+
+        let db = compound::Db::at(dir.path())?;
+        assert!(matches!(
+            db.locate(a.to_borrowed(), &mut Vec::new(), &mut pack::cache::DecodeEntryNoop),
+            Some(Err(compound::locate::Error::DeltaBaseDepthExceeded(_)))
+        ));
+        assert!(matches!(
+            db.header(a.to_borrowed()),
+            Some(Err(compound::locate::Error::DeltaBaseDepthExceeded(_)))
+        ));
+        assert!(matches!(
+            db.stream(b.to_borrowed(), &mut pack::cache::DecodeEntryNoop)
+                .map(|res| res.map(|mut stream| stream.read_to_end(&mut Vec::new()))),
+            Some(Err(compound::locate::Error::DeltaBaseDepthExceeded(_)))
+        ));
+        Ok(())
+    }
+}
+
+mod object_cache {
+    use crate::{compound::db, hex_to_id};
+    use git_odb::{compound, pack};
+
+    #[test]
+    fn repeated_lookups_of_packed_and_loose_objects_are_served_from_the_cache() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let db = db().with_object_cache(64 * 1024);
+        let ids = [
+            hex_to_id("4f705dc1b10a2151e193a340f23bf9dc97dc3037"),
+            hex_to_id("6e288ae61ebb815a1abac73191b064d5b4fc9a50"),
+            hex_to_id("37d4e6c5c48ba0d245164c4e10d5f41140cab980"),
+        ];
+        let mut first = Vec::new();
+        for id in &ids {
+            let mut buf = Vec::new();
+            let object = db
+                .locate(id.to_borrowed(), &mut buf, &mut pack::cache::DecodeEntryNoop)
+                .expect("present")?;
+            first.push((object.kind, object.data.to_owned()));
+        }
+        let cache = db.object_cache.as_ref().expect("cache set");
+        assert_eq!(cache.statistics().hits, 0);
+        assert_eq!(
+            cache.num_entries(),
+            ids.len() + 1,
+            "the base of the ref-delta in another pack was looked up and cached as well"
+        );
+
+        for (id, expected) in ids.iter().zip(first) {
+            let mut buf = Vec::new();
+            let object = db
+                .locate(id.to_borrowed(), &mut buf, &mut pack::cache::DecodeEntryNoop)
+                .expect("present")?;
+            assert_eq!((object.kind, object.data.to_owned()), expected);
+        }
+        assert_eq!(cache.statistics().hits, ids.len() as u64);
+        Ok(())
+    }
+
+    #[test]
+    fn least_recently_used_objects_are_evicted_to_stay_within_capacity() {
+        let cache = compound::ObjectCache::new(10);
+        let (a, b, c) = (
+            hex_to_id("4f705dc1b10a2151e193a340f23bf9dc97dc3037"),
+            hex_to_id("6e288ae61ebb815a1abac73191b064d5b4fc9a50"),
+            hex_to_id("37d4e6c5c48ba0d245164c4e10d5f41140cab980"),
+        );
+        cache.put(a.to_borrowed(), git_object::Kind::Blob, b"hello");
+        cache.put(b.to_borrowed(), git_object::Kind::Tree, b"world");
+        let mut buf = Vec::new();
+        assert_eq!(cache.get(a.to_borrowed(), &mut buf), Some(git_object::Kind::Blob));
+        assert_eq!(buf, b"hello");
+
+        cache.put(c.to_borrowed(), git_object::Kind::Commit, b"!");
+        assert!(
+            cache.get(b.to_borrowed(), &mut buf).is_none(),
+            "b was used least recently"
+        );
+        assert!(cache.get(a.to_borrowed(), &mut buf).is_some());
+        assert!(cache.bytes_used() <= cache.capacity());
+
+        cache.put(b.to_borrowed(), git_object::Kind::Tree, b"way too large");
+        assert!(
+            cache.get(b.to_borrowed(), &mut buf).is_none(),
+            "objects larger than the cache aren't stored"
+        );
+        assert_eq!(cache.num_entries(), 2);
+    }
+}
+
+mod repack {
+    use crate::{
+        compound::{locate, OBJECTS},
+        fixture_path,
+    };
+    use git_features::progress;
+    use git_object::owned;
+    use git_odb::{
+        compound::{self, repack},
+        pack,
+    };
+    use std::{fs, path::Path};
+
+    fn copy_recursively(from: &Path, to: &Path) -> std::io::Result<()> {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                copy_recursively(&entry.path(), &to.join(entry.file_name()))?;
+            } else {
+                fs::copy(entry.path(), to.join(entry.file_name()))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn db_in_tempdir() -> Result<(tempfile::TempDir, compound::Db), Box<dyn std::error::Error>> {
+        let dir = tempfile::TempDir::new()?;
+        copy_recursively(&fixture_path(OBJECTS), dir.path())?;
+        let db = compound::Db::at(dir.path())?;
+        Ok((dir, db))
+    }
+
+    fn all_ids(db: &compound::Db) -> Result<Vec<owned::Id>, Box<dyn std::error::Error>> {
+        let mut ids = db.loose.iter().collect::<Result<Vec<_>, _>>()?;
+        for bundle in &db.bundles {
+            ids.extend(bundle.index.iter()?.map(|e| e.oid));
+        }
+        ids.sort();
+        ids.dedup();
+        Ok(ids)
+    }
+
+    #[test]
+    fn all_objects_are_consolidated_into_one_pack_and_redundant_ones_are_deleted(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let (dir, db) = db_in_tempdir()?;
+        let ids = all_ids(&db)?;
+        let old_packs: Vec<_> = db.bundles.iter().map(|b| b.pack.path().to_owned()).collect();
+
+        let outcome = db.repack(
+            progress::Discard,
+            repack::Options {
+                delete_redundant: true,
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(outcome.num_objects as usize, ids.len());
+        assert!(
+            outcome.num_reused_deltas > 0,
+            "deltas whose base precedes them are copied"
+        );
+        assert_eq!(
+            outcome.num_copied_entries,
+            outcome.num_objects - 1,
+            "all packed objects are copied, only the loose one is compressed"
+        );
+        assert_eq!(outcome.num_removed_loose_objects, 1);
+        assert_eq!(outcome.removed_packs, old_packs);
+        for path in &old_packs {
+            assert!(!path.exists());
+            assert!(!path.with_extension("idx").exists());
+        }
+        let write = outcome.write.expect("a pack was written");
+        assert!(write.data_path.expect("written to directory").is_file());
+        assert!(write.index_path.expect("written to directory").is_file());
+
+        let db = compound::Db::at(dir.path())?;
+        assert_eq!(db.bundles.len(), 1, "only the new pack remains");
+        assert_eq!(db.loose.iter().count(), 0, "there are no loose objects anymore");
+        assert_eq!(all_ids(&db)?, ids);
+        for id in &ids {
+            locate(&db, &id.to_string());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn nothing_is_deleted_by_default_and_deltas_can_be_resolved() -> Result<(), Box<dyn std::error::Error>> {
+        let (dir, db) = db_in_tempdir()?;
+        let ids = all_ids(&db)?;
+        let outcome = db.repack(
+            progress::Discard,
+            repack::Options {
+                reuse_deltas: false,
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(outcome.num_reused_deltas, 0, "all objects are written undeltified");
+        assert_eq!(
+            outcome.num_copied_entries,
+            outcome.num_objects - 2,
+            "packed objects are still copied unless they are deltas, and the loose object is compressed"
+        );
+        assert_eq!(outcome.num_removed_loose_objects, 0);
+        assert!(outcome.removed_packs.is_empty());
+
+        let db = compound::Db::at(dir.path())?;
+        assert_eq!(db.bundles.len(), 3, "the new pack is added to the existing ones");
+        assert_eq!(db.loose.iter().count(), 1);
+        let new_bundle = &db.bundles[0];
+        assert_eq!(new_bundle.index.num_objects() as usize, ids.len());
+        assert!(
+            new_bundle.index.iter()?.all(|e| new_bundle
+                .pack
+                .entry(e.pack_offset)
+                .expect("valid entry")
+                .header
+                .is_base()),
+            "the newest pack is the one we just wrote"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn kept_packs_are_left_alone_and_the_multi_pack_index_is_deleted() -> Result<(), Box<dyn std::error::Error>> {
+        let (dir, db) = db_in_tempdir()?;
+        let ids = all_ids(&db)?;
+        let kept_pack = db.bundles[0].pack.path().to_owned();
+        fs::write(kept_pack.with_extension("keep"), b"")?;
+        let multi_index_path = dir.path().join("pack").join(pack::multi_index::FILE_NAME);
+        pack::multi_index::File::write_from_indices(
+            db.bundles
+                .iter()
+                .map(|b| pack::index::File::at(b.index.path()))
+                .collect::<Result<_, _>>()?,
+            fs::File::create(&multi_index_path)?,
+            progress::Discard,
+        )?;
+
+        let outcome = db.repack(
+            progress::Discard,
+            repack::Options {
+                delete_redundant: true,
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(outcome.removed_packs, vec![db.bundles[1].pack.path().to_owned()]);
+        assert!(outcome.removed_multi_index);
+        assert!(!multi_index_path.exists(), "it would refer to the removed pack");
+        for extension in &["keep", "pack", "idx"] {
+            assert!(
+                kept_pack.with_extension(extension).is_file(),
+                "kept packs stay as they are"
+            );
+        }
+        let kept_index = &db.bundles[0].index;
+        let write = outcome.write.expect("a pack was written");
+        let new_bundle = pack::Bundle::at(write.index_path.expect("written to directory"))?;
+        assert!(
+            new_bundle
+                .index
+                .iter()?
+                .all(|e| kept_index.lookup(e.oid.to_borrowed()).is_none()),
+            "objects of kept packs are not repacked"
+        );
+
+        let db = compound::Db::at(dir.path())?;
+        assert_eq!(db.bundles.len(), 2, "the kept pack and the new one remain");
+        assert_eq!(db.loose.iter().count(), 0);
+        assert_eq!(all_ids(&db)?, ids, "no object was lost");
+        Ok(())
+    }
+
+    #[test]
+    fn empty_databases_produce_no_pack() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::TempDir::new()?;
+        let outcome = compound::Db::at(dir.path())?.repack(
+            progress::Discard,
+            repack::Options {
+                delete_redundant: true,
+                ..Default::default()
+            },
+        )?;
+        assert!(outcome.write.is_none());
+        assert_eq!(outcome.num_objects, 0);
+        assert!(!dir.path().join("pack").exists());
+        Ok(())
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{fixture_path, hex_to_id};
-use git_object::owned;
+use git_object::{borrowed, owned};
 use git_odb::loose::{self, Db};
 use pretty_assertions::assert_eq;
 
@@ -25,11 +25,45 @@ fn iter() {
     oids.sort();
     assert_eq!(oids, object_ids())
 }
-pub fn locate_oid(id: owned::Id) -> loose::Object {
-    ldb()
-        .locate(id.to_borrowed())
-        .expect("id present")
-        .expect("read success")
+#[test]
+fn header_matches_locate() -> Result<(), Box<dyn std::error::Error>> {
+    let db = ldb();
+    for id in object_ids() {
+        let object = locate_oid(id.to_borrowed());
+        assert_eq!(
+            db.header(id.to_borrowed()).expect("id present")?,
+            (object.kind, object.size as u64)
+        );
+    }
+    assert!(db
+        .header(hex_to_id("ffffffffffffffffffffffffffffffffffffffff").to_borrowed())
+        .is_none());
+    Ok(())
+}
+
+#[test]
+fn stream_matches_locate() -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::Read;
+    let db = ldb();
+    for id in object_ids() {
+        let mut object = locate_oid(id.to_borrowed());
+        let mut expected = Vec::new();
+        object.stream()?.read_to_end(&mut expected)?;
+
+        let (kind, size, mut stream) = db.stream(id.to_borrowed()).expect("id present")?;
+        assert_eq!((kind, size), (object.kind, object.size as u64));
+        let mut actual = Vec::new();
+        stream.read_to_end(&mut actual)?;
+        assert_eq!(actual, expected);
+    }
+    assert!(db
+        .stream(hex_to_id("ffffffffffffffffffffffffffffffffffffffff").to_borrowed())
+        .is_none());
+    Ok(())
+}
+
+pub fn locate_oid(id: borrowed::Id) -> loose::Object {
+    ldb().locate(id).expect("id present").expect("read success")
 }
 
 mod write {
@@ -44,7 +78,7 @@ mod write {
         let db = loose::Db::at(dir.path());
 
         for oid in object_ids() {
-            let mut obj = locate_oid(oid.clone());
+            let mut obj = locate_oid(oid.to_borrowed());
             let actual = db.write(&obj.decode()?.into(), HashKind::Sha1)?;
             assert_eq!(actual, oid);
             assert_eq!(
@@ -62,6 +96,28 @@ mod write {
         }
         Ok(())
     }
+
+    #[test]
+    fn write_without_compression() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let db = loose::Db::at(dir.path()).with_compression(git_odb::CompressionLevel::NONE);
+
+        for oid in object_ids() {
+            let mut obj = locate_oid(oid.to_borrowed());
+            let actual = db.write(&obj.decode()?.into(), HashKind::Sha1)?;
+            assert_eq!(actual, oid);
+            assert_eq!(
+                db.locate(oid.to_borrowed()).expect("id present")?.decode()?,
+                obj.decode()?
+            );
+            let hex = oid.to_hex_string();
+            assert!(
+                std::fs::metadata(dir.path().join(&hex[..2]).join(&hex[2..]))?.len() > obj.size as u64,
+                "stored blocks are never smaller than the object"
+            );
+        }
+        Ok(())
+    }
 }
 
 mod locate {
@@ -77,7 +133,7 @@ mod locate {
     use std::io::Read;
 
     fn locate(hex: &str) -> loose::Object {
-        locate_oid(hex_to_id(hex))
+        locate_oid(hex_to_id(hex).to_borrowed())
     }
 
     #[test]
@@ -22,6 +22,11 @@ pub fn fixture_path(path: &str) -> PathBuf {
     PathBuf::from("tests").join("fixtures").join(path)
 }
 
+mod alternate;
+mod commit_graph;
+mod compound;
 mod loose;
 mod pack;
+mod prefix;
+mod sha256;
 mod sink;
@@ -0,0 +1,291 @@
+use crate::{fixture_path, hex_to_id};
+use git_odb::pack::{self, bitmap};
+use std::collections::BTreeSet;
+
+const BITMAP_PACK_INDEX: &str = "packs/bitmap/pack-e4eabb62f12b1cb64124003b120034ebae2c8ced.idx";
+const HEAD: &str = "eb0d5788bfa9e04d697e674d2e72396d8fb646d5";
+const HEAD_3: &str = "7cc0eb4ab767649eea7095fea4aa9fbf798ea507";
+const HEAD_5: &str = "959c3a0bb45535c5857e2a609eb46df04315580e";
+const TAG: &str = "b956dafafbef46d65a2bfbd206804aae4a88aeb0";
+
+fn bundle_and_bitmap() -> Result<(pack::Bundle, bitmap::File), Box<dyn std::error::Error>> {
+    let bundle = pack::Bundle::at(fixture_path(BITMAP_PACK_INDEX))?;
+    let bitmap = bundle.bitmap().expect("bitmap next to pack")?;
+    Ok((bundle, bitmap))
+}
+
+#[test]
+fn init() -> Result<(), Box<dyn std::error::Error>> {
+    let (bundle, bitmap) = bundle_and_bitmap()?;
+    assert_eq!(bitmap.version(), 1);
+    assert!(bitmap.is_full_dag());
+    assert_eq!(bitmap.pack_checksum(), bundle.pack.checksum());
+    assert_eq!(bitmap.num_entries(), 6, "every commit has a bitmap");
+
+    let other = pack::Bundle::at(fixture_path(crate::pack::SMALL_PACK_INDEX))?;
+    assert!(other.bitmap().is_none(), "not every pack has a bitmap");
+    Ok(())
+}
+
+#[test]
+fn type_bitmaps() -> Result<(), Box<dyn std::error::Error>> {
+    let (bundle, bitmap) = bundle_and_bitmap()?;
+    let mut all = bitmap::Bitmap::default();
+    for (ewah, expected_count) in &[
+        (bitmap.commits(), 6),
+        (bitmap.trees(), 12),
+        (bitmap.blobs(), 12),
+        (bitmap.tags(), 1),
+    ] {
+        let bits = ewah.decompress()?;
+        assert_eq!(bits.count_ones(), *expected_count);
+        let mut overlap = bits.clone();
+        overlap.and(&all);
+        assert_eq!(overlap.count_ones(), 0, "each object has exactly one type");
+        all.or(&bits);
+    }
+    assert_eq!(all.count_ones(), bundle.index.num_objects() as usize);
+    Ok(())
+}
+
+#[test]
+fn reachable_objects() -> Result<(), Box<dyn std::error::Error>> {
+    let (bundle, bitmap) = bundle_and_bitmap()?;
+    let reachable = |commits: &[&str]| -> Result<BTreeSet<_>, bitmap::Error> {
+        let ids: Vec<_> = commits.iter().map(|hex| hex_to_id(hex)).collect();
+        Ok(bundle
+            .reachable_objects(&bitmap, ids.iter().map(|id| id.to_borrowed()))?
+            .into_iter()
+            .collect())
+    };
+
+    assert_eq!(
+        reachable(&[HEAD_5])?,
+        [
+            HEAD_5,
+            "0e2000649e73b0421aa00047ad357beed4303c81",
+            "94ab4029428e158d2568affd41b27ca685e7edfb",
+            "a0054e492840f572e48a3cb791d2e083afaf08f6",
+            "b0707d40cba94799391bc9a966823d6c78d91f7f"
+        ]
+        .iter()
+        .map(|hex| hex_to_id(hex))
+        .collect(),
+        "the same objects as 'git rev-list --objects'"
+    );
+    assert_eq!(reachable(&[HEAD_3])?.len(), 15);
+    assert_eq!(reachable(&[HEAD_5, HEAD_3])?.len(), 15, "ancestors don't add objects");
+    assert_eq!(reachable(&[HEAD])?.len(), 30, "everything but the tag object");
+
+    assert!(matches!(reachable(&[TAG]), Err(bitmap::Error::NoBitmap(_))));
+    assert!(matches!(
+        reachable(&["ffffffffffffffffffffffffffffffffffffffff"]),
+        Err(bitmap::Error::NotFound(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn name_hash_cache() -> Result<(), Box<dyn std::error::Error>> {
+    let (bundle, bitmap) = bundle_and_bitmap()?;
+    let blob = hex_to_id("a0054e492840f572e48a3cb791d2e083afaf08f6");
+    let index_position = bundle.index.lookup(blob.to_borrowed()).expect("blob in pack");
+    assert_eq!(
+        bitmap.name_hash_at_index_position(index_position),
+        Some(pack::data::output::name_hash(b"d1/f"))
+    );
+    assert_eq!(bitmap.name_hash_at_index_position(bundle.index.num_objects()), None);
+    Ok(())
+}
+
+mod ewah {
+    use git_odb::pack::bitmap::{ewah, Bitmap};
+
+    fn round_trip(bitmap: &Bitmap) -> Result<ewah::Vec, Box<dyn std::error::Error>> {
+        let compressed = ewah::Vec::compress(bitmap);
+        let mut buf = Vec::new();
+        compressed.write_to(&mut buf)?;
+        let (decoded, rest) = ewah::Vec::from_bytes(&buf)?;
+        assert!(rest.is_empty(), "all bytes are consumed");
+        assert_eq!(decoded, compressed);
+        assert_eq!(
+            decoded.decompress()?.iter_ones().collect::<Vec<_>>(),
+            bitmap.iter_ones().collect::<Vec<_>>()
+        );
+        Ok(decoded)
+    }
+
+    #[test]
+    fn compress_and_decompress() -> Result<(), Box<dyn std::error::Error>> {
+        assert_eq!(round_trip(&Bitmap::default())?.num_words(), 1, "just a run-length word");
+
+        let mut sparse = Bitmap::default();
+        for position in &[0, 1, 63, 64, 1000, 100_000] {
+            assert!(sparse.insert(*position));
+        }
+        assert!(!sparse.insert(1000), "bits are only set once");
+        let compressed = round_trip(&sparse)?;
+        assert_eq!(compressed.num_bits(), 100_001, "the last set bit determines the size");
+        assert!(compressed.num_words() < 10, "runs of zeroes are compressed");
+
+        let mut dense = Bitmap::default();
+        for position in (10..64 * 200).chain(64 * 300..64 * 300 + 7) {
+            dense.insert(position);
+        }
+        assert!(round_trip(&dense)?.num_words() < 10, "runs of ones are compressed");
+        Ok(())
+    }
+}
+
+mod write_from_bundle {
+    use super::{BITMAP_PACK_INDEX, HEAD, HEAD_3, HEAD_5, TAG};
+    use crate::{fixture_path, hex_to_id};
+    use git_features::progress;
+    use git_odb::pack::{self, bitmap};
+    use std::fs;
+
+    fn ones(bitmap: &bitmap::Bitmap) -> Vec<usize> {
+        bitmap.iter_ones().collect()
+    }
+
+    fn bundle_in_tempdir() -> Result<(tempfile::TempDir, pack::Bundle), Box<dyn std::error::Error>> {
+        let dir = tempfile::TempDir::new()?;
+        let index_path = dir.path().join("pack.idx");
+        fs::copy(fixture_path(BITMAP_PACK_INDEX), &index_path)?;
+        fs::copy(
+            fixture_path(BITMAP_PACK_INDEX).with_extension("pack"),
+            index_path.with_extension("pack"),
+        )?;
+        let bundle = pack::Bundle::at(index_path)?;
+        Ok((dir, bundle))
+    }
+
+    #[test]
+    fn produces_the_same_bitmaps_as_git() -> Result<(), Box<dyn std::error::Error>> {
+        let (_dir, bundle) = bundle_in_tempdir()?;
+        assert!(bundle.bitmap().is_none());
+        let tips = [hex_to_id(HEAD), hex_to_id(TAG)];
+        let outcome = bundle.write_bitmap(
+            tips.iter().map(|id| id.to_borrowed()),
+            progress::Discard,
+            bitmap::write::Options { commit_spacing: 1 },
+        )?;
+        assert_eq!(outcome.num_commits, 6);
+        assert_eq!(outcome.num_entries, 6, "every commit was selected");
+
+        let actual = bundle.bitmap().expect("bitmap was written")?;
+        let (_, expected) = super::bundle_and_bitmap()?;
+        assert!(actual.is_full_dag());
+        assert_eq!(actual.num_entries(), expected.num_entries());
+        for (actual, expected) in &[
+            (actual.commits(), expected.commits()),
+            (actual.trees(), expected.trees()),
+            (actual.blobs(), expected.blobs()),
+            (actual.tags(), expected.tags()),
+        ] {
+            assert_eq!(ones(&actual.decompress()?), ones(&expected.decompress()?));
+        }
+        for entry in expected.entries() {
+            assert_eq!(
+                ones(&actual.bitmap_at_index_position(entry.index_position).expect("entry")?),
+                ones(
+                    &expected
+                        .bitmap_at_index_position(entry.index_position)
+                        .expect("entry")?
+                )
+            );
+        }
+        for index_position in 0..bundle.index.num_objects() {
+            assert_eq!(
+                actual.name_hash_at_index_position(index_position),
+                expected.name_hash_at_index_position(index_position),
+                "names are derived from the paths at which objects are first seen, just like git does"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn selects_tips_and_spaced_out_commits() -> Result<(), Box<dyn std::error::Error>> {
+        let (_dir, bundle) = bundle_in_tempdir()?;
+        let tips = [hex_to_id(HEAD_5), hex_to_id(HEAD)];
+        let outcome = bundle.write_bitmap(
+            tips.iter().map(|id| id.to_borrowed()),
+            progress::Discard,
+            bitmap::write::Options { commit_spacing: 3 },
+        )?;
+        assert_eq!(outcome.num_commits, 6);
+        assert_eq!(
+            outcome.num_entries, 3,
+            "both tips and the commit three commits before HEAD"
+        );
+
+        let bitmap = bundle.bitmap().expect("bitmap was written")?;
+        assert_eq!(
+            bundle
+                .reachable_objects(&bitmap, std::iter::once(hex_to_id(HEAD).to_borrowed()))?
+                .len(),
+            30
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn written_bitmaps_pass_the_test_of_git() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::TempDir::new()?;
+        let git = |args: &[&str]| -> Result<(), Box<dyn std::error::Error>> {
+            let output = std::process::Command::new("git")
+                .arg("--git-dir")
+                .arg(dir.path())
+                .args(args)
+                .output()?;
+            assert!(
+                output.status.success(),
+                "git {:?} failed: {}",
+                args,
+                String::from_utf8_lossy(&output.stderr)
+            );
+            Ok(())
+        };
+        git(&["init", "--bare", "--quiet"])?;
+        let index_path = dir
+            .path()
+            .join("objects")
+            .join("pack")
+            .join(fixture_path(BITMAP_PACK_INDEX).file_name().expect("file name"));
+        fs::copy(fixture_path(BITMAP_PACK_INDEX), &index_path)?;
+        fs::copy(
+            fixture_path(BITMAP_PACK_INDEX).with_extension("pack"),
+            index_path.with_extension("pack"),
+        )?;
+        git(&["update-ref", "refs/heads/main", HEAD])?;
+        git(&["update-ref", "refs/tags/tag", TAG])?;
+
+        let bundle = pack::Bundle::at(index_path)?;
+        let tips = [hex_to_id(HEAD), hex_to_id(TAG)];
+        bundle.write_bitmap(
+            tips.iter().map(|id| id.to_borrowed()),
+            progress::Discard,
+            bitmap::write::Options { commit_spacing: 3 },
+        )?;
+        for commit in &[HEAD, HEAD_3] {
+            git(&["rev-list", "--test-bitmap", commit])?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn fails_if_a_tip_is_not_in_the_pack() -> Result<(), Box<dyn std::error::Error>> {
+        let (_dir, bundle) = bundle_in_tempdir()?;
+        let tip = hex_to_id("ffffffffffffffffffffffffffffffffffffffff");
+        assert!(matches!(
+            bundle.write_bitmap(
+                std::iter::once(tip.to_borrowed()),
+                progress::Discard,
+                Default::default()
+            ),
+            Err(bitmap::write::Error::TipNotFound(_))
+        ));
+        Ok(())
+    }
+}
@@ -24,7 +24,7 @@ mod locate {
                 let bundle = pack::Bundle::at(fixture_path(data_path))?;
 
                 let mut buf = Vec::new();
-                for entry in bundle.index.iter() {
+                for entry in bundle.index.iter()? {
                     let obj = bundle
                         .locate(entry.oid.to_borrowed(), &mut buf, &mut pack::cache::DecodeEntryNoop)
                         .expect("id present")?;
@@ -33,6 +33,55 @@ mod locate {
             }
             Ok(())
         }
+
+        #[test]
+        fn header_matches_decoded_object() -> Result<(), Box<dyn std::error::Error>> {
+            for (index_path, _data_path) in PACKS_AND_INDICES {
+                let bundle = pack::Bundle::at(fixture_path(index_path))?;
+                let mut buf = Vec::new();
+                let mut num_deltas = 0;
+                for entry in bundle.index.iter()? {
+                    let obj = bundle
+                        .locate(entry.oid.to_borrowed(), &mut buf, &mut pack::cache::DecodeEntryNoop)
+                        .expect("id present")?;
+                    assert_eq!(
+                        bundle.header(entry.oid.to_borrowed()).expect("id present")?,
+                        (obj.kind, obj.data.len() as u64)
+                    );
+                    num_deltas += bundle.pack.entry(entry.pack_offset)?.header.is_delta() as usize;
+                }
+                assert!(num_deltas > 0, "deltified entries are covered as well");
+            }
+            Ok(())
+        }
+
+        #[test]
+        fn stream_matches_decoded_object() -> Result<(), Box<dyn std::error::Error>> {
+            use std::io::Read;
+            for (index_path, _data_path) in PACKS_AND_INDICES {
+                let bundle = pack::Bundle::at(fixture_path(index_path))?;
+                let mut buf = Vec::new();
+                for entry in bundle.index.iter()? {
+                    let obj = bundle
+                        .locate(entry.oid.to_borrowed(), &mut buf, &mut pack::cache::DecodeEntryNoop)
+                        .expect("id present")?;
+                    let mut stream = bundle
+                        .stream(entry.oid.to_borrowed(), &mut pack::cache::DecodeEntryNoop)
+                        .expect("id present")?;
+                    assert_eq!((stream.kind(), stream.size()), (obj.kind, obj.data.len() as u64));
+
+                    let (mut streamed, mut chunk) = (Vec::new(), [0u8; 7]);
+                    loop {
+                        match stream.read(&mut chunk)? {
+                            0 => break,
+                            n => streamed.extend_from_slice(&chunk[..n]),
+                        }
+                    }
+                    assert_eq!(streamed, obj.data, "small reads yield the entire object");
+                }
+            }
+            Ok(())
+        }
     }
 
     #[test]
@@ -93,6 +142,7 @@ mod write_to_directory {
             pack_kind: pack::data::Kind::V2,
             index_path: None,
             data_path: None,
+            reverse_index_path: None,
         })
     }
 
@@ -112,19 +162,30 @@ mod write_to_directory {
     fn given_a_directory() -> Result<(), Box<dyn std::error::Error>> {
         let dir = TempDir::new()?;
         let mut res = write_pack(Some(&dir), SMALL_PACK)?;
-        let (index_path, data_path) = (res.index_path.take(), res.data_path.take());
+        let (index_path, data_path, reverse_index_path) = (
+            res.index_path.take(),
+            res.data_path.take(),
+            res.reverse_index_path.take(),
+        );
         assert_eq!(res, expected_outcome()?);
         let mut sorted_entries = fs::read_dir(&dir)?.filter_map(Result::ok).collect::<Vec<_>>();
         sorted_entries.sort_by_key(|e| e.file_name());
-        assert_eq!(sorted_entries.len(), 2, "we want a pack and the corresponding index");
+        assert_eq!(
+            sorted_entries.len(),
+            3,
+            "we want a pack and the corresponding index and reverse index"
+        );
 
-        let pack_hash = res.index.data_hash.to_sha1_hex_string();
+        let pack_hash = res.index.data_hash.to_hex_string();
         assert_eq!(file_name(&sorted_entries[0]), format!("{}.idx", pack_hash));
         assert_eq!(Some(sorted_entries[0].path()), index_path);
 
         assert_eq!(file_name(&sorted_entries[1]), format!("{}.pack", pack_hash));
         assert_eq!(Some(sorted_entries[1].path()), data_path);
 
+        assert_eq!(file_name(&sorted_entries[2]), format!("{}.rev", pack_hash));
+        assert_eq!(Some(sorted_entries[2].path()), reverse_index_path);
+
         res.index_path = index_path;
         assert!(res.to_bundle().transpose()?.is_some());
         Ok(())
@@ -144,12 +205,286 @@ mod write_to_directory {
             None,
             directory,
             progress::Discard,
+            None::<pack::Bundle>,
             bundle::write::Options {
                 thread_limit: None,
                 iteration_mode: pack::data::iter::Mode::Verify,
                 index_kind: pack::index::Kind::V2,
+                hash_kind: git_object::HashKind::Sha1,
+                compression: git_odb::CompressionLevel::default(),
             },
         )
         .map_err(Into::into)
     }
 }
+
+mod write_to_directory_thin {
+    use crate::{fixture_path, hex_to_id};
+    use git_features::progress;
+    use git_odb::pack::{self, bundle};
+    use std::fs;
+    use tempfile::TempDir;
+
+    const THIN_PACK: &str = "packs/thin/thin.pack";
+    const THIN_PACK_BASES_INDEX: &str = "packs/thin/pack-b7ae4363e891466b5b41fb574c20bae5fce78c02.idx";
+
+    fn options() -> bundle::write::Options {
+        bundle::write::Options {
+            thread_limit: None,
+            iteration_mode: pack::data::iter::Mode::Verify,
+            index_kind: pack::index::Kind::V2,
+            hash_kind: git_object::HashKind::Sha1,
+            compression: git_odb::CompressionLevel::default(),
+        }
+    }
+
+    #[test]
+    fn fails_without_base_object_lookup() -> Result<(), Box<dyn std::error::Error>> {
+        let res = pack::Bundle::write_to_directory(
+            fs::File::open(fixture_path(THIN_PACK))?,
+            None,
+            None::<&std::path::Path>,
+            progress::Discard,
+            None::<pack::Bundle>,
+            options(),
+        );
+        assert!(matches!(
+            res,
+            Err(bundle::write::Error::IndexWrite(
+                pack::index::write::Error::IteratorInvariantRefDeltaBase(_, _)
+            ))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn appends_missing_bases_to_make_the_pack_self_contained() -> Result<(), Box<dyn std::error::Error>> {
+        let bases = pack::Bundle::at(fixture_path(THIN_PACK_BASES_INDEX))?;
+        let dir = TempDir::new()?;
+        let outcome = pack::Bundle::write_to_directory(
+            fs::File::open(fixture_path(THIN_PACK))?,
+            None,
+            Some(&dir),
+            progress::Discard,
+            Some(&bases),
+            options(),
+        )?;
+        assert_eq!(outcome.index.num_objects, 4, "the single ref-delta base was added");
+
+        let bundle = outcome.to_bundle().expect("written to disk")?;
+        let base_position = bundle
+            .index
+            .lookup(hex_to_id("40f8c6f24d8e290e368178005a45d1903126faaf").to_borrowed())
+            .expect("base present");
+        assert_eq!(
+            bundle.index.pack_offset_at_index(base_position),
+            bundle.index.iter()?.map(|e| e.pack_offset).max(),
+            "the base is appended after all other entries"
+        );
+        assert_eq!(
+            bundle.pack.checksum(),
+            outcome.index.data_hash,
+            "the trailer is computed from the altered pack"
+        );
+        bundle.index.verify_integrity(
+            Some((
+                &bundle.pack,
+                pack::index::verify::Mode::Sha1CRC32Decode,
+                pack::index::traverse::Algorithm::DeltaTreeLookup,
+            )),
+            None,
+            progress::Discard.into(),
+            || pack::cache::DecodeEntryNoop,
+        )?;
+
+        let mut buf = Vec::new();
+        for (hex_id, kind) in &[
+            ("4f705dc1b10a2151e193a340f23bf9dc97dc3037", git_object::Kind::Commit),
+            ("6e288ae61ebb815a1abac73191b064d5b4fc9a50", git_object::Kind::Blob),
+            ("40f8c6f24d8e290e368178005a45d1903126faaf", git_object::Kind::Blob),
+        ] {
+            let id = hex_to_id(hex_id);
+            let object = bundle
+                .locate(id.to_borrowed(), &mut buf, &mut pack::cache::DecodeEntryNoop)
+                .expect("object present")?;
+            assert_eq!(object.kind, *kind);
+            object.verify_checksum(id.to_borrowed())?;
+        }
+        Ok(())
+    }
+
+    /// A pack written without support for offset deltas, whose ref deltas all have an undeltified base in the pack.
+    const REF_DELTA_PACK: &str = "packs/ref-delta/pack-8985ac818fbc232b5a3c31251b6df7fcf215c7e9.pack";
+
+    #[test]
+    fn bases_in_the_pack_are_not_looked_up() -> Result<(), Box<dyn std::error::Error>> {
+        let small_pack = pack::Bundle::at(fixture_path(crate::pack::SMALL_PACK_INDEX))?;
+        for lookup in &[None, Some(&small_pack)] {
+            let dir = TempDir::new()?;
+            let outcome = pack::Bundle::write_to_directory(
+                fs::File::open(fixture_path(REF_DELTA_PACK))?,
+                None,
+                Some(&dir),
+                progress::Discard,
+                *lookup,
+                options(),
+            )?;
+            assert_eq!(outcome.index.num_objects, 42, "no base was added");
+            assert_eq!(
+                outcome.index.data_hash,
+                hex_to_id("8985ac818fbc232b5a3c31251b6df7fcf215c7e9"),
+                "the pack is written as it was received"
+            );
+
+            let bundle = outcome.to_bundle().expect("written to disk")?;
+            bundle.index.verify_integrity(
+                Some((
+                    &bundle.pack,
+                    pack::index::verify::Mode::Sha1CRC32Decode,
+                    pack::index::traverse::Algorithm::DeltaTreeLookup,
+                )),
+                None,
+                progress::Discard.into(),
+                || pack::cache::DecodeEntryNoop,
+            )?;
+            assert!(
+                bundle
+                    .index
+                    .iter()?
+                    .map(|e| e.oid)
+                    .eq(small_pack.index.iter()?.map(|e| e.oid)),
+                "it contains the same objects as the pack it was created from"
+            );
+        }
+        Ok(())
+    }
+
+    /// A pack without offset deltas, whose ref deltas form chains and thus have deltified bases in the pack.
+    const REF_DELTA_CHAIN_PACK: &str = "packs/ref-delta/pack-779e17dec86979e3897b6c272d13a791e8a91bca.pack";
+
+    #[test]
+    fn deltified_bases_in_the_pack_are_not_appended() -> Result<(), Box<dyn std::error::Error>> {
+        let pack_bundle = pack::Bundle::at(fixture_path(REF_DELTA_CHAIN_PACK).with_extension("idx"))?;
+        for lookup in &[None, Some(&pack_bundle)] {
+            let dir = TempDir::new()?;
+            let outcome = pack::Bundle::write_to_directory(
+                fs::File::open(fixture_path(REF_DELTA_CHAIN_PACK))?,
+                None,
+                Some(&dir),
+                progress::Discard,
+                *lookup,
+                options(),
+            )?;
+            assert_eq!(outcome.index.num_objects, 15, "no base was added");
+            assert_eq!(
+                outcome.index.data_hash,
+                hex_to_id("779e17dec86979e3897b6c272d13a791e8a91bca"),
+                "the pack is written as it was received"
+            );
+
+            let bundle = outcome.to_bundle().expect("written to disk")?;
+            bundle.index.verify_integrity(
+                Some((
+                    &bundle.pack,
+                    pack::index::verify::Mode::Sha1CRC32Decode,
+                    pack::index::traverse::Algorithm::DeltaTreeLookup,
+                )),
+                None,
+                progress::Discard.into(),
+                || pack::cache::DecodeEntryNoop,
+            )?;
+            assert!(
+                bundle
+                    .index
+                    .iter()?
+                    .map(|e| (e.oid, e.pack_offset, e.crc32))
+                    .eq(pack_bundle.index.iter()?.map(|e| (e.oid, e.pack_offset, e.crc32))),
+                "the index is the same as the one created by git"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn empty_packs_are_written_with_and_without_lookup() -> Result<(), Box<dyn std::error::Error>> {
+        let mut empty_pack = pack::data::parse::encode_header(pack::data::Kind::V2, 0).to_vec();
+        let mut hash = git_features::hash::Sha1::default();
+        hash.update(&empty_pack);
+        let pack_hash = hash.digest();
+        empty_pack.extend_from_slice(&pack_hash);
+
+        let small_pack = pack::Bundle::at(fixture_path(crate::pack::SMALL_PACK_INDEX))?;
+        for lookup in &[None, Some(&small_pack)] {
+            let dir = TempDir::new()?;
+            let outcome = pack::Bundle::write_to_directory(
+                std::io::Cursor::new(empty_pack.clone()),
+                None,
+                Some(&dir),
+                progress::Discard,
+                *lookup,
+                options(),
+            )?;
+            assert_eq!(outcome.index.num_objects, 0);
+            assert_eq!(outcome.index.data_hash, git_object::owned::Id::from(pack_hash));
+            assert_eq!(
+                fs::read(outcome.data_path.as_ref().expect("written to disk"))?,
+                empty_pack,
+                "the pack is written as it was received"
+            );
+
+            let bundle = outcome.to_bundle().expect("written to disk")?;
+            assert_eq!(bundle.index.num_objects(), 0);
+            assert_eq!(bundle.pack.checksum(), outcome.index.data_hash);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn entry_headers_are_written_as_received() -> Result<(), Box<dyn std::error::Error>> {
+        let blob = pack::data::output::Entry::from_data(
+            git_object::owned::Id::null_sha1(),
+            &pack::Object {
+                kind: git_object::Kind::Blob,
+                data: b"hello",
+            },
+            git_odb::CompressionLevel::default(),
+        )?;
+        let mut pack_data = pack::data::parse::encode_header(pack::data::Kind::V2, 1).to_vec();
+        // A blob of 5 bytes whose size has a superfluous continuation byte, which is valid but not how git encodes it.
+        pack_data.extend_from_slice(&[0b1011_0101, 0]);
+        pack_data.extend_from_slice(&blob.compressed_data);
+        let mut hash = git_features::hash::Sha1::default();
+        hash.update(&pack_data);
+        let pack_hash = hash.digest();
+        pack_data.extend_from_slice(&pack_hash);
+
+        let small_pack = pack::Bundle::at(fixture_path(crate::pack::SMALL_PACK_INDEX))?;
+        let dir = TempDir::new()?;
+        let outcome = pack::Bundle::write_to_directory(
+            std::io::Cursor::new(pack_data.clone()),
+            None,
+            Some(&dir),
+            progress::Discard,
+            Some(&small_pack),
+            options(),
+        )?;
+        assert_eq!(
+            fs::read(outcome.data_path.as_ref().expect("written to disk"))?,
+            pack_data,
+            "the pack is written as it was received"
+        );
+
+        let bundle = outcome.to_bundle().expect("written to disk")?;
+        bundle.index.verify_integrity(
+            Some((
+                &bundle.pack,
+                pack::index::verify::Mode::Sha1CRC32Decode,
+                pack::index::traverse::Algorithm::DeltaTreeLookup,
+            )),
+            None,
+            progress::Discard.into(),
+            || pack::cache::DecodeEntryNoop,
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+mod memory_capped {
+    use crate::{
+        fixture_path,
+        pack::{INDEX_V2, PACK_FOR_INDEX_V2},
+    };
+    use git_features::progress::Discard;
+    use git_object::Kind;
+    use git_odb::pack::{
+        self,
+        cache::{DecodeEntry, DecodeEntryMemoryCapped},
+        index,
+    };
+
+    fn contains(mut cache: &DecodeEntryMemoryCapped, offset: u64) -> bool {
+        cache.get(offset, &mut Vec::new()).is_some()
+    }
+
+    #[test]
+    fn stays_within_capacity_and_returns_what_was_put() {
+        let mut cache = DecodeEntryMemoryCapped::new(10);
+        cache.put(1, b"hello", Kind::Blob, 3, 0);
+        cache.put(2, b"world", Kind::Tree, 4, 0);
+        assert_eq!(cache.bytes_used(), 10);
+
+        let mut buf = Vec::new();
+        assert_eq!(cache.get(1, &mut buf), Some((Kind::Blob, 3)));
+        assert_eq!(buf, b"hello");
+
+        cache.put(3, b"!", Kind::Blob, 1, 0);
+        assert_eq!(cache.num_entries(), 2);
+        assert!(cache.bytes_used() <= cache.capacity());
+        assert!(!contains(&cache, 2), "the least recently used entry is evicted first");
+
+        cache.put(4, b"way too large", Kind::Blob, 1, 0);
+        assert!(!contains(&cache, 4), "objects larger than the cache are not stored");
+        assert_eq!(cache.num_entries(), 2);
+    }
+
+    #[test]
+    fn entries_of_long_delta_chains_are_kept_longer() {
+        let mut cache = DecodeEntryMemoryCapped::new(2);
+        cache.put(1, b"a", Kind::Blob, 1, 2);
+        cache.put(2, b"b", Kind::Blob, 1, 0);
+        for offset in 3..5 {
+            cache.put(offset, b"c", Kind::Blob, 1, 0);
+            assert!(
+                contains(&cache, 1),
+                "the expensive entry stays while there are cheaper ones"
+            );
+        }
+        for offset in 5..8 {
+            cache.put(offset, b"d", Kind::Blob, 1, 0);
+        }
+        assert!(
+            !contains(&cache, 1),
+            "but ages and is evicted once it isn't used anymore"
+        );
+    }
+
+    #[test]
+    fn can_be_shared_by_all_threads_of_a_traversal() -> Result<(), Box<dyn std::error::Error>> {
+        let idx = index::File::at(fixture_path(INDEX_V2))?;
+        let pack = pack::data::File::at(fixture_path(PACK_FOR_INDEX_V2))?;
+        let cache = DecodeEntryMemoryCapped::new(64 * 1024);
+        let (_, outcome, _) = idx.verify_integrity(
+            Some((
+                &pack,
+                index::verify::Mode::Sha1CRC32Decode,
+                index::traverse::Algorithm::Lookup,
+            )),
+            Some(4),
+            Discard.into(),
+            || &cache,
+        )?;
+        let outcome = outcome.expect("traversal outcome");
+        assert!(outcome.cache.hits > 0, "decoded bases are reused");
+        assert!(cache.bytes_used() <= cache.capacity());
+        assert!(cache.num_entries() > 0);
+        Ok(())
+    }
+}
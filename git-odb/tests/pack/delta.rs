@@ -0,0 +1,76 @@
+use git_odb::pack::data::delta;
+
+/// Deterministic pseudo-random bytes, compressing badly like real-world binary data
+fn noise(len: usize, mut seed: u32) -> Vec<u8> {
+    (0..len)
+        .map(|_| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (seed >> 16) as u8
+        })
+        .collect()
+}
+
+fn round_trip(base: &[u8], target: &[u8]) -> Vec<u8> {
+    let mut instructions = Vec::new();
+    delta::encode(base, target, &mut instructions);
+    let mut out = Vec::new();
+    delta::apply(base, &instructions, &mut out).expect("valid delta");
+    assert_eq!(out, target, "applying the delta to the base yields the target");
+    instructions
+}
+
+#[test]
+fn identical_data_is_copied_entirely() {
+    let data = noise(1000, 1);
+    let instructions = round_trip(&data, &data);
+    assert!(instructions.len() < 10, "a single copy suffices");
+}
+
+#[test]
+fn empty_base_or_target() {
+    let data = noise(100, 2);
+    assert_eq!(round_trip(&[], &[]).len(), 2, "just the header");
+    round_trip(&[], &data);
+    round_trip(&data, &[]);
+}
+
+#[test]
+fn small_edits_produce_small_deltas() {
+    let base = noise(10_000, 3);
+    let mut target = base.clone();
+    target.splice(5000..5010, b"an insertion in the middle".iter().cloned());
+    target.drain(100..200);
+    target.extend_from_slice(b"and something at the end");
+    let instructions = round_trip(&base, &target);
+    assert!(
+        instructions.len() < 100,
+        "only the changes are inserted, but got {} bytes",
+        instructions.len()
+    );
+}
+
+#[test]
+fn unrelated_data_is_inserted() {
+    let base = noise(500, 4);
+    let target = noise(1000, 5);
+    let instructions = round_trip(&base, &target);
+    assert!(instructions.len() > target.len(), "insert instructions have overhead");
+}
+
+#[test]
+fn copies_larger_than_the_maximum_copy_size_are_split() {
+    let base = noise(0x10000 * 3 + 17, 6);
+    let mut target = base.clone();
+    target.insert(0x10000 + 5, b'x');
+    round_trip(&base, &target);
+}
+
+#[test]
+fn encoding_stops_once_the_delta_gets_too_large() {
+    let base = noise(500, 7);
+    let target = noise(1000, 8);
+    let index = delta::Index::new(base);
+    let mut out = Vec::new();
+    assert!(!index.encode(&target, &mut out, 100));
+    assert!(index.encode(&target, &mut out, usize::MAX));
+}
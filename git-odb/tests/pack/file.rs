@@ -13,10 +13,7 @@ mod method {
     #[test]
     fn checksum() {
         let p = pack_at(SMALL_PACK);
-        assert_eq!(
-            hex::encode(p.checksum().sha1()),
-            "0f3ea84cd1bba10c2a03d736a460635082833e59"
-        );
+        assert_eq!(p.checksum().to_hex_string(), "0f3ea84cd1bba10c2a03d736a460635082833e59");
     }
 
     #[test]
@@ -85,7 +82,7 @@ mod decode_entry {
         }
 
         let p = pack_at(SMALL_PACK);
-        let entry = p.entry(offset);
+        let entry = p.entry(offset).expect("valid offset");
         let mut buf = Vec::new();
         p.decode_entry(entry, &mut buf, resolve_with_panic, &mut cache::DecodeEntryNoop)
             .expect("valid offset provides valid entry");
@@ -135,7 +132,7 @@ mod decompress_entry {
 
     fn decompress_entry_at_offset(offset: u64) -> Vec<u8> {
         let p = pack_at(SMALL_PACK);
-        let entry = p.entry(offset);
+        let entry = p.entry(offset).expect("valid offset");
 
         let size = entry.decompressed_size as usize;
         let mut buf = Vec::with_capacity(size);
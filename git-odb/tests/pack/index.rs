@@ -27,10 +27,10 @@ mod method {
                     assertion
                 );
             }
-            for entry in idx.iter() {
+            for entry in idx.iter()? {
                 let index = idx.lookup(entry.oid.to_borrowed()).expect("id present");
                 assert_eq!(entry.oid.to_borrowed(), idx.oid_at_index(index));
-                assert_eq!(entry.pack_offset, idx.pack_offset_at_index(index));
+                assert_eq!(Some(entry.pack_offset), idx.pack_offset_at_index(index));
                 assert_eq!(entry.crc32, idx.crc32_at_index(index));
             }
             Ok(())
@@ -57,14 +57,67 @@ mod method {
                     assertion
                 );
             }
-            for entry in idx.iter() {
+            for entry in idx.iter()? {
                 let index = idx.lookup(entry.oid.to_borrowed()).expect("id present");
                 assert_eq!(entry.oid.to_borrowed(), idx.oid_at_index(index));
-                assert_eq!(entry.pack_offset, idx.pack_offset_at_index(index));
+                assert_eq!(Some(entry.pack_offset), idx.pack_offset_at_index(index));
                 assert_eq!(entry.crc32, idx.crc32_at_index(index), "{} {:?}", index, entry);
             }
             Ok(())
         }
+
+        #[test]
+        fn trailing_bytes_are_accepted_as_long_as_they_could_be_large_offsets() -> Result<(), Box<dyn std::error::Error>>
+        {
+            let dir = tempfile::tempdir()?;
+            let data = std::fs::read(fixture_path(INDEX_V2))?;
+            let max_large_offsets_len = (30 - 1) * 8;
+            for (extra_bytes, is_ok) in &[
+                (2, true),
+                (max_large_offsets_len, true),
+                (max_large_offsets_len + 1, false),
+            ] {
+                let path = dir.path().join("index.idx");
+                let mut padded = data.clone();
+                padded.resize(data.len() + extra_bytes, 0);
+                std::fs::write(&path, padded)?;
+                match index::File::at(&path) {
+                    Ok(idx) => {
+                        assert!(is_ok, "{} extra bytes must be rejected", extra_bytes);
+                        assert_eq!(idx.hash_kind(), git_object::HashKind::Sha1);
+                    }
+                    Err(_) => assert!(!is_ok, "{} extra bytes must be accepted", extra_bytes),
+                }
+            }
+            Ok(())
+        }
+
+        #[test]
+        fn large_offsets_out_of_bounds_are_detected_on_access() -> Result<(), Box<dyn std::error::Error>> {
+            let dir = tempfile::tempdir()?;
+            let mut data = std::fs::read(fixture_path(INDEX_V2))?;
+            let num_objects = 30;
+            let first_offset = 4 * 2 + 256 * 4 + num_objects * (20 + 4);
+            data[first_offset..first_offset + 4].copy_from_slice(&(1u32 << 31 | 5).to_be_bytes());
+            let path = dir.path().join("index.idx");
+            std::fs::write(&path, data)?;
+
+            let idx = index::File::at(&path)?;
+            assert_eq!(idx.pack_offset_at_index(0), None);
+            assert!(idx.pack_offset_at_index(1).is_some());
+            assert!(matches!(
+                idx.iter().map(|entries| entries.count()),
+                Err(index::access::Error::LargeOffsetOutOfBounds { index_position: 0 })
+            ));
+            assert!(idx.sorted_offsets().is_err(), "offsets aren't silently dropped");
+            assert!(matches!(
+                idx.verify_integrity(None, None, git_features::progress::Discard.into(), || {
+                    git_odb::pack::cache::DecodeEntryNoop
+                }),
+                Err(index::traverse::Error::Index(_))
+            ));
+            Ok(())
+        }
     }
 
     mod any {
@@ -228,6 +281,7 @@ fn pack_lookup() -> Result<(), Box<dyn std::error::Error>> {
                 num_tags: 0,
                 num_trees: 15,
                 pack_size: 51875,
+                cache: Default::default(),
             },
         ),
         (
@@ -253,6 +307,7 @@ fn pack_lookup() -> Result<(), Box<dyn std::error::Error>> {
                 num_tags: 0,
                 num_trees: 2,
                 pack_size: 49113,
+                cache: Default::default(),
             },
         ),
         (
@@ -279,6 +334,7 @@ fn pack_lookup() -> Result<(), Box<dyn std::error::Error>> {
                 num_tags: 0,
                 num_trees: 14,
                 pack_size: 3732,
+                cache: Default::default(),
             },
         ),
     ] {
@@ -288,11 +344,20 @@ fn pack_lookup() -> Result<(), Box<dyn std::error::Error>> {
         assert_eq!(pack.kind(), pack::data::Kind::V2);
         assert_eq!(pack.num_objects(), idx.num_objects());
         for algo in ALGOS {
+            let mut stats = stats.to_owned();
+            if let index::traverse::Algorithm::Lookup = algo {
+                // without a cache, each delta in a chain is looked up once and not found
+                stats.cache.misses = stats
+                    .objects_per_chain_length
+                    .iter()
+                    .map(|(chain_length, num_objects)| (chain_length * num_objects) as u64)
+                    .sum();
+            }
             for mode in MODES {
                 assert_eq!(
                     idx.verify_integrity(Some((&pack, *mode, *algo)), None, Discard.into(), || DecodeEntryNoop)
                         .map(|(a, b, _)| (a, b))?,
-                    (idx.index_checksum(), Some(stats.to_owned())),
+                    (idx.index_checksum(), Some(stats.clone())),
                     "{:?} -> {:?}",
                     algo,
                     mode
@@ -304,10 +369,10 @@ fn pack_lookup() -> Result<(), Box<dyn std::error::Error>> {
             .values()
             .map(|v| *v as usize)
             .sum::<usize>();
-        let sorted_offsets = idx.sorted_offsets();
+        let sorted_offsets = idx.sorted_offsets()?;
         assert_eq!(num_objects, sorted_offsets.len());
-        for idx_entry in idx.iter() {
-            let pack_entry = pack.entry(idx_entry.pack_offset);
+        for idx_entry in idx.iter()? {
+            let pack_entry = pack.entry(idx_entry.pack_offset)?;
             assert_ne!(pack_entry.data_offset, idx_entry.pack_offset);
             assert!(sorted_offsets.binary_search(&idx_entry.pack_offset).is_ok());
         }
@@ -320,7 +385,7 @@ fn pack_lookup() -> Result<(), Box<dyn std::error::Error>> {
 
             let mut buf = Vec::new();
             buf.resize(entry.decompressed_size as usize, 0);
-            let pack_entry = pack.entry(offset_from_index);
+            let pack_entry = pack.entry(offset_from_index)?;
             assert_eq!(
                 pack_entry.pack_offset(),
                 entry.pack_offset,
@@ -395,7 +460,7 @@ fn iter() -> Result<(), Box<dyn std::error::Error>> {
         );
         assert_eq!(idx.index_checksum(), hex_to_id(index_checksum));
         assert_eq!(idx.pack_checksum(), hex_to_id(pack_checksum));
-        assert_eq!(idx.iter().count(), *num_objects as usize);
+        assert_eq!(idx.iter()?.count(), *num_objects as usize);
     }
     Ok(())
 }
@@ -4,7 +4,7 @@ use git_odb::pack;
 fn size_of_entry() {
     assert_eq!(
         std::mem::size_of::<pack::data::iter::Entry>(),
-        104,
+        128,
         "let's keep the size in check as we have many of them"
     );
 }
@@ -30,7 +30,7 @@ mod new_from_header {
 
                 let mut buf = Vec::<u8>::new();
                 entry.header.to_write(entry.decompressed_size, &mut buf)?;
-                let new_entry = pack::data::Entry::from_bytes(&buf, entry.pack_offset);
+                let new_entry = pack::data::Entry::from_bytes(&buf, entry.pack_offset, git_object::HashKind::Sha1)?;
 
                 assert_eq!(
                     new_entry.header_size(),
@@ -0,0 +1,172 @@
+//! Feed truncated and bit-flipped fixtures to all decoding paths, which may fail but must never panic.
+use crate::{
+    fixture_path,
+    pack::{multi_index::MULTI_PACK_INDEX, INDEX_V1, INDEX_V2, PACK_FOR_INDEX_V1, SMALL_PACK, SMALL_PACK_INDEX},
+};
+use git_object::{owned, HashKind};
+use git_odb::{loose, pack};
+use std::{fs, path::Path};
+
+/// Deterministically produce truncated copies of `data` as well as copies with flipped bits.
+fn mutations(data: &[u8]) -> impl Iterator<Item = Vec<u8>> + '_ {
+    let step = (data.len() / 97).max(1);
+    let truncated = (0..data.len()).step_by(step).map(move |len| data[..len].to_vec());
+    let flipped = (0..data.len()).step_by(step / 3 + 1).map(move |pos| {
+        let mut data = data.to_vec();
+        data[pos] ^= 1 << (pos % 8);
+        data
+    });
+    truncated.chain(flipped)
+}
+
+fn write_to(dir: &Path, name: &str, data: &[u8]) -> std::io::Result<std::path::PathBuf> {
+    let path = dir.join(name);
+    fs::write(&path, data)?;
+    Ok(path)
+}
+
+fn decode_all_entries(pack: &pack::data::File, offsets: impl IntoIterator<Item = u64>) {
+    let mut buf = Vec::new();
+    for offset in offsets {
+        let entry = match pack.entry(offset) {
+            Ok(entry) => entry,
+            Err(_) => continue,
+        };
+        pack.decode_header(entry.clone(), |_id| None).ok();
+        pack.decode_entry(entry, &mut buf, |_id, _out| None, &mut pack::cache::DecodeEntryNoop)
+            .ok();
+    }
+}
+
+#[test]
+fn entry_headers_of_any_length_or_type_can_be_decoded_without_panic() {
+    let mut buf = Vec::new();
+    pack::data::Header::OfsDelta { base_distance: 1 << 40 }
+        .to_write(1 << 50, &mut buf)
+        .expect("writing to memory works");
+    for len in 0..buf.len() {
+        assert!(
+            pack::data::Entry::from_bytes(&buf[..len], 1 << 41, HashKind::Sha1).is_err(),
+            "truncated headers are an error"
+        );
+    }
+    for first_byte in 0..=u8::MAX {
+        let mut bytes = buf.clone();
+        bytes[0] = first_byte;
+        if let Ok(entry) = pack::data::Entry::from_bytes(&bytes, 12, HashKind::Sha1) {
+            if let pack::data::Header::OfsDelta { base_distance } = entry.header {
+                assert_eq!(
+                    entry.base_pack_offset(base_distance),
+                    None,
+                    "bases can't be before the pack"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn packs_with_mutations_can_be_decoded_without_panic() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let index = pack::index::File::at(fixture_path(SMALL_PACK_INDEX))?;
+    let offsets: Vec<_> = index.iter()?.map(|e| e.pack_offset).collect();
+    let data = fs::read(fixture_path(SMALL_PACK))?;
+    for (n, mutated) in mutations(&data).enumerate() {
+        let path = write_to(dir.path(), &format!("{}.pack", n), &mutated)?;
+        if let Ok(pack) = pack::data::File::at(&path) {
+            decode_all_entries(&pack, offsets.iter().copied());
+            if let Ok(iter) = pack.streaming_iter() {
+                iter.take_while(Result::is_ok).for_each(drop);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn indices_with_mutations_can_be_read_without_panic() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let pack = pack::data::File::at(fixture_path(PACK_FOR_INDEX_V1))?;
+    for index_path in &[INDEX_V1, INDEX_V2, SMALL_PACK_INDEX] {
+        let data = fs::read(fixture_path(index_path))?;
+        for (n, mutated) in mutations(&data).enumerate() {
+            let path = write_to(dir.path(), &format!("{}.idx", n), &mutated)?;
+            if let Ok(index) = pack::index::File::at(&path) {
+                let entries: Vec<_> = index.iter().map(|entries| entries.collect()).unwrap_or_default();
+                for entry in &entries {
+                    index.lookup(entry.oid.to_borrowed());
+                }
+                index.lookup(owned::Id::null_sha1().to_borrowed());
+                for idx in 0..index.num_objects() {
+                    index.oid_at_index(idx);
+                    index.pack_offset_at_index(idx);
+                    index.crc32_at_index(idx);
+                }
+                if *index_path == INDEX_V1 {
+                    decode_all_entries(&pack, entries.iter().map(|e| e.pack_offset));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn multi_pack_indices_with_mutations_can_be_read_without_panic() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let data = fs::read(fixture_path(MULTI_PACK_INDEX))?;
+    for (n, mutated) in mutations(&data).enumerate() {
+        let path = write_to(dir.path(), &format!("{}-multi-pack-index", n), &mutated)?;
+        if let Ok(file) = pack::multi_index::File::at(&path) {
+            let entries: Vec<_> = file.iter().collect();
+            for entry in &entries {
+                file.lookup(entry.oid.to_borrowed());
+            }
+            file.lookup(owned::Id::null_sha1().to_borrowed());
+            for index in 0..file.num_objects() {
+                file.oid_at_index(index);
+                file.pack_id_and_pack_offset_at_index(index);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn deltas_with_mutations_can_be_applied_without_panic() {
+    let base: Vec<u8> = (0..2000u32).map(|n| (n * 7 % 251) as u8).collect();
+    let mut target = base[500..1500].to_vec();
+    target.extend_from_slice(b"some inserted data which isn't in the base");
+    target.extend_from_slice(&base[..300]);
+    let mut delta = Vec::new();
+    pack::data::delta::encode(&base, &target, &mut delta);
+
+    let mut out = Vec::new();
+    for mutated in mutations(&delta) {
+        pack::data::delta::apply(&base, &mutated, &mut out).ok();
+    }
+    for byte in 0..=u8::MAX {
+        let mut mutated = delta.clone();
+        let last = mutated.len() - 1;
+        mutated[last] = byte;
+        mutated.push(byte);
+        pack::data::delta::apply(&base, &mutated, &mut out).ok();
+    }
+}
+
+#[test]
+fn loose_object_headers_with_mutations_can_be_decoded_without_panic() {
+    let header = b"commit 18446744073709551615\0";
+    for mutated in mutations(header) {
+        loose::object::header::decode(&mutated).ok();
+    }
+    for invalid in &[
+        &b"blob -1\0"[..],
+        b"blob 1 2\0",
+        b"blob 18446744073709551616\0",
+        b"blob\0",
+        b"\0",
+    ] {
+        assert!(loose::object::header::decode(invalid).is_err());
+    }
+}
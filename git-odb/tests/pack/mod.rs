@@ -13,8 +13,15 @@ const PACKS_AND_INDICES: &[(&'static str, &'static str)] =
 const V2_PACKS_AND_INDICES: &[(&'static str, &'static str)] =
     &[(SMALL_PACK_INDEX, SMALL_PACK), (INDEX_V2, PACK_FOR_INDEX_V2)];
 
+mod bitmap;
 mod bundle;
+mod cache;
+mod delta;
 mod file;
 mod index;
 mod iter;
+mod malformed;
+mod multi_index;
+mod output;
+mod rev;
 mod tree;
@@ -0,0 +1,170 @@
+use crate::{
+    fixture_path,
+    pack::{INDEX_V2, SMALL_PACK_INDEX},
+};
+use git_odb::pack::{self, index, multi_index};
+use std::path::PathBuf;
+
+pub const MULTI_PACK_INDEX: &str = "packs/multi-pack-index";
+
+#[test]
+fn init() -> Result<(), Box<dyn std::error::Error>> {
+    let file = multi_index::File::at(fixture_path(MULTI_PACK_INDEX))?;
+    assert_eq!(file.version(), 1);
+    assert_eq!(file.num_packs(), 2);
+    assert_eq!(file.num_objects(), 72);
+    assert_eq!(
+        file.index_names(),
+        &[
+            PathBuf::from("pack-11fdfa9e156ab73caae3b6da867192221f2089c2.idx"),
+            PathBuf::from("pack-a2bf8e71d8c18879e499335762dd95119d93d9f1.idx")
+        ]
+    );
+    assert_eq!(file.index_path(1), fixture_path(SMALL_PACK_INDEX));
+    Ok(())
+}
+
+#[test]
+fn init_fails_on_other_files() {
+    assert!(matches!(
+        multi_index::File::at(fixture_path(INDEX_V2)),
+        Err(multi_index::init::Error::Corrupt(_))
+    ));
+}
+
+#[test]
+fn lookup_matches_the_pack_indices() -> Result<(), Box<dyn std::error::Error>> {
+    let file = multi_index::File::at(fixture_path(MULTI_PACK_INDEX))?;
+    let mut num_objects = 0;
+    for (pack_index, index_path) in [INDEX_V2, SMALL_PACK_INDEX].iter().enumerate() {
+        let idx = index::File::at(fixture_path(index_path))?;
+        for entry in idx.iter()? {
+            assert_eq!(
+                file.lookup(entry.oid.to_borrowed()),
+                Some((pack_index as u32, entry.pack_offset))
+            );
+            num_objects += 1;
+        }
+    }
+    assert_eq!(num_objects, file.num_objects());
+    assert_eq!(
+        file.lookup(crate::hex_to_id("ffffffffffffffffffffffffffffffffffffffff").to_borrowed()),
+        None
+    );
+
+    for (index, entry) in file.iter().enumerate() {
+        let index = index as u32;
+        assert_eq!(file.lookup_index(entry.oid.to_borrowed()), Some(index));
+        assert_eq!(entry.oid.to_borrowed(), file.oid_at_index(index));
+        assert_eq!(
+            Some((entry.pack_index, entry.pack_offset)),
+            file.pack_id_and_pack_offset_at_index(index)
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn verify_integrity() -> Result<(), Box<dyn std::error::Error>> {
+    let file = multi_index::File::at(fixture_path(MULTI_PACK_INDEX))?;
+    let (id, outcomes, _) = file.verify_integrity(None, None, None::<git_features::progress::Discard>, || {
+        pack::cache::DecodeEntryNoop
+    })?;
+    assert_eq!(id, file.checksum());
+    assert!(outcomes.is_none());
+
+    let (_, outcomes, _) = file.verify_integrity(
+        Some((index::verify::Mode::Sha1CRC32Decode, index::traverse::Algorithm::Lookup)),
+        None,
+        None::<git_features::progress::Discard>,
+        || pack::cache::DecodeEntryNoop,
+    )?;
+    let outcomes = outcomes.expect("packs were verified");
+    assert_eq!(
+        outcomes
+            .iter()
+            .map(|o| o.num_blobs + o.num_trees + o.num_commits + o.num_tags)
+            .sum::<u32>(),
+        file.num_objects()
+    );
+    Ok(())
+}
+
+mod write_from_indices {
+    use crate::{
+        fixture_path,
+        pack::{multi_index::MULTI_PACK_INDEX, INDEX_V2, PACK_FOR_INDEX_V2, SMALL_PACK, SMALL_PACK_INDEX},
+    };
+    use git_features::progress;
+    use git_odb::pack::{index, multi_index};
+    use std::fs;
+
+    #[test]
+    fn writes_the_same_bytes_as_git() -> Result<(), Box<dyn std::error::Error>> {
+        let mut buf = Vec::new();
+        let outcome = multi_index::File::write_from_indices(
+            vec![
+                index::File::at(fixture_path(SMALL_PACK_INDEX))?,
+                index::File::at(fixture_path(INDEX_V2))?,
+            ],
+            &mut buf,
+            progress::Discard,
+        )?;
+        let expected = fs::read(fixture_path(MULTI_PACK_INDEX))?;
+        assert_eq!(buf, expected, "the order of the input indices doesn't matter");
+        assert_eq!(outcome.num_objects, 72);
+        assert_eq!(outcome.num_packs, 2);
+        assert_eq!(outcome.num_duplicates, 0);
+        assert_eq!(
+            outcome.multi_index_hash,
+            multi_index::File::at(fixture_path(MULTI_PACK_INDEX))?.checksum()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn references_objects_contained_in_multiple_packs_once() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::TempDir::new()?;
+        for (index, pack, name) in &[
+            (SMALL_PACK_INDEX, SMALL_PACK, "pack-a"),
+            (SMALL_PACK_INDEX, SMALL_PACK, "pack-b"),
+            (INDEX_V2, PACK_FOR_INDEX_V2, "pack-c"),
+        ] {
+            fs::copy(fixture_path(index), dir.path().join(name).with_extension("idx"))?;
+            fs::copy(fixture_path(pack), dir.path().join(name).with_extension("pack"))?;
+        }
+        let indices = ["pack-a", "pack-b", "pack-c"]
+            .iter()
+            .map(|name| index::File::at(dir.path().join(name).with_extension("idx")))
+            .collect::<Result<Vec<_>, _>>()?;
+        let path = dir.path().join("multi-pack-index");
+        let outcome = multi_index::File::write_from_indices(indices, fs::File::create(&path)?, progress::Discard)?;
+        assert_eq!(outcome.num_objects, 72);
+        assert_eq!(outcome.num_packs, 3);
+        assert_eq!(
+            outcome.num_duplicates, 42,
+            "all objects of the small pack are duplicated"
+        );
+
+        let file = multi_index::File::at(&path)?;
+        assert_eq!(file.num_objects(), 72);
+        file.verify_integrity(None, None, None::<progress::Discard>, || {
+            git_odb::pack::cache::DecodeEntryNoop
+        })?;
+        Ok(())
+    }
+
+    #[test]
+    fn fails_if_an_index_is_provided_twice() -> Result<(), Box<dyn std::error::Error>> {
+        let res = multi_index::File::write_from_indices(
+            vec![
+                index::File::at(fixture_path(SMALL_PACK_INDEX))?,
+                index::File::at(fixture_path(SMALL_PACK_INDEX))?,
+            ],
+            Vec::new(),
+            progress::Discard,
+        );
+        assert!(matches!(res, Err(multi_index::write::Error::DuplicateIndexName(_))));
+        Ok(())
+    }
+}
@@ -0,0 +1,367 @@
+use crate::{fixture_path, pack::SMALL_PACK_INDEX};
+use git_features::progress;
+use git_object::owned;
+use git_odb::pack;
+
+fn small_pack_ids(bundle: &pack::Bundle) -> Vec<owned::Id> {
+    bundle
+        .index
+        .iter()
+        .expect("valid large offsets")
+        .map(|e| e.oid)
+        .collect()
+}
+
+fn entries_of<'a>(
+    db: &'a (impl git_odb::Locate + Sync),
+    ids: Vec<owned::Id>,
+) -> impl Iterator<Item = Result<Vec<pack::data::output::Entry>, pack::data::output::Error>> + 'a {
+    entries_with_options(
+        db,
+        ids,
+        pack::data::output::Options {
+            thread_limit: None,
+            chunk_size: 3,
+            delta: None,
+            ..Default::default()
+        },
+    )
+}
+
+fn entries_with_options<'a>(
+    db: &'a (impl git_odb::Locate + Sync),
+    ids: Vec<owned::Id>,
+    options: pack::data::output::Options,
+) -> impl Iterator<Item = Result<Vec<pack::data::output::Entry>, pack::data::output::Error>> + 'a {
+    pack::data::output::objects_to_entries_iter(
+        db,
+        || pack::cache::DecodeEntryNoop,
+        ids.into_iter(),
+        progress::Discard,
+        options,
+    )
+}
+
+mod from_entries_iter {
+    use super::{entries_of, small_pack_ids};
+    use crate::{fixture_path, loose::db::object_ids, pack::SMALL_PACK_INDEX};
+    use git_object::HashKind;
+    use git_odb::{loose, pack, pack::data::output};
+
+    #[test]
+    fn pack_stream_is_readable_and_matches_yielded_entries() -> Result<(), Box<dyn std::error::Error>> {
+        let bundle = pack::Bundle::at(fixture_path(SMALL_PACK_INDEX))?;
+        let ids = small_pack_ids(&bundle);
+        let num_entries = ids.len() as u32;
+
+        let mut pack_iter =
+            output::bytes::FromEntriesIter::new(entries_of(&bundle, ids), Vec::new(), num_entries, HashKind::Sha1);
+        let written_entries = pack_iter.by_ref().collect::<Result<Vec<_>, _>>()?.concat();
+        let trailer = pack_iter.digest().expect("iteration is complete");
+        let pack_data = pack_iter.into_write();
+
+        assert_eq!(written_entries.len(), num_entries as usize);
+        assert_eq!(written_entries.last().and_then(|e| e.trailer.clone()), Some(trailer));
+
+        let read_entries = pack::data::Iter::new_from_header(
+            std::io::BufReader::new(pack_data.as_slice()),
+            pack::data::iter::Mode::Verify,
+            pack::data::iter::CompressedBytesMode::CRC32,
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(
+            read_entries, written_entries,
+            "what we write is exactly what is read back"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn loose_objects_can_be_a_source_too() -> Result<(), Box<dyn std::error::Error>> {
+        let db = loose::Db::at(fixture_path("objects"));
+        let ids = object_ids();
+        let num_entries = ids.len() as u32;
+        let pack_iter =
+            output::bytes::FromEntriesIter::new(entries_of(&db, ids), Vec::new(), num_entries, HashKind::Sha1);
+        assert_eq!(pack_iter.collect::<Result<Vec<_>, _>>()?.concat().len(), 7);
+        Ok(())
+    }
+
+    #[test]
+    fn mismatching_entry_count_is_an_error() -> Result<(), Box<dyn std::error::Error>> {
+        let bundle = pack::Bundle::at(fixture_path(SMALL_PACK_INDEX))?;
+        let ids = small_pack_ids(&bundle);
+        let num_entries = ids.len() as u32;
+
+        let res = output::bytes::FromEntriesIter::new(
+            entries_of(&bundle, ids.clone()),
+            Vec::new(),
+            num_entries + 1,
+            HashKind::Sha1,
+        )
+        .collect::<Result<Vec<_>, _>>();
+        assert!(matches!(
+            res,
+            Err(output::bytes::Error::EntryCountMismatch {
+                expected: 43,
+                actual: 42
+            })
+        ));
+
+        let entries = entries_of(&bundle, ids).collect::<Result<Vec<_>, _>>()?.concat();
+        let chunks: Vec<_> = entries.chunks(3).map(|chunk| Ok(chunk.to_vec())).collect();
+        let res = output::bytes::FromEntriesIter::new(chunks.into_iter(), Vec::new(), 3, HashKind::Sha1)
+            .collect::<Result<Vec<_>, _>>();
+        assert!(
+            matches!(
+                res,
+                Err(output::bytes::Error::EntryCountMismatch { expected: 3, actual: 6 })
+            ),
+            "entries in chunks after the announced amount was reached aren't silently dropped"
+        );
+        Ok(())
+    }
+}
+
+#[test]
+fn objects_to_entries_iter_fails_on_missing_objects() -> Result<(), Box<dyn std::error::Error>> {
+    let bundle = pack::Bundle::at(fixture_path(SMALL_PACK_INDEX))?;
+    let res = entries_of(&bundle, vec![owned::Id::null_sha1()]).collect::<Result<Vec<_>, _>>();
+    assert!(matches!(res, Err(pack::data::output::Error::NotFound(_))));
+    Ok(())
+}
+
+#[test]
+fn name_hash_sorts_by_the_end_of_the_path() {
+    use pack::data::output::name_hash;
+    assert_eq!(name_hash(b""), 0);
+    assert_eq!(name_hash(b"a b"), name_hash(b"ab"), "whitespace is ignored");
+    assert_eq!(
+        name_hash(b"src/a/file.rs") >> 24,
+        name_hash(b"other/file.rs") >> 24,
+        "the last character has the most influence"
+    );
+    assert_ne!(name_hash(b"file.rs"), name_hash(b"file.md"));
+}
+
+mod write_entries_to_directory {
+    use super::{entries_with_options, small_pack_ids};
+    use crate::{fixture_path, pack::SMALL_PACK_INDEX};
+    use git_features::progress;
+    use git_odb::pack::{self, data::output};
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn pack_and_index_can_be_verified_and_contain_all_objects() -> Result<(), Box<dyn std::error::Error>> {
+        let mut pack_sizes = Vec::new();
+        for delta in &[None, Some(output::DeltaOptions::default())] {
+            pack_sizes.push(write_and_verify(output::Options {
+                thread_limit: None,
+                chunk_size: 50,
+                delta: *delta,
+                ..Default::default()
+            })?);
+        }
+        assert!(
+            pack_sizes[1] < pack_sizes[0],
+            "delta compression makes packs smaller: {:?}",
+            pack_sizes
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn delta_bases_are_searched_across_chunks() -> Result<(), Box<dyn std::error::Error>> {
+        let mut pack_sizes = Vec::new();
+        for chunk_size in &[1, 50] {
+            pack_sizes.push(write_and_verify(output::Options {
+                chunk_size: *chunk_size,
+                delta: Some(output::DeltaOptions::default()),
+                ..Default::default()
+            })?);
+        }
+        assert_eq!(
+            pack_sizes[0], pack_sizes[1],
+            "the window slides across chunks, so their size doesn't affect which deltas are found"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn compression_level_affects_pack_size_but_not_content() -> Result<(), Box<dyn std::error::Error>> {
+        let mut pack_sizes = Vec::new();
+        for compression in &[git_odb::CompressionLevel::NONE, git_odb::CompressionLevel::BEST] {
+            pack_sizes.push(write_and_verify(output::Options {
+                chunk_size: 50,
+                compression: *compression,
+                ..Default::default()
+            })?);
+        }
+        assert!(
+            pack_sizes[1] < pack_sizes[0],
+            "uncompressed packs are larger: {:?}",
+            pack_sizes
+        );
+        Ok(())
+    }
+
+    fn write_and_verify(options: output::Options) -> Result<u64, Box<dyn std::error::Error>> {
+        let source = pack::Bundle::at(fixture_path(SMALL_PACK_INDEX))?;
+        let ids = small_pack_ids(&source);
+        let dir = TempDir::new()?;
+
+        let outcome = pack::Bundle::write_entries_to_directory(
+            entries_with_options(&source, ids.clone(), options),
+            ids.len() as u32,
+            Some(dir.path()),
+            progress::Discard,
+            pack::bundle::write::Options {
+                thread_limit: None,
+                iteration_mode: pack::data::iter::Mode::Verify,
+                index_kind: pack::index::Kind::V2,
+                hash_kind: git_object::HashKind::Sha1,
+                compression: git_odb::CompressionLevel::default(),
+            },
+        )?;
+        assert_eq!(outcome.index.num_objects, 42);
+
+        let bundle = outcome.to_bundle().expect("written to directory")?;
+        bundle.index.verify_integrity(
+            Some((
+                &bundle.pack,
+                pack::index::verify::Mode::Sha1CRC32Decode,
+                pack::index::traverse::Algorithm::DeltaTreeLookup,
+            )),
+            None,
+            progress::Discard.into(),
+            || pack::cache::DecodeEntryNoop,
+        )?;
+
+        let mut buf = Vec::new();
+        for id in ids {
+            let object = bundle
+                .locate(id.to_borrowed(), &mut buf, &mut pack::cache::DecodeEntryNoop)
+                .expect("object present")?;
+            object.verify_checksum(id.to_borrowed())?;
+        }
+        Ok(fs::metadata(outcome.data_path.expect("written to directory"))?.len())
+    }
+}
+
+mod from_pack_entry {
+    use crate::{fixture_path, pack::SMALL_PACK_INDEX};
+    use git_features::progress;
+    use git_object::owned;
+    use git_odb::pack::{self, data::output};
+    use std::{collections::HashMap, fs};
+    use tempfile::TempDir;
+
+    /// Copy all entries of `bundle` in pack order, allowing deltas to be copied if `with_deltas` is set.
+    fn copy_entries(
+        bundle: &pack::Bundle,
+        with_deltas: bool,
+    ) -> Result<Vec<Option<output::Entry>>, Box<dyn std::error::Error>> {
+        let reverse_index = bundle.index.reverse_index()?;
+        let mut object_index_by_id = HashMap::<owned::Id, _>::new();
+        let mut entries = Vec::new();
+        for index_position in reverse_index.iter() {
+            let entry = output::Entry::from_pack_entry(bundle, &reverse_index, index_position, |base_id| {
+                with_deltas
+                    .then(|| object_index_by_id.get(&base_id.into()).copied())
+                    .flatten()
+            })
+            .transpose()?;
+            object_index_by_id.insert(
+                bundle.index.oid_at_index(index_position).into(),
+                (entries.len(), bundle.kind_at_index(index_position).expect("kind known")),
+            );
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
+    #[test]
+    fn copied_entries_produce_the_same_objects_with_the_same_compressed_data() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let source = pack::Bundle::at(fixture_path(SMALL_PACK_INDEX))?;
+        let entries = copy_entries(&source, true)?
+            .into_iter()
+            .map(|e| e.expect("all entries can be copied as all bases are part of the output"))
+            .collect::<Vec<_>>();
+        assert!(entries
+            .iter()
+            .any(|e| matches!(e.kind, output::EntryKind::DeltaRef { .. })));
+
+        let dir = TempDir::new()?;
+        let num_entries = entries.len() as u32;
+        let outcome = pack::Bundle::write_entries_to_directory(
+            std::iter::once(Ok(entries)),
+            num_entries,
+            Some(dir.path()),
+            progress::Discard,
+            pack::bundle::write::Options {
+                thread_limit: None,
+                iteration_mode: pack::data::iter::Mode::Verify,
+                index_kind: pack::index::Kind::V2,
+                hash_kind: git_object::HashKind::Sha1,
+                compression: git_odb::CompressionLevel::default(),
+            },
+        )?;
+        assert_eq!(
+            fs::metadata(outcome.data_path.as_ref().expect("written to directory"))?.len(),
+            fs::metadata(source.pack.path())?.len(),
+            "nothing was recompressed"
+        );
+
+        let bundle = outcome.to_bundle().expect("written to directory")?;
+        let (mut expected, mut actual) = (Vec::new(), Vec::new());
+        for entry in source.index.iter()? {
+            let id = entry.oid.to_borrowed();
+            let index_position = bundle.index.lookup(id).expect("all objects were copied");
+            assert_eq!(
+                bundle.index.crc32_at_index(index_position),
+                entry.crc32,
+                "entries are byte-for-byte equal"
+            );
+            let expected_object = source
+                .locate(id, &mut expected, &mut pack::cache::DecodeEntryNoop)
+                .expect("present")?;
+            let actual_object = bundle
+                .locate(id, &mut actual, &mut pack::cache::DecodeEntryNoop)
+                .expect("present")?;
+            assert_eq!(actual_object.kind, expected_object.kind);
+            assert_eq!(actual_object.data, expected_object.data);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn deltas_whose_base_is_not_part_of_the_output_cannot_be_copied() -> Result<(), Box<dyn std::error::Error>> {
+        let source = pack::Bundle::at(fixture_path(SMALL_PACK_INDEX))?;
+        let reverse_index = source.index.reverse_index()?;
+        let entries = copy_entries(&source, false)?;
+        for (index_position, entry) in reverse_index.iter().zip(entries) {
+            let is_delta = source.entry_at_index(index_position)?.header.is_delta();
+            assert_eq!(entry.is_none(), is_delta);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn corrupt_entries_are_detected_by_their_crc32() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = TempDir::new()?;
+        let index_path = dir.path().join("pack.idx");
+        fs::copy(fixture_path(SMALL_PACK_INDEX), &index_path)?;
+        let mut pack_data = fs::read(fixture_path(SMALL_PACK_INDEX).with_extension("pack"))?;
+        pack_data[pack::data::File::HEADER_LEN + 5] ^= 0xff;
+        fs::write(index_path.with_extension("pack"), pack_data)?;
+
+        let bundle = pack::Bundle::at(&index_path)?;
+        let reverse_index = bundle.index.reverse_index()?;
+        let first_entry = reverse_index.index_position_at_pack_position(0);
+        let res = output::Entry::from_pack_entry(&bundle, &reverse_index, first_entry, |_| None).expect("a base entry");
+        assert!(matches!(res, Err(output::Error::Crc32Mismatch { .. })));
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+use crate::{
+    fixture_path,
+    pack::{INDEX_V1, INDEX_V2, SMALL_PACK_INDEX},
+};
+use git_odb::pack;
+use std::fs;
+
+#[test]
+fn from_index_orders_objects_by_pack_offset() -> Result<(), Box<dyn std::error::Error>> {
+    for index_path in &[INDEX_V1, INDEX_V2, SMALL_PACK_INDEX] {
+        let index = pack::index::File::at(fixture_path(index_path))?;
+        let rev = pack::rev::File::from_index(&index);
+        assert_eq!(rev.num_objects(), index.num_objects());
+        assert!(rev.path().is_none());
+        assert_eq!(rev.pack_checksum(), None, "only files know their pack");
+        assert_eq!(
+            rev.iter()
+                .map(|index_position| index.pack_offset_at_index(index_position).expect("valid large offsets"))
+                .collect::<Vec<_>>(),
+            index.sorted_offsets()?
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn pack_position_by_offset() -> Result<(), Box<dyn std::error::Error>> {
+    let index = pack::index::File::at(fixture_path(INDEX_V2))?;
+    let rev = pack::rev::File::from_index(&index);
+    for (pack_position, pack_offset) in index.sorted_offsets()?.into_iter().enumerate() {
+        assert_eq!(
+            rev.pack_position_by_offset(&index, pack_offset),
+            Some(pack_position as u32)
+        );
+        assert_eq!(rev.pack_position_by_offset(&index, pack_offset + 1), None);
+    }
+    assert_eq!(rev.pack_position_by_offset(&index, 0), None);
+    Ok(())
+}
+
+#[test]
+fn write_and_read_back() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::TempDir::new()?;
+    let index_path = dir.path().join("pack.idx");
+    fs::copy(fixture_path(INDEX_V2), &index_path)?;
+    let index = pack::index::File::at(&index_path)?;
+    assert!(
+        index.reverse_index()?.path().is_none(),
+        "without a file, the reverse index is computed"
+    );
+
+    let checksum = pack::rev::File::write_from_index(&index, fs::File::create(index_path.with_extension("rev"))?)?;
+    let rev = index.reverse_index()?;
+    assert_eq!(rev.path(), Some(index_path.with_extension("rev").as_path()));
+    assert_eq!(rev.version(), 1);
+    assert_eq!(rev.checksum(), Some(checksum));
+    assert_eq!(rev.pack_checksum(), Some(index.pack_checksum()));
+    assert!(rev.iter().eq(pack::rev::File::from_index(&index).iter()));
+    Ok(())
+}
+
+#[test]
+fn reverse_index_fails_on_a_file_for_another_pack() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::TempDir::new()?;
+    let index_path = dir.path().join("pack.idx");
+    fs::copy(fixture_path(INDEX_V2), &index_path)?;
+    let other_index = pack::index::File::at(fixture_path(INDEX_V1))?;
+    pack::rev::File::write_from_index(&other_index, fs::File::create(index_path.with_extension("rev"))?)?;
+    assert!(matches!(
+        pack::index::File::at(&index_path)?.reverse_index(),
+        Err(pack::rev::init::Error::IndexMismatch { .. })
+    ));
+    Ok(())
+}
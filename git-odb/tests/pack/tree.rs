@@ -19,11 +19,12 @@ mod method {
         fn tree(index_path: &str, pack_path: &str) -> Result<(), Box<dyn std::error::Error>> {
             let idx = pack::index::File::at(fixture_path(index_path))?;
             pack::tree::Tree::from_offsets_in_pack(
-                idx.sorted_offsets().into_iter(),
+                idx.sorted_offsets()?.into_iter(),
                 |ofs| *ofs,
                 fixture_path(pack_path),
+                idx.hash_kind(),
                 git_features::progress::Discard,
-                |id| idx.lookup(id).map(|index| idx.pack_offset_at_index(index)),
+                |id| idx.lookup(id).and_then(|index| idx.pack_offset_at_index(index)),
             )?;
             Ok(())
         }
@@ -0,0 +1,111 @@
+//! The fixture has a pack with `810d968…`, `810d965…` and `69b954…`, and the loose objects `69b95e…` and `810d968…`.
+use crate::{fixture_path, hex_to_id};
+use git_object::owned::{self, Prefix};
+use git_odb::{compound, loose, pack, prefix};
+
+const OBJECTS: &str = "prefix/objects";
+const X: &str = "810d968819381d65823b2826cb9666aa998f667c";
+const Y: &str = "810d965cb2957fd6a1aeee0c05e680e9d5450dd0";
+const U: &str = "69b954bc896d135c5ec073c607fa2b0c402c9a84";
+const V: &str = "69b95e057e4e7b8b4a4786db071aeab1ad2b810b";
+
+fn prefix(hex: &str) -> Prefix {
+    Prefix::from_hex(hex).expect("valid prefix")
+}
+
+fn ambiguous_candidates<T: std::fmt::Debug>(res: Option<Result<T, prefix::Error>>) -> Vec<owned::Id> {
+    match res {
+        Some(Err(prefix::Error::Ambiguous(_, candidates))) => candidates,
+        res => panic!("expected an ambiguous prefix, got {:?}", res),
+    }
+}
+
+#[test]
+fn index_lookup_prefix() -> Result<(), Box<dyn std::error::Error>> {
+    let bundle = pack::Bundle::at(fixture_path(
+        "prefix/objects/pack/pack-cb1ac921f52d14176be8ee3aafd9f58a22e0f119.idx",
+    ))?;
+    let index = &bundle.index;
+    let x = index.lookup(hex_to_id(X).to_borrowed());
+    assert_eq!(index.lookup_prefix(&prefix(&X[..7])).transpose()?, x);
+    assert_eq!(
+        index.lookup_prefix(&prefix(&U[..4])).transpose()?,
+        index.lookup(hex_to_id(U).to_borrowed()),
+        "the loose object isn't seen by the index"
+    );
+    assert_eq!(
+        ambiguous_candidates(index.lookup_prefix(&prefix(&X[..6]))),
+        vec![hex_to_id(Y), hex_to_id(X)]
+    );
+    assert_eq!(index.lookup_prefix_range(&prefix(&X[..6])).len(), 2);
+    assert!(index.lookup_prefix(&prefix("ffff")).is_none());
+    assert!(index.lookup_prefix(&prefix("0000")).is_none());
+    Ok(())
+}
+
+#[test]
+fn loose_lookup_prefix() -> Result<(), prefix::Error> {
+    let db = loose::Db::at(fixture_path(OBJECTS));
+    assert_eq!(db.lookup_prefix(&prefix(&X[..4])).transpose()?, Some(hex_to_id(X)));
+    assert_eq!(
+        db.lookup_prefix(&prefix(&U[..5])).transpose()?,
+        Some(hex_to_id(V)),
+        "the packed object isn't seen by the loose database"
+    );
+    assert!(db.lookup_prefix(&prefix(&U[..6])).is_none());
+    assert!(db.lookup_prefix(&prefix("ffff")).is_none());
+    Ok(())
+}
+
+#[test]
+fn compound_lookup_prefix() -> Result<(), Box<dyn std::error::Error>> {
+    let db = compound::Db::at(fixture_path(OBJECTS))?;
+    assert_eq!(
+        db.lookup_prefix(&prefix(&X[..7])).transpose()?,
+        Some(hex_to_id(X)),
+        "objects stored both packed and loose are a single candidate"
+    );
+    assert_eq!(
+        ambiguous_candidates(db.lookup_prefix(&prefix(&U[..5]))),
+        vec![hex_to_id(U), hex_to_id(V)],
+        "candidates come from packs and loose objects"
+    );
+    assert_eq!(
+        ambiguous_candidates(db.lookup_prefix(&prefix(&X[..6]))),
+        vec![hex_to_id(Y), hex_to_id(X)]
+    );
+    assert!(db.lookup_prefix(&prefix("ffff")).is_none());
+
+    let err = db
+        .lookup_prefix(&prefix(&U[..5]))
+        .expect("found")
+        .expect_err("ambiguous");
+    assert_eq!(
+        err.to_string(),
+        format!("The short id 69b95 is ambiguous, candidates are: {}, {}", U, V)
+    );
+    Ok(())
+}
+
+#[test]
+fn compound_shortest_unique_prefix() -> Result<(), Box<dyn std::error::Error>> {
+    let db = compound::Db::at(fixture_path(OBJECTS))?;
+    let shortest = |hex: &str, min_hex_len: usize| -> Result<String, prefix::Error> {
+        Ok(db
+            .shortest_unique_prefix(hex_to_id(hex).to_borrowed(), min_hex_len)?
+            .to_string())
+    };
+    // These match `git rev-parse --short=4`
+    assert_eq!(shortest(X, 4)?, "810d968");
+    assert_eq!(shortest(Y, 4)?, "810d965");
+    assert_eq!(shortest(U, 4)?, "69b954");
+    assert_eq!(shortest(V, 0)?, "69b95e");
+    assert_eq!(shortest(V, 9)?, &V[..9], "the minimum length is respected");
+    assert_eq!(
+        shortest("ffffffffffffffffffffffffffffffffffffffff", 4)?,
+        "ffff",
+        "ids don't have to exist"
+    );
+    assert_eq!(shortest("810d968000000000000000000000000000000000", 4)?, "810d9680");
+    Ok(())
+}
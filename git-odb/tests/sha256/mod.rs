@@ -0,0 +1,208 @@
+//! The fixture is a repository using SHA256 with a pack of the first two commits, in which `437dd9…` is a delta
+//! against `3a86e2…`, and loose objects for the third commit `bddb84…`.
+use crate::fixture_path;
+use git_features::progress::Discard;
+use git_object::{owned, HashKind};
+use git_odb::{compound, loose, pack, Write};
+
+const OBJECTS: &str = "sha256";
+const INDEX: &str = "sha256/pack/pack-3e18ad7e6df83f0bfe63a8819b8c7a020e826be94357f9f20beddb69f715da0c.idx";
+const PACK: &str = "sha256/pack/pack-3e18ad7e6df83f0bfe63a8819b8c7a020e826be94357f9f20beddb69f715da0c.pack";
+const DELTA_BLOB: &str = "437dd9d653be2b8864fc1fec221e1b6ecd6cc5f2941afd8a21641aa6366d01bc";
+const DIR_TREE: &str = "cbdf5774614ae67edf24d0acb6c331f186b579952fc8074d04c843336b26b26b";
+const THIRD_COMMIT: &str = "bddb84c2b3d9fa930ec2734f92956ff66894c6372495b98c67297005584db00e";
+const SECOND_COMMIT: &str = "5d850259d01166a76ad02b963adba04f1ad1906db541c14788cb3e3cad48e8b8";
+
+fn hex_to_id(hex: &str) -> owned::Id {
+    owned::Id::from_hex(hex.as_bytes()).expect("64 bytes hex")
+}
+
+fn seq(n: usize) -> Vec<u8> {
+    (1..=n).map(|i| format!("{}\n", i)).collect::<String>().into_bytes()
+}
+
+#[test]
+fn index_and_pack_infer_their_hash_kind() -> Result<(), Box<dyn std::error::Error>> {
+    let bundle = pack::Bundle::at(fixture_path(INDEX))?;
+    assert_eq!(bundle.index.hash_kind(), HashKind::Sha256);
+    assert_eq!(bundle.pack.hash_kind(), HashKind::Sha256);
+    assert_eq!(bundle.index.num_objects(), 9);
+    assert_eq!(bundle.index.pack_checksum(), bundle.pack.checksum());
+    assert_eq!(
+        bundle.pack.checksum(),
+        hex_to_id("3e18ad7e6df83f0bfe63a8819b8c7a020e826be94357f9f20beddb69f715da0c")
+    );
+    for entry in bundle.index.iter()? {
+        assert_eq!(entry.oid.kind(), HashKind::Sha256);
+        assert_eq!(
+            bundle
+                .index
+                .lookup(entry.oid.to_borrowed())
+                .and_then(|index| bundle.index.pack_offset_at_index(index)),
+            Some(entry.pack_offset)
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn index_verify_integrity() -> Result<(), Box<dyn std::error::Error>> {
+    let bundle = pack::Bundle::at(fixture_path(INDEX))?;
+    assert_eq!(bundle.pack.verify_checksum(Discard)?, bundle.pack.checksum());
+    for algorithm in &[
+        pack::index::traverse::Algorithm::Lookup,
+        pack::index::traverse::Algorithm::DeltaTreeLookup,
+    ] {
+        let (id, outcome, _) = bundle.index.verify_integrity(
+            Some((
+                &bundle.pack,
+                pack::index::verify::Mode::Sha1CRC32DecodeEncode,
+                *algorithm,
+            )),
+            None,
+            Discard.into(),
+            || pack::cache::DecodeEntryNoop,
+        )?;
+        assert_eq!(id, bundle.index.index_checksum());
+        assert_eq!(
+            outcome.expect("a pack was given").objects_per_chain_length.get(&1),
+            Some(&1),
+            "one delta"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn bundle_locate_and_decode() -> Result<(), Box<dyn std::error::Error>> {
+    let bundle = pack::Bundle::at(fixture_path(INDEX))?;
+    let mut buf = Vec::new();
+    let blob = bundle
+        .locate(
+            hex_to_id(DELTA_BLOB).to_borrowed(),
+            &mut buf,
+            &mut pack::cache::DecodeEntryNoop,
+        )
+        .expect("object present")?;
+    assert_eq!(blob.kind, git_object::Kind::Blob);
+    assert_eq!(blob.data, seq(200).as_slice());
+
+    let tree = bundle
+        .locate(
+            hex_to_id(DIR_TREE).to_borrowed(),
+            &mut buf,
+            &mut pack::cache::DecodeEntryNoop,
+        )
+        .expect("object present")?;
+    let tree = tree.decode_with_hash(HashKind::Sha256)?;
+    let entries = &tree.as_tree().expect("a tree").entries;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].filename, "b");
+    assert_eq!(entries[0].oid, hex_to_id(DELTA_BLOB).to_borrowed());
+    Ok(())
+}
+
+#[test]
+fn loose_iter_locate_and_write() -> Result<(), Box<dyn std::error::Error>> {
+    let db = loose::Db::at(fixture_path(OBJECTS));
+    let mut ids = db.iter().collect::<Result<Vec<_>, _>>()?;
+    ids.sort();
+    assert_eq!(ids.len(), 5);
+    assert!(ids.iter().all(|id| id.kind() == HashKind::Sha256));
+
+    let tmp = tempfile::TempDir::new()?;
+    let written_db = loose::Db::at(tmp.path());
+    for id in &ids {
+        let mut object = db.locate(id.to_borrowed()).expect("object present")?;
+        object.verify_checksum(id.to_borrowed())?;
+        let object = owned::Object::from(object.decode_with_hash(HashKind::Sha256)?);
+        assert_eq!(written_db.write(&object, HashKind::Sha256)?, *id);
+    }
+
+    let mut object = db
+        .locate(hex_to_id(THIRD_COMMIT).to_borrowed())
+        .expect("object present")?;
+    let object = object.decode_with_hash(HashKind::Sha256)?;
+    let commit = object.as_commit().expect("a commit");
+    assert_eq!(commit.parents.len(), 1);
+    assert_eq!(commit.parents[0], SECOND_COMMIT);
+    Ok(())
+}
+
+#[test]
+fn compound_locate_packed_and_loose_objects() -> Result<(), Box<dyn std::error::Error>> {
+    let db = compound::Db::at(fixture_path(OBJECTS))?;
+    let mut buf = Vec::new();
+    for id in &[DELTA_BLOB, THIRD_COMMIT] {
+        let object = db
+            .locate(hex_to_id(id).to_borrowed(), &mut buf, &mut pack::cache::DecodeEntryNoop)
+            .expect("object present")?;
+        object.verify_checksum(hex_to_id(id).to_borrowed())?;
+    }
+    Ok(())
+}
+
+#[test]
+fn write_pack_and_index_like_git() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::TempDir::new()?;
+    let outcome = pack::Bundle::write_to_directory(
+        std::fs::File::open(fixture_path(PACK))?,
+        None,
+        Some(dir.path()),
+        Discard,
+        None::<pack::Bundle>,
+        pack::bundle::write::Options {
+            thread_limit: None,
+            iteration_mode: pack::data::iter::Mode::Verify,
+            index_kind: pack::index::Kind::V2,
+            hash_kind: HashKind::Sha256,
+            compression: git_odb::CompressionLevel::default(),
+        },
+    )?;
+    let expected = pack::index::File::at(fixture_path(INDEX))?;
+    assert_eq!(outcome.index.data_hash, expected.pack_checksum());
+    assert_eq!(
+        outcome.index.index_hash,
+        expected.index_checksum(),
+        "the index is the same as the one written by git"
+    );
+
+    let bundle = outcome.to_bundle().expect("written to directory")?;
+    assert_eq!(bundle.index.hash_kind(), HashKind::Sha256);
+    let rev = bundle.index.reverse_index()?;
+    assert_eq!(rev.hash_kind(), HashKind::Sha256);
+    assert_eq!(rev.pack_checksum(), Some(expected.pack_checksum()));
+    Ok(())
+}
+
+#[test]
+fn multi_pack_indices_of_sha256_packs_are_rejected() -> Result<(), Box<dyn std::error::Error>> {
+    assert!(matches!(
+        pack::multi_index::File::write_from_indices(
+            vec![pack::index::File::at(fixture_path(INDEX))?],
+            Vec::new(),
+            Discard
+        ),
+        Err(pack::multi_index::write::Error::UnsupportedHashKind {
+            kind: HashKind::Sha256,
+            ..
+        })
+    ));
+    Ok(())
+}
+
+#[test]
+fn bitmaps_of_sha256_packs_are_rejected() -> Result<(), Box<dyn std::error::Error>> {
+    let bundle = pack::Bundle::at(fixture_path(INDEX))?;
+    assert!(matches!(
+        pack::bitmap::File::write_from_bundle(
+            &bundle,
+            Some(hex_to_id(SECOND_COMMIT).to_borrowed()),
+            Vec::new(),
+            Discard,
+            Default::default()
+        ),
+        Err(pack::bitmap::write::Error::UnsupportedHashKind(HashKind::Sha256))
+    ));
+    Ok(())
+}
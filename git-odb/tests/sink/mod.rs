@@ -5,7 +5,7 @@ use git_odb::Write;
 #[test]
 fn write() -> Result<(), Box<dyn std::error::Error>> {
     for oid in object_ids() {
-        let mut obj = locate_oid(oid);
+        let mut obj = locate_oid(oid.to_borrowed());
         let actual = git_odb::sink().write(&obj.decode()?.into(), HashKind::Sha1)?;
         assert_eq!(actual, oid);
     }
@@ -31,9 +31,7 @@ mod to_read {
         let size = pack_entries.size_hint().0 - 1;
         let last = pack_entries.skip(size).next().expect("last entry")?;
         assert_eq!(
-            last.trailer
-                .expect("trailer to exist on last entry")
-                .to_sha1_hex_string(),
+            last.trailer.expect("trailer to exist on last entry").to_hex_string(),
             "150a1045f04dc0fc2dbf72313699fda696bf4126"
         );
         Ok(())
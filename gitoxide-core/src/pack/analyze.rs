@@ -0,0 +1,396 @@
+use crate::OutputFormat;
+use anyhow::{anyhow, Context as AnyhowContext, Result};
+use bytesize::ByteSize;
+use git_features::progress::{self, Progress};
+use git_object::{
+    borrowed,
+    bstr::{BString, ByteSlice},
+    owned, Kind, TreeMode,
+};
+use git_odb::pack;
+use std::{collections::BTreeMap, io, path::Path};
+
+pub struct Context<W: io::Write> {
+    /// The amount of paths, extensions, blobs and delta chains to list, the largest or deepest first
+    pub limit: usize,
+    pub format: OutputFormat,
+    pub out: W,
+}
+
+/// The space taken by a set of pack entries
+#[derive(Default, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sizes {
+    pub num_objects: u64,
+    /// The amount of bytes the entries take in the pack, including their header
+    pub compressed: u64,
+    /// The amount of bytes of the objects once decompressed, with all deltas resolved
+    pub decompressed: u64,
+}
+
+impl Sizes {
+    fn add(&mut self, object: &Object) {
+        self.num_objects += 1;
+        self.compressed += object.compressed;
+        self.decompressed += object.decompressed;
+    }
+}
+
+/// The `sizes` of all entries attributed to a path or a file extension
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct NamedSizes {
+    pub name: String,
+    pub sizes: Sizes,
+}
+
+/// A single entry in the pack
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Object {
+    pub id: owned::Id,
+    pub kind: Kind,
+    /// The first path at which the object was found when walking the trees of all commits, newest first.
+    ///
+    /// Paths of trees end with a slash, the root tree is `/`, and commits, tags and unreachable objects have none.
+    pub path: Option<String>,
+    /// The amount of bytes the entry takes in the pack, including its header
+    pub compressed: u64,
+    /// The size of the object once decompressed, with all deltas resolved
+    pub decompressed: u64,
+    /// The amount of deltas to apply to obtain the object, 0 if it is stored as a whole
+    pub delta_chain_length: u32,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Outcome {
+    pub total: Sizes,
+    pub by_kind: BTreeMap<Kind, Sizes>,
+    /// Commits, tags and objects that couldn't be reached from any commit in the pack
+    pub without_path: Sizes,
+    /// The paths taking the most space in the pack
+    pub by_path: Vec<NamedSizes>,
+    /// The extensions of blob paths taking the most space in the pack, with an empty name for paths without one
+    pub by_extension: Vec<NamedSizes>,
+    /// The blobs with the largest decompressed size
+    pub largest_blobs: Vec<Object>,
+    /// The objects at the end of the longest delta chains
+    pub deepest_delta_chains: Vec<Object>,
+}
+
+/// Attribute the space taken by each entry of the pack or index at `path` to the path of the object in the trees
+/// of the commits in the pack, and report it along with the largest blobs and deepest delta chains.
+pub fn pack_or_pack_index(
+    path: impl AsRef<Path>,
+    mut progress: impl Progress,
+    Context { limit, format, out }: Context<impl io::Write>,
+) -> Result<()> {
+    let path = path.as_ref();
+    let bundle = pack::Bundle::at(path).with_context(|| format!("Could not open pack at '{}'", path.display()))?;
+    progress.init(Some(2), progress::steps());
+    let mut objects = {
+        let mut progress = progress.add_child("classifying objects");
+        classify(&bundle, &mut progress)?
+    };
+    progress.inc();
+    {
+        let mut progress = progress.add_child("walking trees");
+        attribute_paths(&bundle, &mut objects, &mut progress)?;
+    }
+    progress.inc();
+    let outcome = summarize(objects, limit);
+
+    match format {
+        OutputFormat::Human => drop(human_output(out, &outcome, limit)),
+        #[cfg(feature = "serde1")]
+        OutputFormat::Json => serde_json::to_writer_pretty(out, &outcome)?,
+    };
+    Ok(())
+}
+
+/// Learn kind, sizes and delta chain length of all objects in the pack, in the order of the index.
+fn classify(bundle: &pack::Bundle, progress: &mut impl Progress) -> Result<Vec<Object>> {
+    let num_objects = bundle.index.num_objects();
+    progress.init(Some(num_objects as usize), progress::count("objects"));
+
+    // Entries end where the next one begins, or where the trailing checksum of the pack begins
+    let mut offsets = (0..num_objects)
+        .map(|index_position| {
+            bundle
+                .index
+                .pack_offset_at_index(index_position)
+                .map(|offset| (offset, index_position))
+                .ok_or_else(|| {
+                    anyhow!(
+                        "The pack index has no offset for the object at index {}",
+                        index_position
+                    )
+                })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    offsets.sort();
+    let mut compressed_sizes = vec![0; num_objects as usize];
+    for (current, next_offset) in offsets.iter().zip(
+        offsets
+            .iter()
+            .skip(1)
+            .map(|(offset, _)| *offset)
+            .chain(std::iter::once(bundle.pack.pack_end() as u64)),
+    ) {
+        let (offset, index_position) = *current;
+        compressed_sizes[index_position as usize] = next_offset.saturating_sub(offset);
+    }
+
+    let mut objects = Vec::with_capacity(num_objects as usize);
+    for (index_position, compressed) in (0..num_objects).zip(compressed_sizes) {
+        let id = bundle.index.oid_at_index(index_position);
+        let (kind, decompressed) = bundle
+            .header(id)
+            .expect("id from our own index")
+            .with_context(|| format!("Could not decode header of object {}", id))?;
+        objects.push(Object {
+            id: id.into(),
+            kind,
+            path: None,
+            compressed,
+            decompressed,
+            delta_chain_length: delta_chain_length(bundle, index_position)?,
+        });
+        progress.inc();
+    }
+    Ok(objects)
+}
+
+fn delta_chain_length(bundle: &pack::Bundle, index_position: u32) -> Result<u32> {
+    let mut entry = bundle.entry_at_index(index_position)?;
+    let mut length = 0;
+    loop {
+        entry = match entry.header {
+            pack::data::Header::OfsDelta { base_distance } => bundle.pack.entry(
+                entry
+                    .base_pack_offset(base_distance)
+                    .ok_or_else(|| anyhow!("Delta base at offset {} is out of bounds", entry.pack_offset()))?,
+            )?,
+            pack::data::Header::RefDelta { base_id } => match bundle.index.lookup(base_id.to_borrowed()) {
+                Some(base_index_position) => bundle.entry_at_index(base_index_position)?,
+                // the base is not part of this pack, which ends the chain
+                None => return Ok(length + 1),
+            },
+            _ => return Ok(length),
+        };
+        length += 1;
+        if length > bundle.index.num_objects() {
+            return Err(anyhow!(
+                "The delta chain of object at index {} never ends",
+                index_position
+            ));
+        }
+    }
+}
+
+/// Assign the first path at which each tree and blob is found when walking the trees of all commits, newest first.
+fn attribute_paths(bundle: &pack::Bundle, objects: &mut [Object], progress: &mut impl Progress) -> Result<()> {
+    let mut buf = Vec::new();
+    let mut cache = pack::cache::DecodeEntryLRU::default();
+
+    let mut commits = Vec::new();
+    for (index_position, object) in objects.iter().enumerate() {
+        if object.kind != Kind::Commit {
+            continue;
+        }
+        let data = locate(bundle, object.id.to_borrowed(), &mut buf, &mut cache)?;
+        let commit =
+            borrowed::Commit::from_bytes(data).with_context(|| format!("Could not parse commit {}", object.id))?;
+        commits.push((commit.committer.time.time, index_position, commit.tree()));
+    }
+    commits.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+    progress.init(Some(commits.len()), progress::count("commits"));
+    for (_, _, tree_id) in commits {
+        let mut trees = vec![(tree_id, BString::from("/"))];
+        while let Some((tree_id, tree_path)) = trees.pop() {
+            let index_position = match bundle.index.lookup(tree_id.to_borrowed()) {
+                Some(index_position) => index_position as usize,
+                None => continue,
+            };
+            if objects[index_position].path.is_some() {
+                continue;
+            }
+            objects[index_position].path = Some(tree_path.to_str_lossy().into_owned());
+
+            let data = locate(bundle, tree_id.to_borrowed(), &mut buf, &mut cache)?;
+            let tree = borrowed::Tree::from_bytes_with_hash(data, tree_id.kind())
+                .with_context(|| format!("Could not parse tree {}", tree_id))?;
+            for entry in tree.entries {
+                let mut entry_path = if tree_path == "/" {
+                    BString::default()
+                } else {
+                    tree_path.clone()
+                };
+                entry_path.extend_from_slice(entry.filename);
+                match entry.mode {
+                    // submodules are not part of the pack
+                    TreeMode::Commit => {}
+                    TreeMode::Tree => {
+                        entry_path.push(b'/');
+                        trees.push((entry.oid.into(), entry_path));
+                    }
+                    TreeMode::Blob | TreeMode::BlobExecutable | TreeMode::Link => {
+                        if let Some(index_position) = bundle.index.lookup(entry.oid) {
+                            objects[index_position as usize]
+                                .path
+                                .get_or_insert_with(|| entry_path.to_str_lossy().into_owned());
+                        }
+                    }
+                }
+            }
+        }
+        progress.inc();
+    }
+    Ok(())
+}
+
+fn locate<'a>(
+    bundle: &pack::Bundle,
+    id: borrowed::Id,
+    buf: &'a mut Vec<u8>,
+    cache: &mut pack::cache::DecodeEntryLRU,
+) -> Result<&'a [u8]> {
+    Ok(bundle
+        .locate(id, buf, cache)
+        .expect("id from our own index")
+        .with_context(|| format!("Could not decode object {}", id))?
+        .data)
+}
+
+fn extension(path: &str) -> &str {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    match file_name.rfind('.') {
+        Some(pos) if pos > 0 => &file_name[pos + 1..],
+        _ => "",
+    }
+}
+
+fn largest(by_name: BTreeMap<String, Sizes>, limit: usize) -> Vec<NamedSizes> {
+    let mut by_name: Vec<_> = by_name
+        .into_iter()
+        .map(|(name, sizes)| NamedSizes { name, sizes })
+        .collect();
+    by_name.sort_by(|a, b| b.sizes.compressed.cmp(&a.sizes.compressed).then(a.name.cmp(&b.name)));
+    by_name.truncate(limit);
+    by_name
+}
+
+fn summarize(objects: Vec<Object>, limit: usize) -> Outcome {
+    let mut total = Sizes::default();
+    let mut by_kind = BTreeMap::new();
+    let mut without_path = Sizes::default();
+    let mut by_path = BTreeMap::<String, Sizes>::new();
+    let mut by_extension = BTreeMap::<String, Sizes>::new();
+    for object in &objects {
+        total.add(object);
+        by_kind.entry(object.kind).or_insert_with(Sizes::default).add(object);
+        match &object.path {
+            Some(path) => {
+                by_path.entry(path.clone()).or_default().add(object);
+                if object.kind == Kind::Blob {
+                    by_extension.entry(extension(path).to_owned()).or_default().add(object);
+                }
+            }
+            None => without_path.add(object),
+        }
+    }
+
+    let mut largest_blobs: Vec<_> = objects.iter().filter(|o| o.kind == Kind::Blob).cloned().collect();
+    largest_blobs.sort_by(|a, b| b.decompressed.cmp(&a.decompressed).then(a.id.cmp(&b.id)));
+    largest_blobs.truncate(limit);
+
+    let mut deepest_delta_chains: Vec<_> = objects.into_iter().filter(|o| o.delta_chain_length > 0).collect();
+    deepest_delta_chains.sort_by(|a, b| b.delta_chain_length.cmp(&a.delta_chain_length).then(a.id.cmp(&b.id)));
+    deepest_delta_chains.truncate(limit);
+
+    Outcome {
+        total,
+        by_kind,
+        without_path,
+        by_path: largest(by_path, limit),
+        by_extension: largest(by_extension, limit),
+        largest_blobs,
+        deepest_delta_chains,
+    }
+}
+
+fn human_output(mut out: impl io::Write, outcome: &Outcome, limit: usize) -> io::Result<()> {
+    let width = 12;
+    let print_sizes = |out: &mut dyn io::Write, sizes: &Sizes, name: &str| {
+        writeln!(
+            out,
+            "\t{:>width$} {:>width$} {:>8} {}",
+            ByteSize(sizes.compressed).to_string(),
+            ByteSize(sizes.decompressed).to_string(),
+            sizes.num_objects,
+            name,
+            width = width
+        )
+    };
+    let header = format!(
+        "\t{:>width$} {:>width$} {:>8} ",
+        "compressed",
+        "decompressed",
+        "objects",
+        width = width
+    );
+
+    writeln!(out, "by kind")?;
+    writeln!(out, "{}kind", header)?;
+    for (kind, sizes) in &outcome.by_kind {
+        print_sizes(&mut out, sizes, &kind.to_string())?;
+    }
+    print_sizes(&mut out, &outcome.total, "->")?;
+
+    writeln!(out, "\nby path (largest {})", limit)?;
+    writeln!(out, "{}path", header)?;
+    for NamedSizes { name, sizes } in &outcome.by_path {
+        print_sizes(&mut out, sizes, name)?;
+    }
+    print_sizes(&mut out, &outcome.without_path, "<no path>")?;
+
+    writeln!(out, "\nby extension (largest {})", limit)?;
+    writeln!(out, "{}extension", header)?;
+    for NamedSizes { name, sizes } in &outcome.by_extension {
+        print_sizes(&mut out, sizes, if name.is_empty() { "<none>" } else { name })?;
+    }
+
+    let print_object = |out: &mut dyn io::Write, object: &Object| {
+        writeln!(
+            out,
+            "\t{:>width$} {:>width$} {:>6} {} {}",
+            ByteSize(object.compressed).to_string(),
+            ByteSize(object.decompressed).to_string(),
+            object.delta_chain_length,
+            object.id,
+            object.path.as_deref().unwrap_or("<no path>"),
+            width = width
+        )
+    };
+    let header = format!(
+        "\t{:>width$} {:>width$} {:>6} ",
+        "compressed",
+        "decompressed",
+        "deltas",
+        width = width
+    );
+    writeln!(out, "\nlargest blobs")?;
+    writeln!(out, "{}id path", header)?;
+    for object in &outcome.largest_blobs {
+        print_object(&mut out, object)?;
+    }
+
+    writeln!(out, "\ndeepest delta chains")?;
+    writeln!(out, "{}id path", header)?;
+    for object in &outcome.deepest_delta_chains {
+        print_object(&mut out, object)?;
+    }
+    Ok(())
+}
@@ -127,10 +127,10 @@ impl git_odb::Write for OutputWriter {
 }
 
 impl OutputWriter {
-    fn new(path: Option<impl AsRef<Path>>, compress: bool) -> Self {
+    fn new(path: Option<impl AsRef<Path>>, compress: bool, level: git_odb::CompressionLevel) -> Self {
         match path {
-            Some(path) => OutputWriter::Loose(loose::Db::at(path.as_ref())),
-            None => OutputWriter::Sink(git_odb::sink().compress(compress)),
+            Some(path) => OutputWriter::Loose(loose::Db::at(path.as_ref()).with_compression(level)),
+            None => OutputWriter::Sink(git_odb::sink().compress(compress).compression_level(level)),
         }
     }
 }
@@ -140,6 +140,8 @@ pub struct Context {
     pub thread_limit: Option<usize>,
     pub delete_pack: bool,
     pub sink_compress: bool,
+    /// The level from 0 to 9 at which to compress objects, or `None` for the default of zlib
+    pub compression_level: Option<u32>,
     pub verify: bool,
 }
 
@@ -152,6 +154,7 @@ pub fn pack_or_pack_index<P>(
         thread_limit,
         delete_pack,
         sink_compress,
+        compression_level,
         verify,
     }: Context,
 ) -> Result<()>
@@ -162,6 +165,7 @@ where
 {
     use anyhow::Context;
 
+    let compression_level = super::compression_level(compression_level)?;
     let path = pack_path.as_ref();
     let bundle = pack::Bundle::at(path).with_context(|| {
         format!(
@@ -196,7 +200,7 @@ where
         {
             let object_path = object_path.map(|p| p.as_ref().to_owned());
             move || {
-                let out = OutputWriter::new(object_path.clone(), sink_compress);
+                let out = OutputWriter::new(object_path.clone(), sink_compress, compression_level);
                 let object_verifier = if verify {
                     object_path.as_ref().map(loose::Db::at)
                 } else {
@@ -205,18 +209,18 @@ where
                 move |object_kind, buf, index_entry, progress| {
                     let written_id = out
                         .write_buf(object_kind, buf, HashKind::Sha1)
-                        .map_err(|err| Error::Write(Box::new(err) as Box<dyn std::error::Error + Send + Sync>, object_kind, index_entry.oid))?;
+                        .map_err(|err| Error::Write(Box::new(err) as Box<dyn std::error::Error + Send + Sync>, object_kind, index_entry.oid.clone()))?;
                     if written_id != index_entry.oid {
                        if let git_object::Kind::Tree = object_kind {
                            progress.info(format!("The tree in pack named {} was written as {} due to modes 100664 and 100640 rewritten as 100644.", index_entry.oid, written_id));
                        } else {
-                           return Err(Error::ObjectEncodeMismatch(object_kind, index_entry.oid, written_id))
+                           return Err(Error::ObjectEncodeMismatch(object_kind, index_entry.oid.clone(), written_id))
                        }
                     }
                     if let Some(verifier) = object_verifier.as_ref() {
                         let mut obj = verifier.locate(written_id.to_borrowed())
-                                            .ok_or_else(|| Error::WrittenFileMissing(written_id))?
-                                            .map_err(|err| Error::WrittenFileCorrupt(err, written_id))?;
+                                            .ok_or_else(|| Error::WrittenFileMissing(written_id.clone()))?
+                                            .map_err(|err| Error::WrittenFileCorrupt(err, written_id.clone()))?;
                         obj.verify_checksum(written_id.to_borrowed())?;
                     }
                     Ok(())
@@ -237,6 +241,10 @@ where
     if delete_pack {
         fs::remove_file(&index_path)
             .and_then(|_| fs::remove_file(&data_path))
+            .and_then(|_| match fs::remove_file(index_path.with_extension("rev")) {
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                res => res,
+            })
             .with_context(|| {
                 format!(
                     "Failed to delete pack index file at '{} or data file at '{}'",
@@ -83,6 +83,8 @@ where
         thread_limit: ctx.thread_limit,
         iteration_mode: ctx.iteration_mode.into(),
         index_kind: pack::index::Kind::default(),
+        hash_kind: git_object::HashKind::Sha1,
+        compression: git_odb::CompressionLevel::default(),
     };
     let out = ctx.out;
     let format = ctx.format;
@@ -90,11 +92,18 @@ where
         Some(pack) => {
             let pack_len = pack.metadata()?.len();
             let pack_file = fs::File::open(pack)?;
-            pack::Bundle::write_to_directory(pack_file, Some(pack_len), directory, progress, options)
+            pack::Bundle::write_to_directory(
+                pack_file,
+                Some(pack_len),
+                directory,
+                progress,
+                None::<git_odb::loose::Db>,
+                options,
+            )
         }
         None => {
             let stdin = io::stdin();
-            pack::Bundle::write_to_directory(stdin, None, directory, progress, options)
+            pack::Bundle::write_to_directory(stdin, None, directory, progress, None::<git_odb::loose::Db>, options)
         }
     }
     .with_context(|| "Failed to write pack and index")?;
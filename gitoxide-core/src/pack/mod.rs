@@ -1,3 +1,15 @@
+pub mod analyze;
 pub mod explode;
 pub mod index;
+pub mod multi_index;
+pub mod repack;
 pub mod verify;
+
+/// Turn the compression `level` given by the user into one that can be used for writing, or use the default.
+fn compression_level(level: Option<u32>) -> anyhow::Result<git_odb::CompressionLevel> {
+    match level {
+        Some(level) => git_odb::CompressionLevel::new(level)
+            .ok_or_else(|| anyhow::anyhow!("The compression level must be between 0 and 9, got {}", level)),
+        None => Ok(git_odb::CompressionLevel::default()),
+    }
+}
@@ -0,0 +1,67 @@
+use crate::OutputFormat;
+use anyhow::{Context as AnyhowContext, Result};
+use git_features::progress::Progress;
+use git_odb::pack;
+use std::{fs, io, path::Path};
+
+pub use git_odb::pack::multi_index::FILE_NAME;
+
+/// Write a `multi-pack-index` file for all pack indices in `directory`, usually `.git/objects/pack`, replacing
+/// an existing one.
+pub fn create(
+    directory: impl AsRef<Path>,
+    progress: impl Progress,
+    format: OutputFormat,
+    out: impl io::Write,
+) -> Result<()> {
+    let directory = directory.as_ref();
+    let mut indices = Vec::new();
+    for entry in fs::read_dir(directory)
+        .with_context(|| format!("Could not list pack directory at '{}'", directory.display()))?
+    {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) == Some("idx") {
+            indices.push(
+                pack::index::File::at(&path)
+                    .with_context(|| format!("Could not open pack index at '{}'", path.display()))?,
+            );
+        }
+    }
+
+    // Like git, use a lock file to prevent concurrent writers and make the final rename atomic
+    let lock_path = directory.join(format!("{}.lock", FILE_NAME));
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&lock_path)
+        .with_context(|| format!("Could not create lock file at '{}'", lock_path.display()))?;
+    let res = pack::multi_index::File::write_from_indices(indices, io::BufWriter::new(file), progress)
+        .map_err(anyhow::Error::from)
+        .and_then(|outcome| {
+            fs::rename(&lock_path, directory.join(FILE_NAME))?;
+            Ok(outcome)
+        });
+    let outcome = match res {
+        Ok(outcome) => outcome,
+        Err(err) => {
+            fs::remove_file(&lock_path).ok();
+            return Err(err.context("Failed to write multi-pack-index"));
+        }
+    };
+
+    match format {
+        OutputFormat::Human => drop(human_output(out, outcome)),
+        #[cfg(feature = "serde1")]
+        OutputFormat::Json => serde_json::to_writer_pretty(out, &outcome)?,
+    };
+    Ok(())
+}
+
+fn human_output(mut out: impl io::Write, outcome: pack::multi_index::write::Outcome) -> io::Result<()> {
+    writeln!(&mut out, "multi-pack-index: {}", outcome.multi_index_hash)?;
+    writeln!(
+        &mut out,
+        "objects: {} in {} packs ({} duplicates)",
+        outcome.num_objects, outcome.num_packs, outcome.num_duplicates
+    )
+}
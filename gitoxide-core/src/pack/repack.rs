@@ -0,0 +1,83 @@
+use crate::OutputFormat;
+use anyhow::{Context as AnyhowContext, Result};
+use git_features::progress::Progress;
+use git_odb::compound;
+use std::{io, path::Path};
+
+pub struct Context<W: io::Write> {
+    /// The amount of threads to use at most when creating the index
+    pub thread_limit: Option<usize>,
+    /// If set, the deltas of existing packs are not copied and all objects are stored undeltified
+    pub no_reuse_delta: bool,
+    /// If set, all loose objects and packs that are contained in the new pack are deleted, along with the
+    /// multi-pack-index
+    pub delete_redundant: bool,
+    /// The level from 0 to 9 at which to compress objects, or `None` for the default of zlib
+    pub compression_level: Option<u32>,
+    pub format: OutputFormat,
+    pub out: W,
+}
+
+/// Consolidate all loose objects and packs of the object database at `objects_directory`, usually `.git/objects`,
+/// into a single new pack.
+pub fn repack<P>(
+    objects_directory: impl AsRef<Path>,
+    progress: P,
+    Context {
+        thread_limit,
+        no_reuse_delta,
+        delete_redundant,
+        compression_level,
+        format,
+        out,
+    }: Context<impl io::Write>,
+) -> Result<()>
+where
+    P: Progress,
+    <<<P as Progress>::SubProgress as Progress>::SubProgress as Progress>::SubProgress: Send,
+{
+    let objects_directory = objects_directory.as_ref();
+    let db = compound::Db::at(objects_directory)
+        .with_context(|| format!("Could not open object database at '{}'", objects_directory.display()))?;
+    let outcome = db.repack(
+        progress,
+        compound::repack::Options {
+            thread_limit,
+            reuse_deltas: !no_reuse_delta,
+            delete_redundant,
+            compression: super::compression_level(compression_level)?,
+        },
+    )?;
+
+    match format {
+        OutputFormat::Human => drop(human_output(out, outcome)),
+        #[cfg(feature = "serde1")]
+        OutputFormat::Json => serde_json::to_writer_pretty(out, &outcome)?,
+    };
+    Ok(())
+}
+
+fn human_output(mut out: impl io::Write, outcome: compound::repack::Outcome) -> io::Result<()> {
+    match outcome.write.as_ref().and_then(|write| write.data_path.as_ref()) {
+        Some(data_path) => writeln!(
+            &mut out,
+            "pack: {} ({} objects, {} copied entries, {} reused deltas)",
+            data_path.display(),
+            outcome.num_objects,
+            outcome.num_copied_entries,
+            outcome.num_reused_deltas
+        )?,
+        None => writeln!(&mut out, "nothing to pack")?,
+    }
+    writeln!(
+        &mut out,
+        "removed: {} loose objects, {} packs{}",
+        outcome.num_removed_loose_objects,
+        outcome.removed_packs.len(),
+        if outcome.removed_multi_index {
+            ", the multi-pack-index"
+        } else {
+            ""
+        }
+    )
+}
@@ -56,6 +56,12 @@ pub struct Context<W1: io::Write, W2: io::Write> {
     pub thread_limit: Option<usize>,
     pub mode: index::verify::Mode,
     pub algorithm: Algorithm,
+    /// If set, all threads share a cache of decoded objects holding at most this amount of bytes, instead of each using
+    /// a small cache of its own. It is unused if statistics are output.
+    ///
+    /// Only [`Algorithm::LessMemory`] uses a cache, which is why the statistics of cache hits and misses are only
+    /// gathered by it.
+    pub cache_memory_cap: Option<usize>,
 }
 
 impl Default for Context<Vec<u8>, Vec<u8>> {
@@ -65,6 +71,7 @@ impl Default for Context<Vec<u8>, Vec<u8>> {
             thread_limit: None,
             mode: index::verify::Mode::Sha1CRC32,
             algorithm: Algorithm::LessMemory,
+            cache_memory_cap: None,
             out: Vec::new(),
             err: Vec::new(),
         }
@@ -72,16 +79,18 @@ impl Default for Context<Vec<u8>, Vec<u8>> {
 }
 
 #[allow(clippy::large_enum_variant)]
-enum EitherCache {
+enum EitherCache<'a> {
     Left(pack::cache::DecodeEntryNoop),
     Right(pack::cache::DecodeEntryLRU),
+    Shared(&'a pack::cache::DecodeEntryMemoryCapped),
 }
 
-impl pack::cache::DecodeEntry for EitherCache {
-    fn put(&mut self, offset: u64, data: &[u8], kind: Kind, compressed_size: usize) {
+impl<'a> pack::cache::DecodeEntry for EitherCache<'a> {
+    fn put(&mut self, offset: u64, data: &[u8], kind: Kind, compressed_size: usize, delta_chain_length: u32) {
         match self {
-            EitherCache::Left(v) => v.put(offset, data, kind, compressed_size),
-            EitherCache::Right(v) => v.put(offset, data, kind, compressed_size),
+            EitherCache::Left(v) => v.put(offset, data, kind, compressed_size, delta_chain_length),
+            EitherCache::Right(v) => v.put(offset, data, kind, compressed_size, delta_chain_length),
+            EitherCache::Shared(v) => v.put(offset, data, kind, compressed_size, delta_chain_length),
         }
     }
 
@@ -89,6 +98,7 @@ impl pack::cache::DecodeEntry for EitherCache {
         match self {
             EitherCache::Left(v) => v.get(offset, out),
             EitherCache::Right(v) => v.get(offset, out),
+            EitherCache::Shared(v) => v.get(offset, out),
         }
     }
 }
@@ -103,6 +113,7 @@ pub fn pack_or_pack_index<P, W1, W2>(
         output_statistics,
         thread_limit,
         algorithm,
+        cache_memory_cap,
     }: Context<W1, W2>,
 ) -> Result<(owned::Id, Option<index::traverse::Outcome>)>
 where
@@ -111,11 +122,41 @@ where
     W1: io::Write,
     W2: io::Write,
     <<P as Progress>::SubProgress as Progress>::SubProgress: Send,
+    <<<P as Progress>::SubProgress as Progress>::SubProgress as Progress>::SubProgress: Send,
 {
     let path = path.as_ref();
+    let shared_cache = cache_memory_cap.map(pack::cache::DecodeEntryMemoryCapped::new);
+    let cache = || -> EitherCache<'_> {
+        match (output_statistics, shared_cache.as_ref()) {
+            // turn off acceleration as we need to see entire chains all the time
+            (Some(_), _) => EitherCache::Left(pack::cache::DecodeEntryNoop),
+            (None, Some(shared_cache)) => EitherCache::Shared(shared_cache),
+            (None, None) => EitherCache::Right(pack::cache::DecodeEntryLRU::default()),
+        }
+    };
+    if path.file_name().and_then(|name| name.to_str()) == Some("multi-pack-index") {
+        let multi_index = pack::multi_index::File::at(path).with_context(|| "Could not open multi-pack-index file")?;
+        let (id, outcomes, _) = multi_index
+            .verify_integrity(Some((mode, algorithm.into())), thread_limit, progress, cache)
+            .with_context(|| "Verification failure")?;
+        if let Some(outcomes) = outcomes.as_ref() {
+            match output_statistics {
+                Some(OutputFormat::Human) => {
+                    for (name, stats) in multi_index.index_names().iter().zip(outcomes) {
+                        writeln!(out, "{}", name.display())?;
+                        print_statistics(&mut out, stats)?;
+                    }
+                }
+                #[cfg(feature = "serde1")]
+                Some(OutputFormat::Json) => serde_json::to_writer_pretty(out, outcomes)?,
+                _ => {}
+            };
+        }
+        return Ok((id, None));
+    }
     let ext = path.extension().and_then(|ext| ext.to_str()).ok_or_else(|| {
         anyhow!(
-            "Cannot determine data type on path without extension '{}', expecting default extensions 'idx' and 'pack'",
+            "Cannot determine data type on path without extension '{}', expecting default extensions 'idx' and 'pack' or a 'multi-pack-index' file",
             path.display()
         )
     })?;
@@ -140,14 +181,6 @@ where
                     e
                 })
                 .ok();
-            let cache = || -> EitherCache {
-                if output_statistics.is_some() {
-                    // turn off acceleration as we need to see entire chains all the time
-                    EitherCache::Left(pack::cache::DecodeEntryNoop)
-                } else {
-                    EitherCache::Right(pack::cache::DecodeEntryLRU::default())
-                }
-            };
 
             idx.verify_integrity(
                 pack.as_ref().map(|p| (p, mode, algorithm.into())),
@@ -1,5 +1,7 @@
 use anyhow::{Context as AnyhowContext, Result};
 
+pub mod statistics;
+
 pub fn init() -> Result<()> {
     git_repository::init::repository().with_context(|| "Repository initialization failed")
 }
@@ -0,0 +1,424 @@
+use crate::OutputFormat;
+use anyhow::{Context as AnyhowContext, Result};
+use bytesize::ByteSize;
+use git_features::progress::{self, Progress};
+use git_object::{
+    borrowed,
+    bstr::{BString, ByteSlice},
+    owned, Kind, TreeMode,
+};
+use git_odb::{compound, pack};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    io,
+    path::Path,
+};
+
+pub struct Context<W: io::Write> {
+    /// The amount of objects to list for each statistic, the largest or deepest first
+    pub limit: usize,
+    /// Blobs of this size in bytes or larger are listed
+    pub blob_size_threshold: u64,
+    pub format: OutputFormat,
+    pub out: W,
+}
+
+/// The amount and total size of a set of objects
+#[derive(Default, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sizes {
+    pub num_objects: u64,
+    /// The sum of the decompressed sizes of all objects
+    pub total_size: u64,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct ObjectSize {
+    pub id: owned::Id,
+    /// The size of the decompressed object in bytes
+    pub size: u64,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct TreeEntries {
+    pub id: owned::Id,
+    pub num_entries: usize,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct TreeDepth {
+    /// A commit pointing to `tree`
+    pub commit: owned::Id,
+    pub tree: owned::Id,
+    /// The amount of nested trees, with a tree containing only blobs having a depth of 1
+    pub depth: u32,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct LongestPath {
+    /// A commit pointing to `tree`
+    pub commit: owned::Id,
+    pub tree: owned::Id,
+    /// The longest path of any entry in `tree` and its subtrees
+    pub path: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct TagChain {
+    pub id: owned::Id,
+    /// The amount of tags to peel until reaching an object that isn't a tag, 1 for tags pointing to anything else
+    pub depth: u32,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Outcome {
+    pub by_kind: BTreeMap<Kind, Sizes>,
+    pub biggest_commits: Vec<ObjectSize>,
+    pub trees_with_most_entries: Vec<TreeEntries>,
+    /// The root trees of commits with the deepest nesting of trees
+    pub deepest_trees: Vec<TreeDepth>,
+    /// The root trees of commits containing the longest paths
+    pub longest_paths: Vec<LongestPath>,
+    pub blob_size_threshold: u64,
+    /// All blobs at least `blob_size_threshold` bytes in size
+    pub blobs_over_threshold: Vec<ObjectSize>,
+    pub deepest_tag_chains: Vec<TagChain>,
+}
+
+/// The shape of a tree and all of its subtrees
+#[derive(Default, Clone)]
+struct TreeShape {
+    depth: u32,
+    longest_path: BString,
+}
+
+/// Produce statistics about all objects in the object database at `objects_directory` and its alternates, to find
+/// objects which are unusually large or deeply nested, similar to `git-sizer`.
+pub fn statistics(
+    objects_directory: impl AsRef<Path>,
+    mut progress: impl Progress,
+    Context {
+        limit,
+        blob_size_threshold,
+        format,
+        out,
+    }: Context<impl io::Write>,
+) -> Result<()> {
+    let objects_directory = objects_directory.as_ref();
+    let db = compound::Db::at(objects_directory)
+        .with_context(|| format!("Could not open object database at '{}'", objects_directory.display()))?;
+    let mut buf = Vec::new();
+    let mut cache = pack::cache::DecodeEntryLRU::default();
+    progress.init(Some(3), progress::steps());
+
+    let ids = {
+        let mut progress = progress.add_child("enumerate objects");
+        progress.init(None, progress::count("objects"));
+        let ids = object_ids(&db)?;
+        progress.inc_by(ids.len());
+        ids
+    };
+    progress.inc();
+
+    let mut by_kind = BTreeMap::<Kind, Sizes>::new();
+    let mut biggest_commits = Vec::new();
+    let mut blobs_over_threshold = Vec::new();
+    let mut commits = Vec::new();
+    let mut trees_with_most_entries = Vec::new();
+    let mut tag_targets = HashMap::new();
+    {
+        let mut progress = progress.add_child("classify objects");
+        progress.init(Some(ids.len()), progress::count("objects"));
+        for id in ids {
+            let (kind, size) = db
+                .header(id.to_borrowed())
+                .expect("enumerated objects exist")
+                .with_context(|| format!("Could not decode header of object {}", id))?;
+            let sizes = by_kind.entry(kind).or_default();
+            sizes.num_objects += 1;
+            sizes.total_size += size;
+            match kind {
+                Kind::Blob => {
+                    if size >= blob_size_threshold {
+                        blobs_over_threshold.push(ObjectSize { id, size });
+                    }
+                }
+                Kind::Commit => {
+                    let data = locate(&db, id.to_borrowed(), &mut buf, &mut cache)?;
+                    let commit =
+                        borrowed::Commit::from_bytes(data).with_context(|| format!("Could not parse commit {}", id))?;
+                    commits.push((id.clone(), commit.tree()));
+                    biggest_commits.push(ObjectSize { id, size });
+                }
+                Kind::Tree => {
+                    let data = locate(&db, id.to_borrowed(), &mut buf, &mut cache)?;
+                    let tree = borrowed::Tree::from_bytes_with_hash(data, id.kind())
+                        .with_context(|| format!("Could not parse tree {}", id))?;
+                    trees_with_most_entries.push(TreeEntries {
+                        num_entries: tree.entries.len(),
+                        id,
+                    });
+                }
+                Kind::Tag => {
+                    let data = locate(&db, id.to_borrowed(), &mut buf, &mut cache)?;
+                    let tag = borrowed::Tag::from_bytes(data).with_context(|| format!("Could not parse tag {}", id))?;
+                    tag_targets.insert(id, (tag.target_kind == Kind::Tag).then(|| tag.target()));
+                }
+            }
+            progress.inc();
+        }
+    }
+    progress.inc();
+
+    let (mut deepest_trees, mut longest_paths) = {
+        let mut progress = progress.add_child("walk trees");
+        progress.init(Some(commits.len()), progress::count("commits"));
+        let mut shapes = HashMap::new();
+        let mut seen_trees = HashSet::new();
+        let (mut deepest_trees, mut longest_paths) = (Vec::new(), Vec::new());
+        for (commit, tree) in commits {
+            progress.inc();
+            if !seen_trees.insert(tree.clone()) {
+                continue;
+            }
+            let shape = tree_shape(&db, &tree, &mut shapes, &mut buf, &mut cache)?;
+            deepest_trees.push(TreeDepth {
+                commit: commit.clone(),
+                tree: tree.clone(),
+                depth: shape.depth,
+            });
+            longest_paths.push(LongestPath {
+                commit,
+                tree,
+                path: shape.longest_path.to_str_lossy().into_owned(),
+            });
+        }
+        (deepest_trees, longest_paths)
+    };
+    progress.inc();
+
+    let mut deepest_tag_chains: Vec<_> = tag_targets
+        .keys()
+        .map(|id| TagChain {
+            id: id.clone(),
+            depth: tag_chain_depth(id, &tag_targets),
+        })
+        .collect();
+
+    biggest_commits.sort_by(|a, b| b.size.cmp(&a.size).then(a.id.cmp(&b.id)));
+    biggest_commits.truncate(limit);
+    trees_with_most_entries.sort_by(|a, b| b.num_entries.cmp(&a.num_entries).then(a.id.cmp(&b.id)));
+    trees_with_most_entries.truncate(limit);
+    deepest_trees.sort_by(|a, b| b.depth.cmp(&a.depth).then(a.tree.cmp(&b.tree)));
+    deepest_trees.truncate(limit);
+    longest_paths.sort_by(|a, b| b.path.len().cmp(&a.path.len()).then(a.tree.cmp(&b.tree)));
+    longest_paths.truncate(limit);
+    blobs_over_threshold.sort_by(|a, b| b.size.cmp(&a.size).then(a.id.cmp(&b.id)));
+    deepest_tag_chains.sort_by(|a, b| b.depth.cmp(&a.depth).then(a.id.cmp(&b.id)));
+    deepest_tag_chains.truncate(limit);
+
+    let outcome = Outcome {
+        by_kind,
+        biggest_commits,
+        trees_with_most_entries,
+        deepest_trees,
+        longest_paths,
+        blob_size_threshold,
+        blobs_over_threshold,
+        deepest_tag_chains,
+    };
+    match format {
+        OutputFormat::Human => drop(human_output(out, &outcome)),
+        #[cfg(feature = "serde1")]
+        OutputFormat::Json => serde_json::to_writer_pretty(out, &outcome)?,
+    };
+    Ok(())
+}
+
+/// All ids of our packs and loose objects and those of our alternates, in order and without duplicates.
+fn object_ids(db: &compound::Db) -> Result<BTreeSet<owned::Id>> {
+    let mut ids = BTreeSet::new();
+    for db in std::iter::once(db).chain(db.alternates.iter()) {
+        for bundle in &db.bundles {
+            let entries = bundle
+                .index
+                .iter()
+                .with_context(|| format!("Could not list objects in '{}'", bundle.index.path().display()))?;
+            ids.extend(entries.map(|entry| entry.oid));
+        }
+        for id in db.loose.iter() {
+            ids.insert(id.with_context(|| format!("Could not list loose objects in '{}'", db.loose.path.display()))?);
+        }
+    }
+    Ok(ids)
+}
+
+fn locate<'a>(
+    db: &compound::Db,
+    id: borrowed::Id,
+    buf: &'a mut Vec<u8>,
+    cache: &mut pack::cache::DecodeEntryLRU,
+) -> Result<&'a [u8]> {
+    Ok(db
+        .locate(id, buf, cache)
+        .expect("enumerated objects exist")
+        .with_context(|| format!("Could not decode object {}", id))?
+        .data)
+}
+
+/// Compute the shape of the tree with `id` from the shapes of its subtrees, which are computed first and remembered
+/// in `shapes`. Subtrees which are missing or which contain themselves are considered empty.
+fn tree_shape(
+    db: &compound::Db,
+    id: &owned::Id,
+    shapes: &mut HashMap<owned::Id, TreeShape>,
+    buf: &mut Vec<u8>,
+    cache: &mut pack::cache::DecodeEntryLRU,
+) -> Result<TreeShape> {
+    // Subtrees and the longest blob path of trees whose subtrees are still being computed
+    let mut pending = HashMap::<owned::Id, (Vec<(owned::Id, BString)>, BString)>::new();
+    let mut stack = vec![(id.clone(), false)];
+    while let Some((id, subtrees_done)) = stack.pop() {
+        if shapes.contains_key(&id) {
+            continue;
+        }
+        if subtrees_done {
+            let (subtrees, longest_blob_path) = pending.remove(&id).expect("pending until subtrees are done");
+            let mut shape = TreeShape {
+                depth: 1,
+                longest_path: longest_blob_path,
+            };
+            for (subtree, name) in subtrees {
+                let subtree_shape = shapes.get(&subtree).cloned().unwrap_or_default();
+                shape.depth = shape.depth.max(subtree_shape.depth + 1);
+                let mut path = name;
+                if !subtree_shape.longest_path.is_empty() {
+                    path.push(b'/');
+                    path.extend_from_slice(&subtree_shape.longest_path);
+                }
+                if path.len() > shape.longest_path.len() {
+                    shape.longest_path = path;
+                }
+            }
+            shapes.insert(id, shape);
+            continue;
+        }
+        if pending.contains_key(&id) {
+            continue;
+        }
+        let data = match db.locate(id.to_borrowed(), buf, cache) {
+            Some(res) => res.with_context(|| format!("Could not decode tree {}", id))?.data,
+            None => continue,
+        };
+        let tree = borrowed::Tree::from_bytes_with_hash(data, id.kind())
+            .with_context(|| format!("Could not parse tree {}", id))?;
+        let mut subtrees = Vec::new();
+        let mut longest_blob_path = BString::default();
+        for entry in tree.entries {
+            if entry.mode == TreeMode::Tree {
+                subtrees.push((entry.oid.into(), entry.filename.to_owned()));
+            } else if entry.filename.len() > longest_blob_path.len() {
+                longest_blob_path = entry.filename.to_owned();
+            }
+        }
+        stack.push((id.clone(), true));
+        stack.extend(
+            subtrees
+                .iter()
+                .filter(|(subtree, _)| !shapes.contains_key(subtree) && !pending.contains_key(subtree))
+                .map(|(subtree, _)| (subtree.clone(), false)),
+        );
+        pending.insert(id, (subtrees, longest_blob_path));
+    }
+    Ok(shapes.get(id).cloned().unwrap_or_default())
+}
+
+/// The amount of tags to peel, starting at the tag with `id`, until reaching an object that isn't a known tag.
+fn tag_chain_depth(id: &owned::Id, tag_targets: &HashMap<owned::Id, Option<owned::Id>>) -> u32 {
+    let mut depth = 1;
+    let mut target = tag_targets.get(id).cloned().flatten();
+    while let Some(id) = target {
+        match tag_targets.get(&id) {
+            // stop at tags pointing to themselves, which would otherwise never end
+            Some(next) if (depth as usize) <= tag_targets.len() => {
+                depth += 1;
+                target = next.clone();
+            }
+            _ => break,
+        }
+    }
+    depth
+}
+
+fn human_output(mut out: impl io::Write, outcome: &Outcome) -> io::Result<()> {
+    let width = 12;
+    writeln!(out, "objects")?;
+    writeln!(out, "\t{:>8} {:>width$} kind", "count", "size", width = width)?;
+    let mut total = Sizes::default();
+    for (kind, sizes) in &outcome.by_kind {
+        total.num_objects += sizes.num_objects;
+        total.total_size += sizes.total_size;
+        writeln!(
+            out,
+            "\t{:>8} {:>width$} {}",
+            sizes.num_objects,
+            ByteSize(sizes.total_size),
+            kind,
+            width = width
+        )?;
+    }
+    writeln!(
+        out,
+        "\t{:>8} {:>width$} ->",
+        total.num_objects,
+        ByteSize(total.total_size),
+        width = width
+    )?;
+
+    writeln!(out, "\nbiggest commits")?;
+    for ObjectSize { id, size } in &outcome.biggest_commits {
+        writeln!(out, "\t{:>width$} {}", ByteSize(*size), id, width = width)?;
+    }
+
+    writeln!(out, "\ntrees with the most entries")?;
+    for TreeEntries { id, num_entries } in &outcome.trees_with_most_entries {
+        writeln!(out, "\t{:>width$} {}", num_entries, id, width = width)?;
+    }
+
+    writeln!(out, "\ndeepest trees")?;
+    writeln!(out, "\t{:>width$} tree (commit)", "depth", width = width)?;
+    for TreeDepth { commit, tree, depth } in &outcome.deepest_trees {
+        writeln!(out, "\t{:>width$} {} ({})", depth, tree, commit, width = width)?;
+    }
+
+    writeln!(out, "\nlongest paths")?;
+    writeln!(out, "\t{:>width$} tree (commit) path", "length", width = width)?;
+    for LongestPath { commit, tree, path } in &outcome.longest_paths {
+        writeln!(
+            out,
+            "\t{:>width$} {} ({}) {}",
+            path.len(),
+            tree,
+            commit,
+            path,
+            width = width
+        )?;
+    }
+
+    writeln!(out, "\nblobs of {} or more", ByteSize(outcome.blob_size_threshold))?;
+    for ObjectSize { id, size } in &outcome.blobs_over_threshold {
+        writeln!(out, "\t{:>width$} {}", ByteSize(*size), id, width = width)?;
+    }
+
+    writeln!(out, "\ndeepest tag chains")?;
+    for TagChain { id, depth } in &outcome.deepest_tag_chains {
+        writeln!(out, "\t{:>width$} {}", depth, id, width = width)?;
+    }
+    Ok(())
+}
@@ -31,6 +31,10 @@ mod options {
         PackVerify(PackVerify),
         PackExplode(PackExplode),
         IndexFromPack(IndexFromPack),
+        MultiIndexCreate(MultiIndexCreate),
+        PackRepack(PackRepack),
+        PackAnalyze(PackAnalyze),
+        RepositoryStatistics(RepositoryStatistics),
     }
     /// Create an index from a packfile.
     ///
@@ -60,6 +64,74 @@ mod options {
         #[argh(positional)]
         pub directory: Option<PathBuf>,
     }
+    /// Create a multi-pack-index for all packs in a directory.
+    #[derive(FromArgs, PartialEq, Debug)]
+    #[argh(subcommand, name = "pack-multi-index-create")]
+    pub struct MultiIndexCreate {
+        /// the directory containing the '.idx' and '.pack' files, commonly '.git/objects/pack'.
+        ///
+        /// The 'multi-pack-index' file will be written into it.
+        #[argh(positional)]
+        pub directory: PathBuf,
+    }
+    /// Consolidate all loose objects and packs of an object database into a single new pack.
+    ///
+    /// The new pack is written atomically, and nothing is deleted unless '--delete-redundant' is given.
+    /// Packs with a '.keep' file are neither repacked nor deleted.
+    #[derive(FromArgs, PartialEq, Debug)]
+    #[argh(subcommand, name = "pack-repack")]
+    pub struct PackRepack {
+        /// delete all loose objects and packs that are contained in the new pack once it was written.
+        #[argh(switch, short = 'd')]
+        pub delete_redundant: bool,
+
+        /// do not copy the deltas of existing packs, but store all objects undeltified.
+        #[argh(switch)]
+        pub no_reuse_delta: bool,
+
+        /// the level from 0 (no compression) to 9 (best compression) at which to compress objects,
+        /// defaulting to the one of zlib.
+        ///
+        /// Entries copied from existing packs keep their compression.
+        #[argh(option)]
+        pub compression_level: Option<u32>,
+
+        /// the object directory to repack, commonly '.git/objects'.
+        #[argh(positional)]
+        pub objects_directory: PathBuf,
+    }
+    /// Attribute the size of each entry in a pack to its path and kind of object.
+    ///
+    /// Paths are found by walking the trees of all commits in the pack, newest first.
+    #[derive(FromArgs, PartialEq, Debug)]
+    #[argh(subcommand, name = "pack-analyze")]
+    pub struct PackAnalyze {
+        /// the amount of paths, extensions, blobs and delta chains to show, the largest or deepest first.
+        #[argh(option, short = 'l', default = "20")]
+        pub limit: usize,
+
+        /// the '.pack' or '.idx' file to analyze.
+        #[argh(positional)]
+        pub path: PathBuf,
+    }
+    /// Report the largest and most deeply nested objects of an object database and its alternates.
+    ///
+    /// This helps to find what makes a repository slow to work with, similar to 'git-sizer'.
+    #[derive(FromArgs, PartialEq, Debug)]
+    #[argh(subcommand, name = "repository-statistics")]
+    pub struct RepositoryStatistics {
+        /// the amount of commits, trees and tags to show for each statistic, the largest or deepest first.
+        #[argh(option, short = 'l', default = "20")]
+        pub limit: usize,
+
+        /// list all blobs of this size in bytes or larger.
+        #[argh(option, short = 'b', default = "1048576")]
+        pub blob_size_threshold: u64,
+
+        /// the object directory to inspect, commonly '.git/objects'.
+        #[argh(positional)]
+        pub objects_directory: PathBuf,
+    }
     /// Explode a pack into loose objects.
     ///
     /// This can be useful in case of partially invalidated packs to extract as much information as possible,
@@ -85,6 +157,13 @@ mod options {
         #[argh(switch)]
         pub sink_compress: bool,
 
+        /// the level from 0 (no compression) to 9 (best compression) at which to compress objects,
+        /// defaulting to the one of zlib.
+        ///
+        /// Level 0 is useful for fast scratch object databases.
+        #[argh(option)]
+        pub compression_level: Option<u32>,
+
         /// the amount of checks to run. Defaults to 'all'.
         ///
         /// Allowed values:
@@ -130,10 +209,17 @@ mod options {
         /// Possible values are "less-time" and "less-memory". Default is "less-memory".
         pub algorithm: Option<core::pack::verify::Algorithm>,
 
+        /// share a cache of decoded objects of at most this amount of bytes among all threads, instead of using
+        /// a small cache per thread.
+        ///
+        /// Only the 'less-memory' algorithm uses a cache, and it is turned off when outputting statistics.
+        #[argh(option)]
+        pub cache_memory_cap: Option<usize>,
+
         /// output statistical information about the pack
         #[argh(switch, short = 's')]
         pub statistics: bool,
-        /// the '.pack' or '.idx' file whose checksum to validate.
+        /// the '.pack' or '.idx' file whose checksum to validate, or a 'multi-pack-index' file to verify along with all its packs.
         #[argh(positional)]
         pub path: PathBuf,
     }
@@ -195,9 +281,68 @@ pub fn main() -> Result<()> {
                 },
             )
         }
+        SubCommands::MultiIndexCreate(MultiIndexCreate { directory }) => {
+            let (_handle, progress) = prepare(verbose, "pack-multi-index-create", None);
+            core::pack::multi_index::create(
+                directory,
+                progress::DoOrDiscard::from(progress),
+                OutputFormat::Human,
+                io::stdout(),
+            )
+        }
+        SubCommands::PackRepack(PackRepack {
+            delete_redundant,
+            no_reuse_delta,
+            compression_level,
+            objects_directory,
+        }) => {
+            let (_handle, progress) = prepare(verbose, "pack-repack", None);
+            core::pack::repack::repack(
+                objects_directory,
+                progress::DoOrDiscard::from(progress),
+                core::pack::repack::Context {
+                    thread_limit,
+                    no_reuse_delta,
+                    delete_redundant,
+                    compression_level,
+                    format: OutputFormat::Human,
+                    out: io::stdout(),
+                },
+            )
+        }
+        SubCommands::PackAnalyze(PackAnalyze { limit, path }) => {
+            let (_handle, progress) = prepare(verbose, "pack-analyze", None);
+            core::pack::analyze::pack_or_pack_index(
+                path,
+                progress::DoOrDiscard::from(progress),
+                core::pack::analyze::Context {
+                    limit,
+                    format: OutputFormat::Human,
+                    out: io::stdout(),
+                },
+            )
+        }
+        SubCommands::RepositoryStatistics(RepositoryStatistics {
+            limit,
+            blob_size_threshold,
+            objects_directory,
+        }) => {
+            let (_handle, progress) = prepare(verbose, "repository-statistics", None);
+            core::repository::statistics::statistics(
+                objects_directory,
+                progress::DoOrDiscard::from(progress),
+                core::repository::statistics::Context {
+                    limit,
+                    blob_size_threshold,
+                    format: OutputFormat::Human,
+                    out: io::stdout(),
+                },
+            )
+        }
         SubCommands::PackExplode(PackExplode {
             pack_path,
             sink_compress,
+            compression_level,
             object_path,
             verify,
             check,
@@ -213,6 +358,7 @@ pub fn main() -> Result<()> {
                     thread_limit,
                     delete_pack,
                     sink_compress,
+                    compression_level,
                     verify,
                 },
             )
@@ -221,6 +367,7 @@ pub fn main() -> Result<()> {
             path,
             statistics,
             algorithm,
+            cache_memory_cap,
             decode,
             re_encode,
         }) => {
@@ -236,6 +383,7 @@ pub fn main() -> Result<()> {
                         None
                     },
                     algorithm: algorithm.unwrap_or(verify::Algorithm::LessTime),
+                    cache_memory_cap,
                     thread_limit,
                     mode: match (decode, re_encode) {
                         (true, false) => verify::Mode::Sha1CRC32Decode,
@@ -84,6 +84,74 @@ mod options {
             #[clap(parse(from_os_str))]
             directory: Option<PathBuf>,
         },
+        /// Create a multi-pack-index for all packs in a directory.
+        #[clap(setting = AppSettings::ColoredHelp)]
+        #[clap(setting = AppSettings::DisableVersion)]
+        PackMultiIndexCreate {
+            /// The directory containing the '.idx' and '.pack' files, commonly '.git/objects/pack'.
+            ///
+            /// The 'multi-pack-index' file will be written into it.
+            #[clap(parse(from_os_str))]
+            directory: PathBuf,
+        },
+        /// Attribute the size of each entry in a pack to its path and kind of object.
+        ///
+        /// Paths are found by walking the trees of all commits in the pack, newest first.
+        #[clap(setting = AppSettings::ColoredHelp)]
+        #[clap(setting = AppSettings::DisableVersion)]
+        PackAnalyze {
+            /// The amount of paths, extensions, blobs and delta chains to show, the largest or deepest first.
+            #[clap(long, short = "l", default_value = "20")]
+            limit: usize,
+
+            /// The '.pack' or '.idx' file to analyze.
+            #[clap(parse(from_os_str))]
+            path: PathBuf,
+        },
+        /// Report the largest and most deeply nested objects of an object database and its alternates.
+        ///
+        /// This helps to find what makes a repository slow to work with, similar to 'git-sizer'.
+        #[clap(setting = AppSettings::ColoredHelp)]
+        #[clap(setting = AppSettings::DisableVersion)]
+        RepositoryStatistics {
+            /// The amount of commits, trees and tags to show for each statistic, the largest or deepest first.
+            #[clap(long, short = "l", default_value = "20")]
+            limit: usize,
+
+            /// List all blobs of this size in bytes or larger.
+            #[clap(long, short = "b", default_value = "1048576")]
+            blob_size_threshold: u64,
+
+            /// The object directory to inspect, commonly '.git/objects'.
+            #[clap(parse(from_os_str))]
+            objects_directory: PathBuf,
+        },
+        /// Consolidate all loose objects and packs of an object database into a single new pack.
+        ///
+        /// The new pack is written atomically, and nothing is deleted unless '--delete-redundant' is given.
+        /// Packs with a '.keep' file are neither repacked nor deleted.
+        #[clap(setting = AppSettings::ColoredHelp)]
+        #[clap(setting = AppSettings::DisableVersion)]
+        PackRepack {
+            /// Delete all loose objects and packs that are contained in the new pack once it was written.
+            #[clap(long, short = "d")]
+            delete_redundant: bool,
+
+            /// Do not copy the deltas of existing packs, but store all objects undeltified.
+            #[clap(long)]
+            no_reuse_delta: bool,
+
+            /// The level from 0 (no compression) to 9 (best compression) at which to compress objects,
+            /// defaulting to the one of zlib.
+            ///
+            /// Entries copied from existing packs keep their compression.
+            #[clap(long)]
+            compression_level: Option<u32>,
+
+            /// The object directory to repack, commonly '.git/objects'.
+            #[clap(parse(from_os_str))]
+            objects_directory: PathBuf,
+        },
         /// Verify the integrity of a pack or index file
         #[clap(setting = AppSettings::ColoredHelp)]
         #[clap(setting = AppSettings::DisableVersion)]
@@ -115,6 +183,13 @@ mod options {
             #[clap(long)]
             sink_compress: bool,
 
+            /// The level from 0 (no compression) to 9 (best compression) at which to compress objects,
+            /// defaulting to the one of zlib.
+            ///
+            /// Level 0 is useful for fast scratch object databases.
+            #[clap(long)]
+            compression_level: Option<u32>,
+
             /// The '.pack' or '.idx' file to explode into loose objects
             #[clap(parse(from_os_str))]
             pack_path: PathBuf,
@@ -123,7 +198,7 @@ mod options {
             #[clap(parse(from_os_str))]
             object_path: Option<PathBuf>,
         },
-        /// Verify the integrity of a pack or index file
+        /// Verify the integrity of a pack, index or multi-pack-index file
         #[clap(setting = AppSettings::ColoredHelp)]
         #[clap(setting = AppSettings::DisableVersion)]
         PackVerify {
@@ -139,6 +214,13 @@ mod options {
             )]
             algorithm: core::pack::verify::Algorithm,
 
+            /// Share a cache of decoded objects of at most this amount of bytes among all threads, instead of using
+            /// a small cache per thread.
+            ///
+            /// Only the 'less-memory' algorithm uses a cache, and it is turned off when outputting statistics.
+            #[clap(long)]
+            cache_memory_cap: Option<usize>,
+
             #[clap(long, conflicts_with("re-encode"))]
             /// Decode and parse tags, commits and trees to validate their correctness beyond hashing correctly.
             ///
@@ -155,7 +237,7 @@ mod options {
             /// owned objects, causing plenty of allocation to occour.
             re_encode: bool,
 
-            /// The '.pack' or '.idx' file whose checksum to validate.
+            /// The '.pack' or '.idx' file whose checksum to validate, or a 'multi-pack-index' file to verify along with all its packs.
             #[clap(parse(from_os_str))]
             path: PathBuf,
         },
@@ -304,9 +386,88 @@ pub fn main() -> Result<()> {
                 )
             },
         ),
+        Subcommands::PackMultiIndexCreate { directory } => prepare_and_run(
+            "pack-multi-index-create",
+            verbose,
+            progress,
+            progress_keep_open,
+            None,
+            move |progress, out, _err| {
+                core::pack::multi_index::create(
+                    directory,
+                    git_features::progress::DoOrDiscard::from(progress),
+                    format,
+                    out,
+                )
+            },
+        ),
+        Subcommands::PackAnalyze { limit, path } => prepare_and_run(
+            "pack-analyze",
+            verbose,
+            progress,
+            progress_keep_open,
+            None,
+            move |progress, out, _err| {
+                core::pack::analyze::pack_or_pack_index(
+                    path,
+                    git_features::progress::DoOrDiscard::from(progress),
+                    core::pack::analyze::Context { limit, format, out },
+                )
+            },
+        ),
+        Subcommands::RepositoryStatistics {
+            limit,
+            blob_size_threshold,
+            objects_directory,
+        } => prepare_and_run(
+            "repository-statistics",
+            verbose,
+            progress,
+            progress_keep_open,
+            None,
+            move |progress, out, _err| {
+                core::repository::statistics::statistics(
+                    objects_directory,
+                    git_features::progress::DoOrDiscard::from(progress),
+                    core::repository::statistics::Context {
+                        limit,
+                        blob_size_threshold,
+                        format,
+                        out,
+                    },
+                )
+            },
+        ),
+        Subcommands::PackRepack {
+            delete_redundant,
+            no_reuse_delta,
+            compression_level,
+            objects_directory,
+        } => prepare_and_run(
+            "pack-repack",
+            verbose,
+            progress,
+            progress_keep_open,
+            None,
+            move |progress, out, _err| {
+                core::pack::repack::repack(
+                    objects_directory,
+                    git_features::progress::DoOrDiscard::from(progress),
+                    core::pack::repack::Context {
+                        thread_limit,
+                        no_reuse_delta,
+                        delete_redundant,
+                        compression_level,
+                        format,
+                        out,
+                    },
+                )
+            },
+        ),
         Subcommands::PackExplode {
             check,
             sink_compress,
+            compression_level,
             delete_pack,
             pack_path,
             object_path,
@@ -327,6 +488,7 @@ pub fn main() -> Result<()> {
                         thread_limit,
                         delete_pack,
                         sink_compress,
+                        compression_level,
                         verify,
                     },
                 )
@@ -335,6 +497,7 @@ pub fn main() -> Result<()> {
         Subcommands::PackVerify {
             path,
             algorithm,
+            cache_memory_cap,
             decode,
             re_encode,
             statistics,
@@ -358,6 +521,7 @@ pub fn main() -> Result<()> {
                         output_statistics,
                         thread_limit,
                         algorithm,
+                        cache_memory_cap,
                         mode,
                         out,
                         err,
@@ -228,6 +228,12 @@ snapshot="$snapshot/plumbing"
         expect_run $SUCCESSFULLY "$exe_plumbing" pack-verify --algorithm less-memory --decode "$PACK_INDEX_FILE"
       }
     )
+    (with "a cache shared among threads"
+      it "verifies the pack index successfully and with desired output" && {
+        WITH_SNAPSHOT="$snapshot/index-success" \
+        expect_run $SUCCESSFULLY "$exe_plumbing" pack-verify --algorithm less-memory --cache-memory-cap 1000000 "$PACK_INDEX_FILE"
+      }
+    )
     (with "re-encode"
       it "verifies the pack index successfully and with desired output, and re-encodes all objects" && {
         WITH_SNAPSHOT="$snapshot/index-success" \
@@ -255,3 +261,112 @@ snapshot="$snapshot/plumbing"
     )
   )
 )
+
+(when "running 'pack-analyze"
+  snapshot="$snapshot/pack-analyze"
+  PACK_INDEX_FILE="$fixtures/packs/pack-11fdfa9e156ab73caae3b6da867192221f2089c2.idx"
+  (with "a valid pack INDEX file"
+    it "attributes the size of all entries to paths and kinds" && {
+      WITH_SNAPSHOT="$snapshot/index-success" \
+      expect_run $SUCCESSFULLY "$exe_plumbing" pack-analyze --limit 3 "$PACK_INDEX_FILE"
+    }
+    if test "$kind" = "max"; then
+    (with "--format json"
+      it "attributes the size of all entries to paths and kinds and outputs information as JSON" && {
+        WITH_SNAPSHOT="$snapshot/index-json-success" \
+        expect_run $SUCCESSFULLY "$exe_plumbing" --format json pack-analyze --limit 1 "$PACK_INDEX_FILE"
+      }
+    )
+    fi
+  )
+)
+
+(when "running 'pack-multi-index-create"
+  snapshot="$snapshot/pack-multi-index-create"
+  (sandbox
+    (with "a directory containing multiple packs"
+      mkdir pack
+      cp "$fixtures/packs/pack-"*.{idx,pack} pack/
+      it "writes the multi-pack-index and reports what it contains" && {
+        WITH_SNAPSHOT="$snapshot/success" \
+        expect_run $SUCCESSFULLY "$exe_plumbing" pack-multi-index-create pack
+      }
+      it "places the multi-pack-index next to the packs" && {
+        WITH_SNAPSHOT="$snapshot/success-content" \
+        expect_run $SUCCESSFULLY ls pack
+      }
+      (with_program git
+        it "writes a multi-pack-index which git can verify" && {
+          expect_run_sh $SUCCESSFULLY "git init -q --bare repo.git && cp pack/* repo.git/objects/pack/ && git -C repo.git multi-pack-index verify"
+        }
+      )
+      if test "$kind" = "max"; then
+      (with "--format json"
+        it "replaces the multi-pack-index and reports what it contains as JSON" && {
+          WITH_SNAPSHOT="$snapshot/success-json" \
+          expect_run $SUCCESSFULLY "$exe_plumbing" --format json pack-multi-index-create pack
+        }
+      )
+      fi
+    )
+  )
+)
+
+(when "running 'pack-repack"
+  snapshot="$snapshot/pack-repack"
+  (sandbox
+    (with "an object directory with a multi-pack-index and multiple packs, one of which is kept"
+      mkdir -p objects/pack
+      cp "$fixtures/packs/pack-"*.{idx,pack} objects/pack/
+      touch objects/pack/pack-c0438c19fb16422b6bbcce24387b3264416d485b.keep
+      "$exe_plumbing" pack-multi-index-create objects/pack > /dev/null
+      (with "no deletion of redundant packs"
+        it "writes a new pack of all objects which aren't in the kept pack" && {
+          WITH_SNAPSHOT="$snapshot/keep-redundant-success" \
+          expect_run $SUCCESSFULLY "$exe_plumbing" pack-repack objects
+        }
+        it "keeps all packs and the multi-pack-index" && {
+          WITH_SNAPSHOT="$snapshot/keep-redundant-content" \
+          expect_run $SUCCESSFULLY ls objects/pack
+        }
+      )
+      (with "--delete-redundant"
+        it "writes the same pack and deletes the redundant pack along with the multi-pack-index" && {
+          WITH_SNAPSHOT="$snapshot/delete-redundant-success" \
+          expect_run $SUCCESSFULLY "$exe_plumbing" pack-repack --delete-redundant objects
+        }
+        it "keeps only the new and the kept pack" && {
+          WITH_SNAPSHOT="$snapshot/delete-redundant-content" \
+          expect_run $SUCCESSFULLY ls objects/pack
+        }
+        (with_program git
+          it "leaves packs which git can verify" && {
+            expect_run_sh $SUCCESSFULLY "git verify-pack objects/pack/*.idx"
+          }
+        )
+      )
+    )
+  )
+)
+
+(when "running 'repository-statistics"
+  snapshot="$snapshot/repository-statistics"
+  (sandbox
+    (with "an object directory containing a pack"
+      mkdir -p objects/pack
+      cp "$fixtures/packs/pack-11fdfa9e156ab73caae3b6da867192221f2089c2".* objects/pack/
+      it "reports the largest and deepest objects" && {
+        WITH_SNAPSHOT="$snapshot/pack-success" \
+        expect_run $SUCCESSFULLY "$exe_plumbing" repository-statistics --limit 3 --blob-size-threshold 10000 objects
+      }
+      if test "$kind" = "max"; then
+      (with "--format json"
+        it "reports the largest and deepest objects as JSON" && {
+          WITH_SNAPSHOT="$snapshot/pack-json-success" \
+          expect_run $SUCCESSFULLY "$exe_plumbing" --format json repository-statistics --limit 1 --blob-size-threshold 20000 objects
+        }
+      )
+      fi
+    )
+  )
+)